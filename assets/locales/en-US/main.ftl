@@ -3,19 +3,28 @@ title =
     Fish Folk
     Punchy
 start-game = Start Game
+how-to-play = How to Play
 settings = Settings
+assist-ai = Assist AI
+on = On
+off = Off
 quit = Quit
 cancel = Cancel
 save = Save
+back = Back
 
 # Pause Menu
 paused = Paused
 continue = Continue
 main-menu = Main Menu
 
+# Level Complete Menu
+level-complete = Level Complete
+
 # Settings Menu
 controls = Controls
 sound = Sound
+video = Video
 reset = Reset
 
 # Controls
@@ -23,17 +32,82 @@ action = Action
 keyboard-1 = Keyboard 1
 keyboard-2 = Keyboard 2
 gamepad = Gamepad
+move = Move
 move-up = Move Up
 move-down = Move Down
 move-left = Move Left
 move-right = Move Right
+run = Run
 flop-attack = Flop Attack
 shoot = Shoot
 throwgrab = Throw/Grab
+super-attack = Super Attack
+block = Block
+analog-attack = Analog Attack
 bind-input = Press an input or press Escape to cancel.
 
+# Video
+crt-effect = CRT Effect
+scanline-intensity = Scanline Intensity
+screen-curvature = Screen Curvature
+button-prompts = Button Prompts
+auto = Auto
+keyboard = Keyboard
+xbox = Xbox
+playstation = PlayStation
+screen-shake = Screen Shake
+reduced-motion = Reduced Motion
+vsync = VSync
+frame-rate-cap = Frame Rate Cap
+unlimited = Unlimited
+
+# Sound
+music-volume = Music Volume
+effects-volume = Effects Volume
+
 # Debug Tools
 debug-tools = Debug Tools
 show-collision-shapes = Show Collision Shapes
 show-world-inspector = Show World Inspector
-show-ysort-lines = Show Y-Sort Lines
\ No newline at end of file
+show-ysort-lines = Show Y-Sort Lines
+show-collision-offsets = Show Collision Offsets
+show-frame-data = Show Frame Data
+show-input-display = Show Input Display
+show-enemy-ai = Show Enemy AI Targets
+show-practice-hitboxes = Show Practice Hitboxes
+show-movement-bounds = Show Movement Bounds
+show-input-latency = Measure Input Latency
+
+# Frame Data
+frame-data = Frame Data
+fighter = Fighter
+attack = Attack
+startup = Startup
+active = Active
+recovery = Recovery
+current-frame = Current Frame
+damage = Damage
+hitstun = Hitstun
+pushback = Pushback
+
+# Input Latency
+input-latency = Input Latency
+last-latency = Last (frames)
+average-latency = Average (frames)
+samples = Samples
+
+# Level Editor
+level-editor = Level Editor
+show-level-editor = Show Level Editor
+enemy = Enemy
+item = Item
+stop-point = Stop Point
+enemies = Enemies
+items = Items
+stop-points = Stop Points
+export-level = Export
+import-level = Import
+clear = Clear
+
+# Boss Intros
+big-bass-banner = The Big Bass
@@ -4,4 +4,7 @@ start-game = Démarrer Jeu
 # Pause Menu
 paused = En Pause
 continue = Continuez
-main-menu = Menu Principal
\ No newline at end of file
+main-menu = Menu Principal
+
+# Level Complete Menu
+level-complete = Niveau Terminé
\ No newline at end of file
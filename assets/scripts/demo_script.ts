@@ -22,6 +22,11 @@ const Health: BevyType<Health> = { typeName: "punchy::damage::Health" };
 type Stats = {
   max_health: i32; // Note that these number types are just aliases to JavaScripts `number` type.
   movement_speed: f32;
+  run_speed: f32 | null;
+  knockback_resist: f32;
+  poise: f32;
+  poise_regen: f32;
+  preferred_range: f32 | null;
 };
 const Stats: BevyType<Stats> = { typeName: "punchy::fighter::Stats" };
 
@@ -1,15 +1,22 @@
-use std::ops::Range;
+use std::{ops::Range, sync::Mutex};
 
-use crate::GameState;
+use crate::{damage::HitPause, utils::GameTime, GameState};
 use bevy::{
     prelude::*,
     sprite::TextureAtlasSprite,
-    time::{Time, Timer},
-    utils::HashMap,
+    time::Timer,
+    utils::{HashMap, HashSet},
 };
 use iyes_loopless::condition::ConditionSet;
+use once_cell::sync::Lazy;
 use serde::{de::SeqAccess, Deserializer};
 
+/// The animation played in place of an animation that is missing from a spritesheet
+pub const FALLBACK_ANIMATION: &str = "idle";
+
+/// The missing animations that have already been warned about
+static MISSING_ANIMATIONS: Lazy<Mutex<HashSet<String>>> = Lazy::new(default);
+
 pub struct AnimationPlugin;
 
 impl Plugin for AnimationPlugin {
@@ -113,6 +120,8 @@ pub struct Animation {
     pub current_animation: Option<String>,
     pub timer: Timer,
     pub played_once: bool,
+    /// The missing animation that the [`FALLBACK_ANIMATION`] is playing in place of, if any
+    fallback_for: Option<String>,
 }
 
 impl Animation {
@@ -123,11 +132,32 @@ impl Animation {
             current_animation: None,
             timer: Timer::from_seconds(fps, TimerMode::Once),
             played_once: false,
+            fallback_for: None,
         }
     }
 
     /// Start playing a new animation
+    ///
+    /// If the spritesheet doesn't have the animation, the [`FALLBACK_ANIMATION`] is played instead,
+    /// and a warning is logged the first time the animation is missing.
     pub fn play(&mut self, name: &str, repeating: bool) {
+        let name = if !self.animations.contains_key(name)
+            && self.animations.contains_key(FALLBACK_ANIMATION)
+        {
+            warn_missing_animation(name);
+
+            // States replay their animation whenever it isn't the current one, so don't restart the
+            // fallback every frame, or it never finishes.
+            if self.is_playing(name) {
+                return;
+            }
+            self.fallback_for = Some(name.to_owned());
+            FALLBACK_ANIMATION
+        } else {
+            self.fallback_for = None;
+            name
+        };
+
         self.current_animation = Some(name.to_owned());
         self.current_frame = 0;
         self.timer.reset();
@@ -140,6 +170,13 @@ impl Animation {
         self.played_once = false;
     }
 
+    /// Whether `name` is the current animation, or the missing animation that the
+    /// [`FALLBACK_ANIMATION`] is playing in place of
+    pub fn is_playing(&self, name: &str) -> bool {
+        self.current_animation.as_deref() == Some(name)
+            || self.fallback_for.as_deref() == Some(name)
+    }
+
     pub fn is_finished(&self) -> bool {
         self.played_once
     }
@@ -184,7 +221,25 @@ impl Animation {
     }
 }
 
-fn animation_cycling(mut query: Query<(&mut TextureAtlasSprite, &mut Animation)>, time: Res<Time>) {
+/// Log a warning about a missing animation, returning `false` if it was already warned about
+fn warn_missing_animation(name: &str) -> bool {
+    let mut missing = MISSING_ANIMATIONS.lock().unwrap();
+    if missing.contains(name) {
+        return false;
+    }
+
+    warn!(
+        "Animation `{}` is missing, playing `{}` instead",
+        name, FALLBACK_ANIMATION
+    );
+    missing.insert(name.to_owned())
+}
+
+/// Advances the animations of the entities that aren't in a [`HitPause`]
+pub fn animation_cycling(
+    mut query: Query<(&mut TextureAtlasSprite, &mut Animation), Without<HitPause>>,
+    time: GameTime,
+) {
     //TODO: Add a tick method on Animation
     for (mut texture_atlas_sprite, mut animation) in query.iter_mut() {
         if animation.is_finished() && !animation.is_repeating() {
@@ -218,3 +273,46 @@ fn animation_flipping(mut query: Query<(&mut TextureAtlasSprite, &Facing)>) {
         texture_atlas_sprite.flip_x = facing.is_left();
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_missing_animation_falls_back_to_idle() {
+        let clip = |frames| Clip {
+            frames,
+            repeat: true,
+        };
+        let mut animation = Animation::new(
+            0.1,
+            HashMap::from_iter([
+                ("idle".to_string(), clip(0..3)),
+                ("running".to_string(), clip(4..7)),
+            ]),
+        );
+
+        animation.play("running", true);
+        assert_eq!(animation.current_animation.as_deref(), Some("running"));
+
+        animation.play("knocked_sideways", false);
+        assert_eq!(
+            animation.current_animation.as_deref(),
+            Some(FALLBACK_ANIMATION)
+        );
+        assert_eq!(animation.get_current_index(), Some(0));
+        assert!(animation.is_playing("knocked_sideways"));
+
+        // Playing the missing animation again doesn't restart the fallback
+        animation.current_frame = 2;
+        animation.play("knocked_sideways", false);
+        assert_eq!(animation.current_frame, 2);
+
+        // The missing animation is only warned about once
+        assert!(MISSING_ANIMATIONS
+            .lock()
+            .unwrap()
+            .contains("knocked_sideways"));
+        assert!(!warn_missing_animation("knocked_sideways"));
+    }
+}
@@ -1,15 +1,21 @@
 use std::path::{Path, PathBuf};
 
 use bevy::{
-    asset::{Asset, AssetLoader, AssetPath, LoadedAsset},
+    asset::{Asset, AssetIo, AssetIoError, AssetLoader, AssetPath, LoadedAsset, Metadata},
     prelude::AddAsset,
     prelude::*,
     reflect::TypeUuid,
-    utils::HashMap,
+    utils::{BoxedFuture, HashMap, HashSet},
 };
 use bevy_egui::egui;
 
-use crate::{consts::FOOT_PADDING, metadata::*};
+use crate::{
+    consts::FOOT_PADDING,
+    metadata::{
+        migration::{load_migrated, FIGHTER_MIGRATIONS, ITEM_MIGRATIONS, LEVEL_MIGRATIONS},
+        *,
+    },
+};
 
 /// Register game asset and loaders
 pub fn register(app: &mut bevy::prelude::App) {
@@ -131,6 +137,14 @@ impl AssetLoader for GameMetaLoader {
             meta.main_menu.music_handle = music_handle;
             dependencies.push(music_path);
 
+            // Load the stop point blocked sound
+            if let Some(blocked_sound) = &meta.stop_point.blocked_sound {
+                let (blocked_sound_path, blocked_sound_handle) =
+                    get_relative_asset(load_context, &self_path, blocked_sound);
+                meta.stop_point.blocked_sound_handle = Some(blocked_sound_handle);
+                dependencies.push(blocked_sound_path);
+            }
+
             // Load button sounds
             let (play_button_sound_path, play_button_sound_handle) =
                 get_relative_asset(load_context, &self_path, &meta.main_menu.play_button_sound);
@@ -183,7 +197,7 @@ impl AssetLoader for LevelMetaLoader {
         load_context: &'a mut bevy::asset::LoadContext,
     ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
         Box::pin(async move {
-            let mut meta: LevelMeta = serde_yaml::from_slice(bytes)?;
+            let mut meta: LevelMeta = load_migrated(bytes, LEVEL_MIGRATIONS)?;
             trace!(?meta, "Loaded level asset");
 
             let self_path = load_context.path();
@@ -241,6 +255,15 @@ impl AssetLoader for LevelMetaLoader {
             meta.music_handle = music_handle;
             dependencies.push(music_path);
 
+            if let Some(playlist) = &mut meta.playlist {
+                for track in &playlist.tracks {
+                    let (track_path, track_handle) =
+                        get_relative_asset(load_context, self_path, track);
+                    playlist.track_handles.push(track_handle);
+                    dependencies.push(track_path);
+                }
+            }
+
             load_context.set_default_asset(LoadedAsset::new(meta).with_dependencies(dependencies));
 
             Ok(())
@@ -261,7 +284,7 @@ impl AssetLoader for FighterLoader {
         load_context: &'a mut bevy::asset::LoadContext,
     ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
         Box::pin(async move {
-            let mut meta: FighterMeta = serde_yaml::from_slice(bytes)?;
+            let mut meta: FighterMeta = load_migrated(bytes, FIGHTER_MIGRATIONS)?;
             trace!(?meta, "Loaded fighter asset");
 
             let self_path = load_context.path();
@@ -300,6 +323,57 @@ impl AssetLoader for FighterLoader {
                 }
             }
 
+            if let Some(footsteps) = &mut meta.audio.footsteps {
+                for sound in &footsteps.sounds {
+                    let (asset_path, sound_handle) =
+                        get_relative_asset(load_context, self_path, sound);
+
+                    dependencies.push(asset_path);
+                    footsteps.sound_handles.push(sound_handle);
+                }
+            }
+
+            if let Some(whiff) = &meta.audio.whiff {
+                let (asset_path, sound_handle) = get_relative_asset(load_context, self_path, whiff);
+
+                dependencies.push(asset_path);
+                meta.audio.whiff_handle = Some(sound_handle);
+            }
+
+            if let Some(voices) = &mut meta.audio.voices {
+                for (state, sounds) in &voices.states {
+                    for sound in sounds {
+                        let (asset_path, sound_handle) =
+                            get_relative_asset(load_context, self_path, sound);
+
+                        dependencies.push(asset_path);
+                        voices
+                            .state_handles
+                            .entry(*state)
+                            .or_insert_with(Vec::new)
+                            .push(sound_handle);
+                    }
+                }
+            }
+
+            if let Some(boss_intro) = &mut meta.boss_intro {
+                if let Some(sound) = &boss_intro.sound {
+                    let (asset_path, sound_handle) =
+                        get_relative_asset(load_context, self_path, sound);
+
+                    dependencies.push(asset_path);
+                    boss_intro.sound_handle = Some(sound_handle);
+                }
+            }
+
+            if let Some(reinforcements) = &mut meta.reinforcements {
+                let (fighter_path, fighter_handle) =
+                    get_relative_asset(load_context, self_path, &reinforcements.fighter);
+
+                dependencies.push(fighter_path);
+                reinforcements.fighter_handle = fighter_handle;
+            }
+
             for (index, image) in meta.spritesheet.image.iter().enumerate() {
                 let (texture_path, texture_handle) =
                     get_relative_asset(load_context, load_context.path(), image);
@@ -318,7 +392,14 @@ impl AssetLoader for FighterLoader {
                 );
                 meta.spritesheet.atlas_handle.push(atlas_handle);
                 meta.center_y = meta.spritesheet.tile_size.y as f32 / 2.;
-                meta.collision_offset = meta.center_y - FOOT_PADDING;
+                meta.collision_offset = meta
+                    .custom_collision_offset
+                    .unwrap_or(meta.center_y - FOOT_PADDING);
+            }
+
+            // Warn about likely misplaced colliders
+            for warning in meta.validate() {
+                warn!("Fighter {:?}: {}", self_path, warning);
             }
 
             if let Some(ref mut attachment) = meta.attachment {
@@ -362,7 +443,7 @@ impl AssetLoader for ItemLoader {
         load_context: &'a mut bevy::asset::LoadContext,
     ) -> bevy::utils::BoxedFuture<'a, Result<(), anyhow::Error>> {
         Box::pin(async move {
-            let mut meta: ItemMeta = serde_yaml::from_slice(bytes)?;
+            let mut meta: ItemMeta = load_migrated(bytes, ITEM_MIGRATIONS)?;
             trace!(?meta, "Loaded item asset");
 
             let self_path = load_context.path();
@@ -413,6 +494,14 @@ impl AssetLoader for ItemLoader {
                         }
                     }
 
+                    if let Some(whiff) = &audio.whiff {
+                        let (asset_path, sound_handle) =
+                            get_relative_asset(load_context, self_path, whiff);
+
+                        dependencies.push(asset_path);
+                        audio.whiff_handle = Some(sound_handle);
+                    }
+
                     for (index, image) in spritesheet.image.iter().enumerate() {
                         let (texture_path, texture_handle) =
                             get_relative_asset(load_context, load_context.path(), image);
@@ -511,3 +600,159 @@ impl EguiFontDefinitions {
         &self.0
     }
 }
+
+/// The asset source that a path was resolved to by [`LayeredAssetIo`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssetLayer {
+    Mod,
+    Base,
+}
+
+/// Asset IO that layers a mod pack directory over the base game assets.
+///
+/// Any asset present in the mod directory takes precedence over the base asset with the same path,
+/// and anything missing from the mod directory is loaded from the base game, so that mods only
+/// need to contain the files they replace or add.
+pub struct LayeredAssetIo {
+    mod_io: Box<dyn AssetIo>,
+    base_io: Box<dyn AssetIo>,
+}
+
+impl LayeredAssetIo {
+    pub fn new(mod_io: Box<dyn AssetIo>, base_io: Box<dyn AssetIo>) -> Self {
+        Self { mod_io, base_io }
+    }
+
+    /// Get the layer that the asset at the given path will be loaded from
+    pub fn resolve(&self, path: &Path) -> AssetLayer {
+        if self.mod_io.get_metadata(path).is_ok() {
+            AssetLayer::Mod
+        } else {
+            AssetLayer::Base
+        }
+    }
+
+    fn layer_io(&self, layer: AssetLayer) -> &dyn AssetIo {
+        match layer {
+            AssetLayer::Mod => &*self.mod_io,
+            AssetLayer::Base => &*self.base_io,
+        }
+    }
+}
+
+impl AssetIo for LayeredAssetIo {
+    fn load_path<'a>(&'a self, path: &'a Path) -> BoxedFuture<'a, Result<Vec<u8>, AssetIoError>> {
+        Box::pin(async move {
+            if self.resolve(path) == AssetLayer::Mod {
+                match self.mod_io.load_path(path).await {
+                    Ok(bytes) => return Ok(bytes),
+                    Err(e) => warn!(
+                        "Could not load mod asset {:?}, falling back to base asset: {}",
+                        path, e
+                    ),
+                }
+            }
+
+            self.base_io.load_path(path).await
+        })
+    }
+
+    fn read_directory(
+        &self,
+        path: &Path,
+    ) -> Result<Box<dyn Iterator<Item = PathBuf>>, AssetIoError> {
+        // Merge the directory entries of both layers, the mod directory may not exist
+        let mod_entries = self.mod_io.read_directory(path);
+        let base_entries = self.base_io.read_directory(path);
+
+        match (mod_entries, base_entries) {
+            (Ok(mod_entries), Ok(base_entries)) => {
+                let entries = mod_entries.chain(base_entries).collect::<HashSet<_>>();
+                Ok(Box::new(entries.into_iter()))
+            }
+            (Ok(entries), Err(_)) | (Err(_), Ok(entries)) => Ok(entries),
+            (Err(_), Err(e)) => Err(e),
+        }
+    }
+
+    fn get_metadata(&self, path: &Path) -> Result<Metadata, AssetIoError> {
+        self.layer_io(self.resolve(path)).get_metadata(path)
+    }
+
+    fn watch_path_for_changes(&self, path: &Path) -> Result<(), AssetIoError> {
+        self.layer_io(self.resolve(path))
+            .watch_path_for_changes(path)
+    }
+
+    fn watch_for_changes(&self) -> Result<(), AssetIoError> {
+        self.base_io.watch_for_changes()?;
+        self.mod_io.watch_for_changes()
+    }
+}
+
+#[cfg(all(test, not(target_arch = "wasm32")))]
+mod test {
+    use bevy::asset::FileAssetIo;
+
+    use super::*;
+
+    #[test]
+    fn test_layered_asset_io_prefers_mod_assets() {
+        let root = std::env::temp_dir().join(format!("punchy-mod-test-{}", std::process::id()));
+        let base_dir = root.join("base");
+        let mod_dir = root.join("mod");
+        std::fs::create_dir_all(base_dir.join("fighters")).unwrap();
+        std::fs::create_dir_all(mod_dir.join("fighters")).unwrap();
+
+        std::fs::write(base_dir.join("fighters/fishy.fighter.yaml"), "name: Fishy").unwrap();
+        std::fs::write(
+            base_dir.join("fighters/sharky.fighter.yaml"),
+            "name: Sharky",
+        )
+        .unwrap();
+        std::fs::write(mod_dir.join("fighters/fishy.fighter.yaml"), "name: Modded").unwrap();
+
+        let io = LayeredAssetIo::new(
+            Box::new(FileAssetIo::new(&mod_dir, false)),
+            Box::new(FileAssetIo::new(&base_dir, false)),
+        );
+
+        assert_eq!(
+            io.resolve(Path::new("fighters/fishy.fighter.yaml")),
+            AssetLayer::Mod
+        );
+        assert_eq!(
+            io.resolve(Path::new("fighters/sharky.fighter.yaml")),
+            AssetLayer::Base
+        );
+        assert_eq!(
+            io.resolve(Path::new("fighters/missing.fighter.yaml")),
+            AssetLayer::Base
+        );
+
+        // The mod's version of an asset is loaded instead of the base one, and the rest still load
+        // from the base assets
+        let load = |path: &str| {
+            let bytes = bevy::tasks::block_on(io.load_path(Path::new(path))).unwrap();
+            String::from_utf8(bytes).unwrap()
+        };
+        assert_eq!(load("fighters/fishy.fighter.yaml"), "name: Modded");
+        assert_eq!(load("fighters/sharky.fighter.yaml"), "name: Sharky");
+
+        // Both layers are listed once
+        let mut entries = io
+            .read_directory(Path::new("fighters"))
+            .unwrap()
+            .collect::<Vec<_>>();
+        entries.sort();
+        assert_eq!(
+            entries,
+            [
+                PathBuf::from("fighters/fishy.fighter.yaml"),
+                PathBuf::from("fighters/sharky.fighter.yaml"),
+            ]
+        );
+
+        std::fs::remove_dir_all(root).unwrap();
+    }
+}
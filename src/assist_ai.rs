@@ -0,0 +1,186 @@
+//! The assist AI, which controls the second player as an ally when playing solo
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::plugin::InputManagerSystem;
+
+use crate::{
+    animation::Facing,
+    camera::{BossIntro, CameraIntro},
+    consts,
+    enemy::Enemy,
+    enemy_ai::choose_player,
+    fighter::{AvailableAttacks, Inventory},
+    fighter_state::{
+        attack_transition, FighterStateCollectSystems, Grabbing, Idling, Moving, StateTransition,
+        StateTransitionIntents,
+    },
+    item::Item,
+    metadata::AttackStrength,
+    player::LivingPlayer,
+    GameState, Stats,
+};
+
+pub struct AssistAiPlugin;
+
+impl Plugin for AssistAiPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set_to_stage(
+            CoreStage::PreUpdate,
+            ConditionSet::new()
+                .label(FighterStateCollectSystems)
+                .after(InputManagerSystem::Update)
+                .run_in_state(GameState::InGame)
+                // The ally waits for the camera and boss intros like the other players
+                .run_unless_resource_exists::<CameraIntro>()
+                .run_unless_resource_exists::<BossIntro>()
+                .with_system(emit_assist_ai_intents)
+                .into(),
+        );
+    }
+}
+
+/// Marker component for a player controlled by the assist AI instead of by input
+#[derive(Component)]
+pub struct AssistAi;
+
+/// Controls the assist AI players
+///
+/// The ally attacks the enemies that come close, picks up the items lying nearby, and otherwise
+/// follows the human players, staying behind them so that it doesn't get in their way.
+fn emit_assist_ai_intents(
+    mut allies: Query<
+        (
+            &Transform,
+            &Stats,
+            &AvailableAttacks,
+            &Inventory,
+            &mut Facing,
+            &mut StateTransitionIntents,
+        ),
+        (With<AssistAi>, Or<(With<Idling>, With<Moving>)>),
+    >,
+    humans: Query<&Transform, (LivingPlayer, Without<AssistAi>)>,
+    enemies: Query<&Transform, With<Enemy>>,
+    items: Query<&Transform, With<Item>>,
+) {
+    let enemies = enemies.iter().collect::<Vec<_>>();
+    let items = items.iter().collect::<Vec<_>>();
+    let humans = humans.iter().collect::<Vec<_>>();
+
+    for (transform, stats, available_attacks, inventory, mut facing, mut intents) in &mut allies {
+        let position = transform.translation.truncate();
+        let within = |target: &Transform, distance: f32| {
+            target.translation.truncate().distance(position) <= distance
+        };
+
+        let closest_enemy = choose_player(&enemies, transform)
+            .filter(|enemy| within(enemy, consts::ASSIST_AI_ENGAGE_DISTANCE));
+        let closest_item = choose_player(&items, transform)
+            .filter(|item| inventory.is_none() && within(item, consts::ASSIST_AI_ITEM_DISTANCE));
+
+        let target = if let Some(enemy) = closest_enemy {
+            // Attack the enemies in reach
+            if within(&enemy, consts::ASSIST_AI_ATTACK_DISTANCE) {
+                *facing = if enemy.translation.x > position.x {
+                    Facing::Right
+                } else {
+                    Facing::Left
+                };
+
+                if let Some(transition) =
+                    attack_transition(available_attacks, AttackStrength::Light)
+                {
+                    intents.push_back(transition);
+                }
+                continue;
+            }
+
+            enemy.translation.truncate()
+        } else if let Some(item) = closest_item {
+            // Pick up the items in reach
+            if within(&item, consts::PICK_ITEM_RADIUS) {
+                intents.push_back(StateTransition::new(Grabbing, Grabbing::PRIORITY, true));
+                continue;
+            }
+
+            item.translation.truncate()
+        } else if let Some(human) = choose_player(&humans, transform)
+            .filter(|human| !within(human, consts::ASSIST_AI_FOLLOW_DISTANCE))
+        {
+            // Follow the human players, from behind
+            human.translation.truncate() - Vec2::new(consts::ASSIST_AI_FOLLOW_DISTANCE / 2., 0.)
+        } else {
+            continue;
+        };
+
+        intents.push_back(StateTransition::new(
+            Moving::new((target - position).normalize_or_zero(), stats, false),
+            Moving::PRIORITY,
+            false,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        attack::AttackFrames, fighter_state::Punching, metadata::AttackMeta, player::Player,
+    };
+
+    #[test]
+    fn test_ally_approaches_and_attacks_nearby_enemy() {
+        let mut world = World::new();
+        let punch = AttackMeta {
+            name: "punch".into(),
+            damage: 10,
+            frames: AttackFrames {
+                startup: 0,
+                active: 1,
+                recovery: 2,
+            },
+            ..default()
+        };
+        world.spawn((Player, Transform::from_xyz(0., 0., 0.)));
+        let ally = world
+            .spawn((
+                Player,
+                AssistAi,
+                Idling,
+                Transform::from_xyz(50., 0., 0.),
+                Stats {
+                    movement_speed: 100.,
+                    ..default()
+                },
+                AvailableAttacks {
+                    attacks: vec![punch],
+                },
+                Inventory(None),
+                Facing::Left,
+                StateTransitionIntents::default(),
+            ))
+            .id();
+        let enemy = world.spawn((Enemy, Transform::from_xyz(200., 0., 0.))).id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(emit_assist_ai_intents);
+        let mut take_intent = |world: &mut World| {
+            stage.run(world);
+            let mut intents = world.get_mut::<StateTransitionIntents>(ally).unwrap();
+            assert_eq!(intents.len(), 1);
+            intents.pop_front().unwrap()
+        };
+
+        // The ally moves toward the enemy
+        let intent = take_intent(&mut world);
+        let moving = intent.data.downcast_ref::<Moving>().unwrap();
+        assert!(moving.velocity.x > 0.);
+
+        // And attacks it once in reach
+        world.get_mut::<Transform>(enemy).unwrap().translation.x = 80.;
+        let intent = take_intent(&mut world);
+        assert!(intent.data.is::<Punching>());
+        assert!(!world.get::<Facing>(ally).unwrap().is_left());
+    }
+}
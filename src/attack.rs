@@ -1,10 +1,12 @@
 use std::time::Duration;
 
 use bevy::{
+    ecs::system::SystemParam,
     hierarchy::DespawnRecursiveExt,
     math::Vec2,
     prelude::*,
     reflect::{FromReflect, Reflect},
+    utils::HashSet,
 };
 use bevy_rapier2d::prelude::*;
 use iyes_loopless::prelude::*;
@@ -12,13 +14,18 @@ use iyes_loopless::prelude::*;
 use serde::Deserialize;
 
 use crate::{
-    animation::Animation,
-    damage::{DamageEvent, Damageable, Health},
+    animation::{Animation, Facing},
+    damage::{
+        self, Clash, DamageEvent, DamageFalloff, Damageable, Health, Invulnerable, ParryEvent, Pull,
+    },
     enemy::Enemy,
-    fighter_state::MeleeWeapon,
+    fighter::Airborne,
+    fighter_state::{Bullet, MeleeWeapon, Parrying},
     item::{Drop, Explodable},
-    metadata::ColliderMeta,
+    metadata::{BurnMeta, ColliderMeta, HitReaction},
     player::Player,
+    projectile::Projectile,
+    status_effect::{Burning, Confused},
     GameState,
 };
 
@@ -35,6 +42,7 @@ impl Plugin for AttackPlugin {
                     .run_in_state(GameState::InGame)
                     .with_system(activate_hitbox)
                     .with_system(deactivate_hitbox)
+                    .with_system(follow_hitbox_path)
                     .with_system(breakable_system)
                     .with_system(damage_flash)
                     .into(),
@@ -42,7 +50,8 @@ impl Plugin for AttackPlugin {
             // Attack damage is run in PostUpdate to make sure it runs after rapier generates collision events
             .add_system_to_stage(CoreStage::PostUpdate, attack_damage_system)
             // Event for when Breakable breaks
-            .add_event::<BrokeEvent>();
+            .add_event::<BrokeEvent>()
+            .add_event::<WhiffEvent>();
     }
 }
 
@@ -50,6 +59,13 @@ impl Plugin for AttackPlugin {
 #[derive(Component)]
 pub struct FlashingTimer {
     pub timer: Timer,
+    /// The color the sprite is tinted with while flashing
+    pub color: Color,
+}
+
+impl FlashingTimer {
+    /// The color used to flash entities that have taken damage
+    pub const DAMAGE_COLOR: Color = Color::rgb(255.0, 255.0, 255.0);
 }
 
 /// A component representing an attack that can do damage to [`Damageable`]s with [`Health`].
@@ -63,6 +79,30 @@ pub struct Attack {
     pub hitstun_duration: f32,
     /// add this for attacks that are not immediately active, used in activate_hitbox
     pub hitbox_meta: Option<ColliderMeta>,
+    /// The priority of the attack when it clashes with an opposing attack, if it can clash
+    pub clash_priority: Option<u32>,
+    /// The velocity the attack launches its target up with, replacing the pushback
+    pub launch: Option<Vec2>,
+    /// The extra damage dealt to airborne targets, as a fraction of the damage
+    pub antiair_bonus: Option<f32>,
+    /// The speed the attack pulls its target toward the attacker with, replacing the pushback
+    pub pull: Option<f32>,
+    /// The burn the attack sets its target on fire with
+    pub burn: Option<BurnMeta>,
+    /// How long the attack confuses its target for, in seconds
+    pub confuse: Option<f32>,
+    /// How the target reacts to the hit
+    pub reaction: HitReaction,
+}
+
+impl Attack {
+    /// Get the damage dealt to a target, depending on whether it is airborne
+    pub fn damage_to(&self, airborne: bool) -> i32 {
+        match self.antiair_bonus {
+            Some(bonus) if airborne => (self.damage as f32 * (1.0 + bonus)).round() as i32,
+            _ => self.damage,
+        }
+    }
 }
 
 #[derive(Component)]
@@ -100,13 +140,49 @@ pub struct BrokeEvent {
 /// Must be added to an entity that is a child of an entity with an [`Animation`] and an [`Attack`]
 /// and will be used to spawn a collider for that attack during the `active` frames.
 /// Each field is an index refering to an animation frame
-#[derive(Component, Debug, Clone, Copy, Deserialize, Reflect, FromReflect)]
+#[derive(Component, Debug, Default, Clone, Copy, Deserialize, Reflect, FromReflect)]
 pub struct AttackFrames {
     pub startup: usize,
     pub active: usize,
     pub recovery: usize,
 }
 
+impl AttackFrames {
+    /// Whether the attack can deal damage on an animation frame, from its first active frame until
+    /// its recovery
+    pub fn is_active(&self, frame: usize) -> bool {
+        frame >= self.startup && frame < self.recovery
+    }
+}
+
+/// Tracks whether an attack has hit anything, to tell the attacks that whiffed.
+///
+/// Attacks without it, like projectiles, never whiff.
+#[derive(Component, Default)]
+pub struct Connected(pub bool);
+
+/// Event sent when an attack's active frames pass without it hitting anything
+pub struct WhiffEvent {
+    /// The entity the attack belonged to, a fighter or the weapon it is holding
+    pub attacker: Entity,
+}
+
+/// The hitbox offsets of an attack, one per active frame, that its hitbox moves along during the
+/// active frames.
+///
+/// The offsets are already mirrored to the side the attacker is facing.
+#[derive(Component, Clone, Debug)]
+pub struct HitboxPath(pub Vec<Vec2>);
+
+impl HitboxPath {
+    /// Get the hitbox offset on an animation frame, staying on the first offset before the active
+    /// frames and on the last one after the end of the path
+    pub fn offset_at(&self, frames: &AttackFrames, frame: usize) -> Option<Vec2> {
+        let index = frame.saturating_sub(frames.startup);
+        self.0.get(index).or_else(|| self.0.last()).copied()
+    }
+}
+
 /// Activates inactive attacks after the animation on the attack reaches the active frames by
 /// adding a collider to the attack entity.
 //TODO: is there a way we can move the adding of collision layers here as well?
@@ -146,21 +222,46 @@ fn activate_hitbox(
     }
 }
 
-/// Deactivate collisions for entities with [`AttackFrames`]
-fn deactivate_hitbox(
-    query: Query<(Entity, &AttackFrames, &Parent), (With<Attack>, With<Collider>)>,
+/// Deactivate collisions for entities with [`AttackFrames`], reporting the attacks that whiffed
+pub(crate) fn deactivate_hitbox(
+    query: Query<
+        (Entity, &AttackFrames, &Parent, Option<&Connected>),
+        (With<Attack>, With<Collider>),
+    >,
     animated_query: Query<&Animation>,
     mut commands: Commands,
+    mut whiff_events: EventWriter<WhiffEvent>,
 ) {
-    for (entity, attack_frames, parent) in query.iter() {
+    for (entity, attack_frames, parent, connected) in query.iter() {
         if let Ok(animation) = animated_query.get(**parent) {
             if animation.current_frame >= attack_frames.recovery {
+                if matches!(connected, Some(Connected(false))) {
+                    whiff_events.send(WhiffEvent {
+                        attacker: parent.get(),
+                    });
+                }
                 commands.entity(entity).despawn_recursive();
             }
         }
     }
 }
 
+/// Moves the hitboxes of attacks with a [`HitboxPath`] to the offset of their parent's current
+/// animation frame
+fn follow_hitbox_path(
+    mut attacks: Query<(&mut Transform, &HitboxPath, &AttackFrames, &Parent)>,
+    animated_query: Query<&Animation>,
+) {
+    for (mut transform, path, attack_frames, parent) in &mut attacks {
+        if let Ok(animation) = animated_query.get(**parent) {
+            if let Some(offset) = path.offset_at(attack_frames, animation.current_frame) {
+                transform.translation.x = offset.x;
+                transform.translation.y = offset.y;
+            }
+        }
+    }
+}
+
 // flash component
 // changes an entity's sprite to white for a specified amount of time
 fn damage_flash(
@@ -169,8 +270,8 @@ fn damage_flash(
     time: Res<Time>,
 ) {
     for (mut timer, timer_e, mut timer_sprite) in flash_query.iter_mut() {
-        //Set the color to white
-        timer_sprite.color = Color::rgb(255.0, 255.0, 255.0);
+        //Set the flash color
+        timer_sprite.color = timer.color;
 
         //run the timer
         timer.timer.tick(time.delta());
@@ -183,16 +284,59 @@ fn damage_flash(
     }
 }
 
-/// Depletes the health of damageables that have collided with attacks
-fn attack_damage_system(
+/// Depletes the health of damageables that have collided with attacks.
+///
+/// Opposing attacks that can clash and hit each other are resolved first, so that the canceled
+/// attacks don't deal any damage.
+pub(crate) fn attack_damage_system(
     mut commands: Commands,
     mut events: EventReader<CollisionEvent>,
-    mut damageables: Query<(&mut Health, &Damageable)>,
+    mut damageables: Query<(
+        &mut Health,
+        &Damageable,
+        Option<&Invulnerable>,
+        Option<&Airborne>,
+        Option<&mut Burning>,
+        Option<&mut Confused>,
+    )>,
     attacks: Query<&Attack>,
+    mut connections: Query<&mut Connected>,
+    attack_frames: Query<(&AttackFrames, &Parent)>,
+    animations: Query<&Animation>,
     hurtboxes: Query<&Parent, With<Hurtbox>>,
+    owners: AttackOwners,
+    transforms: Query<&GlobalTransform>,
+    colliders: Query<(&Collider, &GlobalTransform)>,
+    parries: Query<(&Parrying, &Facing, &GlobalTransform)>,
+    falloffs: Query<(&DamageFalloff, &Projectile)>,
     mut event_writer: EventWriter<DamageEvent>,
+    mut parry_events: EventWriter<ParryEvent>,
 ) {
-    for event in events.iter() {
+    let events = events.iter().collect::<Vec<_>>();
+
+    let mut canceled = HashSet::new();
+    for event in &events {
+        if let CollisionEvent::Started(e1, e2, _flags) = event {
+            if let (Ok(first), Ok(second)) = (attacks.get(*e1), attacks.get(*e2)) {
+                if let (Some(first_priority), Some(second_priority)) =
+                    (first.clash_priority, second.clash_priority)
+                {
+                    let losers = match Clash::new(first_priority, second_priority) {
+                        Clash::FirstWins => vec![*e2],
+                        Clash::SecondWins => vec![*e1],
+                        Clash::Trade => vec![*e1, *e2],
+                    };
+                    for loser in losers {
+                        if canceled.insert(loser) {
+                            commands.entity(loser).despawn_recursive();
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    for event in events {
         if let CollisionEvent::Started(e1, e2, _flags) = event {
             let (attack_entity, hurtbox_entity) =
                 if attacks.contains(*e1) && hurtboxes.contains(*e2) {
@@ -202,29 +346,139 @@ fn attack_damage_system(
                 } else {
                     continue;
                 };
+            if canceled.contains(&attack_entity) {
+                continue;
+            }
+
+            // Attacks with frame data only deal damage during their active frames, even if their
+            // hitbox overlapped something before
+            if let Ok((frames, parent)) = attack_frames.get(attack_entity) {
+                if let Ok(animation) = animations.get(parent.get()) {
+                    if !frames.is_active(animation.current_frame) {
+                        continue;
+                    }
+                }
+            }
 
             let attack = attacks.get(attack_entity).unwrap();
             if let Ok(hurtbox_parent) = hurtboxes.get(hurtbox_entity) {
                 let hurtbox_parent_entity = hurtbox_parent.get();
-                let (mut health, damageable) = damageables.get_mut(hurtbox_parent_entity).unwrap();
+
+                // Fighters are never hit by their own attacks, even with friendly fire
+                let owner = owners.get(attack_entity);
+                if owner == hurtbox_parent_entity {
+                    continue;
+                }
+                let (mut health, damageable, invulnerable, airborne, burning, confused) =
+                    damageables.get_mut(hurtbox_parent_entity).unwrap();
 
                 //apply damage to target
-                if **damageable {
-                    **health -= attack.damage;
+                if damage::can_take_damage(Some(damageable), invulnerable) {
+                    // Parrying fighters negate the attacks hitting them from within their cone
+                    if let Ok((parrying, facing, transform)) = parries.get(hurtbox_parent_entity) {
+                        let position = transform.translation().truncate();
+                        let attack_position = transforms
+                            .get(owner)
+                            .map_or(position, |owner| owner.translation().truncate());
+
+                        if parrying.is_in_window()
+                            && parrying.covers(facing, position, attack_position)
+                        {
+                            if let Ok(mut connected) = connections.get_mut(attack_entity) {
+                                connected.0 = true;
+                            }
+                            parry_events.send(ParryEvent {
+                                parrier: hurtbox_parent_entity,
+                                attack: attack_entity,
+                            });
+                            continue;
+                        }
+                    }
+
+                    let mut damage = attack.damage_to(airborne.is_some());
+
+                    // Bullets deal less damage the further they have travelled
+                    if let Ok((falloff, projectile)) = falloffs.get(attack_entity) {
+                        let position = transforms
+                            .get(attack_entity)
+                            .map(|transform| transform.translation().truncate())
+                            .unwrap_or(falloff.origin);
+                        damage = falloff.damage(damage, projectile, position);
+                    }
+                    **health -= damage;
+
+                    // Fire attacks set the target on fire, or keep it burning
+                    if let Some(burn) = attack.burn {
+                        match burning {
+                            Some(mut burning) => burning.refresh(burn),
+                            None => {
+                                commands
+                                    .entity(hurtbox_parent_entity)
+                                    .insert(Burning::new(burn));
+                            }
+                        }
+                    }
+
+                    // Confusing attacks turn the target against its own team for a while
+                    if let Some(duration) = attack.confuse {
+                        match confused {
+                            Some(mut confused) => confused.refresh(duration),
+                            None => {
+                                commands
+                                    .entity(hurtbox_parent_entity)
+                                    .insert(Confused::new(duration));
+                            }
+                        }
+                    }
 
                     //Damage flash of 100ms upon an entity taking damage
                     commands
                         .entity(hurtbox_parent_entity)
                         .insert(FlashingTimer {
                             timer: Timer::new(Duration::from_millis(100), TimerMode::Repeating),
+                            color: FlashingTimer::DAMAGE_COLOR,
                         });
 
+                    // Fall back to the damaged entity's position if the attack has none
+                    let source_position = transforms
+                        .get(attack_entity)
+                        .or_else(|_| transforms.get(hurtbox_parent_entity))
+                        .map(|transform| transform.translation().truncate())
+                        .unwrap_or_default();
+
+                    // Fall back to the attack position if either collider is gone
+                    let contact_position =
+                        match (colliders.get(attack_entity), colliders.get(hurtbox_entity)) {
+                            (Ok(attack_collider), Ok(hurtbox_collider)) => damage::contact_point(
+                                collider_rect(attack_collider),
+                                collider_rect(hurtbox_collider),
+                            ),
+                            _ => source_position,
+                        };
+
+                    // Pull attacks pull their target toward the attacker
+                    let pull = attack.pull.map(|speed| Pull {
+                        target: transforms
+                            .get(owner)
+                            .map(|transform| transform.translation().truncate())
+                            .unwrap_or(source_position),
+                        speed,
+                    });
+
+                    if let Ok(mut connected) = connections.get_mut(attack_entity) {
+                        connected.0 = true;
+                    }
+
                     event_writer.send(DamageEvent {
                         damageing_entity: attack_entity,
-                        damage_velocity: attack.pushback,
-                        damage: attack.damage,
+                        damage_velocity: attack.launch.unwrap_or(attack.pushback),
+                        damage,
                         damaged_entity: hurtbox_parent_entity,
+                        source_position,
+                        contact_position,
                         hitstun_duration: attack.hitstun_duration,
+                        pull,
+                        reaction: attack.reaction,
                     })
                 }
             }
@@ -232,6 +486,43 @@ fn attack_damage_system(
     }
 }
 
+/// Looks up the entity that an attack comes from
+#[derive(SystemParam)]
+pub struct AttackOwners<'w, 's> {
+    parents: Query<'w, 's, &'static Parent>,
+    bullets: Query<'w, 's, &'static Bullet>,
+}
+
+impl<'w, 's> AttackOwners<'w, 's> {
+    /// Get the entity that an attack comes from.
+    ///
+    /// Melee attacks are children of the attacking fighter, or of the weapon it is holding, so this
+    /// is the root of the attack's hierarchy. Bullets come from the fighter that shot them, and
+    /// other attacks without a parent, like thrown items, come from themselves.
+    pub fn get(&self, attack: Entity) -> Entity {
+        if let Ok(bullet) = self.bullets.get(attack) {
+            return bullet.shooter;
+        }
+
+        let mut owner = attack;
+        while let Ok(parent) = self.parents.get(owner) {
+            owner = parent.get();
+        }
+        owner
+    }
+}
+
+/// Get the world bounding box of a collider, ignoring its rotation
+fn collider_rect((collider, transform): (&Collider, &GlobalTransform)) -> Rect {
+    let aabb = collider.raw.compute_local_aabb();
+    let translation = transform.translation().truncate();
+
+    Rect::from_corners(
+        Vec2::new(aabb.mins.x, aabb.mins.y) + translation,
+        Vec2::new(aabb.maxs.x, aabb.maxs.y) + translation,
+    )
+}
+
 fn breakable_system(
     mut events: EventReader<CollisionEvent>,
     mut despawn_query: Query<(
@@ -271,3 +562,48 @@ fn breakable_system(
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_hitbox_follows_its_path() {
+        let mut world = World::new();
+        let fighter = world.spawn(Animation::new(0.1, default())).id();
+        let path = vec![Vec2::new(10., 20.), Vec2::new(20., 10.), Vec2::new(25., 0.)];
+        let attack = world
+            .spawn((
+                Transform::default(),
+                HitboxPath(path.clone()),
+                AttackFrames {
+                    startup: 1,
+                    active: 4,
+                    recovery: 5,
+                },
+            ))
+            .id();
+        world.entity_mut(fighter).push_children(&[attack]);
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(follow_hitbox_path);
+        let mut offset_at = |world: &mut World, frame: usize| {
+            world.get_mut::<Animation>(fighter).unwrap().current_frame = frame;
+            stage.run(world);
+            world
+                .get::<Transform>(attack)
+                .unwrap()
+                .translation
+                .truncate()
+        };
+
+        // The hitbox waits on the start of the path during the startup
+        assert_eq!(offset_at(&mut world, 0), path[0]);
+        // Sweeps along it over the active frames
+        assert_eq!(offset_at(&mut world, 1), path[0]);
+        assert_eq!(offset_at(&mut world, 2), path[1]);
+        assert_eq!(offset_at(&mut world, 3), path[2]);
+        // And holds the end of the path for the rest of them
+        assert_eq!(offset_at(&mut world, 4), path[2]);
+    }
+}
@@ -0,0 +1,238 @@
+//! Attract mode, where the game plays itself after the main menu has been left idle
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::{plugin::InputManagerSystem, prelude::ActionState};
+
+use crate::{
+    animation::Facing,
+    consts,
+    enemy::Enemy,
+    fighter::AvailableAttacks,
+    fighter_state::{
+        attack_transition, FighterStateCollectSystems, Idling, Moving, StateTransition,
+        StateTransitionIntents,
+    },
+    game_state::{GameStateRequests, TransitionPriority},
+    input::MenuAction,
+    metadata::{AttackStrength, GameMeta, LevelHandle, LevelMeta},
+    player::Player,
+    utils::ResetController,
+    GameState, Stats,
+};
+
+pub struct AttractModePlugin;
+
+impl Plugin for AttractModePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_enter_system(GameState::MainMenu, start_menu_idle_timer)
+            .add_system(
+                menu_idle_timer
+                    .run_in_state(GameState::MainMenu)
+                    .run_if_resource_exists::<MenuIdleTimer>(),
+            )
+            .add_system(
+                exit_attract_mode
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<AttractMode>(),
+            )
+            .add_system_set_to_stage(
+                CoreStage::PreUpdate,
+                ConditionSet::new()
+                    .label(FighterStateCollectSystems)
+                    .after(InputManagerSystem::Update)
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<AttractMode>()
+                    .with_system(emit_attract_mode_player_intents)
+                    .into(),
+            );
+    }
+}
+
+/// Resource present while the game is playing itself in attract mode
+#[derive(Resource)]
+pub struct AttractMode;
+
+/// Counts how long the main menu has been left without input
+#[derive(Resource)]
+pub struct MenuIdleTimer {
+    pub timer: Timer,
+    /// The level played in attract mode
+    pub level: Handle<LevelMeta>,
+}
+
+impl MenuIdleTimer {
+    pub fn new(timeout: f32, level: Handle<LevelMeta>) -> Self {
+        Self {
+            timer: Timer::from_seconds(timeout, TimerMode::Once),
+            level,
+        }
+    }
+}
+
+/// Start counting idle time when entering the main menu, if attract mode is enabled
+fn start_menu_idle_timer(mut commands: Commands, game: Res<GameMeta>) {
+    // We may be coming back from attract mode
+    commands.remove_resource::<AttractMode>();
+
+    if let Some(timeout) = game.main_menu.attract_mode_timeout {
+        commands.insert_resource(MenuIdleTimer::new(timeout, game.start_level_handle.clone()));
+    } else {
+        commands.remove_resource::<MenuIdleTimer>();
+    }
+}
+
+/// Start attract mode once the main menu has been idle for long enough
+fn menu_idle_timer(
+    mut commands: Commands,
+    mut state_requests: ResMut<GameStateRequests>,
+    mut idle_timer: ResMut<MenuIdleTimer>,
+    menu_input: Query<&ActionState<MenuAction>>,
+    time: Res<Time>,
+) {
+    if menu_input
+        .iter()
+        .any(|input| !input.get_pressed().is_empty())
+    {
+        idle_timer.timer.reset();
+        return;
+    }
+
+    idle_timer.timer.tick(time.delta());
+
+    if idle_timer.timer.finished() {
+        commands.insert_resource(AttractMode);
+        commands.insert_resource(LevelHandle(idle_timer.level.clone()));
+        state_requests.request(GameState::LoadingLevel, TransitionPriority::Navigation);
+        commands.remove_resource::<MenuIdleTimer>();
+    }
+}
+
+/// Go back to the main menu when any menu input is pressed during attract mode
+fn exit_attract_mode(
+    mut state_requests: ResMut<GameStateRequests>,
+    menu_input: Query<&ActionState<MenuAction>>,
+    reset_controller: ResetController,
+) {
+    if menu_input
+        .iter()
+        .any(|input| !input.get_just_pressed().is_empty())
+    {
+        reset_controller.reset_world();
+        state_requests.request(GameState::MainMenu, TransitionPriority::Navigation);
+    }
+}
+
+/// Controls the players during attract mode, walking them to the closest enemy and attacking
+///
+/// When there are no enemies left to fight, the players walk forward through the level.
+fn emit_attract_mode_player_intents(
+    mut players: Query<
+        (
+            &Transform,
+            &Stats,
+            &AvailableAttacks,
+            &mut Facing,
+            &mut StateTransitionIntents,
+        ),
+        (With<Player>, Or<(With<Idling>, With<Moving>)>),
+    >,
+    enemies: Query<&Transform, With<Enemy>>,
+) {
+    for (transform, stats, available_attacks, mut facing, mut intents) in &mut players {
+        let position = transform.translation.truncate();
+        let closest_enemy = enemies
+            .iter()
+            .map(|enemy| enemy.translation.truncate())
+            .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)));
+
+        let direction = match closest_enemy {
+            Some(enemy) if enemy.distance(position) <= consts::ATTRACT_MODE_ATTACK_DISTANCE => {
+                *facing = if enemy.x > position.x {
+                    Facing::Right
+                } else {
+                    Facing::Left
+                };
+
+                if let Some(transition) =
+                    attack_transition(available_attacks, AttackStrength::Light)
+                {
+                    intents.push_back(transition);
+                }
+                continue;
+            }
+            Some(enemy) => (enemy - position).normalize_or_zero(),
+            None => Vec2::X,
+        };
+
+        intents.push_back(StateTransition::new(
+            Moving::new(direction, stats, false),
+            Moving::PRIORITY,
+            false,
+        ));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::game_state::GameStatePlugin;
+
+    fn step(app: &mut App, seconds: f32) {
+        let mut time = app.world.resource_mut::<Time>();
+        let last_update = time.last_update().unwrap();
+        time.update_with_instant(last_update + Duration::from_secs_f32(seconds));
+        app.update();
+    }
+
+    #[test]
+    fn test_idle_menu_enters_attract_mode_and_input_exits() {
+        let mut app = App::new();
+        let mut time = Time::default();
+        time.update();
+        app.insert_resource(time)
+            .add_plugin(GameStatePlugin)
+            .insert_resource(MenuIdleTimer::new(1.0, default()))
+            .add_system(menu_idle_timer.run_if_resource_exists::<MenuIdleTimer>())
+            .add_system(exit_attract_mode.run_if_resource_exists::<AttractMode>());
+        let input = app.world.spawn(ActionState::<MenuAction>::default()).id();
+
+        // Not idle for long enough
+        step(&mut app, 0.6);
+        assert!(app.world.get_resource::<AttractMode>().is_none());
+
+        // Menu input resets the idle time
+        app.world
+            .get_mut::<ActionState<MenuAction>>(input)
+            .unwrap()
+            .press(MenuAction::Down);
+        step(&mut app, 0.6);
+        app.world
+            .get_mut::<ActionState<MenuAction>>(input)
+            .unwrap()
+            .release(MenuAction::Down);
+        step(&mut app, 0.6);
+        assert!(app.world.get_resource::<AttractMode>().is_none());
+
+        // Idle past the timeout starts attract mode
+        step(&mut app, 0.6);
+        assert!(app.world.get_resource::<AttractMode>().is_some());
+        assert_eq!(
+            app.world.resource::<NextState<GameState>>().0,
+            GameState::LoadingLevel
+        );
+
+        // Any input goes back to the main menu
+        app.world
+            .get_mut::<ActionState<MenuAction>>(input)
+            .unwrap()
+            .press(MenuAction::Confirm);
+        app.update();
+        assert_eq!(
+            app.world.resource::<NextState<GameState>>().0,
+            GameState::MainMenu
+        );
+    }
+}
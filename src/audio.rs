@@ -1,16 +1,32 @@
 // Multiple sounds can be played by one channel, but splitting music/effects is cleaner.
 // Also for cleanness (named channels have evident function), we don't use the default channel.
-use rand::{prelude::SliceRandom, thread_rng};
+use rand::{prelude::SliceRandom, Rng};
 
-use bevy::{prelude::*, utils::HashMap};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
 use bevy_egui::{egui::output::OutputEvent, EguiContext};
-use bevy_kira_audio::{AudioApp, AudioChannel, AudioControl, AudioSource};
+use bevy_kira_audio::{
+    AudioApp, AudioChannel, AudioControl, AudioInstance, AudioSource, PlaybackState,
+};
 use iyes_loopless::prelude::*;
 
 use crate::{
     animation::Animation,
+    attack::WhiffEvent,
     config::ENGINE_CONFIG,
-    metadata::{GameMeta, LevelHandle, LevelMeta},
+    fighter_state::{
+        AirAttacking, Chaining, Dying, Flopping, GroundSlam, HitStun, KnockedDown, MeleeAttacking,
+        MeleeWeapon, Moving, Punching, Shooting, SuperAttacking,
+    },
+    metadata::{
+        AudioSettings, FighterMeta, GameMeta, LevelHandle, LevelMeta, PlaylistOrder,
+        SoundVariationMeta, VoiceState,
+    },
+    movement::StopPointBlockedEvent,
+    platform::load_settings,
+    utils::{CosmeticRng, GameTime},
     GameState,
 };
 
@@ -23,12 +39,19 @@ pub struct MusicChannel;
 #[derive(Resource)]
 pub struct EffectsChannel;
 
+/// Sets the volume of the audio channels according to the [`AudioSettings`]
 pub fn set_audio_channels_volume(
     music_channel: Res<AudioChannel<MusicChannel>>,
     effects_channel: Res<AudioChannel<EffectsChannel>>,
+    audio_settings: Option<Res<AudioSettings>>,
 ) {
-    music_channel.set_volume(0.5);
-    effects_channel.set_volume(0.5);
+    let audio_settings = match audio_settings {
+        Some(audio_settings) if audio_settings.is_changed() => audio_settings,
+        _ => return,
+    };
+
+    music_channel.set_volume(audio_settings.music_volume.clamp(0.0, 1.0));
+    effects_channel.set_volume(audio_settings.effects_volume.clamp(0.0, 1.0));
 }
 
 pub struct AudioPlugin;
@@ -38,12 +61,33 @@ impl Plugin for AudioPlugin {
         app.add_plugin(bevy_kira_audio::AudioPlugin)
             .add_audio_channel::<MusicChannel>()
             .add_audio_channel::<EffectsChannel>()
-            .add_startup_system(set_audio_channels_volume)
+            .add_system(set_audio_channels_volume.after(load_settings))
             .add_enter_system(GameState::InGame, play_level_music)
             .add_exit_system(GameState::InGame, stop_level_music)
+            .add_system(
+                advance_playlist
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<MusicDirector>(),
+            )
             .add_system_to_stage(
                 CoreStage::PostUpdate,
                 animation_audio_playback.run_in_state(GameState::InGame),
+            )
+            .add_event::<FootstepEvent>()
+            .add_event::<VoiceEvent>()
+            .add_system(stop_point_blocked_sound.run_in_state(GameState::InGame))
+            .add_system(footstep_strides.run_in_state(GameState::InGame))
+            .add_system(whiff_sounds.run_in_state(GameState::InGame))
+            .add_system(
+                footstep_sounds
+                    .run_in_state(GameState::InGame)
+                    .after(footstep_strides),
+            )
+            .add_system(voice_lines.run_in_state(GameState::InGame))
+            .add_system(
+                voice_sounds
+                    .run_in_state(GameState::InGame)
+                    .after(voice_lines),
             );
     }
 }
@@ -66,12 +110,63 @@ impl AnimationAudioPlayback {
     }
 }
 
+/// The playback rate and volume a sound is played with
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct SoundPlayback {
+    playback_rate: f64,
+    volume: f64,
+}
+
+impl SoundPlayback {
+    /// Pick the playback of a sound at random within its variation, relative to the volume of the
+    /// effects
+    fn new(variation: Option<&SoundVariationMeta>, volume: f64, rng: &mut impl Rng) -> Self {
+        match variation {
+            Some(variation) => {
+                let pitch = variation.pitch.abs().min(1.0);
+                Self {
+                    playback_rate: 1.0 + rng.gen_range(-pitch..=pitch),
+                    volume: volume * (1.0 - rng.gen_range(0.0..=variation.volume.clamp(0.0, 1.0))),
+                }
+            }
+            None => Self {
+                playback_rate: 1.0,
+                volume,
+            },
+        }
+    }
+
+    fn play(self, effects_channel: &AudioChannel<EffectsChannel>, sound: Handle<AudioSource>) {
+        effects_channel
+            .play(sound)
+            .with_playback_rate(self.playback_rate)
+            .with_volume(self.volume);
+    }
+}
+
+/// Get the volume of the effects, which varied sounds are played relative to
+fn effects_volume(audio_settings: Option<&AudioSettings>) -> f64 {
+    audio_settings.map_or(1.0, |audio_settings| {
+        audio_settings.effects_volume.clamp(0.0, 1.0) as f64
+    })
+}
+
 pub fn animation_audio_playback(
     mut commands: Commands,
-    mut query: Query<(Entity, &Animation, &mut AnimationAudioPlayback)>,
+    mut query: Query<(
+        Entity,
+        &Animation,
+        &mut AnimationAudioPlayback,
+        Option<&Handle<FighterMeta>>,
+    )>,
+    fighter_assets: Res<Assets<FighterMeta>>,
     effects_channel: Res<AudioChannel<EffectsChannel>>,
+    audio_settings: Option<Res<AudioSettings>>,
+    mut rng: ResMut<CosmeticRng>,
 ) {
-    for (entity, animation, mut state_effects) in query.iter_mut() {
+    let volume = effects_volume(audio_settings.as_deref());
+
+    for (entity, animation, mut state_effects, fighter_handle) in query.iter_mut() {
         // The safest way to remove the sound component is on the next state, because the component
         // can be remove only at the last frame of animation, which in theory, may be skipped if
         // there is an unexpected lag.
@@ -88,7 +183,12 @@ pub fn animation_audio_playback(
                 if state_effects.last_played.unwrap_or(IMPOSSIBLE_ANIMATION_I)
                     != fighter_animation_i
                 {
-                    effects_channel.play(audio_handle.clone());
+                    // The sounds of fighters vary every time they play
+                    let variation = fighter_handle
+                        .and_then(|handle| fighter_assets.get(handle))
+                        .and_then(|fighter| fighter.audio.variation.as_ref());
+                    SoundPlayback::new(variation, volume, &mut **rng)
+                        .play(&effects_channel, audio_handle.clone());
                     state_effects.last_played = Some(fighter_animation_i);
                 }
             }
@@ -96,11 +196,249 @@ pub fn animation_audio_playback(
     }
 }
 
+/// Times the strides of a moving fighter. It is removed when the fighter stops moving, so that
+/// the first footstep is heard as soon as it starts moving again.
+#[derive(Component)]
+pub struct FootstepStride(pub Timer);
+
+/// Sent when a moving fighter takes a step
+pub struct FootstepEvent {
+    pub position: Vec2,
+    pub sound: Handle<AudioSource>,
+}
+
+/// Sends a [`FootstepEvent`] on every stride of the moving fighters that have footstep sounds
+pub fn footstep_strides(
+    mut commands: Commands,
+    mut fighters: Query<(
+        Entity,
+        &Handle<FighterMeta>,
+        &Transform,
+        Option<&Moving>,
+        Option<&mut FootstepStride>,
+    )>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    mut footstep_events: EventWriter<FootstepEvent>,
+    mut rng: ResMut<CosmeticRng>,
+    time: GameTime,
+) {
+    for (entity, fighter_handle, transform, moving, stride) in &mut fighters {
+        let footsteps = fighter_assets
+            .get(fighter_handle)
+            .and_then(|fighter| fighter.audio.footsteps.as_ref());
+        let footsteps = match (footsteps, moving) {
+            (Some(footsteps), Some(_)) => footsteps,
+            _ => {
+                if stride.is_some() {
+                    commands.entity(entity).remove::<FootstepStride>();
+                }
+                continue;
+            }
+        };
+
+        // The first step is taken right away, then one every stride
+        let steps = match stride {
+            Some(mut stride) => {
+                stride.0.tick(time.delta());
+                stride.0.times_finished_this_tick()
+            }
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(FootstepStride(Timer::from_seconds(
+                        footsteps.cadence,
+                        TimerMode::Repeating,
+                    )));
+                1
+            }
+        };
+
+        for _ in 0..steps {
+            if let Some(sound) = footsteps.sound_handles.choose(&mut **rng) {
+                footstep_events.send(FootstepEvent {
+                    position: transform.translation.truncate(),
+                    sound: sound.clone_weak(),
+                });
+            }
+        }
+    }
+}
+
+/// Plays the footstep sounds
+pub fn footstep_sounds(
+    mut events: EventReader<FootstepEvent>,
+    effects_channel: Res<AudioChannel<EffectsChannel>>,
+) {
+    for event in events.iter() {
+        effects_channel.play(event.sound.clone());
+    }
+}
+
+/// Keeps a fighter quiet until its last voice clip's cooldown is over
+#[derive(Component)]
+pub struct VoiceCooldown(pub Timer);
+
+/// Sent when a fighter shouts one of its voice clips
+pub struct VoiceEvent {
+    pub fighter: Entity,
+    pub state: VoiceState,
+    pub sound: Handle<AudioSource>,
+}
+
+/// Sends a [`VoiceEvent`] when a fighter with voice clips enters one of the voiced states, unless
+/// it is still in the cooldown of its last one
+pub fn voice_lines(
+    mut commands: Commands,
+    mut fighters: Query<(&Handle<FighterMeta>, Option<&mut VoiceCooldown>)>,
+    attacking: Query<
+        Entity,
+        Or<(
+            Added<Punching>,
+            Added<Chaining>,
+            Added<Flopping>,
+            Added<SuperAttacking>,
+            Added<AirAttacking>,
+            Added<MeleeAttacking>,
+            Added<Shooting>,
+            Added<GroundSlam>,
+        )>,
+    >,
+    hurt: Query<Entity, Or<(Added<HitStun>, Added<KnockedDown>)>>,
+    dying: Query<Entity, Added<Dying>>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    mut voice_events: EventWriter<VoiceEvent>,
+    mut rng: ResMut<CosmeticRng>,
+    time: GameTime,
+) {
+    for (_, cooldown) in &mut fighters {
+        if let Some(mut cooldown) = cooldown {
+            cooldown.0.tick(time.delta());
+        }
+    }
+
+    let entered = dying
+        .iter()
+        .map(|entity| (entity, VoiceState::Dying))
+        .chain(hurt.iter().map(|entity| (entity, VoiceState::Hurt)))
+        .chain(
+            attacking
+                .iter()
+                .map(|entity| (entity, VoiceState::Attacking)),
+        );
+    // Fighters entering several states at once only shout the first one
+    let mut voiced = HashSet::new();
+    for (entity, state) in entered {
+        if voiced.contains(&entity) {
+            continue;
+        }
+        let (fighter_handle, cooldown) = match fighters.get_mut(entity) {
+            Ok(fighter) => fighter,
+            Err(_) => continue,
+        };
+        let voices = match fighter_assets
+            .get(fighter_handle)
+            .and_then(|fighter| fighter.audio.voices.as_ref())
+        {
+            Some(voices) => voices,
+            None => continue,
+        };
+        if state != VoiceState::Dying
+            && matches!(&cooldown, Some(cooldown) if !cooldown.0.finished())
+        {
+            continue;
+        }
+
+        let sound = voices
+            .state_handles
+            .get(&state)
+            .and_then(|sounds| sounds.choose(&mut **rng));
+        if let Some(sound) = sound {
+            voice_events.send(VoiceEvent {
+                fighter: entity,
+                state,
+                sound: sound.clone_weak(),
+            });
+            voiced.insert(entity);
+
+            let timer = Timer::from_seconds(voices.cooldown, TimerMode::Once);
+            match cooldown {
+                Some(mut cooldown) => cooldown.0 = timer,
+                None => {
+                    commands.entity(entity).insert(VoiceCooldown(timer));
+                }
+            }
+        }
+    }
+}
+
+/// Plays the voice clips of the fighters, varied like their other sounds
+pub fn voice_sounds(
+    mut events: EventReader<VoiceEvent>,
+    fighters: Query<&Handle<FighterMeta>>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    effects_channel: Res<AudioChannel<EffectsChannel>>,
+    audio_settings: Option<Res<AudioSettings>>,
+    mut rng: ResMut<CosmeticRng>,
+) {
+    let volume = effects_volume(audio_settings.as_deref());
+
+    for event in events.iter() {
+        let variation = fighters
+            .get(event.fighter)
+            .ok()
+            .and_then(|handle| fighter_assets.get(handle))
+            .and_then(|fighter| fighter.audio.variation.as_ref());
+        SoundPlayback::new(variation, volume, &mut **rng)
+            .play(&effects_channel, event.sound.clone());
+    }
+}
+
+/// Plays the whiff sounds of the attacks that didn't hit anything, of the weapon the attack was
+/// made with, or of the fighter
+pub fn whiff_sounds(
+    mut events: EventReader<WhiffEvent>,
+    weapons: Query<&MeleeWeapon>,
+    fighters: Query<&Handle<FighterMeta>>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    effects_channel: Res<AudioChannel<EffectsChannel>>,
+) {
+    for event in events.iter() {
+        let sound = if let Ok(weapon) = weapons.get(event.attacker) {
+            weapon.audio.whiff_handle.as_ref()
+        } else {
+            fighters
+                .get(event.attacker)
+                .ok()
+                .and_then(|handle| fighter_assets.get(handle))
+                .and_then(|fighter| fighter.audio.whiff_handle.as_ref())
+        };
+
+        if let Some(sound) = sound {
+            effects_channel.play(sound.clone_weak());
+        }
+    }
+}
+
+/// Plays the stop point blocked sound when a player starts pushing against an active stop point
+pub fn stop_point_blocked_sound(
+    game: Res<GameMeta>,
+    mut events: EventReader<StopPointBlockedEvent>,
+    effects_channel: Res<AudioChannel<EffectsChannel>>,
+) {
+    // Only play the sound once, even if multiple players got blocked at the same time
+    if events.iter().count() > 0 {
+        if let Some(sound) = &game.stop_point.blocked_sound_handle {
+            effects_channel.play(sound.clone_weak());
+        }
+    }
+}
+
 /// Plays main menu sounds
 pub fn main_menu_sounds(
     game: Res<GameMeta>,
     mut context: ResMut<EguiContext>,
     effects_channel: Res<AudioChannel<EffectsChannel>>,
+    mut rng: ResMut<CosmeticRng>,
 ) {
     for event in &context.ctx_mut().output().events {
         if let OutputEvent::Clicked(info) = event {
@@ -115,7 +453,7 @@ pub fn main_menu_sounds(
                     effects_channel.play(
                         game.main_menu
                             .button_sound_handles
-                            .choose(&mut thread_rng())
+                            .choose(&mut **rng)
                             .expect("No button sounds")
                             .clone_weak(),
                     );
@@ -138,16 +476,326 @@ pub fn stop_menu_music(music_channel: Res<AudioChannel<MusicChannel>>) {
     music_channel.stop();
 }
 
+/// Picks the level music, going through the tracks of the level's playlist when it has one
+#[derive(Resource, Default)]
+pub struct MusicDirector {
+    /// The level music, followed by the tracks of the playlist
+    tracks: Vec<Handle<AudioSource>>,
+    order: PlaylistOrder,
+    /// The indices of the tracks in the order of the current pass through the playlist
+    queue: Vec<usize>,
+    /// The position in the queue of the track playing
+    position: usize,
+    /// The instance of the track playing
+    instance: Option<Handle<AudioInstance>>,
+}
+
+impl MusicDirector {
+    pub fn new(level: &LevelMeta) -> Self {
+        let mut tracks = vec![level.music_handle.clone()];
+        let mut order = PlaylistOrder::default();
+        if let Some(playlist) = &level.playlist {
+            tracks.extend(playlist.track_handles.iter().cloned());
+            order = playlist.order;
+        }
+
+        Self {
+            tracks,
+            order,
+            ..default()
+        }
+    }
+
+    /// Whether there are other tracks to move on to, rather than looping the level music
+    pub fn is_playlist(&self) -> bool {
+        self.tracks.len() > 1
+    }
+
+    /// Move on to the next track, starting the playlist over after the last one, shuffled with
+    /// `rng` if it plays in random order
+    fn next_track(&mut self, rng: &mut impl Rng) -> Handle<AudioSource> {
+        self.position += 1;
+        if self.position >= self.queue.len() {
+            let last = self.queue.last().copied();
+            self.queue = (0..self.tracks.len()).collect();
+            if self.order == PlaylistOrder::Shuffle {
+                self.queue.shuffle(rng);
+
+                // Don't play the same track twice in a row when starting over
+                if self.queue.len() > 1 && self.queue.first().copied() == last {
+                    let end = self.queue.len() - 1;
+                    self.queue.swap(0, end);
+                }
+            }
+            self.position = 0;
+        }
+
+        self.tracks[self.queue[self.position]].clone()
+    }
+
+    /// Get the track to start, if the track playing has ended
+    fn on_playback(
+        &mut self,
+        state: PlaybackState,
+        rng: &mut impl Rng,
+    ) -> Option<Handle<AudioSource>> {
+        matches!(state, PlaybackState::Stopped).then(|| self.next_track(rng))
+    }
+}
+
 pub fn play_level_music(
+    mut commands: Commands,
     level_handle: Res<LevelHandle>,
     assets: Res<Assets<LevelMeta>>,
     music_channel: Res<AudioChannel<MusicChannel>>,
+    mut rng: ResMut<CosmeticRng>,
 ) {
     if let Some(level) = assets.get(&level_handle) {
-        music_channel.play(level.music_handle.clone());
+        let mut director = MusicDirector::new(level);
+        if director.is_playlist() {
+            director.instance = Some(music_channel.play(director.next_track(&mut **rng)).handle());
+        } else {
+            music_channel
+                .play(level.music_handle.clone())
+                .looped()
+                .loop_from(level_music_loop_start(level));
+        }
+        commands.insert_resource(director);
     }
 }
 
-pub fn stop_level_music(music_channel: Res<AudioChannel<MusicChannel>>) {
+/// Starts the next track of the level's playlist once the one playing ends
+fn advance_playlist(
+    mut director: ResMut<MusicDirector>,
+    music_channel: Res<AudioChannel<MusicChannel>>,
+    mut rng: ResMut<CosmeticRng>,
+) {
+    let state = match &director.instance {
+        Some(instance) => music_channel.state(instance),
+        None => return,
+    };
+
+    if let Some(track) = director.on_playback(state, &mut **rng) {
+        director.instance = Some(music_channel.play(track).handle());
+    }
+}
+
+/// Get the position, in seconds, the level music loops back to, after playing its intro once
+fn level_music_loop_start(level: &LevelMeta) -> f64 {
+    level.music_loop_start.unwrap_or_default().max(0.0)
+}
+
+pub fn stop_level_music(mut commands: Commands, music_channel: Res<AudioChannel<MusicChannel>>) {
     music_channel.stop();
+    commands.remove_resource::<MusicDirector>();
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use bevy::asset::HandleId;
+
+    use crate::metadata::{FootstepsMeta, PlaylistMeta, VoicesMeta};
+
+    #[test]
+    fn test_level_music_loops_after_its_intro() {
+        let beach = include_str!("../assets/levels/1_beach/beach.level.yaml");
+
+        // Tracks without an intro loop in full, from the start
+        let level: LevelMeta = serde_yaml::from_str(beach).unwrap();
+        assert_eq!(level.music_loop_start, None);
+        assert_eq!(level_music_loop_start(&level), 0.0);
+
+        // Tracks with an intro loop back to the end of it
+        let mut level: LevelMeta =
+            serde_yaml::from_str(&format!("music_loop_start: 12.5\n{beach}")).unwrap();
+        assert_eq!(level_music_loop_start(&level), 12.5);
+
+        // A loop start before the start of the track loops the whole track
+        level.music_loop_start = Some(-3.);
+        assert_eq!(level_music_loop_start(&level), 0.0);
+    }
+
+    #[test]
+    fn test_sequential_playlist_moves_to_the_next_track() {
+        let mut level: LevelMeta =
+            serde_yaml::from_str(include_str!("../assets/levels/1_beach/beach.level.yaml"))
+                .unwrap();
+        let first = Handle::<AudioSource>::weak(HandleId::from("first.mp3"));
+        let second = Handle::<AudioSource>::weak(HandleId::from("second.mp3"));
+        level.music_handle = first.clone();
+
+        // A single track loops instead
+        assert!(!MusicDirector::new(&level).is_playlist());
+
+        level.playlist = Some(PlaylistMeta {
+            tracks: vec!["second.mp3".into()],
+            track_handles: vec![second.clone()],
+            order: PlaylistOrder::Sequential,
+        });
+        let mut director = MusicDirector::new(&level);
+        let mut rng = CosmeticRng::new(7);
+        assert!(director.is_playlist());
+        assert_eq!(director.next_track(&mut *rng), first);
+
+        // The first track keeps playing until it ends, then the second one starts
+        assert_eq!(director.on_playback(PlaybackState::Queued, &mut *rng), None);
+        assert_eq!(
+            director.on_playback(PlaybackState::Stopped, &mut *rng),
+            Some(second)
+        );
+
+        // After the last track, the playlist starts over
+        assert_eq!(
+            director.on_playback(PlaybackState::Stopped, &mut *rng),
+            Some(first)
+        );
+    }
+
+    #[test]
+    fn test_sound_variation_stays_within_its_range() {
+        let variation = SoundVariationMeta {
+            pitch: 0.1,
+            volume: 0.2,
+        };
+        let mut rng = CosmeticRng::new(7);
+
+        let playbacks = (0..20)
+            .map(|_| SoundPlayback::new(Some(&variation), 0.5, &mut *rng))
+            .collect::<Vec<_>>();
+        for playback in &playbacks {
+            assert!((0.9..=1.1).contains(&playback.playback_rate));
+            assert!((0.4..=0.5).contains(&playback.volume));
+        }
+        assert!(playbacks
+            .iter()
+            .any(|playback| playback.playback_rate != playbacks[0].playback_rate));
+
+        // The same seed always picks the same playbacks
+        let mut rng = CosmeticRng::new(7);
+        assert_eq!(
+            SoundPlayback::new(Some(&variation), 0.5, &mut *rng),
+            playbacks[0]
+        );
+
+        // Sounds without a variation always play the same
+        assert_eq!(
+            SoundPlayback::new(None, 0.5, &mut *rng),
+            SoundPlayback {
+                playback_rate: 1.0,
+                volume: 0.5
+            }
+        );
+    }
+
+    #[test]
+    fn test_footsteps_follow_the_cadence() {
+        let mut app = App::new();
+        let mut time = Time::default();
+        time.update();
+        app.insert_resource(time)
+            .init_resource::<Assets<FighterMeta>>()
+            .init_resource::<CosmeticRng>()
+            .add_event::<FootstepEvent>()
+            .add_system(footstep_strides);
+
+        let mut fighter: FighterMeta = serde_yaml::from_str(include_str!(
+            "../assets/fighters/bandit/bandit.fighter.yaml"
+        ))
+        .unwrap();
+        fighter.audio.footsteps = Some(FootstepsMeta {
+            sounds: vec!["step.ogg".into()],
+            sound_handles: vec![Handle::default()],
+            cadence: 0.25,
+        });
+        let fighter_handle = app.world.resource_mut::<Assets<FighterMeta>>().add(fighter);
+        let fighter = app
+            .world
+            .spawn((fighter_handle, Transform::default(), Moving::default()))
+            .id();
+
+        let mut step = |app: &mut App, seconds: f32| {
+            let mut time = app.world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(seconds));
+            app.update();
+            app.world
+                .resource_mut::<Events<FootstepEvent>>()
+                .drain()
+                .count()
+        };
+
+        // A step when starting to move, then one every 0.25 seconds
+        let mut footsteps = step(&mut app, 0.125);
+        for _ in 0..8 {
+            footsteps += step(&mut app, 0.125);
+        }
+        assert_eq!(footsteps, 5);
+
+        // Standing still is silent, and the stride starts over when moving again
+        app.world.entity_mut(fighter).remove::<Moving>();
+        assert_eq!(step(&mut app, 0.125), 0);
+        assert_eq!(step(&mut app, 0.125), 0);
+        app.world.entity_mut(fighter).insert(Moving::default());
+        assert_eq!(step(&mut app, 0.125), 1);
+        assert_eq!(step(&mut app, 0.125), 0);
+    }
+
+    #[test]
+    fn test_hurt_voice_waits_for_its_cooldown() {
+        let mut app = App::new();
+        let mut time = Time::default();
+        time.update();
+        app.insert_resource(time)
+            .init_resource::<Assets<FighterMeta>>()
+            .init_resource::<CosmeticRng>()
+            .add_event::<VoiceEvent>()
+            .add_system(voice_lines);
+
+        let mut fighter: FighterMeta = serde_yaml::from_str(include_str!(
+            "../assets/fighters/bandit/bandit.fighter.yaml"
+        ))
+        .unwrap();
+        fighter.audio.voices = Some(VoicesMeta {
+            states: HashMap::from_iter([(VoiceState::Hurt, vec!["hurt.ogg".into()])]),
+            state_handles: HashMap::from_iter([(VoiceState::Hurt, vec![Handle::default()])]),
+            cooldown: 1.0,
+        });
+        let fighter_handle = app.world.resource_mut::<Assets<FighterMeta>>().add(fighter);
+        let fighter = app.world.spawn(fighter_handle).id();
+
+        let step = |app: &mut App, seconds: f32| {
+            let mut time = app.world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(seconds));
+            app.update();
+            app.world
+                .resource_mut::<Events<VoiceEvent>>()
+                .drain()
+                .map(|event| event.state)
+                .collect::<Vec<_>>()
+        };
+        let hurt = |app: &mut App| {
+            app.world
+                .entity_mut(fighter)
+                .remove::<HitStun>()
+                .insert(HitStun::default());
+        };
+
+        // Getting hurt shouts the hurt voice clip
+        hurt(&mut app);
+        assert_eq!(step(&mut app, 0.1), vec![VoiceState::Hurt]);
+        assert!(step(&mut app, 0.1).is_empty());
+
+        // But not again while its cooldown is running
+        hurt(&mut app);
+        assert!(step(&mut app, 0.3).is_empty());
+
+        // Until the cooldown is over
+        step(&mut app, 1.0);
+        hurt(&mut app);
+        assert_eq!(step(&mut app, 0.1), vec![VoiceState::Hurt]);
+    }
 }
@@ -1,8 +1,26 @@
 use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+use bevy_kira_audio::{AudioChannel, AudioControl};
 use bevy_parallax::ParallaxMoveEvent;
 use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::ActionState;
+use rand::Rng;
 
-use crate::{consts, metadata::GameMeta, movement::VelocitySystems, GameState, Player};
+use crate::{
+    audio::EffectsChannel,
+    consts,
+    enemy::{Boss, TripPointX},
+    fighter_state::{BossBombThrow, GroundSlam},
+    input::MenuAction,
+    metadata::{
+        AccessibilitySettings, CameraWaypointMeta, FighterMeta, GameMeta, LevelBoundsMeta,
+        LevelMeta,
+    },
+    movement::VelocitySystems,
+    player::LivingPlayer,
+    utils::{CosmeticRng, GameTime},
+    GameState, Player,
+};
 
 pub struct CameraPlugin;
 
@@ -10,52 +28,354 @@ impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app
             // Register reflect types
+            .register_type::<SortLayer>()
             .register_type::<YSort>()
+            .init_resource::<CameraShake>()
+            .init_resource::<CameraTilt>()
             // Add systems
+            .add_system_to_stage(CoreStage::First, reset_camera_shake)
+            .add_system_to_stage(CoreStage::First, reset_camera_tilt)
             .add_system_set_to_stage(
                 CoreStage::PostUpdate,
                 ConditionSet::new()
                     .run_in_state(GameState::InGame)
                     .after(VelocitySystems)
-                    .with_system(camera_follow_player)
+                    .with_system(camera_zoom_to_players.before(camera_follow_player))
+                    .with_system(
+                        camera_follow_player
+                            .run_unless_resource_exists::<CameraIntro>()
+                            .run_unless_resource_exists::<BossCameraFocus>()
+                            .run_unless_resource_exists::<BossIntro>(),
+                    )
+                    .with_system(start_boss_camera_focus)
+                    .with_system(
+                        camera_boss_focus
+                            .run_unless_resource_exists::<CameraIntro>()
+                            .run_unless_resource_exists::<BossIntro>()
+                            .run_if_resource_exists::<BossCameraFocus>(),
+                    )
+                    .with_system(
+                        start_boss_intro
+                            .run_unless_resource_exists::<CameraIntro>()
+                            .run_unless_resource_exists::<BossIntro>(),
+                    )
+                    .with_system(boss_intro.run_if_resource_exists::<BossIntro>())
                     .with_system(y_sort)
                     .into(),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                camera_shake
+                    .run_in_state(GameState::InGame)
+                    .after(camera_follow_player)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                camera_tilt
+                    .run_in_state(GameState::InGame)
+                    .after(camera_shake)
+                    .before(TransformSystem::TransformPropagate),
+            )
+            .add_system(
+                camera_intro
+                    .run_in_state(GameState::InGame)
+                    .run_if_resource_exists::<CameraIntro>(),
             );
     }
 }
 
-/// Component to sort entities by their y position.
-/// Takes in a base value usually the sprite default Z with possibly an height offset.
-/// this value could be tweaked to implement virtual Z for jumping
+/// Resource present while the camera pans through the level intro.
+///
+/// The players don't have control and the camera doesn't follow them until the intro is over.
+#[derive(Resource)]
+pub struct CameraIntro {
+    pub waypoints: Vec<CameraWaypointMeta>,
+    /// The camera x position when the intro started, which it goes back to when it is over
+    pub start_x: Option<f32>,
+    /// Time elapsed since the start of the intro
+    pub elapsed: f32,
+}
+
+impl CameraIntro {
+    pub fn new(waypoints: Vec<CameraWaypointMeta>) -> Self {
+        Self {
+            waypoints,
+            start_x: None,
+            elapsed: 0.,
+        }
+    }
+
+    /// Get the camera x position after `elapsed` seconds, or `None` if the intro is over
+    pub fn camera_x(&self, start_x: f32, elapsed: f32) -> Option<f32> {
+        let mut from_x = start_x;
+        let mut time = elapsed;
+
+        for waypoint in &self.waypoints {
+            if time < waypoint.duration {
+                return Some(from_x + (waypoint.x - from_x) * time / waypoint.duration);
+            }
+
+            time -= waypoint.duration;
+            from_x = waypoint.x;
+        }
+
+        None
+    }
+}
+
+/// Pans the camera through the level intro, which can be skipped by pressing any menu button.
+///
+/// Once the intro is over the camera goes back to where it started and normal follow resumes.
+pub fn camera_intro(
+    mut commands: Commands,
+    mut intro: ResMut<CameraIntro>,
+    camera_query: Query<&Transform, With<Camera>>,
+    menu_input: Query<&ActionState<MenuAction>>,
+    mut move_event_writer: EventWriter<ParallaxMoveEvent>,
+    time: GameTime,
+) {
+    let camera = camera_query.single();
+    let start_x = *intro.start_x.get_or_insert(camera.translation.x);
+    intro.elapsed += time.delta_seconds();
+
+    let skipped = menu_input
+        .iter()
+        .any(|input| !input.get_just_pressed().is_empty());
+
+    let target_x = match intro.camera_x(start_x, intro.elapsed) {
+        Some(x) if !skipped => x,
+        _ => {
+            commands.remove_resource::<CameraIntro>();
+            start_x
+        }
+    };
+
+    // The x axis is handled by the parallax plugin.
+    move_event_writer.send(ParallaxMoveEvent {
+        camera_move_speed: target_x - camera.translation.x,
+    });
+}
+
+/// Marker for the bosses whose intro has been played, so that it only plays once
+#[derive(Component)]
+pub struct BossIntroPlayed;
+
+/// Resource present while the camera frames a boss that showed up, under its name banner.
+///
+/// This is distinct from the [`CameraIntro`] of the level. The players don't have control and the
+/// camera doesn't follow them until the intro is over.
+#[derive(Resource)]
+pub struct BossIntro {
+    pub boss: Entity,
+    /// The localization key of the boss's name banner
+    pub banner: String,
+    /// How long, in seconds, the camera frames the boss
+    pub duration: f32,
+    /// The camera x position when the intro started, which it goes back to when it is over
+    pub start_x: Option<f32>,
+    /// Time elapsed since the start of the intro
+    pub elapsed: f32,
+}
+
+impl BossIntro {
+    /// Whether the camera is done framing the boss, and is going back to where it started
+    pub fn is_returning(&self) -> bool {
+        self.elapsed >= self.duration
+    }
+}
+
+/// Starts the intro of a boss once the players reach its trip point, playing its sound
+fn start_boss_intro(
+    mut commands: Commands,
+    bosses: Query<
+        (Entity, &TripPointX, &Handle<FighterMeta>),
+        (With<Boss>, Without<BossIntroPlayed>),
+    >,
+    player_query: Query<&Transform, LivingPlayer>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    effects_channel: Option<Res<AudioChannel<EffectsChannel>>>,
+) {
+    let max_player_x = match max_player_x(player_query.iter()) {
+        Some(x) => x,
+        None => return,
+    };
+
+    for (boss, trip_point_x, fighter_handle) in &bosses {
+        let fighter = match fighter_assets.get(fighter_handle) {
+            Some(fighter) => fighter,
+            None => continue,
+        };
+        if trip_point_x.0 != f32::MIN && max_player_x <= trip_point_x.0 {
+            continue;
+        }
+        commands.entity(boss).insert(BossIntroPlayed);
+
+        let meta = match &fighter.boss_intro {
+            Some(meta) => meta,
+            None => continue,
+        };
+        if let (Some(sound), Some(effects_channel)) = (&meta.sound_handle, &effects_channel) {
+            effects_channel.play(sound.clone_weak());
+        }
+        commands.insert_resource(BossIntro {
+            boss,
+            banner: meta.banner.clone(),
+            duration: meta.duration,
+            start_x: None,
+            elapsed: 0.,
+        });
+
+        // One intro at a time
+        return;
+    }
+}
+
+/// Frames the boss during its intro, which can be skipped by pressing any menu button.
+///
+/// Once the intro is over the camera goes back to where it started and normal follow resumes.
+fn boss_intro(
+    mut commands: Commands,
+    mut intro: ResMut<BossIntro>,
+    boss_query: Query<&Transform, With<Boss>>,
+    camera_query: Query<(&Transform, &OrthographicProjection), (With<Camera>, Without<Boss>)>,
+    menu_input: Query<&ActionState<MenuAction>>,
+    mut move_event_writer: EventWriter<ParallaxMoveEvent>,
+    level_meta: Res<LevelMeta>,
+    time: GameTime,
+) {
+    let (camera, projection) = match camera_query.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    let start_x = *intro.start_x.get_or_insert(camera.translation.x);
+    intro.elapsed += time.delta_seconds();
+
+    // Skipping the intro puts the camera straight back
+    let skipped = menu_input
+        .iter()
+        .any(|input| !input.get_just_pressed().is_empty());
+    if skipped {
+        commands.remove_resource::<BossIntro>();
+        move_event_writer.send(ParallaxMoveEvent {
+            camera_move_speed: start_x - camera.translation.x,
+        });
+        return;
+    }
+
+    let half_view_width = projection.right * projection.scale;
+    let target_x = match boss_query.get(intro.boss) {
+        Ok(boss) if !intro.is_returning() => match level_meta.bounds.right {
+            Some(right) => boss.translation.x.min(right - half_view_width),
+            None => boss.translation.x,
+        },
+        _ => {
+            // Hand the camera back to the normal follow once it is back where it started
+            if (camera.translation.x - start_x).abs() < 1. {
+                commands.remove_resource::<BossIntro>();
+                return;
+            }
+            start_x
+        }
+    };
+
+    // The x axis is handled by the parallax plugin.
+    move_event_writer.send(ParallaxMoveEvent {
+        camera_move_speed: (target_x - camera.translation.x) * consts::CAMERA_SPEED,
+    });
+}
+
+/// The band of draw order that a y-sorted entity stays in.
+///
+/// The z position of each band is configured by the game's [`DrawLayersMeta`].
+///
+/// [`DrawLayersMeta`]: crate::metadata::DrawLayersMeta
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Reflect)]
+pub enum SortLayer {
+    GroundItems,
+    #[default]
+    Fighters,
+    Projectiles,
+}
+
+/// Component to sort entities by their y position, within the band of their layer.
+///
+/// Must only be added to entities without a parent, since it sets their local z position.
 #[derive(Component, Default, Reflect)]
 #[reflect(Component)]
-pub struct YSort(pub f32);
+pub struct YSort(pub SortLayer);
 
 /// Applies the y-sorting to the entities Z position.
-pub fn y_sort(mut query: Query<(&mut Transform, &YSort)>) {
+pub fn y_sort(mut query: Query<(&mut Transform, &YSort)>, game: Res<GameMeta>) {
     for (mut transform, ysort) in query.iter_mut() {
-        transform.translation.z = ysort.0 - transform.translation.y;
+        transform.translation.z = game.draw_layers.z(ysort.0, transform.translation.y);
+    }
+}
+
+/// Get the x position of the rightmost of the given players, if any.
+pub fn max_player_x<'a>(players: impl Iterator<Item = &'a Transform>) -> Option<f32> {
+    players
+        .map(|transform| transform.translation.x)
+        .max_by(|ax, bx| ax.total_cmp(bx))
+}
+
+/// Get the horizontal distance between the leftmost and the rightmost of the given players.
+pub fn player_spread<'a>(players: impl Iterator<Item = &'a Transform>) -> f32 {
+    let (min_x, max_x) = players.fold((f32::MAX, f32::MIN), |(min_x, max_x), transform| {
+        (
+            min_x.min(transform.translation.x),
+            max_x.max(transform.translation.x),
+        )
+    });
+
+    (max_x - min_x).max(0.)
+}
+
+/// Zooms the camera out as the living players spread apart, and back in as they come together.
+pub fn camera_zoom_to_players(
+    player_query: Query<&Transform, LivingPlayer>,
+    mut camera_query: Query<&mut OrthographicProjection, With<Camera>>,
+    game_meta: Res<GameMeta>,
+    time: Res<Time>,
+) {
+    let target_zoom = game_meta
+        .camera_zoom
+        .zoom_for_spread(player_spread(player_query.iter()));
+
+    for mut projection in &mut camera_query {
+        // Ease towards the target zoom
+        let t = 1.0 - (-consts::CAMERA_ZOOM_SPEED * time.delta_seconds()).exp();
+        projection.scale += (target_zoom - projection.scale) * t;
     }
 }
 
 /// Moves the camera according to the RIGHT_BOUNDARY_DISTANCE. Note that this does not enforce
 /// limitations of any kind - that's up to the players movement logic (e.g. max distance).
+///
+/// The boundary is scaled with the camera zoom, so that it stays at the same place on screen.
+///
+/// Only living players are followed. If all the players are dying the camera stays where it is.
+///
+/// The camera doesn't show past the right bound of the level, if it has one.
 pub fn camera_follow_player(
-    player_query: Query<&Transform, With<Player>>,
-    camera_query: Query<&Transform, (With<Camera>, Without<Player>)>,
+    player_query: Query<&Transform, LivingPlayer>,
+    camera_query: Query<(&Transform, &OrthographicProjection), (With<Camera>, Without<Player>)>,
     mut move_event_writer: EventWriter<ParallaxMoveEvent>,
     game_meta: Res<GameMeta>,
+    level_meta: Res<LevelMeta>,
 ) {
-    let max_player_x = player_query
-        .iter()
-        .map(|transform| transform.translation.x)
-        .max_by(|ax, bx| ax.total_cmp(bx));
+    if let Some(max_player_x) = max_player_x(player_query.iter()) {
+        let (camera, projection) = camera_query.single();
 
-    if let Some(max_player_x) = max_player_x {
-        let camera = camera_query.single();
+        let mut max_player_x_diff = max_player_x
+            - camera.translation.x
+            - game_meta.camera_move_right_boundary * projection.scale;
 
-        let max_player_x_diff =
-            max_player_x - camera.translation.x - game_meta.camera_move_right_boundary;
+        if let Some(right) = level_meta.bounds.right {
+            let max_camera_x = right - projection.right * projection.scale;
+            max_player_x_diff =
+                max_player_x_diff.min((max_camera_x - camera.translation.x) / consts::CAMERA_SPEED);
+        }
 
         if max_player_x_diff > 0. {
             // The x axis is handled by the parallax plugin.
@@ -67,3 +387,742 @@ pub fn camera_follow_player(
         }
     }
 }
+
+/// Resource present while the camera is biased toward the boss starting a major attack.
+///
+/// Once the focus is over, the camera pans back to framing the players, and normal follow resumes.
+#[derive(Resource)]
+pub struct BossCameraFocus {
+    pub boss: Entity,
+    /// The camera x position when the focus started, which it pans back to unless the players have
+    /// moved on since
+    pub start_x: Option<f32>,
+    /// Time elapsed since the boss started the attack
+    pub elapsed: f32,
+}
+
+/// Get the camera x position biased toward the boss by `weight`, from the center of the players,
+/// while keeping every player at least `margin` away from the edges of the view.
+pub fn boss_focus_x(
+    min_player_x: f32,
+    max_player_x: f32,
+    boss_x: f32,
+    weight: f32,
+    half_view_width: f32,
+    margin: f32,
+) -> f32 {
+    let players_x = (min_player_x + max_player_x) / 2.;
+    let focus_x = players_x + (boss_x - players_x) * weight.clamp(0., 1.);
+
+    let min_x = max_player_x - half_view_width + margin;
+    let max_x = min_player_x + half_view_width - margin;
+    if min_x <= max_x {
+        focus_x.clamp(min_x, max_x)
+    } else {
+        players_x
+    }
+}
+
+/// Starts biasing the camera toward the boss when it starts a ground slam or a bomb throw
+fn start_boss_camera_focus(
+    mut commands: Commands,
+    bosses: Query<Entity, (With<Boss>, Or<(Added<GroundSlam>, Added<BossBombThrow>)>)>,
+    focus: Option<ResMut<BossCameraFocus>>,
+    game_meta: Res<GameMeta>,
+) {
+    if game_meta.boss_focus.is_none() {
+        return;
+    }
+    let boss = match bosses.iter().next() {
+        Some(boss) => boss,
+        None => return,
+    };
+
+    // A new attack during the focus extends it, still panning back to where it started
+    match focus {
+        Some(mut focus) => {
+            focus.boss = boss;
+            focus.elapsed = 0.;
+        }
+        None => commands.insert_resource(BossCameraFocus {
+            boss,
+            start_x: None,
+            elapsed: 0.,
+        }),
+    }
+}
+
+/// Moves the camera toward the boss while the [`BossCameraFocus`] lasts, then pans it back to
+/// framing the players before handing it back to [`camera_follow_player`].
+fn camera_boss_focus(
+    mut commands: Commands,
+    mut focus: ResMut<BossCameraFocus>,
+    player_query: Query<&Transform, LivingPlayer>,
+    boss_query: Query<&Transform, With<Boss>>,
+    camera_query: Query<(&Transform, &OrthographicProjection), (With<Camera>, Without<Player>)>,
+    mut move_event_writer: EventWriter<ParallaxMoveEvent>,
+    game_meta: Res<GameMeta>,
+    level_meta: Res<LevelMeta>,
+    time: GameTime,
+) {
+    let (camera, projection) = match camera_query.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    let start_x = *focus.start_x.get_or_insert(camera.translation.x);
+    focus.elapsed += time.delta_seconds();
+
+    let meta = match game_meta.boss_focus {
+        Some(meta) => meta,
+        None => {
+            commands.remove_resource::<BossCameraFocus>();
+            return;
+        }
+    };
+    let min_player_x = player_query
+        .iter()
+        .map(|transform| transform.translation.x)
+        .min_by(f32::total_cmp);
+    let max_player_x = max_player_x(player_query.iter());
+    let boss = boss_query.get(focus.boss).ok();
+
+    let half_view_width = projection.right * projection.scale;
+    let clamp_to_bounds = |x: f32| match level_meta.bounds.right {
+        Some(right) => x.min(right - half_view_width),
+        None => x,
+    };
+    let target_x = match (min_player_x, max_player_x, boss) {
+        (Some(min_player_x), Some(max_player_x), Some(boss)) if focus.elapsed < meta.duration => {
+            clamp_to_bounds(boss_focus_x(
+                min_player_x,
+                max_player_x,
+                boss.translation.x,
+                meta.weight,
+                half_view_width,
+                meta.player_margin,
+            ))
+        }
+        _ => {
+            // Pan back to where the camera started, or to where the normal follow would have taken
+            // it if the players have moved on since
+            let framing_x = max_player_x.map_or(start_x, |max_player_x| {
+                let follow_x =
+                    max_player_x - game_meta.camera_move_right_boundary * projection.scale;
+                clamp_to_bounds(start_x.max(follow_x))
+            });
+
+            // Then hand the camera back to the normal follow
+            if (camera.translation.x - framing_x).abs() < 1. {
+                commands.remove_resource::<BossCameraFocus>();
+                return;
+            }
+            framing_x
+        }
+    };
+
+    // The x axis is handled by the parallax plugin.
+    move_event_writer.send(ParallaxMoveEvent {
+        camera_move_speed: (target_x - camera.translation.x) * consts::CAMERA_SPEED,
+    });
+}
+
+/// Resource for shaking the camera, such as for explosions.
+///
+/// The shake is added on top of where the camera would otherwise be at the end of the frame, and
+/// taken back at the start of the next one, so that the rest of the game never sees it.
+#[derive(Resource, Default)]
+pub struct CameraShake {
+    /// The strength of the shake, from 0 to 1, which decays over time
+    pub trauma: f32,
+    /// The offset currently applied to the camera
+    offset: Vec2,
+}
+
+impl CameraShake {
+    /// Shake the camera harder, up to full strength
+    pub fn add_trauma(&mut self, trauma: f32) {
+        self.trauma = (self.trauma + trauma).min(1.0);
+    }
+}
+
+/// Resource for rolling the camera on heavy impacts, such as ground slams and explosions.
+///
+/// Like the [`CameraShake`], the tilt is applied at the end of the frame and taken back at the
+/// start of the next one.
+#[derive(Resource, Default)]
+pub struct CameraTilt {
+    /// The strength of the current tilt, from 0 to 1
+    pub strength: f32,
+    /// How long, in seconds, the camera has been easing back to level for
+    pub elapsed: f32,
+    /// Whether the current tilt rolls the camera clockwise. Alternates with every impact.
+    clockwise: bool,
+    /// The angle, in radians, currently applied to the camera
+    angle: f32,
+}
+
+impl CameraTilt {
+    /// Roll the camera, easing back to level from the start
+    pub fn add_tilt(&mut self, strength: f32) {
+        self.strength = strength.min(1.0);
+        self.elapsed = 0.;
+        self.clockwise = !self.clockwise;
+    }
+}
+
+/// Dampen a camera shake offset so that the view doesn't show past the right bound of the level.
+///
+/// The shake toward the bound fades out as the right edge of the view comes within `edge_falloff`
+/// of it, and never pushes the view past it.
+pub fn dampen_shake_offset(
+    offset: Vec2,
+    view_right: f32,
+    bounds: &LevelBoundsMeta,
+    edge_falloff: f32,
+) -> Vec2 {
+    match bounds.right {
+        Some(right) if offset.x > 0. => {
+            let room = (right - view_right).max(0.);
+            let dampening = (room / edge_falloff.max(f32::EPSILON)).min(1.0);
+
+            Vec2::new((offset.x * dampening).min(room), offset.y)
+        }
+        _ => offset,
+    }
+}
+
+/// Takes back the camera shake of the last frame
+fn reset_camera_shake(
+    mut shake: ResMut<CameraShake>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+) {
+    if shake.offset == Vec2::ZERO {
+        return;
+    }
+
+    for mut transform in &mut camera_query {
+        transform.translation -= shake.offset.extend(0.);
+    }
+    shake.offset = Vec2::ZERO;
+}
+
+/// Shakes the camera according to the [`CameraShake`], within the bounds of the level
+fn camera_shake(
+    mut shake: ResMut<CameraShake>,
+    mut camera_query: Query<(&mut Transform, &OrthographicProjection), With<Camera>>,
+    game_meta: Res<GameMeta>,
+    level_meta: Res<LevelMeta>,
+    accessibility: Option<Res<AccessibilitySettings>>,
+    mut rng: ResMut<CosmeticRng>,
+    time: Res<Time>,
+) {
+    if shake.trauma <= 0. {
+        return;
+    }
+    let meta = &game_meta.camera_shake;
+    let (mut transform, projection) = match camera_query.get_single_mut() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+
+    // Squaring the trauma makes small shakes subtle, and big ones fall off quickly
+    let direction = Vec2::new(rng.gen_range(-1.0..=1.0), rng.gen_range(-1.0..=1.0));
+    let strength = accessibility.map_or(1.0, |settings| settings.screen_shake.clamp(0.0, 1.0));
+    let offset = direction * meta.max_offset * shake.trauma * shake.trauma * strength;
+
+    let view_right = transform.translation.x + projection.right * projection.scale;
+    let offset = dampen_shake_offset(offset, view_right, &level_meta.bounds, meta.edge_falloff);
+
+    transform.translation += offset.extend(0.);
+    shake.offset = offset;
+    shake.trauma = (shake.trauma - meta.decay * time.delta_seconds()).max(0.);
+}
+
+/// Levels the camera back from the tilt of the last frame
+fn reset_camera_tilt(
+    mut tilt: ResMut<CameraTilt>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+) {
+    if tilt.angle == 0. {
+        return;
+    }
+
+    // Nothing else rotates the camera, so it is set back to exactly level
+    for mut transform in &mut camera_query {
+        transform.rotation = Quat::IDENTITY;
+    }
+    tilt.angle = 0.;
+}
+
+/// Rolls the camera according to the [`CameraTilt`], easing back to level over the tilt duration
+fn camera_tilt(
+    mut tilt: ResMut<CameraTilt>,
+    mut camera_query: Query<&mut Transform, With<Camera>>,
+    game_meta: Res<GameMeta>,
+    accessibility: Option<Res<AccessibilitySettings>>,
+    time: Res<Time>,
+) {
+    if tilt.strength <= 0. {
+        return;
+    }
+    let meta = match game_meta.camera_tilt {
+        Some(meta) => meta,
+        None => return,
+    };
+
+    tilt.elapsed += time.delta_seconds();
+    let reduced_motion = accessibility.map_or(false, |settings| settings.reduced_motion);
+    if reduced_motion || tilt.elapsed >= meta.duration {
+        tilt.strength = 0.;
+        return;
+    }
+    let mut transform = match camera_query.get_single_mut() {
+        Ok(transform) => transform,
+        Err(_) => return,
+    };
+
+    // Ease out, so that the camera settles gently back to level
+    let remaining = 1. - tilt.elapsed / meta.duration;
+    let direction = if tilt.clockwise { -1. } else { 1. };
+    let angle = meta.max_angle.to_radians() * tilt.strength * remaining * remaining * direction;
+
+    transform.rotation = Quat::from_rotation_z(angle);
+    tilt.angle = angle;
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{
+        fighter_state::Dying,
+        metadata::{CameraTiltMeta, CameraZoomMeta},
+    };
+
+    #[test]
+    fn test_dying_player_is_not_followed() {
+        let mut world = World::new();
+        world.spawn((Player, Transform::from_xyz(100., 0., 0.)));
+        world.spawn((Player, Dying, Transform::from_xyz(500., 0., 0.)));
+
+        let mut living_players = world.query_filtered::<&Transform, LivingPlayer>();
+        assert_eq!(max_player_x(living_players.iter(&world)), Some(100.));
+
+        // With every player dying, there is nothing to follow and the camera stays put
+        let mut all_dead = World::new();
+        all_dead.spawn((Player, Dying, Transform::from_xyz(500., 0., 0.)));
+        let mut living_players = all_dead.query_filtered::<&Transform, LivingPlayer>();
+        assert_eq!(max_player_x(living_players.iter(&all_dead)), None);
+    }
+
+    #[test]
+    fn test_player_spread_zooms_out_up_to_cap() {
+        let zoom_meta = CameraZoomMeta {
+            min_zoom: 1.0,
+            max_zoom: 1.5,
+            min_spread: 200.,
+            max_spread: 600.,
+        };
+        let zoom_for_players = |xs: &[f32]| {
+            let transforms = xs
+                .iter()
+                .map(|x| Transform::from_xyz(*x, 0., 0.))
+                .collect::<Vec<_>>();
+            zoom_meta.zoom_for_spread(player_spread(transforms.iter()))
+        };
+
+        let close = zoom_for_players(&[0., 100.]);
+        let apart = zoom_for_players(&[0., 400.]);
+        let far_apart = zoom_for_players(&[0., 500.]);
+        let very_far_apart = zoom_for_players(&[0., 2000.]);
+
+        assert_eq!(close, 1.0);
+        assert!(apart > close);
+        assert!(far_apart > apart);
+        assert_eq!(very_far_apart, 1.5);
+        assert_eq!(zoom_for_players(&[]), 1.0);
+    }
+
+    #[test]
+    fn test_lower_fighter_is_drawn_in_front() {
+        let mut world = World::new();
+        world.insert_resource::<GameMeta>(
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap(),
+        );
+        let behind = world
+            .spawn((
+                YSort(SortLayer::Fighters),
+                Transform::from_xyz(0., -100., 0.),
+            ))
+            .id();
+        let in_front = world
+            .spawn((
+                YSort(SortLayer::Fighters),
+                Transform::from_xyz(0., -150., 0.),
+            ))
+            .id();
+        let item = world
+            .spawn((
+                YSort(SortLayer::GroundItems),
+                Transform::from_xyz(0., -500., 0.),
+            ))
+            .id();
+        let projectile = world
+            .spawn((
+                YSort(SortLayer::Projectiles),
+                Transform::from_xyz(0., 500., 0.),
+            ))
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(y_sort);
+        stage.run(&mut world);
+
+        let z = |entity| world.get::<Transform>(entity).unwrap().translation.z;
+        assert!(z(in_front) > z(behind));
+
+        // Other layers stay in their band, however far up or down the screen they are
+        assert!(z(item) < z(behind));
+        assert!(z(projectile) > z(in_front));
+    }
+
+    #[test]
+    fn test_camera_intro_hands_over_after_waypoints() {
+        let waypoint = |x: f32, duration: f32| CameraWaypointMeta { x, duration };
+        let intro = CameraIntro::new(vec![
+            waypoint(600., 0.),
+            waypoint(600., 1.),
+            waypoint(0., 2.),
+        ]);
+        assert_eq!(intro.camera_x(0., 0.5), Some(600.));
+        assert_eq!(intro.camera_x(0., 2.), Some(300.));
+        assert_eq!(intro.camera_x(0., 3.), None);
+
+        let mut app = App::new();
+        let mut time = Time::default();
+        time.update();
+        app.insert_resource(time)
+            .insert_resource(intro)
+            .add_event::<ParallaxMoveEvent>()
+            .add_system(camera_intro.run_if_resource_exists::<CameraIntro>());
+        app.world.spawn((Camera::default(), Transform::default()));
+        app.world.spawn(ActionState::<MenuAction>::default());
+
+        let mut step = |seconds: f32| {
+            let mut time = app.world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + std::time::Duration::from_secs_f32(seconds));
+            app.update();
+            app.world.get_resource::<CameraIntro>().is_some()
+        };
+
+        // Still panning before the total waypoint duration
+        assert!(step(1.5));
+        assert!(step(1.0));
+
+        // Over after it, handing the camera back to the normal follow
+        assert!(!step(0.6));
+    }
+
+    #[test]
+    fn test_shake_near_right_edge_stays_in_bounds() {
+        let mut world = World::new();
+        let mut game: GameMeta =
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap();
+        game.camera_shake.max_offset = Vec2::new(500., 500.);
+        let mut level: LevelMeta =
+            serde_yaml::from_str(include_str!("../assets/levels/1_beach/beach.level.yaml"))
+                .unwrap();
+        level.bounds.right = Some(1000.);
+        world.insert_resource(game);
+        world.insert_resource(level);
+        world.insert_resource(Time::default());
+        world.init_resource::<CameraShake>();
+        world.init_resource::<CosmeticRng>();
+
+        // The right edge of the view is 10 units away from the end of the level
+        let projection = OrthographicProjection {
+            left: -400.,
+            right: 400.,
+            ..default()
+        };
+        let camera = world
+            .spawn((
+                Camera::default(),
+                Transform::from_xyz(590., 0., 0.),
+                projection,
+            ))
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(reset_camera_shake.before(camera_shake));
+        stage.add_system(camera_shake);
+
+        for _ in 0..50 {
+            world.resource_mut::<CameraShake>().add_trauma(1.0);
+            stage.run(&mut world);
+
+            let x = world.get::<Transform>(camera).unwrap().translation.x;
+            assert!(x + 400. <= 1000.);
+        }
+
+        // The shake toward the edge fades out before reaching it
+        let bounds = world.resource::<LevelMeta>().bounds;
+        let offset = dampen_shake_offset(Vec2::new(20., 5.), 975., &bounds, 50.);
+        assert_eq!(offset, Vec2::new(10., 5.));
+
+        // And the shake away from the edge, or with no edge, is left as it is
+        let offset = dampen_shake_offset(Vec2::new(-20., 5.), 975., &bounds, 50.);
+        assert_eq!(offset, Vec2::new(-20., 5.));
+        let offset = dampen_shake_offset(Vec2::new(300., 0.), 975., &default(), 50.);
+        assert_eq!(offset, Vec2::new(300., 0.));
+    }
+
+    #[test]
+    fn test_camera_tilt_eases_back_to_level() {
+        let mut world = World::new();
+        let mut game: GameMeta =
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap();
+        game.camera_tilt = Some(CameraTiltMeta {
+            max_angle: 5.,
+            duration: 0.4,
+        });
+        world.insert_resource(game);
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+        world.init_resource::<CameraTilt>();
+        let camera = world
+            .spawn((Camera::default(), Transform::from_xyz(100., 0., 0.)))
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(reset_camera_tilt.before(camera_tilt));
+        stage.add_system(camera_tilt);
+        let step = |world: &mut World, stage: &mut SystemStage| {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + std::time::Duration::from_secs_f32(0.05));
+            stage.run(world);
+            world.get::<Transform>(camera).unwrap().rotation
+        };
+
+        // A heavy impact rolls the camera, less and less over time
+        world.resource_mut::<CameraTilt>().add_tilt(1.0);
+        let mut last_angle = f32::MAX;
+        for _ in 0..4 {
+            let angle = step(&mut world, &mut stage).to_axis_angle().1;
+            assert!(angle > 0. && angle < last_angle);
+            last_angle = angle;
+        }
+
+        // And the camera is level again once the tilt is over
+        for _ in 0..5 {
+            step(&mut world, &mut stage);
+        }
+        assert_eq!(
+            world.get::<Transform>(camera).unwrap().rotation,
+            Quat::IDENTITY
+        );
+        assert_eq!(
+            world.get::<Transform>(camera).unwrap().translation,
+            Vec3::new(100., 0., 0.)
+        );
+
+        // With reduced motion, the camera never tilts
+        world.insert_resource(AccessibilitySettings {
+            reduced_motion: true,
+            ..default()
+        });
+        world.resource_mut::<CameraTilt>().add_tilt(1.0);
+        assert_eq!(step(&mut world, &mut stage), Quat::IDENTITY);
+    }
+
+    #[test]
+    fn test_camera_leans_toward_attacking_boss() {
+        let game: GameMeta =
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap();
+        let meta = game.boss_focus.unwrap();
+        let mut level: LevelMeta =
+            serde_yaml::from_str(include_str!("../assets/levels/1_beach/beach.level.yaml"))
+                .unwrap();
+        level.bounds.right = None;
+
+        // Stands in for the parallax plugin, which moves the camera
+        fn move_camera(
+            mut events: EventReader<ParallaxMoveEvent>,
+            mut cameras: Query<&mut Transform, With<Camera>>,
+        ) {
+            for event in events.iter() {
+                cameras.single_mut().translation.x += event.camera_move_speed;
+            }
+        }
+
+        let mut app = App::new();
+        let mut time = Time::default();
+        time.update();
+        app.insert_resource(time)
+            .insert_resource(game)
+            .insert_resource(level)
+            .add_event::<ParallaxMoveEvent>()
+            .add_system(start_boss_camera_focus)
+            .add_system(camera_boss_focus.run_if_resource_exists::<BossCameraFocus>())
+            .add_system(camera_follow_player.run_unless_resource_exists::<BossCameraFocus>())
+            .add_system_to_stage(CoreStage::PostUpdate, move_camera);
+        let camera = app
+            .world
+            .spawn((
+                Camera::default(),
+                Transform::default(),
+                OrthographicProjection {
+                    left: -400.,
+                    right: 400.,
+                    ..default()
+                },
+            ))
+            .id();
+        let player = app.world.spawn((Player, Transform::default())).id();
+        let boss = app
+            .world
+            .spawn((Boss, Transform::from_xyz(600., 0., 0.)))
+            .id();
+
+        let mut step = |app: &mut App, seconds: f32| {
+            let mut time = app.world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + std::time::Duration::from_secs_f32(seconds));
+            app.update();
+            app.world.get::<Transform>(camera).unwrap().translation.x
+        };
+
+        // The camera stays on the player until the boss attacks
+        assert_eq!(step(&mut app, 0.1), 0.);
+        app.world.entity_mut(boss).insert(GroundSlam::default());
+        step(&mut app, 0.1);
+        for _ in 0..5 {
+            step(&mut app, 0.1);
+        }
+
+        // Then it leans toward the boss, without losing the player
+        let x = app.world.get::<Transform>(camera).unwrap().translation.x;
+        assert!(x > 100.);
+        assert!(x <= 400. - meta.player_margin + 0.01);
+
+        // Once the focus is over the camera pans back to the player
+        for _ in 0..20 {
+            step(&mut app, 0.1);
+        }
+        assert!(app.world.get_resource::<BossCameraFocus>().is_none());
+        let x = step(&mut app, 0.1);
+        assert!(x.abs() < 1.);
+
+        // And follows the player again from there
+        app.world
+            .get_mut::<Transform>(player)
+            .unwrap()
+            .translation
+            .x = 600.;
+        assert!(step(&mut app, 0.1) > x);
+    }
+
+    #[test]
+    fn test_boss_intro_locks_input_until_over() {
+        let fighter: FighterMeta = serde_yaml::from_str(include_str!(
+            "../assets/fighters/big_bass/big_bass.fighter.yaml"
+        ))
+        .unwrap();
+        let duration = fighter.boss_intro.as_ref().unwrap().duration;
+        let mut level: LevelMeta =
+            serde_yaml::from_str(include_str!("../assets/levels/1_beach/beach.level.yaml"))
+                .unwrap();
+        level.bounds.right = None;
+
+        // Stands in for the parallax plugin, which moves the camera
+        fn move_camera(
+            mut events: EventReader<ParallaxMoveEvent>,
+            mut cameras: Query<&mut Transform, With<Camera>>,
+        ) {
+            for event in events.iter() {
+                cameras.single_mut().translation.x += event.camera_move_speed;
+            }
+        }
+
+        let mut app = App::new();
+        let mut time = Time::default();
+        time.update();
+        app.insert_resource(time)
+            .insert_resource(level)
+            .init_resource::<Assets<FighterMeta>>()
+            .add_event::<ParallaxMoveEvent>()
+            .add_system(start_boss_intro.run_unless_resource_exists::<BossIntro>())
+            .add_system(boss_intro.run_if_resource_exists::<BossIntro>())
+            .add_system_to_stage(CoreStage::PostUpdate, move_camera);
+        let handle = app.world.resource_mut::<Assets<FighterMeta>>().add(fighter);
+        let camera = app
+            .world
+            .spawn((
+                Camera::default(),
+                Transform::default(),
+                OrthographicProjection {
+                    left: -400.,
+                    right: 400.,
+                    ..default()
+                },
+            ))
+            .id();
+        let player = app.world.spawn((Player, Transform::default())).id();
+        app.world.spawn((
+            Boss,
+            TripPointX(300.),
+            handle,
+            Transform::from_xyz(600., 0., 0.),
+        ));
+
+        let mut step = |app: &mut App, seconds: f32| {
+            let mut time = app.world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + std::time::Duration::from_secs_f32(seconds));
+            app.update();
+        };
+        // The player input is locked while the intro is playing
+        let input_locked = |app: &App| app.world.get_resource::<BossIntro>().is_some();
+
+        // Nothing happens until the players reach the boss
+        step(&mut app, 0.1);
+        assert!(!input_locked(&app));
+        app.world
+            .get_mut::<Transform>(player)
+            .unwrap()
+            .translation
+            .x = 350.;
+        step(&mut app, 0.1);
+        assert!(input_locked(&app));
+
+        // The camera frames the boss for the length of the intro
+        let mut elapsed = 0.;
+        while elapsed < duration - 0.2 {
+            step(&mut app, 0.1);
+            elapsed += 0.1;
+        }
+        assert!(input_locked(&app));
+        assert!((app.world.get::<Transform>(camera).unwrap().translation.x - 600.).abs() < 1.);
+
+        // Then goes back to where it started, giving the control back to the players
+        for _ in 0..20 {
+            step(&mut app, 0.1);
+        }
+        assert!(!input_locked(&app));
+        assert!(
+            app.world
+                .get::<Transform>(camera)
+                .unwrap()
+                .translation
+                .x
+                .abs()
+                < 1.
+        );
+
+        // And the intro only plays once
+        step(&mut app, 0.1);
+        assert!(!input_locked(&app));
+    }
+}
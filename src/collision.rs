@@ -22,6 +22,66 @@ impl BodyLayers {
     pub const ALL: Group = Group::ALL;
 }
 
+/// The side a fighter fights on, deciding which attacks hit it.
+///
+/// The collision groups of fighters and their attacks are derived from the relationships between
+/// the teams, rather than from whether they are players or enemies.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Team {
+    Players,
+    Enemies,
+}
+
+impl Team {
+    pub const ALL: [Team; 2] = [Team::Players, Team::Enemies];
+
+    /// The layer of the team's fighters
+    pub fn body_layer(self) -> Group {
+        match self {
+            Team::Players => BodyLayers::PLAYER,
+            Team::Enemies => BodyLayers::ENEMY,
+        }
+    }
+
+    /// The layer of the team's attacks
+    pub fn attack_layer(self) -> Group {
+        match self {
+            Team::Players => BodyLayers::PLAYER_ATTACK,
+            Team::Enemies => BodyLayers::ENEMY_ATTACK,
+        }
+    }
+
+    /// Whether the team fights against the `other` team
+    pub fn is_hostile_to(self, other: Team) -> bool {
+        self != other
+    }
+
+    /// Get the collision groups of the team's attacks.
+    ///
+    /// Attacks hit the fighters of the hostile teams, and the fighters of their own team too with
+    /// friendly fire. Attacks that can clash also collide with the attacks of the hostile teams.
+    pub fn attack_groups(self, friendly_fire: bool, can_clash: bool) -> CollisionGroups {
+        let mut filters = Group::NONE;
+        for other in Team::ALL {
+            if self.is_hostile_to(other) {
+                filters |= other.body_layer();
+                if can_clash {
+                    filters |= other.attack_layer();
+                }
+            } else if friendly_fire {
+                filters |= other.body_layer();
+            }
+        }
+
+        // Only the players break the breakable items
+        if self == Team::Players {
+            filters |= BodyLayers::BREAKABLE_ITEM;
+        }
+
+        CollisionGroups::new(self.attack_layer(), filters)
+    }
+}
+
 #[derive(Bundle)]
 pub struct PhysicsBundle {
     pub collider: Collider,
@@ -59,3 +119,38 @@ impl Default for PhysicsBundle {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Whether rapier reports collisions between colliders in these groups
+    fn collide(a: CollisionGroups, b: CollisionGroups) -> bool {
+        a.memberships.intersects(b.filters) && b.memberships.intersects(a.filters)
+    }
+
+    #[test]
+    fn test_attacks_only_hit_hostile_teams() {
+        let body = |team: Team| CollisionGroups::new(team.body_layer(), BodyLayers::ALL);
+
+        // Attacks hit the fighters of the hostile team, and not their teammates
+        let player_attack = Team::Players.attack_groups(false, false);
+        let enemy_attack = Team::Enemies.attack_groups(false, false);
+        assert!(collide(player_attack, body(Team::Enemies)));
+        assert!(!collide(player_attack, body(Team::Players)));
+        assert!(collide(enemy_attack, body(Team::Players)));
+        assert!(!collide(enemy_attack, body(Team::Enemies)));
+
+        // Unless friendly fire is on
+        let friendly_fire = Team::Enemies.attack_groups(true, false);
+        assert!(collide(friendly_fire, body(Team::Enemies)));
+        assert!(collide(friendly_fire, body(Team::Players)));
+
+        // Opposing attacks only collide if they can clash
+        assert!(!collide(player_attack, enemy_attack));
+        assert!(collide(
+            Team::Players.attack_groups(false, true),
+            Team::Enemies.attack_groups(false, true)
+        ));
+    }
+}
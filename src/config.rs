@@ -1,6 +1,9 @@
 use once_cell::sync::Lazy;
 use structopt::StructOpt;
 
+/// The default enemy density scale on web, where memory is limited
+const WEB_ENEMY_DENSITY_SCALE: f32 = 0.6;
+
 const DEFAULT_LOG_LEVEL: &str = "info,wgpu=error,bevy_fluent=warn,symphonia_core=warn,symphonia_format_ogg=warn,symphonia_bundle_mp3=warn";
 
 pub static ENGINE_CONFIG: Lazy<EngineConfig> = Lazy::new(|| {
@@ -22,6 +25,12 @@ pub struct EngineConfig {
     #[structopt(short, long)]
     pub asset_dir: Option<String>,
 
+    /// A mod pack directory with assets that override or add to the base game assets
+    ///
+    /// Hot reloading is not supported when a mod pack is loaded.
+    #[structopt(short, long)]
+    pub mod_dir: Option<String>,
+
     /// The .game.yaml asset to load at startup
     #[structopt(default_value = "default.game.yaml")]
     pub game_asset: String,
@@ -40,9 +49,26 @@ pub struct EngineConfig {
     /// `module=level` items.
     #[structopt(short = "l", long, default_value = DEFAULT_LOG_LEVEL)]
     pub log_level: String,
+
+    /// Scale the number of enemies in each group, from 0.0 to 1.0, for weaker devices
+    ///
+    /// Defaults to 1.0, or to a lower density on web, where memory is limited.
+    #[structopt(long)]
+    pub enemy_density_scale: Option<f32>,
 }
 
 impl EngineConfig {
+    /// Get the enemy density scale, from the config or the default for the platform
+    pub fn enemy_density_scale(&self) -> f32 {
+        let default = if cfg!(target_arch = "wasm32") {
+            WEB_ENEMY_DENSITY_SCALE
+        } else {
+            1.0
+        };
+
+        self.enemy_density_scale.unwrap_or(default).clamp(0.0, 1.0)
+    }
+
     #[cfg(target_arch = "wasm32")]
     pub fn from_web_params() -> Self {
         if let Some(query) = web_sys::window().and_then(|w| w.location().search().ok()) {
@@ -52,6 +78,10 @@ impl EngineConfig {
                 config.asset_dir = Some(asset_dir.into());
             }
 
+            if let Some(mod_dir) = parse_url_query_string(&query, "mod_url") {
+                config.mod_dir = Some(mod_dir.into());
+            }
+
             if let Some(game_asset) = parse_url_query_string(&query, "game_asset") {
                 config.game_asset = game_asset.into();
             }
@@ -72,6 +102,12 @@ impl EngineConfig {
                 config.log_level = log_level.into();
             }
 
+            if let Some(enemy_density_scale) =
+                parse_url_query_string(&query, "enemy_density_scale").and_then(|s| s.parse().ok())
+            {
+                config.enemy_density_scale = Some(enemy_density_scale);
+            }
+
             config
         } else {
             Self::web_default()
@@ -85,10 +121,12 @@ impl EngineConfig {
         Self {
             hot_reload: false,
             asset_dir: None,
+            mod_dir: None,
             game_asset: "default.game.yaml".into(),
             auto_start: false,
             debug_tools: false,
             log_level: DEFAULT_LOG_LEVEL.into(),
+            enemy_density_scale: None,
         }
     }
 }
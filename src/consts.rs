@@ -1,15 +1,34 @@
-use bevy::math::Vec2;
+use bevy::math::{Vec2, Vec3};
 
 pub const PLAYER_SPRITE_WIDTH: f32 = 96.;
 pub const PLAYER_HITBOX_HEIGHT: f32 = 50.;
 
-pub const FIGHTERS_Z: f32 = 300.;
-
 /// Absolute value.
 pub const ENEMY_TARGET_MAX_OFFSET: f32 = 40.;
 
 pub const ENEMY_MIN_ATTACK_DISTANCE: f32 = 5.;
 pub const ENEMY_MAX_ATTACK_DISTANCE: f32 = 100.;
+// Fraction of its preferred range under which an enemy backs away from the player
+pub const ENEMY_KITE_RANGE_RATIO: f32 = 0.5;
+// How far past the screen edge enemies start their entrance
+pub const ENEMY_ENTRANCE_MARGIN: f32 = 50.;
+// The height above their location that dropping enemies start their entrance from
+pub const ENEMY_ENTRANCE_DROP_HEIGHT: f32 = 300.;
+pub const ENEMY_ENTRANCE_DROP_SPEED: f32 = 600.;
+// Seconds between the boss's bomb throws
+pub const BOSS_BOMB_THROW_COOLDOWN: f32 = 4.;
+// The horizontal space between the minions a boss calls in, which spread out on both sides of it
+pub const BOSS_REINFORCEMENT_SPACING: f32 = 40.;
+
+// Distance from an enemy at which players attack it in attract mode
+pub const ATTRACT_MODE_ATTACK_DISTANCE: f32 = 50.;
+
+// Distances at which the assist AI ally engages and attacks enemies, picks up items, and follows
+// the human players
+pub const ASSIST_AI_ENGAGE_DISTANCE: f32 = 250.;
+pub const ASSIST_AI_ATTACK_DISTANCE: f32 = 50.;
+pub const ASSIST_AI_ITEM_DISTANCE: f32 = 150.;
+pub const ASSIST_AI_FOLLOW_DISTANCE: f32 = 120.;
 
 // Distance from the player, after which the player movement boundary is moved forward.
 //
@@ -20,6 +39,19 @@ pub const GROUND_HEIGHT: f32 = 100.;
 pub const GROUND_OFFSET: f32 = 0.;
 
 pub const CAMERA_SPEED: f32 = 0.8;
+pub const CAMERA_ZOOM_SPEED: f32 = 3.0;
+// How hard explosions shake the camera, from 0 to 1
+pub const EXPLOSION_CAMERA_SHAKE: f32 = 0.6;
+// How hard heavy impacts tilt the camera, from 0 to 1
+pub const EXPLOSION_CAMERA_TILT: f32 = 0.8;
+pub const GROUND_SLAM_CAMERA_TILT: f32 = 1.0;
+
+// Run speed relative to the walk speed, for fighters that don't set a `run_speed`
+pub const RUN_SPEED_MULTIPLIER: f32 = 1.6;
+// Fraction of the walking speed that downed players crawl at
+pub const DOWNED_CRAWL_SPEED_MULTIPLIER: f32 = 0.25;
+// How fast fighters knocked down in the air fall to the ground, before they start sliding
+pub const KNOCKDOWN_FALL_SPEED: f32 = 400.;
 
 pub const MAX_Y: f32 = (GROUND_HEIGHT / 2.) + GROUND_Y;
 // pub const MIN_Y: f32 = -(GROUND_HEIGHT / 2.) + GROUND_Y;
@@ -28,15 +60,49 @@ pub const MIN_Y: f32 = -(GROUND_HEIGHT / 2.) + GROUND_Y - 50.;
 
 pub const ITEM_ATTACK_VELOCITY: f32 = 80.0;
 pub const HITSTUN_DURATION: f32 = 0.50;
+pub const HURT_INDICATOR_DURATION: f32 = 0.5;
+// Space between the player HUD panels and the screen edges
+pub const PLAYER_HUD_MARGIN: f32 = 20.;
+// Number of segments in each boss attack cooldown indicator
+pub const BOSS_COOLDOWN_SEGMENTS: usize = 4;
 
-pub const ITEM_LAYER: f32 = 100.;
 pub const ITEM_WIDTH: f32 = 30.;
 pub const ITEM_HEIGHT: f32 = 10.;
 
-pub const PROJECTILE_Z: f32 = 101.;
+// How far in front of their fighter held items are drawn
+pub const HELD_ITEM_Z: f32 = 0.1;
 pub const THROW_ITEM_OFFSET: Vec2 = Vec2::from_array([5.0, 30.0]);
 pub const THROW_ITEM_ROTATION_SPEED: f32 = -20.;
+// Fraction of a weapon's attack damage dealt when it is thrown
+pub const WEAPON_THROW_DAMAGE_MULTIPLIER: f32 = 0.5;
+pub const WEAPON_THROW_VELOCITY: Vec2 = Vec2::from_array([350.0, 150.0]);
+pub const WEAPON_THROW_GRAVITY: f32 = 900.;
+pub const WEAPON_THROW_LIFETIME: f32 = 0.5;
+pub const WEAPON_THROW_PUSHBACK: f32 = 120.;
+// How far up or down the movement axis must be held to aim a throw
+pub const THROW_AIM_DEADZONE: f32 = 0.5;
+// The angle, in radians, that aimed throws are tilted by
+pub const THROW_AIM_ANGLE: f32 = 0.5;
+// The distance within which aimed throws lock on to enemies
+pub const THROW_LOCK_ON_RANGE: f32 = 250.;
 
 pub const PICK_ITEM_RADIUS: f32 = 24.;
+// How long before disappearing dropped weapons start blinking, and how fast they blink, in seconds
+pub const DROPPED_ITEM_BLINK_DURATION: f32 = 3.;
+pub const DROPPED_ITEM_BLINK_INTERVAL: f32 = 0.15;
 
 pub const FOOT_PADDING: f32 = 16.;
+
+// The upward speed of launch reactions to attacks without a launch velocity of their own
+pub const LAUNCH_REACTION_SPEED: f32 = 300.;
+
+// The speed fighters fall back to the ground with once their air attack is over
+pub const AIR_ATTACK_FALL_SPEED: f32 = 250.;
+
+// How long, in seconds, the recoil of an attack takes to fade out
+pub const RECOIL_DURATION: f32 = 0.2;
+
+// How often burning fighters take their burn damage, in seconds
+pub const BURN_TICK_INTERVAL: f32 = 0.5;
+pub const BURNING_FLAME_SIZE: Vec2 = Vec2::new(12., 18.);
+pub const BURNING_FLAME_OFFSET: Vec3 = Vec3::new(0., 30., 1.);
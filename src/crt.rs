@@ -0,0 +1,381 @@
+//! The optional CRT screen effect, a post-processing pass that curves the screen and adds
+//! scanlines.
+//!
+//! The effect is applied to the game camera's view before the egui pass, so the HUD and menus
+//! stay sharp.
+
+use bevy::{
+    asset::load_internal_asset,
+    core_pipeline::{core_2d, fullscreen_vertex_shader::fullscreen_shader_vertex_state},
+    ecs::query::{QueryItem, QueryState},
+    prelude::*,
+    reflect::TypeUuid,
+    render::{
+        extract_component::{
+            ComponentUniforms, DynamicUniformIndex, ExtractComponent, ExtractComponentPlugin,
+            UniformComponentPlugin,
+        },
+        render_graph::{Node, NodeRunError, RenderGraph, RenderGraphContext, SlotInfo, SlotType},
+        render_resource::*,
+        renderer::{RenderContext, RenderDevice},
+        texture::BevyDefault,
+        view::{ExtractedView, ViewTarget},
+        RenderApp, RenderStage,
+    },
+};
+
+use crate::{metadata::CrtSettings, platform::load_settings};
+
+const CRT_SHADER_HANDLE: HandleUntyped =
+    HandleUntyped::weak_from_u64(Shader::TYPE_UUID, 7316451932874660143);
+
+/// The name of the CRT node in the 2D render graph
+const CRT_NODE: &str = "crt";
+
+pub struct CrtPlugin;
+
+impl Plugin for CrtPlugin {
+    fn build(&self, app: &mut App) {
+        load_internal_asset!(app, CRT_SHADER_HANDLE, "crt/crt.wgsl", Shader::from_wgsl);
+
+        app.add_plugin(ExtractComponentPlugin::<CrtEffect>::default())
+            .add_plugin(UniformComponentPlugin::<CrtEffect>::default())
+            .add_system(apply_crt_settings.after(load_settings));
+
+        let render_app = match app.get_sub_app_mut(RenderApp) {
+            Ok(render_app) => render_app,
+            Err(_) => return,
+        };
+        render_app
+            .init_resource::<CrtPipeline>()
+            .init_resource::<SpecializedRenderPipelines<CrtPipeline>>()
+            .add_system_to_stage(RenderStage::Prepare, prepare_crt_pipelines);
+
+        let crt_node = CrtNode::new(&mut render_app.world);
+        let mut render_graph = render_app.world.resource_mut::<RenderGraph>();
+        let graph = render_graph
+            .get_sub_graph_mut(core_2d::graph::NAME)
+            .unwrap();
+
+        graph.add_node(CRT_NODE, crt_node);
+        graph
+            .add_slot_edge(
+                graph.input_node().unwrap().id,
+                core_2d::graph::input::VIEW_ENTITY,
+                CRT_NODE,
+                CrtNode::IN_VIEW,
+            )
+            .unwrap();
+        graph
+            .add_node_edge(core_2d::graph::node::TONEMAPPING, CRT_NODE)
+            .unwrap();
+        graph
+            .add_node_edge(
+                CRT_NODE,
+                core_2d::graph::node::END_MAIN_PASS_POST_PROCESSING,
+            )
+            .unwrap();
+    }
+}
+
+/// Applies the CRT effect to a camera.
+///
+/// This is added to and removed from the cameras according to the [`CrtSettings`].
+#[derive(Component, ShaderType, Clone, Debug)]
+pub struct CrtEffect {
+    pub scanline_intensity: f32,
+    pub curvature: f32,
+    /// The number of scanlines from the top to the bottom of the screen.
+    ///
+    /// This is filled in from the camera's projection when the effect is extracted for rendering,
+    /// so that there is one scanline per row of game pixels, whatever the window size or zoom.
+    pub scanline_count: f32,
+}
+
+impl CrtEffect {
+    pub fn new(settings: &CrtSettings) -> Self {
+        Self {
+            scanline_intensity: settings.scanline_intensity.clamp(0.0, 1.0),
+            curvature: settings.curvature.max(0.0),
+            scanline_count: 0.0,
+        }
+    }
+}
+
+impl ExtractComponent for CrtEffect {
+    type Query = (&'static Self, &'static OrthographicProjection);
+    type Filter = With<Camera>;
+
+    fn extract_component((crt, projection): QueryItem<Self::Query>) -> Self {
+        Self {
+            scanline_count: (projection.top - projection.bottom) * projection.scale,
+            ..crt.clone()
+        }
+    }
+}
+
+/// Adds the [`CrtEffect`] to the cameras while it is enabled, and removes it while it isn't
+fn apply_crt_settings(
+    mut commands: Commands,
+    crt_settings: Option<Res<CrtSettings>>,
+    cameras: Query<Entity, With<Camera>>,
+    new_cameras: Query<(), Added<Camera>>,
+) {
+    let crt_settings = match crt_settings {
+        Some(crt_settings) => crt_settings,
+        None => return,
+    };
+    if !crt_settings.is_changed() && new_cameras.is_empty() {
+        return;
+    }
+
+    for camera in &cameras {
+        if crt_settings.enabled {
+            commands
+                .entity(camera)
+                .insert(CrtEffect::new(&crt_settings));
+        } else {
+            commands.entity(camera).remove::<CrtEffect>();
+        }
+    }
+}
+
+#[derive(Resource)]
+struct CrtPipeline {
+    layout: BindGroupLayout,
+}
+
+impl FromWorld for CrtPipeline {
+    fn from_world(render_world: &mut World) -> Self {
+        let layout = render_world
+            .resource::<RenderDevice>()
+            .create_bind_group_layout(&BindGroupLayoutDescriptor {
+                label: Some("crt_bind_group_layout"),
+                entries: &[
+                    BindGroupLayoutEntry {
+                        binding: 0,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Texture {
+                            sample_type: TextureSampleType::Float { filterable: true },
+                            view_dimension: TextureViewDimension::D2,
+                            multisampled: false,
+                        },
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 1,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Sampler(SamplerBindingType::Filtering),
+                        count: None,
+                    },
+                    BindGroupLayoutEntry {
+                        binding: 2,
+                        visibility: ShaderStages::FRAGMENT,
+                        ty: BindingType::Buffer {
+                            ty: BufferBindingType::Uniform,
+                            has_dynamic_offset: true,
+                            min_binding_size: Some(CrtEffect::min_size()),
+                        },
+                        count: None,
+                    },
+                ],
+            });
+
+        CrtPipeline { layout }
+    }
+}
+
+impl SpecializedRenderPipeline for CrtPipeline {
+    /// The format of the view's textures
+    type Key = TextureFormat;
+
+    fn specialize(&self, format: Self::Key) -> RenderPipelineDescriptor {
+        RenderPipelineDescriptor {
+            label: Some("crt".into()),
+            layout: Some(vec![self.layout.clone()]),
+            vertex: fullscreen_shader_vertex_state(),
+            fragment: Some(FragmentState {
+                shader: CRT_SHADER_HANDLE.typed(),
+                shader_defs: vec![],
+                entry_point: "fragment".into(),
+                targets: vec![Some(ColorTargetState {
+                    format,
+                    blend: None,
+                    write_mask: ColorWrites::ALL,
+                })],
+            }),
+            primitive: PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: MultisampleState::default(),
+        }
+    }
+}
+
+#[derive(Component)]
+struct CameraCrtPipeline(CachedRenderPipelineId);
+
+fn prepare_crt_pipelines(
+    mut commands: Commands,
+    mut pipeline_cache: ResMut<PipelineCache>,
+    mut pipelines: ResMut<SpecializedRenderPipelines<CrtPipeline>>,
+    crt_pipeline: Res<CrtPipeline>,
+    views: Query<(Entity, &ExtractedView), With<CrtEffect>>,
+) {
+    for (entity, view) in &views {
+        let format = if view.hdr {
+            ViewTarget::TEXTURE_FORMAT_HDR
+        } else {
+            TextureFormat::bevy_default()
+        };
+        let pipeline_id = pipelines.specialize(&mut pipeline_cache, &crt_pipeline, format);
+
+        commands
+            .entity(entity)
+            .insert(CameraCrtPipeline(pipeline_id));
+    }
+}
+
+/// Render graph node drawing the CRT effect over the views that have a [`CrtEffect`]
+struct CrtNode {
+    query: QueryState<
+        (
+            &'static ViewTarget,
+            &'static CameraCrtPipeline,
+            &'static DynamicUniformIndex<CrtEffect>,
+        ),
+        With<ExtractedView>,
+    >,
+}
+
+impl CrtNode {
+    const IN_VIEW: &'static str = "view";
+
+    fn new(world: &mut World) -> Self {
+        Self {
+            query: QueryState::new(world),
+        }
+    }
+}
+
+impl Node for CrtNode {
+    fn input(&self) -> Vec<SlotInfo> {
+        vec![SlotInfo::new(Self::IN_VIEW, SlotType::Entity)]
+    }
+
+    fn update(&mut self, world: &mut World) {
+        self.query.update_archetypes(world);
+    }
+
+    fn run(
+        &self,
+        graph: &mut RenderGraphContext,
+        render_context: &mut RenderContext,
+        world: &World,
+    ) -> Result<(), NodeRunError> {
+        let view_entity = graph.get_input_entity(Self::IN_VIEW)?;
+
+        // Views without the effect are left as they are
+        let (target, pipeline, uniform_index) = match self.query.get_manual(world, view_entity) {
+            Ok(result) => result,
+            Err(_) => return Ok(()),
+        };
+        let pipeline = match world
+            .resource::<PipelineCache>()
+            .get_render_pipeline(pipeline.0)
+        {
+            Some(pipeline) => pipeline,
+            // The shader is still being compiled
+            None => return Ok(()),
+        };
+        let uniforms = match world.resource::<ComponentUniforms<CrtEffect>>().binding() {
+            Some(uniforms) => uniforms,
+            None => return Ok(()),
+        };
+
+        let post_process = target.post_process_write();
+        let sampler = render_context
+            .render_device
+            .create_sampler(&SamplerDescriptor {
+                // Keep the pixel art crisp
+                mag_filter: FilterMode::Nearest,
+                min_filter: FilterMode::Nearest,
+                ..default()
+            });
+        let bind_group = render_context
+            .render_device
+            .create_bind_group(&BindGroupDescriptor {
+                label: Some("crt_bind_group"),
+                layout: &world.resource::<CrtPipeline>().layout,
+                entries: &[
+                    BindGroupEntry {
+                        binding: 0,
+                        resource: BindingResource::TextureView(post_process.source),
+                    },
+                    BindGroupEntry {
+                        binding: 1,
+                        resource: BindingResource::Sampler(&sampler),
+                    },
+                    BindGroupEntry {
+                        binding: 2,
+                        resource: uniforms,
+                    },
+                ],
+            });
+
+        let mut render_pass =
+            render_context
+                .command_encoder
+                .begin_render_pass(&RenderPassDescriptor {
+                    label: Some("crt_pass"),
+                    color_attachments: &[Some(RenderPassColorAttachment {
+                        view: post_process.destination,
+                        resolve_target: None,
+                        ops: Operations::default(),
+                    })],
+                    depth_stencil_attachment: None,
+                });
+        render_pass.set_pipeline(pipeline);
+        render_pass.set_bind_group(0, &bind_group, &[uniform_index.index()]);
+        render_pass.draw(0..3, 0..1);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_toggling_crt_adds_and_removes_the_effect() {
+        let mut world = World::new();
+        world.insert_resource(CrtSettings {
+            enabled: true,
+            ..default()
+        });
+        let camera = world.spawn(Camera::default()).id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(apply_crt_settings);
+
+        stage.run(&mut world);
+        let crt = world.get::<CrtEffect>(camera).unwrap();
+        assert_eq!(
+            crt.scanline_intensity,
+            CrtSettings::default().scanline_intensity
+        );
+
+        // Turning the effect off removes it from the camera
+        world.resource_mut::<CrtSettings>().enabled = false;
+        stage.run(&mut world);
+        assert!(world.get::<CrtEffect>(camera).is_none());
+
+        // Cameras spawned while the effect is on get it too
+        world.resource_mut::<CrtSettings>().enabled = true;
+        stage.run(&mut world);
+        let new_camera = world.spawn(Camera::default()).id();
+        stage.run(&mut world);
+        assert!(world.get::<CrtEffect>(camera).is_some());
+        assert!(world.get::<CrtEffect>(new_camera).is_some());
+    }
+}
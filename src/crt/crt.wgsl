@@ -0,0 +1,39 @@
+// The CRT screen effect, see `CrtEffect`
+
+#import bevy_core_pipeline::fullscreen_vertex_shader
+
+struct CrtEffect {
+    scanline_intensity: f32,
+    curvature: f32,
+    scanline_count: f32,
+};
+
+@group(0) @binding(0)
+var screen_texture: texture_2d<f32>;
+@group(0) @binding(1)
+var screen_sampler: sampler;
+@group(0) @binding(2)
+var<uniform> crt: CrtEffect;
+
+let PI: f32 = 3.14159265;
+
+@fragment
+fn fragment(in: FullscreenVertexOutput) -> @location(0) vec4<f32> {
+    // Bulge the screen out from its center, more so toward the corners
+    let centered = in.uv * 2.0 - 1.0;
+    let curved = centered + centered * centered.yx * centered.yx * crt.curvature;
+
+    // Past the edges of the curved screen is the black bezel
+    if (abs(curved.x) > 1.0 || abs(curved.y) > 1.0) {
+        return vec4<f32>(0.0, 0.0, 0.0, 1.0);
+    }
+
+    let uv = curved * 0.5 + 0.5;
+    let color = textureSample(screen_texture, screen_sampler, uv);
+
+    // Darken the gaps between the rows of game pixels, brightest at the center of each row
+    let scanline = abs(sin(uv.y * crt.scanline_count * PI));
+    let brightness = mix(1.0, scanline, crt.scanline_intensity);
+
+    return vec4<f32>(color.rgb * brightness, color.a);
+}
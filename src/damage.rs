@@ -1,10 +1,27 @@
 use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    attack::AttackOwners,
+    metadata::{DamageFalloffMeta, FalloffRange, GameMeta, HitReaction},
+    projectile::Projectile,
+    utils::GameTime,
+    GameState,
+};
 
 pub struct DamagePlugin;
 
 impl Plugin for DamagePlugin {
     fn build(&self, app: &mut App) {
-        app.add_event::<DamageEvent>().register_type::<Health>();
+        app.add_event::<DamageEvent>()
+            .add_event::<ParryEvent>()
+            .register_type::<Health>()
+            .add_system(tick_hit_pause.run_in_state(GameState::InGame))
+            .add_system(
+                apply_hit_pause
+                    .run_in_state(GameState::InGame)
+                    .after(tick_hit_pause),
+            );
     }
 }
 
@@ -26,11 +43,671 @@ impl Default for Damageable {
     }
 }
 
+/// Marker for entities in their invincibility frames, like the invulnerability after spawning or
+/// during a dodge, which no source of damage hurts.
+#[derive(Component, Default)]
+#[component(storage = "SparseSet")]
+pub struct Invulnerable;
+
+/// Whether an entity can take damage right now.
+///
+/// Every source of damage checks this before depleting health and sending a [`DamageEvent`], so
+/// that melee attacks, projectiles, explosions, damage over time and ring-outs all honor i-frames
+/// the same way.
+pub fn can_take_damage(
+    damageable: Option<&Damageable>,
+    invulnerable: Option<&Invulnerable>,
+) -> bool {
+    invulnerable.is_none() && damageable.map_or(true, |damageable| **damageable)
+}
+
+/// A component that lowers the damage of a [`Projectile`] the further along its range it is
+#[derive(Component, Clone, Copy, Debug)]
+pub struct DamageFalloff {
+    pub meta: DamageFalloffMeta,
+    /// Where the projectile was fired from
+    pub origin: Vec2,
+}
+
+impl DamageFalloff {
+    /// Get the damage dealt by the projectile when it hits at `position`
+    pub fn damage(&self, damage: i32, projectile: &Projectile, position: Vec2) -> i32 {
+        let travelled = match self.meta.over {
+            FalloffRange::Distance(range) if range > 0.0 => position.distance(self.origin) / range,
+            FalloffRange::Distance(_) => 1.0,
+            FalloffRange::Lifetime => projectile.lifetime.percent(),
+        };
+        let min_multiplier = self.meta.min_multiplier.clamp(0.0, 1.0);
+        let multiplier = 1.0 - (1.0 - min_multiplier) * travelled.clamp(0.0, 1.0);
+
+        (damage as f32 * multiplier).round() as i32
+    }
+}
+
+/// A component that briefly freezes the animation and movement of an entity involved in a hit,
+/// while the rest of the world keeps moving.
+#[derive(Component, Deref, DerefMut)]
+#[component(storage = "SparseSet")]
+pub struct HitPause(pub Timer);
+
+impl HitPause {
+    pub fn new(duration: f32) -> Self {
+        Self(Timer::from_seconds(duration, TimerMode::Once))
+    }
+}
+
+/// The outcome of two opposing attacks hitting each other
+#[derive(Debug, PartialEq, Eq)]
+pub enum Clash {
+    /// The first attack lands and the second is canceled
+    FirstWins,
+    /// The second attack lands and the first is canceled
+    SecondWins,
+    /// Both attacks are canceled
+    Trade,
+}
+
+impl Clash {
+    /// Resolve a clash between two attacks from their clash priorities
+    pub fn new(first_priority: u32, second_priority: u32) -> Self {
+        match first_priority.cmp(&second_priority) {
+            std::cmp::Ordering::Greater => Self::FirstWins,
+            std::cmp::Ordering::Less => Self::SecondWins,
+            std::cmp::Ordering::Equal => Self::Trade,
+        }
+    }
+}
+
+/// Get the approximate point where an attack made contact with a target, from their bounding boxes.
+///
+/// This is the center of the overlap of the boxes, or the point of the attack box nearest to the
+/// target's center if they don't overlap.
+pub fn contact_point(attack: Rect, target: Rect) -> Vec2 {
+    let overlap = attack.intersect(target);
+    if overlap.is_empty() {
+        target.center().clamp(attack.min, attack.max)
+    } else {
+        overlap.center()
+    }
+}
+
 /// Event emitted when an entity is damaged
 pub struct DamageEvent {
     pub damage_velocity: Vec2,
     pub damageing_entity: Entity,
     pub damaged_entity: Entity,
+    /// The world position of the attack that caused the damage
+    pub source_position: Vec2,
+    /// The approximate world position where the attack made contact, for cosmetic effects
+    pub contact_position: Vec2,
     pub damage: i32,
     pub hitstun_duration: f32,
+    /// The pull of attacks that pull their target in, instead of pushing it away
+    pub pull: Option<Pull>,
+    /// How the damaged entity reacts to the hit
+    pub reaction: HitReaction,
+}
+
+impl DamageEvent {
+    /// Whether this is damage over time, like burning, which the damaged entity deals to itself
+    pub fn is_damage_over_time(&self) -> bool {
+        self.damageing_entity == self.damaged_entity
+    }
+}
+
+/// Sent when a parrying fighter negates an attack
+pub struct ParryEvent {
+    pub parrier: Entity,
+    /// The parried attack, which comes from its [owner](AttackOwners::get)
+    pub attack: Entity,
+}
+
+/// The pull of an attack toward the attacker
+#[derive(Clone, Copy, Debug)]
+pub struct Pull {
+    /// The world position the target is pulled toward
+    pub target: Vec2,
+    pub speed: f32,
+}
+
+impl Pull {
+    /// Get the velocity that pulls something at `position` in over `duration` seconds.
+    ///
+    /// The pull stops at its target instead of overshooting it, so that nothing is pulled past the
+    /// attacker, and out of the level bounds the attacker is in.
+    pub fn velocity(&self, position: Vec2, duration: f32) -> Vec2 {
+        let offset = self.target - position;
+        let max_speed = if duration > 0.0 {
+            offset.length() / duration
+        } else {
+            0.0
+        };
+
+        offset.normalize_or_zero() * self.speed.min(max_speed)
+    }
+}
+
+/// Pauses both the attacker and the target of every hit
+fn apply_hit_pause(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    owners: AttackOwners,
+    game: Res<GameMeta>,
+) {
+    if game.hit_pause <= 0.0 {
+        return;
+    }
+
+    // Damage over time doesn't pause anything
+    for event in damage_events
+        .iter()
+        .filter(|event| !event.is_damage_over_time())
+    {
+        let attacker = owners.get(event.damageing_entity);
+        for entity in [attacker, event.damaged_entity] {
+            if let Some(mut entity) = commands.get_entity(entity) {
+                entity.insert(HitPause::new(game.hit_pause));
+            }
+        }
+    }
+}
+
+/// Resumes paused entities once their hit pause is over
+fn tick_hit_pause(
+    mut commands: Commands,
+    mut paused: Query<(Entity, &mut HitPause)>,
+    time: GameTime,
+) {
+    for (entity, mut hit_pause) in &mut paused {
+        if hit_pause.tick(time.delta()).finished() {
+            commands.entity(entity).remove::<HitPause>();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use bevy_rapier2d::{
+        prelude::{Collider, CollisionEvent},
+        rapier::geometry::CollisionEventFlags,
+    };
+
+    use super::*;
+    use crate::{
+        animation::AnimatedSpriteSheetBundle,
+        animation::{animation_cycling, Animation, Clip},
+        attack::BrokeEvent,
+        attack::{
+            attack_damage_system, deactivate_hitbox, Attack, AttackFrames, Connected, Hurtbox,
+            WhiffEvent,
+        },
+        camera::{CameraShake, CameraTilt},
+        item::{explodable_system, Explodable},
+        lifetime::LifetimeExpired,
+        metadata::{BurnMeta, FighterMeta},
+        projectile::projectile_system,
+        status_effect::{burn, Burning},
+    };
+
+    #[test]
+    fn test_bullets_deal_less_damage_further_along_their_range() {
+        let mut world = World::new();
+        world.init_resource::<Events<CollisionEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ParryEvent>>();
+
+        let falloff = DamageFalloff {
+            meta: DamageFalloffMeta {
+                over: FalloffRange::Distance(200.0),
+                min_multiplier: 0.25,
+            },
+            origin: Vec2::ZERO,
+        };
+        let hit_at = |world: &mut World, x: f32| {
+            let mut hurtbox = None;
+            let target = world
+                .spawn((Health(100), Damageable(true)))
+                .with_children(|parent| hurtbox = Some(parent.spawn(Hurtbox).id()))
+                .id();
+            let attack = Attack {
+                damage: 100,
+                ..default()
+            };
+            let bullet = world
+                .spawn((
+                    attack,
+                    Projectile::new(Vec2::X * 100.0, 2.0, attack),
+                    falloff,
+                    GlobalTransform::from_xyz(x, 0.0, 0.0),
+                ))
+                .id();
+            world.send_event(CollisionEvent::Started(
+                bullet,
+                hurtbox.unwrap(),
+                CollisionEventFlags::empty(),
+            ));
+
+            let mut stage = SystemStage::single_threaded();
+            stage.add_system(attack_damage_system);
+            stage.run(world);
+            100 - **world.get::<Health>(target).unwrap()
+        };
+
+        // Bullets hitting near where they were fired deal more damage than near the end of their
+        // range, and never less than the minimum
+        let near = hit_at(&mut world, 10.0);
+        let far = hit_at(&mut world, 190.0);
+        assert!(near > far);
+        assert_eq!(near, 96);
+        assert_eq!(far, 29);
+        assert_eq!(hit_at(&mut world, 1000.0), 25);
+    }
+
+    #[test]
+    fn test_contact_point_from_the_left() {
+        let target = Rect::from_center_size(Vec2::ZERO, Vec2::new(20., 40.));
+
+        // An attack overlapping the target from the left makes contact on its left side
+        let attack = Rect::from_center_size(Vec2::new(-15., 5.), Vec2::new(20., 10.));
+        let contact = contact_point(attack, target);
+        assert!(contact.x < target.center().x && contact.x >= target.min.x);
+        assert!(target.contains(contact));
+
+        // An attack only reaching the target's edge makes contact at its nearest edge
+        let attack = Rect::from_center_size(Vec2::new(-20., 0.), Vec2::new(20., 10.));
+        assert_eq!(contact_point(attack, target), Vec2::new(-10., 0.));
+    }
+
+    #[test]
+    fn test_hit_pause_only_freezes_involved_fighters() {
+        let mut world = World::new();
+        let mut game: GameMeta =
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap();
+        game.hit_pause = 0.2;
+        world.insert_resource(game);
+        world.init_resource::<Events<DamageEvent>>();
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+
+        let fighter = |world: &mut World| {
+            let mut animation = Animation::new(
+                0.05,
+                [(
+                    "idle".to_string(),
+                    Clip {
+                        frames: 0..10,
+                        repeat: true,
+                    },
+                )]
+                .into_iter()
+                .collect(),
+            );
+            animation.play("idle", true);
+            world.spawn((TextureAtlasSprite::default(), animation)).id()
+        };
+        let attacker = fighter(&mut world);
+        let target = fighter(&mut world);
+        let bystander = fighter(&mut world);
+        // The attack comes from a weapon held by the attacker
+        let weapon = world.spawn_empty().id();
+        let attack = world.spawn_empty().id();
+        world.entity_mut(attacker).add_child(weapon);
+        world.entity_mut(weapon).add_child(attack);
+
+        world.send_event(DamageEvent {
+            damage_velocity: Vec2::ZERO,
+            damageing_entity: attack,
+            damaged_entity: target,
+            source_position: Vec2::ZERO,
+            contact_position: Vec2::ZERO,
+            damage: 10,
+            hitstun_duration: 0.5,
+            pull: None,
+            reaction: HitReaction::Knockback,
+        });
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(tick_hit_pause)
+            .add_system(apply_hit_pause.after(tick_hit_pause))
+            .add_system(animation_cycling.after(apply_hit_pause));
+        stage.run(&mut world);
+
+        for _ in 0..2 {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(0.06));
+            stage.run(&mut world);
+        }
+
+        // Only the bystander kept animating during the hit pause
+        let frame = |entity| world.get::<Animation>(entity).unwrap().current_frame;
+        assert_eq!(frame(attacker), 0);
+        assert_eq!(frame(target), 0);
+        assert!(frame(bystander) > 0);
+        assert!(world.get::<HitPause>(target).is_some());
+        assert!(world.get::<HitPause>(attacker).is_some());
+        assert!(world.get::<HitPause>(weapon).is_none());
+    }
+
+    #[test]
+    fn test_higher_priority_attack_wins_clash() {
+        let mut world = World::new();
+        world.init_resource::<Events<CollisionEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ParryEvent>>();
+
+        let spawn_fighter = |world: &mut World, clash_priority: u32| {
+            let mut hurtbox = None;
+            let fighter = world
+                .spawn((Health(100), Damageable(true)))
+                .with_children(|parent| hurtbox = Some(parent.spawn(Hurtbox).id()))
+                .id();
+            let attack = world
+                .spawn(Attack {
+                    damage: 10,
+                    clash_priority: Some(clash_priority),
+                    ..default()
+                })
+                .id();
+            (fighter, hurtbox.unwrap(), attack)
+        };
+        let (strong, strong_hurtbox, strong_attack) = spawn_fighter(&mut world, 2);
+        let (weak, weak_hurtbox, weak_attack) = spawn_fighter(&mut world, 1);
+
+        // Both attacks hit each other and the opposing fighter in the same frame
+        for (e1, e2) in [
+            (strong_attack, weak_attack),
+            (strong_attack, weak_hurtbox),
+            (weak_attack, strong_hurtbox),
+        ] {
+            world.send_event(CollisionEvent::Started(
+                e1,
+                e2,
+                CollisionEventFlags::empty(),
+            ));
+        }
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(attack_damage_system);
+        stage.run(&mut world);
+
+        // The weaker attack is canceled, and the stronger one still lands
+        assert!(world.get_entity(weak_attack).is_none());
+        assert!(world.get_entity(strong_attack).is_some());
+        assert_eq!(**world.get::<Health>(weak).unwrap(), 90);
+        assert_eq!(**world.get::<Health>(strong).unwrap(), 100);
+
+        // Equal priorities trade, canceling both
+        assert_eq!(Clash::new(1, 1), Clash::Trade);
+    }
+
+    #[test]
+    fn test_attacks_only_damage_during_active_frames() {
+        let mut world = World::new();
+        world.init_resource::<Events<CollisionEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ParryEvent>>();
+
+        let mut hurtbox = None;
+        let target = world
+            .spawn((Health(100), Damageable(true)))
+            .with_children(|parent| hurtbox = Some(parent.spawn(Hurtbox).id()))
+            .id();
+        let attacker = world.spawn(Animation::new(0.05, default())).id();
+        let attack = world
+            .spawn((
+                Attack {
+                    damage: 10,
+                    ..default()
+                },
+                AttackFrames {
+                    startup: 2,
+                    active: 3,
+                    recovery: 5,
+                },
+            ))
+            .id();
+        world.entity_mut(attacker).add_child(attack);
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(attack_damage_system);
+        let mut hit_on_frame = |world: &mut World, frame: usize| {
+            world.get_mut::<Animation>(attacker).unwrap().current_frame = frame;
+            world.send_event(CollisionEvent::Started(
+                attack,
+                hurtbox.unwrap(),
+                CollisionEventFlags::empty(),
+            ));
+            stage.run(world);
+            **world.get::<Health>(target).unwrap()
+        };
+
+        // Overlapping the target during the startup frames deals no damage
+        assert_eq!(hit_on_frame(&mut world, 0), 100);
+        assert_eq!(hit_on_frame(&mut world, 1), 100);
+
+        // But it does during the active frames
+        assert_eq!(hit_on_frame(&mut world, 2), 90);
+        assert_eq!(hit_on_frame(&mut world, 4), 80);
+
+        // And no longer once recovering
+        assert_eq!(hit_on_frame(&mut world, 5), 80);
+    }
+
+    #[test]
+    fn test_attacks_that_miss_whiff() {
+        let mut world = World::new();
+        world.init_resource::<Events<CollisionEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ParryEvent>>();
+        world.init_resource::<Events<WhiffEvent>>();
+
+        let mut hurtbox = None;
+        world
+            .spawn((Health(100), Damageable(true)))
+            .with_children(|parent| hurtbox = Some(parent.spawn(Hurtbox).id()));
+        let hurtbox = hurtbox.unwrap();
+        let attacker = world.spawn(Animation::new(0.05, default())).id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(attack_damage_system)
+            .add_system(deactivate_hitbox.after(attack_damage_system));
+        // Swing an attack, overlapping the target or not, and get the attackers that whiffed
+        let mut swing = |world: &mut World, hits: bool| {
+            let attack = world
+                .spawn((
+                    Attack {
+                        damage: 10,
+                        ..default()
+                    },
+                    AttackFrames {
+                        startup: 0,
+                        active: 1,
+                        recovery: 2,
+                    },
+                    Connected::default(),
+                    Collider::cuboid(10., 10.),
+                ))
+                .id();
+            world.entity_mut(attacker).add_child(attack);
+
+            // Active frames
+            world.get_mut::<Animation>(attacker).unwrap().current_frame = 0;
+            if hits {
+                world.send_event(CollisionEvent::Started(
+                    attack,
+                    hurtbox,
+                    CollisionEventFlags::empty(),
+                ));
+            }
+            stage.run(world);
+
+            // Recovery
+            world.get_mut::<Animation>(attacker).unwrap().current_frame = 2;
+            stage.run(world);
+            assert!(world.get_entity(attack).is_none());
+
+            world
+                .resource_mut::<Events<WhiffEvent>>()
+                .drain()
+                .map(|event| event.attacker)
+                .collect::<Vec<_>>()
+        };
+
+        // An attack that never overlaps a target whiffs
+        assert_eq!(swing(&mut world, false), [attacker]);
+
+        // And one that connects doesn't
+        assert!(swing(&mut world, true).is_empty());
+    }
+
+    #[test]
+    fn test_invulnerable_fighters_take_no_damage_from_any_source() {
+        let mut world = World::new();
+        world.init_resource::<Events<CollisionEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ParryEvent>>();
+        world.init_resource::<Events<BrokeEvent>>();
+        world.init_resource::<Events<LifetimeExpired>>();
+        world.init_resource::<CameraShake>();
+        world.init_resource::<CameraTilt>();
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+
+        let mut hurtbox = None;
+        let target = world
+            .spawn((
+                Health(100),
+                Damageable(true),
+                Invulnerable,
+                Burning::new(BurnMeta {
+                    dps: 10.0,
+                    duration: 10.0,
+                }),
+            ))
+            .with_children(|parent| hurtbox = Some(parent.spawn(Hurtbox).id()))
+            .id();
+        let hurtbox = hurtbox.unwrap();
+
+        let fighter: FighterMeta =
+            serde_yaml::from_str(include_str!("../assets/fighters/dev/dev.fighter.yaml")).unwrap();
+        let mut explosion_animation = Animation::new(
+            0.1,
+            [(
+                "explosion".to_string(),
+                Clip {
+                    frames: 0..5,
+                    repeat: false,
+                },
+            )]
+            .into_iter()
+            .collect(),
+        );
+        explosion_animation.play("explosion", false);
+        let explodable = Explodable {
+            attack: fighter.attacks[0].clone(),
+            timer: Timer::from_seconds(1.0, TimerMode::Once),
+            fusing: false,
+            animated_sprite: AnimatedSpriteSheetBundle {
+                sprite_sheet: default(),
+                animation: explosion_animation,
+            },
+            explosion_frames: AttackFrames {
+                startup: 0,
+                active: 4,
+                recovery: 5,
+            },
+            attack_enemy: true,
+        };
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(explodable_system);
+        stage.add_system(projectile_system);
+        stage.add_system(attack_damage_system);
+        stage.add_system(burn);
+        let mut step = |world: &mut World| {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(0.1));
+            stage.run(world);
+        };
+        // Hit the target with a melee attack, a bullet and an explosion, while it keeps burning,
+        // and get the entities that damaged it
+        let mut hit = |world: &mut World| {
+            world.resource_mut::<Events<DamageEvent>>().clear();
+
+            // A bomb goes off, spawning the attack of its explosion
+            let existing_attacks = world
+                .query_filtered::<Entity, With<AttackFrames>>()
+                .iter(world)
+                .collect::<Vec<_>>();
+            world.send_event(BrokeEvent {
+                drop: None,
+                transform: Some(Transform::default()),
+                explodable: Some(explodable.clone()),
+            });
+            step(world);
+            let explosion = world
+                .query_filtered::<Entity, With<AttackFrames>>()
+                .iter(world)
+                .find(|entity| !existing_attacks.contains(entity))
+                .unwrap();
+
+            let attack = Attack {
+                damage: 10,
+                ..default()
+            };
+            let melee = world.spawn(attack).id();
+            let bullet = world
+                .spawn((
+                    attack,
+                    Projectile::new(Vec2::X * 100.0, 2.0, attack),
+                    Transform::default(),
+                ))
+                .id();
+            for attack in [melee, bullet, explosion] {
+                world.send_event(CollisionEvent::Started(
+                    attack,
+                    hurtbox,
+                    CollisionEventFlags::empty(),
+                ));
+            }
+            step(world);
+
+            // The bullet is spent on the hit
+            assert!(world.get_entity(bullet).is_none());
+
+            let events = world.resource::<Events<DamageEvent>>();
+            let damaging = events
+                .get_reader()
+                .iter(events)
+                .filter(|event| event.damaged_entity == target)
+                .map(|event| event.damageing_entity)
+                .collect::<Vec<_>>();
+            (damaging, [melee, bullet, explosion])
+        };
+
+        // Over a whole burn tick, no source hurts the invulnerable target
+        for _ in 0..3 {
+            let (damaging, _) = hit(&mut world);
+            assert!(damaging.is_empty());
+        }
+        assert_eq!(**world.get::<Health>(target).unwrap(), 100);
+
+        // Every source hurts again once the i-frames are over
+        world.entity_mut(target).remove::<Invulnerable>();
+        let mut burn_events = 0;
+        for _ in 0..3 {
+            let (damaging, attacks) = hit(&mut world);
+            for attack in attacks {
+                assert_eq!(damaging.iter().filter(|e| **e == attack).count(), 1);
+            }
+            burn_events += damaging.iter().filter(|e| **e == target).count();
+        }
+        assert_eq!(burn_events, 1);
+    }
 }
@@ -1,9 +1,16 @@
-use bevy::prelude::*;
+use std::time::Duration;
+
+use bevy::{prelude::*, utils::HashMap};
 
 use crate::{
     animation::Facing,
     consts,
-    metadata::{FighterMeta, FighterSpawnMeta},
+    damage::Invulnerable,
+    fighter_state::{BossBombThrow, Idling, SpawningEntrance},
+    interpolation::PreviousTransform,
+    metadata::{EntranceStyle, FighterMeta, FighterSpawnMeta},
+    utils::GameTime,
+    Stats,
 };
 
 #[derive(Component)]
@@ -13,6 +20,82 @@ pub struct Enemy;
 #[derive(Component)]
 pub struct Boss;
 
+/// The cooldowns of a boss's special attacks, which it only uses once they are ready.
+#[derive(Component)]
+pub struct BossCooldowns(pub Vec<BossCooldown>);
+
+pub struct BossCooldown {
+    /// The animation of the attack, which identifies it
+    pub attack: &'static str,
+    pub timer: Timer,
+}
+
+impl BossCooldown {
+    /// A cooldown that starts out ready
+    pub fn new(attack: &'static str, seconds: f32) -> Self {
+        let mut timer = Timer::from_seconds(seconds, TimerMode::Once);
+        timer.tick(timer.duration());
+
+        Self { attack, timer }
+    }
+}
+
+impl Default for BossCooldowns {
+    fn default() -> Self {
+        Self(vec![BossCooldown::new(
+            BossBombThrow::ANIMATION,
+            consts::BOSS_BOMB_THROW_COOLDOWN,
+        )])
+    }
+}
+
+impl BossCooldowns {
+    pub fn tick(&mut self, delta: Duration) {
+        for cooldown in &mut self.0 {
+            cooldown.timer.tick(delta);
+        }
+    }
+
+    /// Get how far along the cooldown of an attack is, from 0 right after the attack to 1 when it
+    /// is ready. Attacks without a cooldown are always ready.
+    pub fn progress(&self, attack: &str) -> f32 {
+        self.0
+            .iter()
+            .find(|cooldown| cooldown.attack == attack)
+            .map_or(1., |cooldown| cooldown.timer.percent())
+    }
+
+    pub fn is_ready(&self, attack: &str) -> bool {
+        self.progress(attack) >= 1.
+    }
+
+    /// Start the cooldown of an attack over, when the boss uses it
+    pub fn trigger(&mut self, attack: &str) {
+        for cooldown in &mut self.0 {
+            if cooldown.attack == attack {
+                cooldown.timer.reset();
+            }
+        }
+    }
+}
+
+/// The time left until a boss calls in its next reinforcements
+#[derive(Component, Deref, DerefMut)]
+pub struct Reinforcements(pub Timer);
+
+impl Reinforcements {
+    pub fn new(interval: f32) -> Self {
+        Self(Timer::from_seconds(interval, TimerMode::Repeating))
+    }
+}
+
+/// Advances the cooldowns of the bosses' attacks
+pub fn tick_boss_cooldowns(mut bosses: Query<&mut BossCooldowns>, time: GameTime) {
+    for mut cooldowns in &mut bosses {
+        cooldowns.tick(time.delta());
+    }
+}
+
 /// X coordinate of the level that requires to be trespassed in order for the enemies to move.
 /// For simplicy, once a given trip point is trespassed for the first time, it's set to f32::MIN.
 #[derive(Component)]
@@ -21,6 +104,139 @@ pub struct TripPointX(pub f32);
 #[derive(Component)]
 pub struct SpawnLocationX(pub f32);
 
+/// The time an enemy stays idle for once its [`TripPointX`] is trespassed, before it engages the
+/// players.
+#[derive(Component)]
+pub struct EngageDelay(pub Timer);
+
+impl EngageDelay {
+    pub fn new(seconds: f32) -> Self {
+        Self(Timer::from_seconds(seconds, TimerMode::Once))
+    }
+
+    /// Whether the enemy may engage the players
+    pub fn is_over(&self) -> bool {
+        self.0.finished()
+    }
+}
+
+/// The entrance an enemy makes when it is activated, instead of appearing in place.
+#[derive(Component)]
+pub struct Entrance(pub EntranceStyle);
+
+impl Entrance {
+    /// Get the position the entrance of an enemy at `location` starts from.
+    ///
+    /// The `view` is the horizontal extent of the camera view, if there is a camera.
+    pub fn start_position(&self, location: Vec2, view: Option<(f32, f32)>) -> Vec2 {
+        match self.0 {
+            EntranceStyle::Walk => {
+                let x = match view {
+                    Some((left, right)) if location.x < (left + right) / 2. => {
+                        location.x.min(left) - consts::ENEMY_ENTRANCE_MARGIN
+                    }
+                    Some((_, right)) => location.x.max(right) + consts::ENEMY_ENTRANCE_MARGIN,
+                    None => location.x + consts::ENEMY_ENTRANCE_MARGIN,
+                };
+                Vec2::new(x, location.y)
+            }
+            EntranceStyle::Drop => location + Vec2::new(0., consts::ENEMY_ENTRANCE_DROP_HEIGHT),
+        }
+    }
+
+    /// Put a freshly activated enemy in the [`SpawningEntrance`] state, moving it to the start of
+    /// its entrance. It can't be damaged until it arrives at its location.
+    pub fn start(
+        &self,
+        commands: &mut Commands,
+        entity: Entity,
+        transform: &Transform,
+        stats: &Stats,
+        view: Option<(f32, f32)>,
+    ) {
+        let location = transform.translation.truncate();
+        let start = Transform::from_translation(
+            self.start_position(location, view)
+                .extend(transform.translation.z),
+        );
+        let speed = match self.0 {
+            EntranceStyle::Walk => stats.movement_speed,
+            EntranceStyle::Drop => consts::ENEMY_ENTRANCE_DROP_SPEED,
+        };
+
+        commands.entity(entity).remove::<Idling>().insert((
+            SpawningEntrance {
+                target: location,
+                speed,
+                is_finished: false,
+            },
+            Invulnerable,
+            start,
+            PreviousTransform(start),
+        ));
+    }
+}
+
+/// Spawns the enemies of a level, thinned out by the density scale.
+///
+/// Enemies are always spawned in the level order, so that they are activated, and draw from the
+/// [`GameRng`][crate::utils::GameRng], in the same order for the same level.
+///
+/// With an `engage_delay`, the enemies wait that long after being tripped before they engage.
+pub fn spawn_enemies(
+    commands: &mut Commands,
+    enemies: &[FighterSpawnMeta],
+    density_scale: f32,
+    engage_delay: Option<f32>,
+) {
+    for enemy in thin_enemies(enemies, density_scale) {
+        let mut ec = commands.spawn(EnemyBundle::new(enemy));
+
+        if let Some(engage_delay) = engage_delay {
+            ec.insert(EngageDelay::new(engage_delay));
+        }
+
+        if enemy.boss {
+            ec.insert((Boss, BossCooldowns::default()));
+        }
+
+        if let Some(style) = enemy.entrance {
+            ec.insert(Entrance(style));
+        }
+    }
+}
+
+/// Get the enemies to spawn with a density scale from `0.0` to `1.0`, which reduces the number of
+/// enemies in each group of enemies sharing a trip point proportionally.
+///
+/// Every group keeps at least one enemy, and bosses are always kept, so that the levels play out
+/// the same way with fewer enemies.
+pub fn thin_enemies(enemies: &[FighterSpawnMeta], density_scale: f32) -> Vec<&FighterSpawnMeta> {
+    let density_scale = density_scale.clamp(0.0, 1.0);
+    let group = |enemy: &FighterSpawnMeta| enemy.trip_point_x.to_bits();
+
+    let mut group_sizes = HashMap::<u32, usize>::default();
+    for enemy in enemies.iter().filter(|enemy| !enemy.boss) {
+        *group_sizes.entry(group(enemy)).or_default() += 1;
+    }
+
+    let mut spawned = HashMap::<u32, usize>::default();
+    enemies
+        .iter()
+        .filter(|enemy| {
+            if enemy.boss {
+                return true;
+            }
+
+            let group = group(enemy);
+            let quota = ((group_sizes[&group] as f32 * density_scale).round() as usize).max(1);
+            let count = spawned.entry(group).or_default();
+            *count += 1;
+            *count <= quota
+        })
+        .collect()
+}
+
 #[derive(Bundle)]
 pub struct EnemyBundle {
     enemy: Enemy,
@@ -52,3 +268,107 @@ impl EnemyBundle {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use rand::Rng;
+
+    use crate::utils::GameRng;
+
+    use super::*;
+
+    /// Run a headless app that loads the given enemies with the given level seed, returning the
+    /// enemy positions and the next random number.
+    fn spawn_level(
+        seed: u64,
+        enemies: Vec<FighterSpawnMeta>,
+        density_scale: f32,
+    ) -> (Vec<Vec3>, u32) {
+        let mut app = App::new();
+        app.insert_resource(GameRng::new(seed)).add_startup_system(
+            move |mut commands: Commands| {
+                spawn_enemies(&mut commands, &enemies, density_scale, None)
+            },
+        );
+        app.update();
+
+        let positions = app
+            .world
+            .query_filtered::<&Transform, With<Enemy>>()
+            .iter(&app.world)
+            .map(|transform| transform.translation)
+            .collect();
+        let next = app.world.resource_mut::<GameRng>().gen();
+
+        (positions, next)
+    }
+
+    fn enemy(x: f32, y: f32, trip_point_x: f32) -> FighterSpawnMeta {
+        FighterSpawnMeta {
+            fighter: "fighters/bandit/bandit.fighter.yaml".into(),
+            fighter_handle: default(),
+            location: Vec3::new(x, y, 0.),
+            trip_point_x,
+            boss: false,
+            entrance: None,
+        }
+    }
+
+    #[test]
+    fn test_same_seed_spawns_identical_enemies() {
+        let enemies = vec![
+            enemy(325., 0., -1.),
+            enemy(225., 50., -1.),
+            enemy(800., 20., -1.),
+        ];
+
+        let (a_positions, a_next) = spawn_level(42, enemies.clone(), 1.0);
+        let (b_positions, b_next) = spawn_level(42, enemies, 1.0);
+
+        assert_eq!(a_positions.len(), 3);
+        assert_eq!(a_positions, b_positions);
+        assert_eq!(a_next, b_next);
+    }
+
+    #[test]
+    fn test_half_density_halves_wave() {
+        let wave = (0..4)
+            .map(|i| enemy(400. + i as f32 * 50., 0., 300.))
+            .collect::<Vec<_>>();
+        let mut enemies = wave.clone();
+        enemies.push(enemy(900., 0., 700.));
+
+        let (full_positions, _) = spawn_level(42, wave.clone(), 1.0);
+        let (half_positions, _) = spawn_level(42, wave, 0.5);
+        assert_eq!(full_positions.len(), 4);
+        assert_eq!(half_positions.len(), 2);
+
+        // Every group keeps at least one enemy
+        let (positions, _) = spawn_level(42, enemies, 0.5);
+        assert_eq!(positions.len(), 3);
+        assert!(positions.iter().any(|position| position.x == 900.));
+    }
+
+    #[test]
+    fn test_boss_cooldown_fills_and_resets() {
+        let mut cooldowns = BossCooldowns::default();
+        let bomb_throw = BossBombThrow::ANIMATION;
+        let seconds = |seconds: f32| Duration::from_secs_f32(seconds);
+
+        // The boss starts out ready
+        assert!(cooldowns.is_ready(bomb_throw));
+
+        // Using the attack resets the cooldown, which then fills back up over time
+        cooldowns.trigger(bomb_throw);
+        assert_eq!(cooldowns.progress(bomb_throw), 0.);
+        cooldowns.tick(seconds(consts::BOSS_BOMB_THROW_COOLDOWN / 2.));
+        assert!((cooldowns.progress(bomb_throw) - 0.5).abs() < 0.01);
+        assert!(!cooldowns.is_ready(bomb_throw));
+        cooldowns.tick(seconds(consts::BOSS_BOMB_THROW_COOLDOWN));
+        assert_eq!(cooldowns.progress(bomb_throw), 1.);
+        assert!(cooldowns.is_ready(bomb_throw));
+
+        cooldowns.trigger(bomb_throw);
+        assert_eq!(cooldowns.progress(bomb_throw), 0.);
+    }
+}
@@ -1,19 +1,22 @@
 //! Enemy fighter AI
 
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 use rand::Rng;
 
 use crate::{
     animation::Facing,
     consts::{self, ENEMY_MAX_ATTACK_DISTANCE, ENEMY_MIN_ATTACK_DISTANCE, ENEMY_TARGET_MAX_OFFSET},
-    enemy::{Boss, Enemy, TripPointX},
+    damage::DamageEvent,
+    enemy::{spawn_enemies, Boss, BossCooldowns, Enemy, EngageDelay, Reinforcements, TripPointX},
     fighter::AvailableAttacks,
     fighter_state::{
-        BossBombThrow, Idling, Moving, ProjectileAttacking, Punching, StateTransition,
-        StateTransitionIntents,
+        BossBombThrow, Downed, Dying, Idling, Moving, ProjectileAttacking, Punching,
+        StateTransition, StateTransitionIntents,
     },
-    metadata::{ItemKind, ItemMeta},
+    metadata::{FighterMeta, FighterSpawnMeta, GameMeta, ItemKind, ItemMeta, LevelMeta},
     player::Player,
+    status_effect::Confused,
+    utils::{GameRng, GameTime},
     Stats,
 };
 
@@ -33,32 +36,128 @@ pub struct WalkTarget {
     pub player_pos: Vec2,
 }
 
+/// The player that an enemy is going after.
+///
+/// Unlike the [`WalkTarget`], this is kept while the enemy attacks, until it picks another target
+/// or dies, so that the enemies going after each player can be counted to spread them out.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Aggro(pub Entity);
+
+/// Sent when an enemy first engages the players, to alert the idle enemies around it
+pub struct EnemyAlertEvent {
+    /// Where the enemy was when it engaged
+    pub position: Vec2,
+}
+
 // For enemys without current target, pick a new spot near the player as target
 ///
 /// This is added to the [`crate::fighter_state::FighterStateCollectSystems`] to collect figher
 /// actions for enemies.
+///
+/// [`Confused`] enemies pick a spot near the closest other enemy instead, and enemies still in
+/// their [`EngageDelay`] stay idle.
+///
+/// [`Downed`] players aren't targeted.
+///
+/// With [`GameMeta::aggro_balancing`], the enemies spread out across the players instead of all
+/// going after the nearest one.
 pub fn set_move_target_near_player(
     mut commands: Commands,
     mut enemies_query: Query<
-        (Entity, &mut TripPointX, &Transform, &AvailableAttacks),
+        (
+            Entity,
+            &mut TripPointX,
+            &Transform,
+            &Stats,
+            &AvailableAttacks,
+            Option<&Confused>,
+            Option<&EngageDelay>,
+            Option<&Aggro>,
+        ),
         (With<Enemy>, With<Idling>, Without<WalkTarget>),
     >,
-    player_query: Query<&Transform, With<Player>>,
+    player_query: Query<(Entity, &Transform), (With<Player>, Without<Downed>)>,
+    all_enemies: Query<(Entity, &Transform), With<Enemy>>,
+    aggro_query: Query<&Aggro, With<Enemy>>,
     items_assets: Res<Assets<ItemMeta>>,
+    level_meta: Res<LevelMeta>,
+    game: Option<Res<GameMeta>>,
+    mut alert_events: EventWriter<EnemyAlertEvent>,
+    mut rng: ResMut<GameRng>,
 ) {
-    let mut rng = rand::thread_rng();
-    let p_transforms = player_query.iter().collect::<Vec<_>>();
-    let max_player_x = p_transforms
+    let players = player_query.iter().collect::<Vec<_>>();
+    let max_player_x = players
         .iter()
-        .map(|transform| transform.translation.x)
+        .map(|(_, transform)| transform.translation.x)
         .max_by(f32::total_cmp);
 
+    // The number of enemies going after each player
+    let mut aggro = HashMap::<Entity, usize>::default();
+    for Aggro(player) in &aggro_query {
+        *aggro.entry(*player).or_default() += 1;
+    }
+    let distance_per_enemy = game
+        .and_then(|game| game.aggro_balancing)
+        .map_or(0., |balancing| balancing.distance_per_enemy);
+
     if let Some(max_player_x) = max_player_x {
-        for (e_entity, mut e_trip_point_x, e_transform, available_attacks) in
-            enemies_query.iter_mut()
+        for (
+            e_entity,
+            mut e_trip_point_x,
+            e_transform,
+            stats,
+            available_attacks,
+            confused,
+            engage_delay,
+            current_aggro,
+        ) in enemies_query.iter_mut()
         {
-            if let Some(p_transform) = choose_player(&p_transforms, e_transform) {
+            if engage_delay.map_or(false, |delay| !delay.is_over()) {
+                continue;
+            }
+
+            // Confused enemies go after the other enemies instead of the players
+            let target = if confused.is_some() {
+                let other_enemies = all_enemies
+                    .iter()
+                    .filter(|(entity, _)| *entity != e_entity)
+                    .map(|(_, transform)| transform)
+                    .collect::<Vec<_>>();
+                choose_player(&other_enemies, e_transform).map(|transform| (None, transform))
+            } else {
+                // The enemy picking a new target doesn't count toward its current one
+                let current_player = current_aggro.map(|Aggro(player)| *player);
+                choose_balanced_player(
+                    &players,
+                    &aggro,
+                    current_player,
+                    e_transform,
+                    distance_per_enemy,
+                )
+                .map(|(player, transform)| (Some(player), transform))
+            };
+
+            if let Some((player, p_transform)) = target {
                 if max_player_x > e_trip_point_x.0 {
+                    // Move the enemy's aggro over to its new target
+                    if let Some(Aggro(current)) = current_aggro {
+                        *aggro.entry(*current).or_default() -= 1;
+                    }
+                    match player {
+                        Some(player) => {
+                            *aggro.entry(player).or_default() += 1;
+                            commands.entity(e_entity).insert(Aggro(player));
+                        }
+                        None => {
+                            commands.entity(e_entity).remove::<Aggro>();
+                        }
+                    }
+
+                    if e_trip_point_x.0 != f32::MIN {
+                        alert_events.send(EnemyAlertEvent {
+                            position: e_transform.translation.truncate(),
+                        });
+                    }
                     e_trip_point_x.0 = f32::MIN;
 
                     let mut x_offset =
@@ -101,6 +200,16 @@ pub fn set_move_target_near_player(
                         _ => {}
                     }
 
+                    // Enemies with a preferred range stay at that range, on their side of the
+                    // player
+                    if let Some(range) = stats.preferred_range {
+                        x_offset = if e_transform.translation.x < p_transform.translation.x {
+                            -range
+                        } else {
+                            range
+                        };
+                    }
+
                     let attack_distance =
                         rng.gen_range(ENEMY_MIN_ATTACK_DISTANCE..ENEMY_MAX_ATTACK_DISTANCE);
 
@@ -108,7 +217,7 @@ pub fn set_move_target_near_player(
                         position: Vec2::new(
                             p_transform.translation.x + x_offset,
                             (p_transform.translation.y + y_offset)
-                                .clamp(consts::MIN_Y, consts::MAX_Y),
+                                .clamp(level_meta.bounds.min_y(), level_meta.bounds.max_y()),
                         ),
                         attack_distance,
                         player_pos: p_transform.translation.truncate(),
@@ -119,6 +228,78 @@ pub fn set_move_target_near_player(
     }
 }
 
+/// Clears the [`Aggro`] of dying enemies, so that they no longer count toward their player
+pub fn clear_dying_enemy_aggro(
+    mut commands: Commands,
+    enemies: Query<Entity, (With<Enemy>, With<Aggro>, Added<Dying>)>,
+) {
+    for enemy in &enemies {
+        commands.entity(enemy).remove::<Aggro>();
+    }
+}
+
+/// Wakes up the enemies near an enemy that engaged or took damage, by tripping their
+/// [`TripPointX`], so that groups of enemies engage together.
+///
+/// Woken up enemies don't alert others in turn.
+pub fn alert_nearby_enemies(
+    mut alert_events: EventReader<EnemyAlertEvent>,
+    mut damage_events: EventReader<DamageEvent>,
+    mut enemies: Query<(&Transform, &mut TripPointX), With<Enemy>>,
+    game: Res<GameMeta>,
+) {
+    let mut alert_positions = alert_events
+        .iter()
+        .map(|event| event.position)
+        .collect::<Vec<_>>();
+    // Damage over time doesn't alert anyone
+    let hits = damage_events
+        .iter()
+        .filter(|event| !event.is_damage_over_time());
+    alert_positions.extend(hits.filter_map(|event| {
+        enemies
+            .get(event.damaged_entity)
+            .ok()
+            .map(|(transform, _)| transform.translation.truncate())
+    }));
+
+    let radius = game.enemy_alert.radius;
+    if alert_positions.is_empty() || radius <= 0.0 {
+        return;
+    }
+
+    for (transform, mut trip_point_x) in &mut enemies {
+        let position = transform.translation.truncate();
+        if trip_point_x.0 != f32::MIN
+            && alert_positions
+                .iter()
+                .any(|alert| alert.distance(position) <= radius)
+        {
+            trip_point_x.0 = f32::MIN;
+        }
+    }
+}
+
+/// Advances the [`EngageDelay`] of the enemies whose [`TripPointX`] has been trespassed
+pub fn tick_engage_delays(
+    mut enemies: Query<(&TripPointX, &mut EngageDelay), With<Enemy>>,
+    players: Query<&Transform, With<Player>>,
+    time: GameTime,
+) {
+    let max_player_x = players
+        .iter()
+        .map(|transform| transform.translation.x)
+        .max_by(f32::total_cmp);
+
+    for (trip_point_x, mut engage_delay) in &mut enemies {
+        let tripped = trip_point_x.0 == f32::MIN
+            || max_player_x.map_or(false, |max_player_x| max_player_x > trip_point_x.0);
+        if tripped {
+            engage_delay.0.tick(time.delta());
+        }
+    }
+}
+
 /// Chooses which player is closer
 pub fn choose_player(p_transforms: &Vec<&Transform>, e_transform: &Transform) -> Option<Transform> {
     if !p_transforms.is_empty() {
@@ -139,12 +320,150 @@ pub fn choose_player(p_transforms: &Vec<&Transform>, e_transform: &Transform) ->
     }
 }
 
+/// Chooses the player that seems closest, with each player seeming `distance_per_enemy` further
+/// away for every enemy already going after them
+///
+/// The `current` player the enemy is going after doesn't count the enemy itself.
+pub fn choose_balanced_player(
+    players: &[(Entity, &Transform)],
+    aggro: &HashMap<Entity, usize>,
+    current: Option<Entity>,
+    e_transform: &Transform,
+    distance_per_enemy: f32,
+) -> Option<(Entity, Transform)> {
+    players
+        .iter()
+        .map(|&(player, transform)| {
+            let mut enemies = aggro.get(&player).copied().unwrap_or_default();
+            if current == Some(player) {
+                enemies = enemies.saturating_sub(1);
+            }
+            let weight = dist(transform, e_transform) + enemies as f32 * distance_per_enemy;
+            (player, transform, weight)
+        })
+        .min_by(|(_, _, a), (_, _, b)| a.total_cmp(b))
+        .map(|(player, transform, _)| (player, *transform))
+}
+
 pub fn dist(transform1: &Transform, transform2: &Transform) -> f32 {
     ((transform1.translation.x - transform2.translation.x).powi(2)
         + (transform1.translation.y - transform2.translation.y).powi(2))
     .sqrt()
 }
 
+/// Whether an enemy at `x` holds its attacks, because it is outside the camera view
+fn holds_offscreen_attack(
+    x: f32,
+    stats: &Stats,
+    is_boss: bool,
+    hold_rule: bool,
+    view: Option<(f32, f32)>,
+) -> bool {
+    let holds = stats
+        .attacks_offscreen
+        .map_or(hold_rule && !is_boss, |attacks| !attacks);
+
+    match view {
+        Some((left, right)) if holds => x < left || x > right,
+        _ => false,
+    }
+}
+
+/// Has the bosses with reinforcements call in their minions every interval once they are fighting,
+/// as long as the enemy cap allows it.
+///
+/// The minions are spawned like the level's enemies, engaging right away, so they hold the players
+/// at the stop points and count toward the victory like any other enemy.
+pub fn call_reinforcements(
+    mut commands: Commands,
+    mut bosses: Query<
+        (
+            Entity,
+            &Handle<FighterMeta>,
+            &Transform,
+            &TripPointX,
+            Option<&mut Reinforcements>,
+        ),
+        (With<Boss>, Without<Dying>),
+    >,
+    enemies: Query<(), (With<Enemy>, Without<Dying>)>,
+    cameras: Query<(&Transform, &OrthographicProjection), (With<Camera>, Without<Boss>)>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    game: Res<GameMeta>,
+    level_meta: Res<LevelMeta>,
+    time: GameTime,
+) {
+    let mut enemy_count = enemies.iter().count();
+    let view_left = cameras
+        .get_single()
+        .ok()
+        .map(|(camera, projection)| camera.translation.x + projection.left * projection.scale);
+
+    for (entity, fighter_handle, transform, trip_point_x, reinforcements) in &mut bosses {
+        let meta = match fighter_assets
+            .get(fighter_handle)
+            .and_then(|fighter| fighter.reinforcements.as_ref())
+        {
+            Some(meta) => meta,
+            None => continue,
+        };
+
+        // Bosses only call for reinforcements once the players have reached them
+        if trip_point_x.0 != f32::MIN {
+            continue;
+        }
+
+        let mut reinforcements = match reinforcements {
+            Some(reinforcements) => reinforcements,
+            None => {
+                commands
+                    .entity(entity)
+                    .insert(Reinforcements::new(meta.interval));
+                continue;
+            }
+        };
+
+        reinforcements.tick(time.delta());
+        if !reinforcements.just_finished() {
+            continue;
+        }
+
+        let count = match game.max_enemies {
+            Some(max_enemies) => meta.count.min(max_enemies.saturating_sub(enemy_count)),
+            None => meta.count,
+        };
+
+        // The minions spread out on both sides of the boss, without going behind the left edge of
+        // the view or past the end of the level
+        let position = transform.translation - Vec3::new(0., consts::GROUND_Y, 0.);
+        let minions = (0..count)
+            .map(|i| {
+                let side = if i % 2 == 0 { -1. } else { 1. };
+                let offset = side * (i / 2 + 1) as f32 * consts::BOSS_REINFORCEMENT_SPACING;
+                let mut x = position.x + offset;
+                if let Some(view_left) = view_left {
+                    x = x.max(view_left);
+                }
+                if let Some(right) = level_meta.bounds.right {
+                    x = x.min(right);
+                }
+
+                FighterSpawnMeta {
+                    fighter: meta.fighter.clone(),
+                    fighter_handle: meta.fighter_handle.clone(),
+                    location: Vec3::new(x, position.y, 0.),
+                    trip_point_x: f32::MIN,
+                    boss: false,
+                    entrance: None,
+                }
+            })
+            .collect::<Vec<_>>();
+
+        spawn_enemies(&mut commands, &minions, 1.0, None);
+        enemy_count += count;
+    }
+}
+
 /// Controls enemy AI fighters
 ///
 /// This is added to the [`crate::fighter_state::FighterStateCollectSystems`] to collect figher
@@ -159,13 +478,26 @@ pub fn emit_enemy_intents(
             &mut Facing,
             &mut StateTransitionIntents,
             Option<&Boss>,
+            Option<&BossCooldowns>,
             &AvailableAttacks,
+            Option<&EngageDelay>,
         ),
         // All enemies that are either moving or idling
         (With<Enemy>, Or<(With<Idling>, With<Moving>)>),
     >,
+    cameras: Query<(&Transform, &OrthographicProjection), (With<Camera>, Without<Enemy>)>,
+    game: Option<Res<GameMeta>>,
     mut commands: Commands,
 ) {
+    let hold_rule = game.map_or(false, |game| game.offscreen_enemies_hold_attacks);
+    // The horizontal extent of the camera view
+    let view = cameras.get_single().ok().map(|(camera, projection)| {
+        (
+            camera.translation.x + projection.left * projection.scale,
+            camera.translation.x + projection.right * projection.scale,
+        )
+    });
+
     for (
         entity,
         transform,
@@ -174,14 +506,46 @@ pub fn emit_enemy_intents(
         mut facing,
         mut intents,
         maybe_boss,
+        boss_cooldowns,
         available_attacks,
+        engage_delay,
     ) in &mut query
     {
+        // Enemies wait for their engage delay to end before moving or attacking
+        if engage_delay.map_or(false, |delay| !delay.is_over()) {
+            continue;
+        }
+
         let position = transform.translation.truncate();
-        let velocity = (target.position - position).normalize() * stats.movement_speed;
+        let mut velocity = (target.position - position).normalize() * stats.movement_speed;
+
+        let in_attack_range = match stats.preferred_range {
+            // Enemies with a preferred range attack as soon as the player is within it
+            Some(range) => {
+                let player_distance = position.distance(target.player_pos);
+
+                // And back away from players that get too close
+                if player_distance < range * consts::ENEMY_KITE_RANGE_RATIO {
+                    velocity =
+                        (position - target.player_pos).normalize_or_zero() * stats.movement_speed;
+                    false
+                } else {
+                    player_distance <= range
+                }
+            }
+            // Other enemies close in on their target
+            None => position.distance(target.position) <= target.attack_distance,
+        };
+
+        // Enemies outside the camera view keep walking into it instead of attacking
+        let holding = in_attack_range
+            && holds_offscreen_attack(position.x, stats, maybe_boss.is_some(), hold_rule, view);
+        if holding {
+            velocity = (target.player_pos - position).normalize_or_zero() * stats.movement_speed;
+        }
 
         // If we're close to our target
-        if position.distance(target.position) <= target.attack_distance {
+        if in_attack_range && !holding {
             // Note that the target includes an offset, so this can still not point to the
             // player.
 
@@ -204,11 +568,17 @@ pub fn emit_enemy_intents(
                     Facing::Left
                 };
 
-                intents.push_back(StateTransition::new(
-                    BossBombThrow::default(),
-                    BossBombThrow::PRIORITY,
-                    false,
-                ))
+                // Bosses wait for their attack to come off cooldown
+                let ready = boss_cooldowns.map_or(true, |cooldowns| {
+                    cooldowns.is_ready(BossBombThrow::ANIMATION)
+                });
+                if ready {
+                    intents.push_back(StateTransition::new(
+                        BossBombThrow::default(),
+                        BossBombThrow::PRIORITY,
+                        false,
+                    ))
+                }
             } else {
                 match available_attacks.current_attack().name.as_str() {
                     "punch" => intents.push_back(StateTransition::new(
@@ -244,10 +614,545 @@ pub fn emit_enemy_intents(
 
             // Move towards our target
             intents.push_back(StateTransition::new(
-                Moving { velocity },
+                Moving {
+                    velocity,
+                    running: false,
+                },
                 Moving::PRIORITY,
                 false,
             ));
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        attack::AttackFrames,
+        metadata::{AttackMeta, HitReaction, ReinforcementsMeta},
+    };
+
+    #[test]
+    fn test_boss_calls_reinforcements_up_to_the_enemy_cap() {
+        let mut world = World::new();
+        let mut game: GameMeta =
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap();
+        game.max_enemies = Some(4);
+        world.insert_resource(game);
+        let mut level: LevelMeta =
+            serde_yaml::from_str(include_str!("../assets/levels/1_beach/beach.level.yaml"))
+                .unwrap();
+        level.bounds.right = Some(520.);
+        world.insert_resource(level);
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+
+        let mut boss_meta: FighterMeta =
+            serde_yaml::from_str(include_str!("../assets/fighters/dev/dev.fighter.yaml")).unwrap();
+        boss_meta.reinforcements = Some(ReinforcementsMeta {
+            fighter: "/fighters/dev/dev.fighter.yaml".into(),
+            fighter_handle: default(),
+            count: 2,
+            interval: 1.0,
+        });
+        world.init_resource::<Assets<FighterMeta>>();
+        let boss_handle = world.resource_mut::<Assets<FighterMeta>>().add(boss_meta);
+        world.spawn((
+            Enemy,
+            Boss,
+            boss_handle,
+            Transform::from_xyz(500., consts::GROUND_Y, 0.),
+            TripPointX(f32::MIN),
+        ));
+        world.spawn((
+            Camera::default(),
+            Transform::from_xyz(680., 0., 0.),
+            OrthographicProjection {
+                left: -200.,
+                right: 200.,
+                ..default()
+            },
+        ));
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(call_reinforcements);
+        let mut step = |world: &mut World| {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(0.1));
+            stage.run(world);
+        };
+        let minions = |world: &mut World| {
+            world
+                .query_filtered::<&Transform, (With<Enemy>, Without<Boss>)>()
+                .iter(world)
+                .map(|transform| transform.translation.x)
+                .collect::<Vec<_>>()
+        };
+
+        // No minions show up before the interval is over
+        for _ in 0..9 {
+            step(&mut world);
+        }
+        assert!(minions(&mut world).is_empty());
+
+        // Then the configured number of minions is called in beside the boss, without going behind
+        // the left edge of the view or past the end of the level
+        for _ in 0..2 {
+            step(&mut world);
+        }
+        let mut called = minions(&mut world);
+        called.sort_by(f32::total_cmp);
+        assert_eq!(called, [480., 520.]);
+
+        // The next call only brings in as many as the enemy cap allows
+        for _ in 0..10 {
+            step(&mut world);
+        }
+        assert_eq!(minions(&mut world).len(), 3);
+    }
+
+    #[test]
+    fn test_enemies_attack_from_their_preferred_range() {
+        let mut world = World::new();
+        let attack = |name: &str| AttackMeta {
+            name: name.into(),
+            frames: AttackFrames {
+                startup: 0,
+                active: 1,
+                recovery: 2,
+            },
+            ..default()
+        };
+        let player_pos = Vec2::new(0., 0.);
+        let mut spawn_enemy = |x: f32, preferred_range: Option<f32>, attack_name: &str| {
+            world
+                .spawn((
+                    Enemy,
+                    Idling,
+                    Transform::from_xyz(x, 0., 0.),
+                    Stats {
+                        preferred_range,
+                        ..default()
+                    },
+                    WalkTarget {
+                        position: player_pos + Vec2::new(ENEMY_MIN_ATTACK_DISTANCE, 0.),
+                        attack_distance: ENEMY_MIN_ATTACK_DISTANCE,
+                        player_pos,
+                    },
+                    Facing::default(),
+                    StateTransitionIntents::default(),
+                    AvailableAttacks {
+                        attacks: vec![attack(attack_name)],
+                    },
+                ))
+                .id()
+        };
+
+        // A ranged enemy approaches until the player is within its preferred range
+        let ranged_far = spawn_enemy(250., Some(180.), "projectile");
+        let ranged_in_range = spawn_enemy(170., Some(180.), "projectile");
+        // And backs away from a player that gets too close
+        let ranged_close = spawn_enemy(40., Some(180.), "projectile");
+        // While a melee enemy closes in to contact
+        let melee_far = spawn_enemy(170., None, "punch");
+        let melee_close = spawn_enemy(8., None, "punch");
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(emit_enemy_intents);
+        stage.run(&mut world);
+
+        // Get the x velocity of a moving enemy, or `None` if it is attacking
+        let moving_x = |enemy: Entity| {
+            let intents = world.get::<StateTransitionIntents>(enemy).unwrap();
+            assert_eq!(intents.len(), 1);
+            let data = &intents[0].data;
+            assert!(
+                data.is::<Moving>() || data.is::<Punching>() || data.is::<ProjectileAttacking>()
+            );
+            data.downcast_ref::<Moving>()
+                .map(|moving| moving.velocity.x)
+        };
+
+        assert!(moving_x(ranged_far).unwrap() < 0.);
+        assert_eq!(moving_x(ranged_in_range), None);
+        assert!(moving_x(ranged_close).unwrap() > 0.);
+        assert!(moving_x(melee_far).unwrap() < 0.);
+        assert_eq!(moving_x(melee_close), None);
+    }
+
+    #[test]
+    fn test_offscreen_enemy_holds_its_attack() {
+        let mut world = World::new();
+        let mut game: GameMeta =
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap();
+        game.offscreen_enemies_hold_attacks = true;
+        world.insert_resource(game);
+        world.spawn((
+            Camera::default(),
+            Transform::default(),
+            OrthographicProjection {
+                left: -200.,
+                right: 200.,
+                ..default()
+            },
+        ));
+
+        let mut spawn_enemy = |x: f32, stats: Stats| {
+            let player_pos = Vec2::new(x - ENEMY_MIN_ATTACK_DISTANCE, 0.);
+            world
+                .spawn((
+                    Enemy,
+                    Idling,
+                    Transform::from_xyz(x, 0., 0.),
+                    stats,
+                    WalkTarget {
+                        position: Vec2::new(x, 0.),
+                        attack_distance: ENEMY_MIN_ATTACK_DISTANCE,
+                        player_pos,
+                    },
+                    Facing::default(),
+                    StateTransitionIntents::default(),
+                    AvailableAttacks {
+                        attacks: vec![AttackMeta {
+                            name: "punch".into(),
+                            frames: AttackFrames {
+                                startup: 0,
+                                active: 1,
+                                recovery: 2,
+                            },
+                            ..default()
+                        }],
+                    },
+                ))
+                .id()
+        };
+        let on_screen = spawn_enemy(150., default());
+        let off_screen = spawn_enemy(300., default());
+        let opted_out = spawn_enemy(
+            300.,
+            Stats {
+                attacks_offscreen: Some(true),
+                ..default()
+            },
+        );
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(emit_enemy_intents);
+        stage.run(&mut world);
+
+        let intent = |enemy: Entity| {
+            let intents = world.get::<StateTransitionIntents>(enemy).unwrap();
+            assert_eq!(intents.len(), 1);
+            &intents[0].data
+        };
+        assert!(intent(on_screen).is::<Punching>());
+        // The off-screen enemy walks toward the player instead of attacking
+        let moving = intent(off_screen).downcast_ref::<Moving>().unwrap();
+        assert!(moving.velocity.x < 0.);
+        assert!(world.get::<WalkTarget>(off_screen).is_some());
+        assert!(intent(opted_out).is::<Punching>());
+    }
+
+    #[test]
+    fn test_damaged_enemy_alerts_nearby_enemies() {
+        let mut world = World::new();
+        let game: GameMeta =
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap();
+        let radius = game.enemy_alert.radius;
+        world.insert_resource(game);
+        world.init_resource::<Events<EnemyAlertEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+
+        let mut spawn_enemy = |x: f32| {
+            world
+                .spawn((
+                    Enemy,
+                    TransformBundle::from_transform(Transform::from_xyz(x, 0., 0.)),
+                    TripPointX(1000.),
+                ))
+                .id()
+        };
+        let damaged = spawn_enemy(0.);
+        let nearby = spawn_enemy(radius - 10.);
+        let far = spawn_enemy(radius + 10.);
+        let attack = world.spawn_empty().id();
+
+        let damage = |damageing_entity| DamageEvent {
+            damage_velocity: Vec2::ZERO,
+            damageing_entity,
+            damaged_entity: damaged,
+            source_position: Vec2::ZERO,
+            contact_position: Vec2::ZERO,
+            damage: 10,
+            hitstun_duration: 0.,
+            pull: None,
+            reaction: HitReaction::Knockback,
+        };
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(alert_nearby_enemies);
+
+        // Damage over time, like burning, doesn't alert anyone
+        world.send_event(damage(damaged));
+        stage.run(&mut world);
+        assert_eq!(world.get::<TripPointX>(damaged).unwrap().0, 1000.);
+        assert_eq!(world.get::<TripPointX>(nearby).unwrap().0, 1000.);
+
+        world.send_event(damage(attack));
+        stage.run(&mut world);
+
+        assert_eq!(world.get::<TripPointX>(damaged).unwrap().0, f32::MIN);
+        assert_eq!(world.get::<TripPointX>(nearby).unwrap().0, f32::MIN);
+        assert_eq!(world.get::<TripPointX>(far).unwrap().0, 1000.);
+    }
+
+    #[test]
+    fn test_confused_enemy_targets_another_enemy() {
+        let mut world = World::new();
+        world.insert_resource::<LevelMeta>(
+            serde_yaml::from_str(include_str!("../assets/levels/1_beach/beach.level.yaml"))
+                .unwrap(),
+        );
+        world.init_resource::<Assets<ItemMeta>>();
+        world.init_resource::<Events<EnemyAlertEvent>>();
+        world.init_resource::<GameRng>();
+        world.spawn((Player, Transform::from_xyz(0., 0., 0.)));
+
+        let mut spawn_enemy = |x: f32| {
+            world
+                .spawn((
+                    Enemy,
+                    Idling,
+                    TripPointX(-1000.),
+                    Transform::from_xyz(x, 0., 0.),
+                    Stats::default(),
+                    AvailableAttacks {
+                        attacks: vec![AttackMeta {
+                            name: "punch".into(),
+                            frames: AttackFrames {
+                                startup: 0,
+                                active: 1,
+                                recovery: 2,
+                            },
+                            ..default()
+                        }],
+                    },
+                ))
+                .id()
+        };
+        let confused = spawn_enemy(100.);
+        let other = spawn_enemy(300.);
+        world.entity_mut(confused).insert(Confused::new(5.));
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(set_move_target_near_player);
+        stage.run(&mut world);
+
+        let target = |enemy: Entity| world.get::<WalkTarget>(enemy).unwrap().player_pos;
+        assert_eq!(target(confused), Vec2::new(300., 0.));
+        assert_eq!(target(other), Vec2::ZERO);
+    }
+
+    #[test]
+    fn test_same_seed_picks_identical_walk_targets() {
+        let walk_targets = |seed: u64| {
+            let mut world = World::new();
+            world.insert_resource::<LevelMeta>(
+                serde_yaml::from_str(include_str!("../assets/levels/1_beach/beach.level.yaml"))
+                    .unwrap(),
+            );
+            world.init_resource::<Assets<ItemMeta>>();
+            world.init_resource::<Events<EnemyAlertEvent>>();
+            world.insert_resource(GameRng::new(seed));
+            let fighter: FighterMeta =
+                serde_yaml::from_str(include_str!("../assets/fighters/dev/dev.fighter.yaml"))
+                    .unwrap();
+            world.spawn((Player, Transform::from_xyz(0., 0., 0.)));
+            let enemies = [100., 200., 300.].map(|x| {
+                world
+                    .spawn((
+                        Enemy,
+                        Idling,
+                        TripPointX(f32::MIN),
+                        Transform::from_xyz(x, 0., 0.),
+                        Stats::default(),
+                        AvailableAttacks {
+                            attacks: vec![fighter.attacks[0].clone()],
+                        },
+                    ))
+                    .id()
+            });
+
+            let mut stage = SystemStage::single_threaded();
+            stage.add_system(set_move_target_near_player);
+            stage.run(&mut world);
+
+            enemies.map(|enemy| {
+                let target = world.get::<WalkTarget>(enemy).unwrap();
+                (target.position, target.attack_distance)
+            })
+        };
+
+        assert_eq!(walk_targets(7), walk_targets(7));
+        assert_ne!(walk_targets(7), walk_targets(8));
+    }
+
+    #[test]
+    fn test_enemies_spread_out_across_the_players() {
+        let mut world = World::new();
+        world.insert_resource::<LevelMeta>(
+            serde_yaml::from_str(include_str!("../assets/levels/1_beach/beach.level.yaml"))
+                .unwrap(),
+        );
+        world.insert_resource::<GameMeta>(
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap(),
+        );
+        world.init_resource::<Assets<ItemMeta>>();
+        world.init_resource::<Events<EnemyAlertEvent>>();
+        world.init_resource::<GameRng>();
+        let fighter: FighterMeta =
+            serde_yaml::from_str(include_str!("../assets/fighters/dev/dev.fighter.yaml")).unwrap();
+        let near_player = world.spawn((Player, Transform::from_xyz(0., 0., 0.))).id();
+        let far_player = world
+            .spawn((Player, Transform::from_xyz(100., 0., 0.)))
+            .id();
+
+        // Every enemy is closer to the same player
+        let start_x = [-20., -30., -40., -50.];
+        let enemies = start_x.map(|x| {
+            world
+                .spawn((
+                    Enemy,
+                    Idling,
+                    TripPointX(f32::MIN),
+                    Transform::from_xyz(x, 0., 0.),
+                    Stats::default(),
+                    AvailableAttacks {
+                        attacks: vec![fighter.attacks[0].clone()],
+                    },
+                    Facing::default(),
+                    StateTransitionIntents::default(),
+                ))
+                .id()
+        });
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(set_move_target_near_player.pipe(emit_enemy_intents));
+        let aggro = |world: &World, player: Entity| {
+            enemies
+                .iter()
+                .filter(|enemy| world.get::<Aggro>(**enemy).map(|aggro| aggro.0) == Some(player))
+                .count()
+        };
+
+        stage.run(&mut world);
+        assert_eq!(aggro(&world, near_player), 2);
+        assert_eq!(aggro(&world, far_player), 2);
+
+        // The enemies reach their targets and attack, which removes their walk targets, and then
+        // pick new ones after the attack. Their aggro is kept through all of it.
+        for cycle in 0..3 {
+            for enemy in enemies {
+                let target = world.get::<WalkTarget>(enemy).unwrap().position;
+                world.get_mut::<Transform>(enemy).unwrap().translation = target.extend(0.);
+            }
+            stage.run(&mut world);
+            assert!(
+                enemies
+                    .iter()
+                    .all(|enemy| world.get::<WalkTarget>(*enemy).is_none()),
+                "cycle {cycle}"
+            );
+            assert_eq!(aggro(&world, near_player), 2, "cycle {cycle}");
+            assert_eq!(aggro(&world, far_player), 2, "cycle {cycle}");
+
+            stage.run(&mut world);
+            assert_eq!(aggro(&world, near_player), 2, "cycle {cycle}");
+            assert_eq!(aggro(&world, far_player), 2, "cycle {cycle}");
+        }
+
+        // Dying enemies no longer count
+        let dying = enemies
+            .into_iter()
+            .find(|enemy| world.get::<Aggro>(*enemy).unwrap().0 == near_player)
+            .unwrap();
+        world.entity_mut(dying).remove::<Idling>().insert(Dying);
+        let mut dying_stage = SystemStage::single_threaded();
+        dying_stage.add_system(clear_dying_enemy_aggro);
+        dying_stage.run(&mut world);
+        assert_eq!(aggro(&world, near_player), 1);
+
+        // Without balancing, they all go after the nearest player
+        world.resource_mut::<GameMeta>().aggro_balancing = None;
+        for (enemy, x) in enemies.into_iter().zip(start_x) {
+            world.entity_mut(enemy).remove::<WalkTarget>();
+            world.get_mut::<Transform>(enemy).unwrap().translation = Vec3::new(x, 0., 0.);
+        }
+        stage.run(&mut world);
+        assert_eq!(aggro(&world, near_player), 3);
+        assert_eq!(aggro(&world, far_player), 0);
+
+        // Downed players aren't targeted
+        world.entity_mut(near_player).insert(Downed::new(10.));
+        for enemy in enemies {
+            world.entity_mut(enemy).remove::<WalkTarget>();
+        }
+        stage.run(&mut world);
+        assert_eq!(aggro(&world, near_player), 0);
+        assert_eq!(aggro(&world, far_player), 3);
+    }
+
+    #[test]
+    fn test_enemy_waits_for_its_engage_delay() {
+        let mut world = World::new();
+        world.insert_resource(Time::default());
+        world.resource_mut::<Time>().update();
+
+        world.spawn((Player, Transform::from_xyz(0., 0., 0.)));
+        let enemy = world
+            .spawn((
+                Enemy,
+                Idling,
+                Transform::from_xyz(200., 0., 0.),
+                Stats::default(),
+                TripPointX(f32::MIN),
+                EngageDelay::new(1.0),
+                WalkTarget {
+                    position: Vec2::ZERO,
+                    attack_distance: ENEMY_MIN_ATTACK_DISTANCE,
+                    player_pos: Vec2::ZERO,
+                },
+                Facing::default(),
+                StateTransitionIntents::default(),
+                AvailableAttacks { attacks: vec![] },
+            ))
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(tick_engage_delays.before(emit_enemy_intents));
+        stage.add_system(emit_enemy_intents);
+        let mut step = |world: &mut World| {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + std::time::Duration::from_secs_f32(0.25));
+            stage.run(world);
+            world
+                .get_mut::<StateTransitionIntents>(enemy)
+                .unwrap()
+                .drain(..)
+                .count()
+        };
+
+        // The enemy stays idle for the length of its delay
+        for _ in 0..3 {
+            assert_eq!(step(&mut world), 0);
+        }
+
+        // And then engages
+        assert_eq!(step(&mut world), 1);
+    }
+}
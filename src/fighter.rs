@@ -1,20 +1,24 @@
 use bevy::prelude::*;
-use rand::prelude::SliceRandom;
+use iyes_loopless::prelude::*;
+use rand::{prelude::SliceRandom, Rng};
 use serde::Deserialize;
 
-use crate::attack::Hurtbox;
+use crate::attack::{AttackOwners, Hurtbox};
 use crate::consts::{self, FOOT_PADDING};
 use crate::metadata::ItemMeta;
 use crate::{
     animation::{AnimatedSpriteSheetBundle, Animation, Facing},
-    camera::YSort,
-    collision::{BodyLayers, PhysicsBundle},
-    damage::{Damageable, Health},
+    camera::{SortLayer, YSort},
+    collision::{PhysicsBundle, Team},
+    damage::{self, DamageEvent, Damageable, Health, HitPause, Invulnerable},
     enemy::Enemy,
-    fighter_state::{Idling, StateTransitionIntents},
-    metadata::{AttackMeta, FighterMeta},
-    movement::LinearVelocity,
+    fighter_state::{HitStun, Idling, KnockedDown, StateTransitionIntents},
+    interpolation::PreviousTransform,
+    metadata::{AttackMeta, FighterMeta, GameMeta},
+    movement::{LinearVelocity, VelocitySystems},
     player::Player,
+    utils::GameTime,
+    GameState,
 };
 
 pub struct FighterPlugin;
@@ -22,7 +26,17 @@ pub struct FighterPlugin;
 impl Plugin for FighterPlugin {
     fn build(&self, app: &mut App) {
         app.register_type::<AvailableAttacks>()
-            .add_system_to_stage(CoreStage::PostUpdate, attachment_system);
+            .add_system_to_stage(CoreStage::PostUpdate, attachment_system)
+            .add_system(gain_super_meter.run_in_state(GameState::InGame))
+            .add_system(regenerate_poise.run_in_state(GameState::InGame))
+            .add_system(ring_out.run_in_state(GameState::InGame))
+            // Recoil goes through the movement constraints, so it can't push past the boundaries
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                apply_recoil
+                    .run_in_state(GameState::InGame)
+                    .before(VelocitySystems),
+            );
     }
 }
 
@@ -43,7 +57,12 @@ pub struct ActiveFighterBundle {
     /// Fighters start off idling, but this component may be removed when the fighter state changes.
     pub idling: Idling,
     pub velocity: LinearVelocity,
+    pub stagger: Stagger,
+    pub juggle_count: JuggleCount,
+    /// Used to interpolate the rendered fighter position between simulation steps
+    pub previous_transform: PreviousTransform,
     pub available_attacks: AvailableAttacks,
+    pub team: Team,
 }
 
 /// Component that defines the currently available attacks on a fighter, modified at runtime when
@@ -65,7 +84,50 @@ impl AvailableAttacks {
 #[serde(deny_unknown_fields)]
 pub struct Stats {
     pub max_health: i32,
+    /// The walking speed
     pub movement_speed: f32,
+    /// The speed while the run modifier is held. Defaults to a multiple of the walking speed.
+    #[serde(default)]
+    pub run_speed: Option<f32>,
+    /// How much of the incoming knockback is ignored, from `0.0` ( full knockback ) to `1.0`
+    /// ( immovable ).
+    #[serde(default)]
+    pub knockback_resist: f32,
+    /// The damage the fighter can take in quick succession before being stunned
+    #[serde(default)]
+    pub poise: f32,
+    /// How much accumulated stagger damage recovers per second
+    #[serde(default)]
+    pub poise_regen: f32,
+    /// The distance from the player at which an enemy attacks, for enemies that keep their distance
+    /// instead of closing in
+    #[serde(default)]
+    pub preferred_range: Option<f32>,
+    /// How fast the fighter speeds up toward its movement speed, per second. Fighters without one
+    /// reach their movement speed instantly.
+    #[serde(default)]
+    pub acceleration: Option<f32>,
+    /// Whether the enemy attacks while it is outside the camera view, overriding the game's
+    /// `offscreen_enemies_hold_attacks` rule. Bosses attack off-screen unless this is set.
+    #[serde(default)]
+    pub attacks_offscreen: Option<bool>,
+}
+
+impl Stats {
+    /// Get the movement speed, depending on whether or not the fighter is running
+    pub fn movement_speed(&self, running: bool) -> f32 {
+        if running {
+            self.run_speed
+                .unwrap_or(self.movement_speed * consts::RUN_SPEED_MULTIPLIER)
+        } else {
+            self.movement_speed
+        }
+    }
+
+    /// Scale an attack's pushback according to this fighter's knockback resistance
+    pub fn scale_knockback(&self, pushback: Vec2) -> Vec2 {
+        pushback * (1.0 - self.knockback_resist.clamp(0.0, 1.0))
+    }
 }
 
 /// The player inventory.
@@ -79,6 +141,190 @@ impl Default for Stats {
         Stats {
             max_health: 100,
             movement_speed: 17000.,
+            run_speed: None,
+            knockback_resist: 0.,
+            poise: 0.,
+            poise_regen: 0.,
+            preferred_range: None,
+            acceleration: None,
+            attacks_offscreen: None,
+        }
+    }
+}
+
+/// The damage accumulated by a fighter towards breaking its poise
+#[derive(Component, Clone, Copy, Debug, Default, Deref, DerefMut)]
+pub struct Stagger(pub f32);
+
+impl Stagger {
+    /// Accumulate the damage of a hit, returning whether the fighter's poise is broken, in which case
+    /// the stagger is reset.
+    pub fn hit(&mut self, damage: f32, poise: f32) -> bool {
+        self.0 += damage;
+
+        if self.0 >= poise {
+            self.0 = 0.;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Marker for fighters that are off the ground.
+///
+/// Added by the jumping states, and removed once the fighter is neither jumping nor in hit stun,
+/// or when it lands from a knockdown.
+#[derive(Component, Clone, Copy, Debug, Default)]
+#[component(storage = "SparseSet")]
+pub struct Airborne {
+    /// The y-height the fighter left the ground from, which it lands back on
+    pub ground_y: f32,
+}
+
+/// Pushes a fighter back from its own attack, fading out over [`consts::RECOIL_DURATION`]
+#[derive(Component, Clone, Debug)]
+#[component(storage = "SparseSet")]
+pub struct Recoil {
+    pub velocity: Vec2,
+    pub timer: Timer,
+}
+
+impl Recoil {
+    /// The recoil of an attack's `self_knockback`, directed away from where the attacker is facing
+    pub fn new(self_knockback: Vec2, facing: &Facing) -> Self {
+        let direction_x = if facing.is_left() { 1.0 } else { -1.0 };
+        Self {
+            velocity: Vec2::new(self_knockback.x.abs() * direction_x, self_knockback.y),
+            timer: Timer::from_seconds(consts::RECOIL_DURATION, TimerMode::Once),
+        }
+    }
+}
+
+/// The number of consecutive hits a fighter has taken while [`Airborne`], reset when it lands
+#[derive(Component, Clone, Copy, Debug, Default, Deref, DerefMut)]
+pub struct JuggleCount(pub u32);
+
+/// Meter that fills up as a player deals and takes damage, enabling the super attack when full
+#[derive(Component, Clone, Copy, Debug, Default)]
+pub struct SuperMeter {
+    pub value: f32,
+    pub max: f32,
+}
+
+impl SuperMeter {
+    pub fn new(max: f32) -> Self {
+        Self { value: 0., max }
+    }
+
+    pub fn add(&mut self, amount: f32) {
+        self.value = (self.value + amount).min(self.max);
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.value >= self.max
+    }
+
+    /// Drain the meter if it is full, returning whether the super attack may be used
+    pub fn try_consume(&mut self) -> bool {
+        if self.is_full() {
+            self.value = 0.;
+            true
+        } else {
+            false
+        }
+    }
+}
+
+/// Recovers accumulated stagger over time
+fn regenerate_poise(mut fighters: Query<(&mut Stagger, &Stats)>, time: GameTime) {
+    for (mut stagger, stats) in &mut fighters {
+        **stagger = (**stagger - stats.poise_regen * time.delta_seconds()).max(0.);
+    }
+}
+
+/// Adds the recoil of fighters' attacks on top of the velocity set by their state
+///
+/// The recoil is held while the fighter is in a [`HitPause`].
+pub fn apply_recoil(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut LinearVelocity, &mut Recoil), Without<HitPause>>,
+    time: GameTime,
+) {
+    for (entity, mut velocity, mut recoil) in &mut fighters {
+        recoil.timer.tick(time.delta());
+        if recoil.timer.finished() {
+            commands.entity(entity).remove::<Recoil>();
+        } else {
+            **velocity += recoil.velocity * recoil.timer.percent_left();
+        }
+    }
+}
+
+/// Kills fighters that are knocked too far past the left or right edge of the camera view.
+///
+/// Only fighters in hit stun, knocked down or in the air can be rung out, so that walking off the screen is
+/// never lethal. Like any other damage, ring-outs honor i-frames.
+fn ring_out(
+    mut fighters: Query<
+        (
+            &Transform,
+            &mut Health,
+            Option<&Damageable>,
+            Option<&Invulnerable>,
+        ),
+        (
+            With<Handle<FighterMeta>>,
+            Or<(With<HitStun>, With<KnockedDown>, With<Airborne>)>,
+        ),
+    >,
+    cameras: Query<(&Transform, &OrthographicProjection), (With<Camera>, Without<Health>)>,
+    game: Res<GameMeta>,
+) {
+    let (camera, projection) = match cameras.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+    let margin = match &game.ring_out {
+        Some(ring_out) => ring_out.margin,
+        None => return,
+    };
+
+    let left = camera.translation.x + projection.left * projection.scale - margin;
+    let right = camera.translation.x + projection.right * projection.scale + margin;
+
+    for (transform, mut health, damageable, invulnerable) in &mut fighters {
+        let x = transform.translation.x;
+        if (x < left || x > right)
+            && **health > 0
+            && damage::can_take_damage(damageable, invulnerable)
+        {
+            **health = 0;
+        }
+    }
+}
+
+/// Fills the super meters of fighters that deal or take damage
+fn gain_super_meter(
+    mut meters: Query<&mut SuperMeter>,
+    owners: AttackOwners,
+    mut damage_events: EventReader<DamageEvent>,
+    game: Res<GameMeta>,
+) {
+    for event in damage_events.iter() {
+        let damage = event.damage as f32;
+
+        if let Ok(mut meter) = meters.get_mut(event.damaged_entity) {
+            meter.add(damage * game.super_meter.gain_per_damage_taken);
+        }
+
+        // Damage over time isn't dealt by anyone
+        if event.is_damage_over_time() {
+            continue;
+        }
+        let attacker = owners.get(event.damageing_entity);
+        if let Ok(mut meter) = meters.get_mut(attacker) {
+            meter.add(damage * game.super_meter.gain_per_damage_dealt);
         }
     }
 }
@@ -92,11 +338,12 @@ impl ActiveFighterBundle {
         transform: &Transform,
         player: Option<&Player>,
         enemy: Option<&Enemy>,
+        rng: &mut impl Rng,
     ) {
-        let body_layers = if player.is_some() {
-            BodyLayers::PLAYER
+        let team = if player.is_some() {
+            Team::Players
         } else if enemy.is_some() {
-            BodyLayers::ENEMY
+            Team::Enemies
         } else {
             unreachable!();
         };
@@ -116,7 +363,7 @@ impl ActiveFighterBundle {
                     texture_atlas: fighter
                         .spritesheet
                         .atlas_handle
-                        .choose(&mut rand::thread_rng())
+                        .choose(rng)
                         .unwrap()
                         .clone(),
                     transform: *transform,
@@ -131,19 +378,22 @@ impl ActiveFighterBundle {
             health: Health(fighter.stats.max_health),
             inventory: default(),
             damageable: default(),
-            // physics_bundle: PhysicsBundle::new(&fighter.hurtbox, body_layers),
+            // physics_bundle: PhysicsBundle::new(&fighter.hurtbox, team.body_layer()),
             idling: Idling,
             state_transition_intents: default(),
-            // ysort: YSort(fighter.spritesheet.tile_size.y as f32 / 2.),
-            ysort: YSort(consts::FIGHTERS_Z),
+            ysort: YSort(SortLayer::Fighters),
             velocity: default(),
+            stagger: default(),
+            juggle_count: default(),
+            previous_transform: PreviousTransform(*transform),
             available_attacks: AvailableAttacks {
                 attacks: fighter.attacks.clone(),
             },
+            team,
         };
         let hurtbox = commands
             .spawn((
-                PhysicsBundle::new(&fighter.hurtbox, body_layers),
+                PhysicsBundle::new(&fighter.hurtbox, team.body_layer()),
                 TransformBundle::from_transform(Transform::from_xyz(
                     0.0,
                     fighter.collision_offset,
@@ -165,11 +415,8 @@ impl ActiveFighterBundle {
             let mut attachment_spritesheet = animated_spritesheet_bundle;
 
             //Change what's needed
-            attachment_spritesheet.sprite_sheet.texture_atlas = attachment
-                .atlas_handle
-                .choose(&mut rand::thread_rng())
-                .unwrap()
-                .clone();
+            attachment_spritesheet.sprite_sheet.texture_atlas =
+                attachment.atlas_handle.choose(rng).unwrap().clone();
             attachment_spritesheet.animation =
                 Animation::new(attachment.animation_fps, attachment.animations.clone());
             attachment_spritesheet.sprite_sheet.transform = Transform::from_xyz(
@@ -242,3 +489,102 @@ pub fn attachment_system(
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::metadata::RingOutMeta;
+
+    #[test]
+    fn test_scale_knockback() {
+        let pushback = Vec2::new(200., 0.);
+        let light = Stats::default();
+        let heavy = Stats {
+            knockback_resist: 0.8,
+            ..default()
+        };
+        let immovable = Stats {
+            knockback_resist: 1.0,
+            ..default()
+        };
+
+        assert_eq!(light.scale_knockback(pushback), pushback);
+        assert!((heavy.scale_knockback(pushback) - Vec2::new(40., 0.)).length() < 1e-4);
+        assert_eq!(immovable.scale_knockback(pushback), Vec2::ZERO);
+
+        // Resistance is clamped to full knockback and immovable
+        let over_resistant = Stats {
+            knockback_resist: 1.5,
+            ..default()
+        };
+        let negative = Stats {
+            knockback_resist: -0.5,
+            ..default()
+        };
+        assert_eq!(over_resistant.scale_knockback(pushback), Vec2::ZERO);
+        assert_eq!(negative.scale_knockback(pushback), pushback);
+    }
+
+    #[test]
+    fn test_super_meter_consume() {
+        let mut meter = SuperMeter::new(100.);
+
+        // Refused while the meter isn't full
+        meter.add(60.);
+        assert!(!meter.try_consume());
+        assert_eq!(meter.value, 60.);
+
+        // Triggered and drained once full
+        meter.add(60.);
+        assert_eq!(meter.value, 100.);
+        assert!(meter.try_consume());
+        assert_eq!(meter.value, 0.);
+        assert!(!meter.try_consume());
+    }
+
+    #[test]
+    fn test_hitstunned_fighter_is_rung_out() {
+        let mut world = World::new();
+        let mut game: GameMeta =
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap();
+        game.ring_out = Some(RingOutMeta { margin: 100. });
+        world.insert_resource(game);
+        world.spawn((
+            Camera::default(),
+            Transform::default(),
+            OrthographicProjection {
+                left: -200.,
+                right: 200.,
+                ..default()
+            },
+        ));
+
+        let fighter = |world: &mut World, x: f32| {
+            world
+                .spawn((
+                    Handle::<FighterMeta>::default(),
+                    Health(100),
+                    Transform::from_xyz(x, 0., 0.),
+                ))
+                .id()
+        };
+        let knocked_back = fighter(&mut world, 350.);
+        world.entity_mut(knocked_back).insert(HitStun::default());
+        let walking = fighter(&mut world, 350.);
+        let in_view = fighter(&mut world, 250.);
+        world.entity_mut(in_view).insert(HitStun::default());
+        let invulnerable = fighter(&mut world, 350.);
+        world
+            .entity_mut(invulnerable)
+            .insert((HitStun::default(), Invulnerable));
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(ring_out);
+        stage.run(&mut world);
+
+        assert_eq!(**world.get::<Health>(knocked_back).unwrap(), 0);
+        assert_eq!(**world.get::<Health>(walking).unwrap(), 100);
+        assert_eq!(**world.get::<Health>(in_view).unwrap(), 100);
+        assert_eq!(**world.get::<Health>(invulnerable).unwrap(), 100);
+    }
+}
@@ -1,31 +1,38 @@
 use std::{collections::VecDeque, time::Duration};
 
 use bevy::{prelude::*, reflect::FromType, utils::HashSet};
-use bevy_mod_js_scripting::ActiveScripts;
-use bevy_rapier2d::prelude::CollisionGroups;
 use iyes_loopless::prelude::*;
 use leafwing_input_manager::{plugin::InputManagerSystem, prelude::ActionState};
-use rand::Rng;
 
 use crate::{
     animation::{AnimatedSpriteSheetBundle, Animation, Facing},
-    attack::{Attack, Breakable},
+    assist_ai::AssistAi,
+    attack::{Attack, AttackOwners, Connected, HitboxPath},
     audio::AnimationAudioPlayback,
-    collision::BodyLayers,
+    camera::{BossIntro, CameraIntro, CameraTilt, SortLayer, YSort},
+    collision::Team,
     consts,
-    damage::{DamageEvent, Health},
-    enemy::{Boss, Enemy},
+    damage::{DamageEvent, DamageFalloff, Health, HitPause, Invulnerable, ParryEvent},
+    enemy::{tick_boss_cooldowns, Boss, BossCooldowns, Enemy},
     enemy_ai,
-    fighter::{Attached, AvailableAttacks, Inventory},
+    fighter::{
+        Airborne, Attached, AvailableAttacks, Inventory, JuggleCount, Recoil, Stagger, SuperMeter,
+    },
     input::PlayerAction,
     item::{
-        AnimatedProjectile, Drop, Explodable, Item, ItemBundle, Projectile, ScriptItemGrabEvent,
-        ScriptItemThrowEvent,
+        throw_aim_angle, Drop, Explodable, Item, ItemBundle, ScriptItemGrabEvent,
+        ScriptItemThrowEvent, ThrownBombBundle, ThrownItemBundle,
     },
     lifetime::Lifetime,
-    metadata::{AttackMeta, AudioMeta, FighterMeta, ItemKind, ItemMeta, ItemSpawnMeta},
-    movement::{AngularVelocity, Force, LinearVelocity},
-    player::Player,
+    metadata::{
+        AirAttackDirection, AttackMeta, AttackStrength, AudioMeta, DamageFalloffMeta, FighterMeta,
+        GameMeta, HitReaction, ItemKind, ItemMeta, ParryMeta,
+    },
+    movement::LinearVelocity,
+    player::{AnalogAttackTrigger, AttackBuffer, Player, StickResponse},
+    projectile::{Projectile, ProjectileBundle},
+    status_effect::Confused,
+    utils::{GameRng, GameTime},
     Collider, GameState, Stats,
 };
 
@@ -38,7 +45,7 @@ pub struct FighterStateCollectSystems;
 
 impl Plugin for FighterStatePlugin {
     fn build(&self, app: &mut App) {
-        app
+        app.add_event::<enemy_ai::EnemyAlertEvent>()
             // The collect systems
             .add_system_set_to_stage(
                 CoreStage::PreUpdate,
@@ -48,9 +55,24 @@ impl Plugin for FighterStatePlugin {
                     .run_in_state(GameState::InGame)
                     .with_system(collect_fighter_eliminations)
                     .with_system(collect_hitstuns)
-                    .with_system(collect_player_actions)
+                    // Players don't have control during the camera and boss intros
+                    .with_system(
+                        collect_player_actions
+                            .run_unless_resource_exists::<CameraIntro>()
+                            .run_unless_resource_exists::<BossIntro>(),
+                    )
+                    .with_system(enemy_ai::alert_nearby_enemies)
+                    .with_system(enemy_ai::tick_engage_delays)
+                    .with_system(enemy_ai::clear_dying_enemy_aggro)
+                    .with_system(tick_boss_cooldowns)
                     .with_system(
-                        enemy_ai::set_move_target_near_player.pipe(enemy_ai::emit_enemy_intents),
+                        enemy_ai::call_reinforcements.run_unless_resource_exists::<BossIntro>(),
+                    )
+                    // The enemies hold still while the boss shows up
+                    .with_system(
+                        enemy_ai::set_move_target_near_player
+                            .pipe(enemy_ai::emit_enemy_intents)
+                            .run_unless_resource_exists::<BossIntro>(),
                     )
                     .into(),
             )
@@ -63,9 +85,16 @@ impl Plugin for FighterStatePlugin {
                     .with_system(transition_from_idle)
                     .with_system(transition_from_chain)
                     .with_system(transition_from_flopping)
+                    .with_system(transition_from_air_attacking)
                     .with_system(transition_from_punching)
+                    .with_system(transition_from_super_attacking)
+                    .with_system(transition_from_parrying)
                     .with_system(transition_from_ground_slam)
                     .with_system(transition_from_hitstun)
+                    .with_system(transition_from_knocked_down)
+                    .with_system(transition_from_getting_up)
+                    .with_system(transition_from_spawning_entrance)
+                    .with_system(transition_from_downed)
                     .with_system(transition_from_melee_attacking)
                     .with_system(transition_from_shooting)
                     .with_system(transition_from_bomb_throw)
@@ -80,13 +109,22 @@ impl Plugin for FighterStatePlugin {
                     .with_system(idling)
                     .with_system(chaining)
                     .with_system(flopping)
+                    .with_system(air_attacking)
                     .with_system(punching)
+                    .with_system(super_attacking)
+                    .with_system(parrying)
                     .with_system(ground_slam)
                     .with_system(moving)
                     .with_system(throwing)
                     .with_system(grabbing)
                     .with_system(hitstun)
+                    .with_system(knocked_down)
+                    .with_system(getting_up)
+                    .with_system(spawning_entrance)
+                    .with_system(land_fighters)
+                    .with_system(downed)
                     .with_system(dying)
+                    .with_system(fade_corpses)
                     .with_system(melee_attacking)
                     .with_system(shooting)
                     .with_system(bomb_throw)
@@ -101,7 +139,7 @@ pub struct StateTransition {
     /// The [`ReflectComponent`] of the state component we want to transition to
     reflect_component: ReflectComponent,
     /// The data of the component we want to transition to
-    data: Box<dyn Reflect>,
+    pub(crate) data: Box<dyn Reflect>,
     /// The priority of the state transition
     ///
     /// A priority of `i32::MAX` should usually be transitioned to immediately regardless of
@@ -207,10 +245,22 @@ impl Idling {
 #[component(storage = "SparseSet")]
 pub struct Moving {
     pub velocity: Vec2,
+    /// Whether the run modifier is held
+    pub running: bool,
 }
 impl Moving {
     pub const PRIORITY: i32 = 10;
     pub const ANIMATION: &'static str = "running";
+    /// Played while running, if the fighter has it, otherwise [`Moving::ANIMATION`] is used.
+    pub const RUN_ANIMATION: &'static str = "sprinting";
+
+    /// Create a moving state from a movement direction and the fighter stats
+    pub fn new(direction: Vec2, stats: &Stats, running: bool) -> Self {
+        Moving {
+            velocity: direction * stats.movement_speed(running),
+            running,
+        }
+    }
 }
 
 /// The player is throwing an item
@@ -288,13 +338,11 @@ pub struct Chaining {
     pub can_extend: bool,
     pub transition_to_final: bool,
     pub transition_to_idle: bool,
-    pub link: u32,
+    /// The index of the current link in the fighter's combo string
+    pub link: usize,
 }
 impl Chaining {
     pub const PRIORITY: i32 = 30;
-    pub const ANIMATION: &'static str = "chaining";
-    pub const FOLLOWUP_ANIMATION: &'static str = "followup";
-    pub const LENGTH: u32 = 2;
 }
 
 #[derive(Component, Reflect, Default, Debug)]
@@ -332,6 +380,91 @@ impl ProjectileAttacking {
     pub const ANIMATION: &'static str = "attacking";
 }
 
+/// Component indicating the player is performing a super attack
+#[derive(Component, Reflect, Default, Debug)]
+#[component(storage = "SparseSet")]
+pub struct SuperAttacking {
+    pub has_started: bool,
+    pub is_finished: bool,
+}
+impl SuperAttacking {
+    pub const PRIORITY: i32 = 38;
+    /// Played if the fighter has it, otherwise [`Punching::ANIMATION`] is used.
+    pub const ANIMATION: &'static str = "super";
+}
+
+/// Component indicating the fighter is attacking in the air, with its air attack for the direction
+/// held when attacking
+#[derive(Component, Reflect, Default, Debug)]
+#[component(storage = "SparseSet")]
+pub struct AirAttacking {
+    pub direction: AirAttackDirection,
+    pub has_started: bool,
+    pub is_finished: bool,
+}
+impl AirAttacking {
+    /// Higher than [`Flopping::PRIORITY`], so that air attacks cancel the jump they are made from
+    pub const PRIORITY: i32 = 32;
+
+    pub fn new(direction: AirAttackDirection) -> Self {
+        Self {
+            direction,
+            ..default()
+        }
+    }
+}
+
+/// Component indicating the player is parrying, negating the attacks that hit it from the front
+/// during the parry window
+#[derive(Component, Reflect, Default, Debug)]
+#[component(storage = "SparseSet")]
+pub struct Parrying {
+    /// How long, in seconds, the fighter has been parrying for
+    pub elapsed: f32,
+    /// How long, in seconds, attacks are parried for
+    pub window: f32,
+    /// How long, in seconds, the parry lasts, including its recovery
+    pub duration: f32,
+    /// The width, in degrees, of the cone in front of the fighter that attacks are parried from
+    pub cone: f32,
+}
+impl Parrying {
+    pub const PRIORITY: i32 = 30;
+    /// Played if the fighter has it, otherwise [`Idling::ANIMATION`] is used.
+    pub const ANIMATION: &'static str = "parry";
+
+    pub fn new(parry: &ParryMeta) -> Self {
+        Self {
+            elapsed: 0.0,
+            window: parry.window,
+            duration: parry.window + parry.recovery,
+            cone: parry.cone,
+        }
+    }
+
+    /// Whether attacks are still parried
+    pub fn is_in_window(&self) -> bool {
+        self.elapsed <= self.window
+    }
+
+    /// Whether an attack coming from `attack_position` is within the cone in front of the fighter,
+    /// at `position` and facing `facing`
+    pub fn covers(&self, facing: &Facing, position: Vec2, attack_position: Vec2) -> bool {
+        let direction = attack_position - position;
+        // Attacks from right on top of the fighter come from no particular direction
+        if direction.length_squared() <= f32::EPSILON {
+            return true;
+        }
+
+        let forward = if facing.is_left() {
+            Vec2::NEG_X
+        } else {
+            Vec2::X
+        };
+        forward.angle_between(direction).abs().to_degrees() <= self.cone / 2.
+    }
+}
+
 /// Component indicating the player is holding a item on it's head
 #[derive(Component, Reflect, Default, Debug)]
 #[component(storage = "SparseSet")]
@@ -347,6 +480,9 @@ pub struct HitStun {
     //velocity > pushback?
     pub pushback: Vec2,
     pub timer: Timer,
+    /// The downward acceleration of the pushback, bringing launched fighters back down by the end
+    /// of the hit stun
+    pub gravity: f32,
 }
 impl HitStun {
     pub const PRIORITY: i32 = 40;
@@ -356,6 +492,87 @@ impl HitStun {
     pub const KNOCKED_RIGHT: &'static str = "knocked_right";
 }
 
+/// Component indicating the fighter has been knocked to the ground, sliding to a stop and lying there
+/// before it gets up
+#[derive(Component, Reflect, Default, Debug)]
+#[component(storage = "SparseSet")]
+pub struct KnockedDown {
+    pub pushback: Vec2,
+    /// How long, in seconds, the fighter slides along the ground with the pushback
+    pub slide_duration: f32,
+    pub timer: Timer,
+}
+impl KnockedDown {
+    pub const PRIORITY: i32 = 45;
+    /// Played if the fighter has it, otherwise [`Dying::ANIMATION`] is used.
+    pub const ANIMATION: &'static str = "knocked_down";
+
+    pub fn new(pushback: Vec2, slide_duration: f32, down_duration: f32) -> Self {
+        Self {
+            pushback,
+            slide_duration,
+            timer: Timer::from_seconds(slide_duration + down_duration, TimerMode::Once),
+        }
+    }
+}
+
+/// Component indicating the fighter is getting up after being knocked down, unable to be damaged
+/// until it is back on its feet
+#[derive(Component, Reflect, Default, Debug)]
+#[component(storage = "SparseSet")]
+pub struct GettingUp {
+    pub timer: Timer,
+}
+impl GettingUp {
+    pub const PRIORITY: i32 = 45;
+    /// Played if the fighter has it, otherwise [`HitStun::HITSTUN`] is used.
+    pub const ANIMATION: &'static str = "getting_up";
+
+    pub fn new(duration: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(duration, TimerMode::Once),
+        }
+    }
+}
+
+/// Component indicating an enemy is making its entrance, moving to its spawn location before the AI
+/// takes over
+#[derive(Component, Reflect, Default, Debug)]
+#[component(storage = "SparseSet")]
+pub struct SpawningEntrance {
+    /// The position the entrance ends at
+    pub target: Vec2,
+    pub speed: f32,
+    pub is_finished: bool,
+}
+impl SpawningEntrance {
+    pub const PRIORITY: i32 = 500;
+}
+
+/// Component indicating the player's health has run out, and that they are waiting for a teammate
+/// to revive them before they die
+#[derive(Component, Reflect, Default, Debug)]
+#[component(storage = "SparseSet")]
+pub struct Downed {
+    /// The player dies when this timer finishes
+    pub timer: Timer,
+    /// How long, in seconds, a teammate has been reviving the player for
+    pub revive_progress: f32,
+    pub is_revived: bool,
+}
+impl Downed {
+    pub const PRIORITY: i32 = 900;
+    /// Played if the fighter has it, otherwise [`Dying::ANIMATION`] is used.
+    pub const ANIMATION: &'static str = "downed";
+
+    pub fn new(duration: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(duration, TimerMode::Once),
+            ..default()
+        }
+    }
+}
+
 /// Component indicating the player is dying
 #[derive(Component, Reflect, Default, Debug)]
 #[component(storage = "SparseSet")]
@@ -382,10 +599,22 @@ fn collect_player_actions(
             &Stats,
             Option<&Holding>,
             Option<&mut Chaining>,
+            Option<&Idling>,
+            Option<&Moving>,
             &AvailableAttacks,
+            &mut AnalogAttackTrigger,
+            &mut AttackBuffer,
+            &StickResponse,
+            &SuperMeter,
+            Option<&Confused>,
+            (Option<&Airborne>, &Handle<FighterMeta>),
         ),
-        With<Player>,
+        (With<Player>, Without<AssistAi>),
     >,
+    keyboard_input: Res<Input<KeyCode>>,
+    time: GameTime,
+    game: Res<GameMeta>,
+    fighter_assets: Res<Assets<FighterMeta>>,
 ) {
     for (
         action_state,
@@ -394,48 +623,97 @@ fn collect_player_actions(
         stats,
         holding,
         chaining,
+        idling,
+        moving,
         available_attacks,
+        mut analog_attack_trigger,
+        mut attack_buffer,
+        stick_response,
+        super_meter,
+        confused,
+        (airborne, meta_handle),
     ) in &mut players
     {
-        // Trigger attacks
+        // Trigger the super attack, if the super meter is full. The meter is only drained once the
+        // fighter starts the attack, in case the transition is rejected.
+        if action_state.just_pressed(PlayerAction::Super)
+            && holding.is_none()
+            && super_meter.is_full()
+        {
+            transition_intents.push_back(StateTransition::new(
+                SuperAttacking::default(),
+                SuperAttacking::PRIORITY,
+                false,
+            ));
+        }
+
+        // Parry, if parries are enabled
+        if let Some(parry) = &game.parry {
+            if action_state.just_pressed(PlayerAction::Block) && holding.is_none() {
+                transition_intents.push_back(StateTransition::new(
+                    Parrying::new(parry),
+                    Parrying::PRIORITY,
+                    false,
+                ));
+            }
+        }
+
+        // Get the attack strength from the analog attack trigger, if the player has one bound,
+        // falling back to the digital attack button.
+        let attack_strength = analog_attack_trigger
+            .update(action_state.value(PlayerAction::AnalogAttack))
+            .or_else(|| {
+                action_state
+                    .just_pressed(PlayerAction::Attack)
+                    .then_some(AttackStrength::Light)
+            });
+
+        // Buffer the attack, using the buffer settings of the device it was pressed on
+        let buffer_controls = attack_buffer.controls(&keyboard_input);
+        attack_buffer.update(
+            buffer_controls,
+            attack_strength,
+            action_state.just_released(PlayerAction::Attack),
+            time.delta_seconds(),
+        );
+
+        // Trigger attacks, keeping them buffered until the fighter is able to attack
         //TODO: can use flop attack again after input buffer/chaining
-        if action_state.just_pressed(PlayerAction::Attack) && holding.is_none() {
+        let can_attack = idling.is_some() || moving.is_some();
+        let can_air_attack = airborne.is_some()
+            && fighter_assets
+                .get(meta_handle)
+                .map_or(false, |fighter| !fighter.air_attacks.is_empty());
+        if holding.is_none() {
             if chaining.is_none() {
-                match available_attacks.current_attack().name.as_str() {
-                    "chain" => transition_intents.push_back(StateTransition::new(
-                        //need to construct a chain with correct inputs
-                        Chaining::default(),
-                        Chaining::PRIORITY,
-                        false,
-                    )),
-                    "punch" => transition_intents.push_back(StateTransition::new(
-                        Punching::default(),
-                        Punching::PRIORITY,
-                        false,
-                    )),
-                    "flop" => transition_intents.push_back(StateTransition::new(
-                        Flopping::default(),
-                        Flopping::PRIORITY,
-                        false,
-                    )),
-                    "melee" => transition_intents.push_back(StateTransition::new(
-                        MeleeAttacking::default(),
-                        MeleeAttacking::PRIORITY,
-                        false,
-                    )),
-                    "projectile" => transition_intents.push_back(StateTransition::new(
-                        Shooting::default(),
-                        Shooting::PRIORITY,
+                if can_attack {
+                    if let Some(strength) = attack_buffer.take() {
+                        if let Some(transition) = attack_transition(available_attacks, strength) {
+                            transition_intents.push_back(transition);
+                        }
+                    }
+                // Fighters off the ground attack with the air attack of the direction held. The
+                // attack stays buffered until the air attack starts, in case it is rejected.
+                } else if can_air_attack && attack_buffer.peek().is_some() {
+                    let stick = action_state
+                        .clamped_axis_pair(PlayerAction::Move)
+                        .map_or(Vec2::ZERO, |axis| axis.xy());
+                    let direction = movement_direction(stick_response, stick, confused.is_some());
+
+                    transition_intents.push_back(StateTransition::new(
+                        AirAttacking::new(AirAttackDirection::from_stick(direction)),
+                        AirAttacking::PRIORITY,
                         false,
-                    )),
-                    _ => {}
+                    ));
                 }
             //todo, change to pushing states and making it additive
             //move variable setting/continue_chain to exit condition
             } else if let Some(mut chaining) = chaining {
-                // if chaining.can_extend {
-                chaining.continue_chain = true;
-                // }
+                if attack_buffer.take().is_some() {
+                    // if chaining.can_extend {
+                    chaining.continue_chain = true;
+                    // }
+                }
             }
         }
 
@@ -459,12 +737,11 @@ fn collect_player_actions(
         // Trigger movement
         if action_state.pressed(PlayerAction::Move) {
             let dual_axis = action_state.clamped_axis_pair(PlayerAction::Move).unwrap();
-            let direction = dual_axis.xy();
+            let direction = movement_direction(stick_response, dual_axis.xy(), confused.is_some());
+            let running = action_state.pressed(PlayerAction::Run);
 
             transition_intents.push_back(StateTransition::new(
-                Moving {
-                    velocity: direction * stats.movement_speed,
-                },
+                Moving::new(direction, stats, running),
                 Moving::PRIORITY,
                 false,
             ));
@@ -472,27 +749,209 @@ fn collect_player_actions(
     }
 }
 
+/// Get the movement direction of a player's stick input, inverted for confused players
+fn movement_direction(stick_response: &StickResponse, stick: Vec2, confused: bool) -> Vec2 {
+    let direction = stick_response.apply(stick);
+    if confused {
+        -direction
+    } else {
+        direction
+    }
+}
+
+/// Get the state transition for an attack of the given strength.
+///
+/// Heavy attacks are flops, for fighters that can flop, and light attacks are the fighter's current
+/// attack.
+pub(crate) fn attack_transition(
+    available_attacks: &AvailableAttacks,
+    strength: AttackStrength,
+) -> Option<StateTransition> {
+    let can_flop = available_attacks.attacks.iter().any(|a| a.name == "flop");
+    let attack_name = match strength {
+        AttackStrength::Heavy if can_flop => "flop",
+        _ => available_attacks.current_attack().name.as_str(),
+    };
+
+    match attack_name {
+        "chain" => Some(StateTransition::new(
+            //need to construct a chain with correct inputs
+            Chaining::default(),
+            Chaining::PRIORITY,
+            false,
+        )),
+        "punch" => Some(StateTransition::new(
+            Punching::default(),
+            Punching::PRIORITY,
+            false,
+        )),
+        "flop" => Some(StateTransition::new(
+            Flopping::default(),
+            Flopping::PRIORITY,
+            false,
+        )),
+        "melee" => Some(StateTransition::new(
+            MeleeAttacking::default(),
+            MeleeAttacking::PRIORITY,
+            false,
+        )),
+        "projectile" => Some(StateTransition::new(
+            Shooting::default(),
+            Shooting::PRIORITY,
+            false,
+        )),
+        _ => None,
+    }
+}
+
 /// Look for attacks that have contacted a figher and queue a hitstun state transition.
 ///
+/// Hits only stun fighters with poise once the damage they accumulate breaks their poise, and
+/// parried attacks stun their attacker instead.
+///
 /// TODO: Not all attacks will have knockback. Maybe we should replace `damage_velocity` with
 /// `damage_impulse` including the knockback time so that it can be ignored by this system if it's
 /// velocity or time is zero.
 fn collect_hitstuns(
-    mut fighters: Query<&mut StateTransitionIntents, With<Handle<FighterMeta>>>,
+    mut commands: Commands,
+    mut fighters: Query<
+        (
+            &mut StateTransitionIntents,
+            &Stats,
+            &mut Stagger,
+            &mut JuggleCount,
+            Option<&Airborne>,
+            Option<&Transform>,
+        ),
+        With<Handle<FighterMeta>>,
+    >,
     mut damage_events: EventReader<DamageEvent>,
+    mut parry_events: EventReader<ParryEvent>,
+    mut super_meters: Query<&mut SuperMeter>,
+    owners: AttackOwners,
+    game: Res<GameMeta>,
 ) {
-    for event in damage_events.iter() {
+    // Parried attacks stagger their attacker, and fill the parrying fighter's super meter
+    for event in parry_events.iter() {
+        let parry = match &game.parry {
+            Some(parry) => parry,
+            None => continue,
+        };
+
+        if let Ok(mut meter) = super_meters.get_mut(event.parrier) {
+            meter.add(parry.meter_gain);
+        }
+
+        let attacker = owners.get(event.attack);
+        if let Ok((mut transition_intents, ..)) = fighters.get_mut(attacker) {
+            transition_intents.push_back(StateTransition::new(
+                HitStun {
+                    pushback: Vec2::ZERO,
+                    timer: Timer::from_seconds(parry.stun_duration, TimerMode::Once),
+                    gravity: 0.0,
+                },
+                HitStun::PRIORITY,
+                false,
+            ));
+        }
+    }
+
+    // Damage over time doesn't stun
+    for event in damage_events
+        .iter()
+        .filter(|event| !event.is_damage_over_time())
+    {
         // If the damaged entity was a fighter
-        if let Ok(mut transition_intents) = fighters.get_mut(event.damaged_entity) {
+        if let Ok((
+            mut transition_intents,
+            stats,
+            mut stagger,
+            mut juggle_count,
+            airborne,
+            transform,
+        )) = fighters.get_mut(event.damaged_entity)
+        {
             if event.hitstun_duration == 0.0 {
                 continue;
             }
+
+            if !stagger.hit(event.damage as f32, stats.poise) {
+                continue;
+            }
+
+            // Consecutive air hits get less effective, so that juggles can't go on forever
+            let mut hitstun_duration = event.hitstun_duration;
+            let mut stats = stats.clone();
+            if airborne.is_some() {
+                hitstun_duration = game
+                    .juggle
+                    .hitstun_duration(hitstun_duration, **juggle_count);
+                stats.knockback_resist = game
+                    .juggle
+                    .knockback_resist(stats.knockback_resist, **juggle_count);
+                **juggle_count += 1;
+            }
+
+            //Hit stun velocity feels strange right now
+            let mut pushback = stats.scale_knockback(match event.pull {
+                // Pull attacks knock their target toward the attacker instead of away
+                Some(pull) => {
+                    let position = transform
+                        .map(|transform| transform.translation.truncate())
+                        .unwrap_or(event.contact_position);
+                    pull.velocity(position, hitstun_duration)
+                }
+                None => event.damage_velocity,
+            });
+
+            match event.reaction {
+                // Staggered fighters are stunned in place
+                HitReaction::Stagger => pushback = Vec2::ZERO,
+                HitReaction::Knockback => {}
+                // Knocked down fighters slide along the ground, then lie there until they get up.
+                // Airborne fighters stay airborne, falling to the ground before they slide.
+                HitReaction::Knockdown => {
+                    transition_intents.push_back(StateTransition::new(
+                        KnockedDown::new(
+                            Vec2::new(pushback.x, 0.0),
+                            hitstun_duration,
+                            game.knockdown.down_duration,
+                        ),
+                        KnockedDown::PRIORITY,
+                        false,
+                    ));
+                    continue;
+                }
+                // Attacks without a launch velocity of their own still knock their target up
+                HitReaction::Launch => {
+                    if pushback.y <= 0.0 {
+                        pushback.y = consts::LAUNCH_REACTION_SPEED;
+                    }
+                }
+            }
+
+            // Launched fighters are airborne, so that the following hits juggle them, and fall
+            // back down to where they were launched from.
+            let mut gravity = 0.0;
+            if pushback.y > 0.0 && hitstun_duration > 0.0 && event.pull.is_none() {
+                gravity = 2.0 * pushback.y / hitstun_duration;
+                // Fighters that are already airborne keep the ground they left from
+                let ground_y = match (airborne, transform) {
+                    (Some(airborne), _) => airborne.ground_y,
+                    (None, Some(transform)) => transform.translation.y,
+                    (None, None) => event.contact_position.y,
+                };
+                commands
+                    .entity(event.damaged_entity)
+                    .insert(Airborne { ground_y });
+            }
+
             // Trigger hit stun
             transition_intents.push_back(StateTransition::new(
                 HitStun {
-                    //Hit stun velocity feels strange right now
-                    pushback: event.damage_velocity,
-                    timer: Timer::from_seconds(event.hitstun_duration, TimerMode::Once),
+                    pushback,
+                    timer: Timer::from_seconds(hitstun_duration, TimerMode::Once),
+                    gravity,
                 },
                 HitStun::PRIORITY,
                 false,
@@ -501,15 +960,61 @@ fn collect_hitstuns(
     }
 }
 
+/// Land the airborne fighters that are no longer jumping, in hit stun or falling from a knockdown,
+/// resetting their juggle count
+fn land_fighters(
+    mut commands: Commands,
+    mut fighters: Query<
+        (Entity, &mut JuggleCount),
+        (
+            With<Airborne>,
+            Without<Flopping>,
+            Without<AirAttacking>,
+            Without<GroundSlam>,
+            Without<HitStun>,
+            Without<KnockedDown>,
+        ),
+    >,
+) {
+    for (entity, mut juggle_count) in &mut fighters {
+        **juggle_count = 0;
+        commands.entity(entity).remove::<Airborne>();
+    }
+}
+
 /// Look for fighters with their health depleated and transition them to dying state
 fn collect_fighter_eliminations(
-    mut fighters: Query<(&Health, &mut StateTransitionIntents), With<Handle<FighterMeta>>>,
+    mut fighters: Query<
+        (
+            Entity,
+            &Health,
+            &mut StateTransitionIntents,
+            Option<&Player>,
+        ),
+        (With<Handle<FighterMeta>>, Without<Downed>),
+    >,
+    teammates: Query<(Entity, &Health), (With<Player>, Without<Downed>, Without<Dying>)>,
+    game: Res<GameMeta>,
 ) {
-    for (health, mut transition_intents) in &mut fighters {
+    for (entity, health, mut transition_intents, player) in &mut fighters {
         // If the fighter health is depleted
         if **health <= 0 {
-            // Transition to dying state
-            transition_intents.push_back(StateTransition::new(Dying, Dying::PRIORITY, false));
+            // Players are downed instead, if there is a teammate left to revive them
+            let can_be_revived = player.is_some()
+                && teammates
+                    .iter()
+                    .any(|(teammate, health)| teammate != entity && **health > 0);
+
+            if can_be_revived {
+                transition_intents.push_back(StateTransition::new(
+                    Downed::new(game.revive.downed_duration),
+                    Downed::PRIORITY,
+                    false,
+                ));
+            } else {
+                // Transition to dying state
+                transition_intents.push_back(StateTransition::new(Dying, Dying::PRIORITY, false));
+            }
         }
     }
 }
@@ -560,6 +1065,35 @@ fn transition_from_flopping(
     }
 }
 
+fn transition_from_air_attacking(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &AirAttacking)>,
+) {
+    'entity: for (entity, mut transition_intents, air_attacking) in &mut fighters {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<AirAttacking>(
+                entity,
+                AirAttacking::PRIORITY,
+                &mut commands,
+            );
+
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
+
+        // If we've landed
+        if air_attacking.is_finished {
+            // Go back to idle
+            commands
+                .entity(entity)
+                .remove::<AirAttacking>()
+                .insert(Idling);
+        }
+    }
+}
+
 fn transition_from_punching(
     mut commands: Commands,
     mut fighters: Query<(Entity, &mut StateTransitionIntents, &Punching)>,
@@ -586,6 +1120,60 @@ fn transition_from_punching(
     }
 }
 
+fn transition_from_super_attacking(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &SuperAttacking)>,
+) {
+    'entity: for (entity, mut transition_intents, super_attacking) in &mut fighters {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<SuperAttacking>(
+                entity,
+                SuperAttacking::PRIORITY,
+                &mut commands,
+            );
+
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
+
+        // If we're done attacking
+        if super_attacking.is_finished {
+            // Go back to idle
+            commands
+                .entity(entity)
+                .remove::<SuperAttacking>()
+                .insert(Idling);
+        }
+    }
+}
+
+fn transition_from_parrying(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &Parrying)>,
+) {
+    'entity: for (entity, mut transition_intents, parrying) in &mut fighters {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<Parrying>(
+                entity,
+                Parrying::PRIORITY,
+                &mut commands,
+            );
+
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
+
+        // Go back to idle at the end of the parry
+        if parrying.elapsed >= parrying.duration {
+            commands.entity(entity).remove::<Parrying>().insert(Idling);
+        }
+    }
+}
+
 fn transition_from_chain(
     mut commands: Commands,
     mut fighters: Query<(Entity, &mut StateTransitionIntents, &mut Chaining)>,
@@ -701,16 +1289,18 @@ fn transition_from_hitstun(
     }
 }
 
-fn transition_from_melee_attacking(
+// Initiate any transitions from the knocked down state
+fn transition_from_knocked_down(
     mut commands: Commands,
-    mut fighters: Query<(Entity, &mut StateTransitionIntents, &MeleeAttacking)>,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &KnockedDown)>,
+    game: Res<GameMeta>,
 ) {
-    'entity: for (entity, mut transition_intents, melee_attacking) in &mut fighters {
+    'entity: for (entity, mut transition_intents, knocked_down) in &mut fighters {
         // Transition to any higher priority states
         let current_state_removed = transition_intents
-            .transition_to_higher_priority_states::<MeleeAttacking>(
+            .transition_to_higher_priority_states::<KnockedDown>(
                 entity,
-                MeleeAttacking::PRIORITY,
+                KnockedDown::PRIORITY,
                 &mut commands,
             );
 
@@ -719,53 +1309,167 @@ fn transition_from_melee_attacking(
             continue 'entity;
         }
 
-        // If we're done attacking
-        if melee_attacking.is_finished {
-            // Go back to idle
+        // Get up when finished, unable to be damaged while getting up
+        if knocked_down.timer.finished() {
             commands
                 .entity(entity)
-                .remove::<MeleeAttacking>()
-                .insert(Idling);
+                .remove::<KnockedDown>()
+                .insert((GettingUp::new(game.knockdown.getup_duration), Invulnerable));
         }
     }
 }
 
-fn transition_from_shooting(
+// Initiate any transitions from the getting up state
+fn transition_from_getting_up(
     mut commands: Commands,
-    mut fighters: Query<(Entity, &mut StateTransitionIntents, &Shooting)>,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &GettingUp)>,
 ) {
-    'entity: for (entity, mut transition_intents, shooting) in &mut fighters {
+    'entity: for (entity, mut transition_intents, getting_up) in &mut fighters {
         // Transition to any higher priority states
         let current_state_removed = transition_intents
-            .transition_to_higher_priority_states::<Shooting>(
+            .transition_to_higher_priority_states::<GettingUp>(
                 entity,
-                Shooting::PRIORITY,
+                GettingUp::PRIORITY,
                 &mut commands,
             );
 
-        // If our current state was removed, don't continue processing this fighter
+        // If our current state was removed, don't continue processing this fighter, which can be
+        // damaged again
         if current_state_removed {
+            commands.entity(entity).remove::<Invulnerable>();
             continue 'entity;
         }
 
-        // If we're done attacking
-        if shooting.is_finished {
-            // Go back to idle
-            commands.entity(entity).remove::<Shooting>().insert(Idling);
+        // Transition to idle when finished
+        if getting_up.timer.finished() {
+            commands
+                .entity(entity)
+                .remove::<(GettingUp, Invulnerable)>()
+                .insert(Idling);
         }
     }
 }
 
-fn transition_from_proj_attacking(
+fn transition_from_spawning_entrance(
     mut commands: Commands,
-    mut fighters: Query<(Entity, &mut StateTransitionIntents, &ProjectileAttacking)>,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &SpawningEntrance)>,
 ) {
-    'entity: for (entity, mut transition_intents, proj_attacking) in &mut fighters {
+    'entity: for (entity, mut transition_intents, entrance) in &mut fighters {
         // Transition to any higher priority states
         let current_state_removed = transition_intents
-            .transition_to_higher_priority_states::<ProjectileAttacking>(
+            .transition_to_higher_priority_states::<SpawningEntrance>(
                 entity,
-                ProjectileAttacking::PRIORITY,
+                SpawningEntrance::PRIORITY,
+                &mut commands,
+            );
+
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
+
+        // Hand over to the AI, and allow the enemy to be damaged, once it has arrived
+        if entrance.is_finished {
+            commands
+                .entity(entity)
+                .remove::<(SpawningEntrance, Invulnerable)>()
+                .insert(Idling);
+        }
+    }
+}
+
+fn transition_from_downed(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &Downed)>,
+) {
+    'entity: for (entity, mut transition_intents, downed) in &mut fighters {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<Downed>(
+                entity,
+                Downed::PRIORITY,
+                &mut commands,
+            );
+
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
+
+        // Get back up once revived, or die if nobody came in time
+        if downed.is_revived {
+            commands.entity(entity).remove::<Downed>().insert(Idling);
+        } else if downed.timer.finished() {
+            commands.entity(entity).remove::<Downed>().insert(Dying);
+        }
+    }
+}
+
+fn transition_from_melee_attacking(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &MeleeAttacking)>,
+) {
+    'entity: for (entity, mut transition_intents, melee_attacking) in &mut fighters {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<MeleeAttacking>(
+                entity,
+                MeleeAttacking::PRIORITY,
+                &mut commands,
+            );
+
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
+
+        // If we're done attacking
+        if melee_attacking.is_finished {
+            // Go back to idle
+            commands
+                .entity(entity)
+                .remove::<MeleeAttacking>()
+                .insert(Idling);
+        }
+    }
+}
+
+fn transition_from_shooting(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &Shooting)>,
+) {
+    'entity: for (entity, mut transition_intents, shooting) in &mut fighters {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<Shooting>(
+                entity,
+                Shooting::PRIORITY,
+                &mut commands,
+            );
+
+        // If our current state was removed, don't continue processing this fighter
+        if current_state_removed {
+            continue 'entity;
+        }
+
+        // If we're done attacking
+        if shooting.is_finished {
+            // Go back to idle
+            commands.entity(entity).remove::<Shooting>().insert(Idling);
+        }
+    }
+}
+
+fn transition_from_proj_attacking(
+    mut commands: Commands,
+    mut fighters: Query<(Entity, &mut StateTransitionIntents, &ProjectileAttacking)>,
+) {
+    'entity: for (entity, mut transition_intents, proj_attacking) in &mut fighters {
+        // Transition to any higher priority states
+        let current_state_removed = transition_intents
+            .transition_to_higher_priority_states::<ProjectileAttacking>(
+                entity,
+                ProjectileAttacking::PRIORITY,
                 &mut commands,
             );
 
@@ -820,10 +1524,10 @@ fn flopping(
         &Handle<FighterMeta>,
         &AvailableAttacks,
         &mut Flopping,
-        Option<&Player>,
-        Option<&Enemy>,
+        Option<&Team>,
     )>,
     fighter_assets: Res<Assets<FighterMeta>>,
+    game: Res<GameMeta>,
 ) {
     for (
         entity,
@@ -834,64 +1538,46 @@ fn flopping(
         meta_handle,
         available_attacks,
         mut flopping,
-        player,
-        enemy,
+        team,
     ) in &mut fighters
     {
-        let is_player = player.is_some();
-        let is_enemy = enemy.is_some();
-        if !is_player && !is_enemy {
-            // This system only knows how to attack for players and enemies
-            continue;
-        }
-
-        let attack = available_attacks.current_attack();
+        let team = match team {
+            Some(team) => *team,
+            // This system only knows how to attack for fighters on a team
+            None => continue,
+        };
+
+        // Heavy attacks may flop even if flopping isn't the current attack
+        let attack = available_attacks
+            .attacks
+            .iter()
+            .rev()
+            .find(|a| a.name == "flop")
+            .unwrap_or_else(|| available_attacks.current_attack());
         if let Some(fighter) = fighter_assets.get(meta_handle) {
             // Start the attack
             if !flopping.has_started {
                 flopping.has_started = true;
                 flopping.start_y = transform.translation.y;
+                commands.entity(entity).insert(Airborne {
+                    ground_y: flopping.start_y,
+                });
 
                 // Start the attack  from the beginning
                 animation.play(Flopping::ANIMATION, false);
 
-                let mut offset = attack.hitbox.offset;
-                if facing.is_left() {
-                    offset.x *= -1.0
-                }
-                offset.y += fighter.collision_offset;
-                let attack_frames = attack.frames;
-
                 // Spawn the attack entity
-                let attack_entity = commands
-                    .spawn(TransformBundle::from_transform(
-                        Transform::from_translation(offset.extend(0.0)),
-                    ))
-                    .insert(CollisionGroups::new(
-                        if is_player {
-                            BodyLayers::PLAYER_ATTACK
-                        } else {
-                            BodyLayers::ENEMY_ATTACK
-                        },
-                        if is_player {
-                            BodyLayers::ENEMY | BodyLayers::BREAKABLE_ITEM
-                        } else {
-                            BodyLayers::PLAYER
-                        },
-                    ))
-                    .insert(Attack {
-                        damage: attack.damage,
-                        pushback: if facing.is_left() {
-                            Vec2::NEG_X
-                        } else {
-                            Vec2::X
-                        } * attack.velocity.unwrap_or(Vec2::ZERO),
-                        hitstun_duration: attack.hitstun_duration,
-                        hitbox_meta: Some(attack.hitbox),
-                    })
-                    .insert(attack_frames)
-                    .id();
-                commands.entity(entity).push_children(&[attack_entity]);
+                spawn_attack_hitbox(
+                    &mut commands,
+                    entity,
+                    attack,
+                    attack.damage,
+                    facing,
+                    fighter.collision_offset,
+                    team,
+                    game.friendly_fire,
+                );
+                start_attack_motion(&mut commands, entity, attack, facing, **velocity);
 
                 // Play attack sound effect
                 if let Some(effects) = fighter.audio.effect_handles.get(Flopping::ANIMATION) {
@@ -938,6 +1624,110 @@ fn flopping(
     }
 }
 
+/// Plays the fighter's air attack for the direction held, moving with it, and then falling back to
+/// the ground the fighter jumped from
+fn air_attacking(
+    mut commands: Commands,
+    mut fighters: Query<(
+        Entity,
+        &mut Animation,
+        &mut Transform,
+        &mut LinearVelocity,
+        &Facing,
+        &Handle<FighterMeta>,
+        &mut AirAttacking,
+        Option<&Airborne>,
+        Option<&Team>,
+        Option<&mut AttackBuffer>,
+    )>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    game: Res<GameMeta>,
+) {
+    for (
+        entity,
+        mut animation,
+        mut transform,
+        mut velocity,
+        facing,
+        meta_handle,
+        mut air_attacking,
+        airborne,
+        team,
+        attack_buffer,
+    ) in &mut fighters
+    {
+        let team = match team {
+            Some(team) => *team,
+            // This system only knows how to attack for fighters on a team
+            None => continue,
+        };
+        let fighter = match fighter_assets.get(meta_handle) {
+            Some(fighter) => fighter,
+            None => continue,
+        };
+        let ground_y = airborne.map_or(transform.translation.y, |airborne| airborne.ground_y);
+
+        let air_attack = match fighter.air_attack(air_attacking.direction) {
+            Some(air_attack) => air_attack,
+            // Fighters without air attacks just land
+            None => {
+                transform.translation.y = ground_y;
+                **velocity = Vec2::ZERO;
+                air_attacking.is_finished = true;
+                continue;
+            }
+        };
+
+        // Start the attack
+        if !air_attacking.has_started {
+            air_attacking.has_started = true;
+            animation.play(&air_attack.animation, false);
+
+            // The attack that triggered the air attack is only used up once it starts
+            if let Some(mut attack_buffer) = attack_buffer {
+                attack_buffer.take();
+            }
+
+            // Play attack sound effect
+            if let Some(effects) = fighter.audio.effect_handles.get(&air_attack.animation) {
+                let fx_playback =
+                    AnimationAudioPlayback::new(air_attack.animation.clone(), effects.clone());
+                commands.entity(entity).insert(fx_playback);
+            }
+
+            // Spawn the attack entity
+            spawn_attack_hitbox(
+                &mut commands,
+                entity,
+                &air_attack.attack,
+                air_attack.attack.damage,
+                facing,
+                fighter.collision_offset,
+                team,
+                game.friendly_fire,
+            );
+        }
+
+        // Move with the attack, and fall back down once it's over
+        **velocity = if animation.is_finished() {
+            Vec2::new(0.0, -consts::AIR_ATTACK_FALL_SPEED)
+        } else if facing.is_left() {
+            Vec2::new(-air_attack.movement.x, air_attack.movement.y)
+        } else {
+            air_attack.movement
+        };
+
+        // Land on the ground
+        if transform.translation.y <= ground_y && velocity.y < 0.0 {
+            transform.translation.y = ground_y;
+            **velocity = Vec2::ZERO;
+            air_attacking.is_finished = true;
+        }
+    }
+}
+
+/// Plays the links of the fighter's combo string, chaining the next link when the attack is
+/// pressed again during the cancel window of the current one
 fn chaining(
     mut commands: Commands,
     mut fighters: Query<
@@ -947,117 +1737,82 @@ fn chaining(
             &mut LinearVelocity,
             &Facing,
             &Handle<FighterMeta>,
-            &AvailableAttacks,
             &mut Chaining,
         ),
         With<Player>,
     >,
     fighter_assets: Res<Assets<FighterMeta>>,
+    game: Res<GameMeta>,
 ) {
-    for (
-        entity,
-        mut animation,
-        mut velocity,
-        facing,
-        meta_handle,
-        available_attacks,
-        mut chaining,
-    ) in &mut fighters
-    {
-        // this seems... potentially panicky
-        if let Some(attack) = available_attacks
-            .attacks
-            .iter()
-            .filter(|a| a.name == *"chain")
-            .last()
-        {
-            if let Some(fighter) = fighter_assets.get(meta_handle) {
-                //if we havent started the chain yet or if we have input during chain window
-                if !chaining.has_started || chaining.continue_chain && chaining.can_extend {
-                    if !chaining.has_started {
-                        chaining.has_started = true;
-                        animation.play(Chaining::ANIMATION, false);
-                        // Play attack sound effect
-                        if let Some(effects) = fighter.audio.effect_handles.get(Chaining::ANIMATION)
-                        {
-                            let fx_playback = AnimationAudioPlayback::new(
-                                Chaining::ANIMATION.to_owned(),
-                                effects.clone(),
-                            );
-                            commands.entity(entity).insert(fx_playback);
-                        }
-                    }
-                    // Start the attack  from the beginning
-
-                    //if we are on chain followup, skip the first frame of the animation
-                    if chaining.continue_chain {
-                        animation.play(Chaining::FOLLOWUP_ANIMATION, false);
-                        animation.current_frame = 2;
-                        chaining.continue_chain = false;
-                        chaining.link += 1;
-                        if chaining.link >= Chaining::LENGTH {
-                            chaining.transition_to_final = true;
-                        }
-                        // Play attack sound effect
-                        if let Some(effects) = fighter
-                            .audio
-                            .effect_handles
-                            .get(Chaining::FOLLOWUP_ANIMATION)
-                        {
-                            let fx_playback = AnimationAudioPlayback::new(
-                                Chaining::FOLLOWUP_ANIMATION.to_owned(),
-                                effects.clone(),
-                            );
-                            commands.entity(entity).insert(fx_playback);
-                        }
-                    }
-                    chaining.can_extend = false;
+    for (entity, mut animation, mut velocity, facing, meta_handle, mut chaining) in &mut fighters {
+        let fighter = match fighter_assets.get(meta_handle) {
+            Some(fighter) => fighter,
+            None => continue,
+        };
+        // Fighters without a combo string have nothing to chain
+        if fighter.combo.is_empty() {
+            chaining.transition_to_idle = true;
+            continue;
+        }
 
-                    let mut offset = attack.hitbox.offset;
-                    if facing.is_left() {
-                        offset.x *= -1.0
+        // Start the combo, or chain the next link if we have input during the cancel window
+        let next_link = if !chaining.has_started {
+            Some(0)
+        } else if chaining.continue_chain && chaining.can_extend {
+            Some(chaining.link + 1)
+        } else {
+            None
+        };
+        if let Some(next_link) = next_link {
+            chaining.continue_chain = false;
+            chaining.can_extend = false;
+
+            match fighter.combo.get(next_link) {
+                Some(link) => {
+                    chaining.has_started = true;
+                    chaining.link = next_link;
+                    animation.play(&link.animation, false);
+                    animation.current_frame = link.start_frame;
+
+                    // Play attack sound effect
+                    if let Some(effects) = fighter.audio.effect_handles.get(&link.animation) {
+                        let fx_playback =
+                            AnimationAudioPlayback::new(link.animation.clone(), effects.clone());
+                        commands.entity(entity).insert(fx_playback);
                     }
-                    offset.y += fighter.collision_offset;
+
                     // Spawn the attack entity
-                    let attack_entity = commands
-                        .spawn(TransformBundle::from_transform(
-                            Transform::from_translation(offset.extend(0.0)),
-                        ))
-                        .insert(CollisionGroups::new(
-                            BodyLayers::PLAYER_ATTACK,
-                            BodyLayers::ENEMY | BodyLayers::BREAKABLE_ITEM,
-                        ))
-                        .insert(Attack {
-                            damage: attack.damage,
-                            pushback: if facing.is_left() {
-                                Vec2::NEG_X
-                            } else {
-                                Vec2::X
-                            } * attack.velocity.unwrap_or(Vec2::ZERO),
-                            hitstun_duration: attack.hitstun_duration,
-                            hitbox_meta: Some(attack.hitbox),
-                        })
-                        .insert(attack.frames)
-                        .id();
-                    commands.entity(entity).push_children(&[attack_entity]);
+                    spawn_attack_hitbox(
+                        &mut commands,
+                        entity,
+                        &link.attack,
+                        link.attack.damage,
+                        facing,
+                        fighter.collision_offset,
+                        Team::Players,
+                        game.friendly_fire,
+                    );
                 }
+                // Chaining past the last link finishes the combo
+                None => chaining.transition_to_final = true,
             }
+        }
 
-            if animation.current_frame > attack.frames.active {
-                chaining.can_extend = true;
-            }
-            // Reset velocity
-            **velocity = Vec2::ZERO;
+        let link = &fighter.combo[chaining.link];
+        if animation.current_frame >= link.cancel_frame() {
+            chaining.can_extend = true;
+        }
+        // Reset velocity
+        **velocity = Vec2::ZERO;
 
-            //move forward a bit during active frames
-            if animation.current_frame > attack.frames.startup
-                && animation.current_frame < attack.frames.recovery
-            {
-                if facing.is_left() {
-                    velocity.x -= 100.0;
-                } else {
-                    velocity.x += 100.0;
-                }
+        //move forward a bit during active frames
+        if animation.current_frame > link.attack.frames.startup
+            && animation.current_frame < link.attack.frames.recovery
+        {
+            if facing.is_left() {
+                velocity.x -= 100.0;
+            } else {
+                velocity.x += 100.0;
             }
         }
 
@@ -1077,10 +1832,10 @@ fn punching(
         &Handle<FighterMeta>,
         &AvailableAttacks,
         &mut Punching,
-        Option<&Player>,
-        Option<&Enemy>,
+        Option<&Team>,
     )>,
     fighter_assets: Res<Assets<FighterMeta>>,
+    game: Res<GameMeta>,
 ) {
     for (
         entity,
@@ -1090,16 +1845,14 @@ fn punching(
         meta_handle,
         available_attacks,
         mut punching,
-        player,
-        enemy,
+        team,
     ) in &mut fighters
     {
-        let is_player = player.is_some();
-        let is_enemy = enemy.is_some();
-        if !is_player && !is_enemy {
-            // This system only knows how to attack for players and enemies
-            continue;
-        }
+        let team = match team {
+            Some(team) => *team,
+            // This system only knows how to attack for fighters on a team
+            None => continue,
+        };
 
         let attack = available_attacks.current_attack();
         if let Some(fighter) = fighter_assets.get(meta_handle) {
@@ -1109,42 +1862,18 @@ fn punching(
                 // Start the attack  from the beginning
                 animation.play(Punching::ANIMATION, false);
 
-                let mut offset = attack.hitbox.offset;
-                if facing.is_left() {
-                    offset.x *= -1.0
-                }
-                offset.y += fighter.collision_offset;
-                let attack_frames = attack.frames;
                 // Spawn the attack entity
-                let attack_entity = commands
-                    .spawn(TransformBundle::from_transform(
-                        Transform::from_translation(offset.extend(0.0)),
-                    ))
-                    .insert(CollisionGroups::new(
-                        if is_player {
-                            BodyLayers::PLAYER_ATTACK
-                        } else {
-                            BodyLayers::ENEMY_ATTACK
-                        },
-                        if is_player {
-                            BodyLayers::ENEMY | BodyLayers::BREAKABLE_ITEM
-                        } else {
-                            BodyLayers::PLAYER
-                        },
-                    ))
-                    .insert(Attack {
-                        damage: attack.damage,
-                        pushback: if facing.is_left() {
-                            Vec2::NEG_X
-                        } else {
-                            Vec2::X
-                        } * attack.velocity.unwrap_or(Vec2::ZERO),
-                        hitstun_duration: attack.hitstun_duration,
-                        hitbox_meta: Some(attack.hitbox),
-                    })
-                    .insert(attack_frames)
-                    .id();
-                commands.entity(entity).push_children(&[attack_entity]);
+                spawn_attack_hitbox(
+                    &mut commands,
+                    entity,
+                    attack,
+                    attack.damage,
+                    facing,
+                    fighter.collision_offset,
+                    team,
+                    game.friendly_fire,
+                );
+                start_attack_motion(&mut commands, entity, attack, facing, **velocity);
 
                 // Play attack sound effect
                 if let Some(effects) = fighter.audio.effect_handles.get(Punching::ANIMATION) {
@@ -1165,6 +1894,120 @@ fn punching(
     }
 }
 
+/// Handle the player super attack state
+fn super_attacking(
+    mut commands: Commands,
+    mut fighters: Query<
+        (
+            Entity,
+            &mut Animation,
+            &mut LinearVelocity,
+            &Facing,
+            &Handle<FighterMeta>,
+            &AvailableAttacks,
+            &mut SuperAttacking,
+            Option<&mut SuperMeter>,
+        ),
+        With<Player>,
+    >,
+    fighter_assets: Res<Assets<FighterMeta>>,
+    game: Res<GameMeta>,
+) {
+    for (
+        entity,
+        mut animation,
+        mut velocity,
+        facing,
+        meta_handle,
+        available_attacks,
+        mut super_attacking,
+        super_meter,
+    ) in &mut fighters
+    {
+        // Use the fighter's super attack, or a stronger version of its current attack
+        let (attack, damage) = match available_attacks
+            .attacks
+            .iter()
+            .rev()
+            .find(|a| a.name == "super")
+        {
+            Some(attack) => (attack, attack.damage),
+            None => {
+                let attack = available_attacks.current_attack();
+                let damage = attack.damage as f32 * game.super_meter.damage_multiplier;
+                (attack, damage as i32)
+            }
+        };
+
+        if let Some(fighter) = fighter_assets.get(meta_handle) {
+            if !super_attacking.has_started {
+                super_attacking.has_started = true;
+
+                // Drain the super meter now that the attack has been accepted
+                if let Some(mut super_meter) = super_meter {
+                    super_meter.try_consume();
+                }
+
+                // Start the attack from the beginning, with the super animation if we have it
+                let animation_name = if animation.animations.contains_key(SuperAttacking::ANIMATION)
+                {
+                    SuperAttacking::ANIMATION
+                } else {
+                    Punching::ANIMATION
+                };
+                animation.play(animation_name, false);
+
+                // Spawn the attack entity
+                spawn_attack_hitbox(
+                    &mut commands,
+                    entity,
+                    attack,
+                    damage,
+                    facing,
+                    fighter.collision_offset,
+                    Team::Players,
+                    game.friendly_fire,
+                );
+                start_attack_motion(&mut commands, entity, attack, facing, **velocity);
+
+                // Play attack sound effect
+                if let Some(effects) = fighter.audio.effect_handles.get(animation_name) {
+                    let fx_playback =
+                        AnimationAudioPlayback::new(animation_name.to_owned(), effects.clone());
+                    commands.entity(entity).insert(fx_playback);
+                }
+            }
+        }
+
+        **velocity = Vec2::ZERO;
+
+        if animation.is_finished() {
+            super_attacking.is_finished = true;
+        }
+    }
+}
+
+/// Handle the parrying fighters, who stand still until the end of the parry
+fn parrying(
+    mut fighters: Query<(&mut Animation, &mut LinearVelocity, &mut Parrying)>,
+    time: GameTime,
+) {
+    for (mut animation, mut velocity, mut parrying) in &mut fighters {
+        // If this is the start of the parry
+        if parrying.elapsed == 0.0 {
+            let animation_name = if animation.animations.contains_key(Parrying::ANIMATION) {
+                Parrying::ANIMATION
+            } else {
+                Idling::ANIMATION
+            };
+            animation.play(animation_name, false);
+        }
+
+        parrying.elapsed += time.delta_seconds();
+        **velocity = Vec2::ZERO;
+    }
+}
+
 fn projectile_attacking(
     mut commands: Commands,
     mut fighters: Query<
@@ -1197,11 +2040,12 @@ fn projectile_attacking(
         if !animation.is_finished() {
             if animation.current_frame == attack.frames.startup && !proj_attacking.thrown {
                 // Spawn projectile
-                commands.spawn(Projectile::from_thrown_item(
+                commands.spawn(ThrownItemBundle::new(
                     transform.translation + consts::THROW_ITEM_OFFSET.extend(0.0),
                     item,
                     facing,
-                    true,
+                    0.0,
+                    Team::Enemies,
                 ));
 
                 proj_attacking.thrown = true;
@@ -1232,6 +2076,8 @@ fn ground_slam(
         With<Boss>,
     >,
     fighter_assets: Res<Assets<FighterMeta>>,
+    game: Res<GameMeta>,
+    mut camera_tilt: ResMut<CameraTilt>,
 ) {
     for (
         entity,
@@ -1247,41 +2093,28 @@ fn ground_slam(
         // Start the attack
         let attack = available_attacks.current_attack();
         if let Some(fighter) = fighter_assets.get(meta_handle) {
-            let mut offset = attack.hitbox.offset;
-            if facing.is_left() {
-                offset.x *= -1.0
-            }
-            offset.y += fighter.collision_offset;
-            let attack_frames = attack.frames;
             if !ground_slam.has_started {
                 ground_slam.has_started = true;
                 ground_slam.start_y = transform.translation.y;
+                commands.entity(entity).insert(Airborne {
+                    ground_y: ground_slam.start_y,
+                });
 
                 // Start the attack  from the beginning
                 animation.play(GroundSlam::ANIMATION, false);
 
                 // Spawn the attack entity
-                let attack_entity = commands
-                    .spawn(TransformBundle::from_transform(
-                        Transform::from_translation(offset.extend(0.0)),
-                    ))
-                    .insert(CollisionGroups::new(
-                        BodyLayers::ENEMY_ATTACK,
-                        BodyLayers::PLAYER,
-                    ))
-                    .insert(Attack {
-                        damage: attack.damage,
-                        pushback: if facing.is_left() {
-                            Vec2::NEG_X
-                        } else {
-                            Vec2::X
-                        } * attack.velocity.unwrap_or(Vec2::ZERO),
-                        hitstun_duration: attack.hitstun_duration,
-                        hitbox_meta: Some(attack.hitbox),
-                    })
-                    .insert(attack_frames)
-                    .id();
-                commands.entity(entity).push_children(&[attack_entity]);
+                spawn_attack_hitbox(
+                    &mut commands,
+                    entity,
+                    attack,
+                    attack.damage,
+                    facing,
+                    fighter.collision_offset,
+                    Team::Enemies,
+                    game.friendly_fire,
+                );
+                start_attack_motion(&mut commands, entity, attack, facing, **velocity);
 
                 // Play attack sound effect
                 if let Some(fighter) = fighter_assets.get(meta_handle) {
@@ -1302,7 +2135,7 @@ fn ground_slam(
                 // Do a forward jump thing
 
                 // Control x movement
-                if animation.current_frame < attack_frames.startup {
+                if animation.current_frame < attack.frames.startup {
                     if facing.is_left() {
                         velocity.x -= 50.0;
                     } else {
@@ -1317,11 +2150,11 @@ fn ground_slam(
                 // the same Y as started(?)
                 // it might be nice to store movement properties as metadata attached to frame
                 // ranges or individual frames?
-                if animation.current_frame < attack_frames.startup {
-                    let v_per_frame = 800.0 / attack_frames.startup as f32;
+                if animation.current_frame < attack.frames.startup {
+                    let v_per_frame = 800.0 / attack.frames.startup as f32;
                     velocity.y += v_per_frame;
-                } else if animation.current_frame < attack_frames.active {
-                    let v_per_frame = 800.0 / (attack_frames.active - attack_frames.startup) as f32;
+                } else if animation.current_frame < attack.frames.active {
+                    let v_per_frame = 800.0 / (attack.frames.active - attack.frames.startup) as f32;
                     velocity.y -= v_per_frame;
                 }
 
@@ -1333,6 +2166,11 @@ fn ground_slam(
                 // Make sure we "land on the ground" ( i.e. the player y position hasn't changed )
                 transform.translation.y = ground_slam.start_y;
 
+                // The landing rocks the camera
+                if !ground_slam.is_finished {
+                    camera_tilt.add_tilt(consts::GROUND_SLAM_CAMERA_TILT);
+                }
+
                 // Set flopping to finished
                 ground_slam.is_finished = true;
             }
@@ -1351,11 +2189,14 @@ fn bomb_throw(
             &Handle<FighterMeta>,
             &mut BossBombThrow,
             &AvailableAttacks,
+            Option<&mut BossCooldowns>,
+            Option<&Team>,
         ),
         With<Boss>,
     >,
     fighter_assets: Res<Assets<FighterMeta>>,
     item_assets: Res<Assets<ItemMeta>>,
+    mut rng: ResMut<GameRng>,
 ) {
     for (
         mut animation,
@@ -1365,10 +2206,18 @@ fn bomb_throw(
         meta_handle,
         mut bomb_throw,
         available_attacks,
+        cooldowns,
+        team,
     ) in &mut fighters
     {
+        let team = match team {
+            Some(team) => *team,
+            // This system only knows how to attack for fighters on a team
+            None => continue,
+        };
+
         // Start the attack
-        if let Some(fighter) = fighter_assets.get(meta_handle) {
+        if fighter_assets.contains(meta_handle) {
             let attack = available_attacks.current_attack();
             let item = item_assets
                 .get(&attack.item_handle)
@@ -1407,14 +2256,11 @@ fn bomb_throw(
             };
             animated_sprite.animation.current_animation = Some("bomb".to_string());
 
-            let mut offset = attack.hitbox.offset;
-            if facing.is_left() {
-                offset.x *= -1.0
-            }
-            offset.y += fighter.collision_offset;
-
             if !bomb_throw.has_started {
                 bomb_throw.has_started = true;
+                if let Some(mut cooldowns) = cooldowns {
+                    cooldowns.trigger(BossBombThrow::ANIMATION);
+                }
 
                 // Start the attack  from the beginning
                 animation.play(BossBombThrow::ANIMATION, false);
@@ -1433,10 +2279,12 @@ fn bomb_throw(
 
                     // Spawn bomb
                     commands
-                        .spawn(AnimatedProjectile::new(
+                        .spawn(ThrownBombBundle::new(
                             item,
                             facing,
                             animated_sprite.clone(),
+                            team,
+                            &mut **rng,
                         ))
                         .insert(Explodable {
                             attack: attack.clone(),
@@ -1455,6 +2303,7 @@ fn bomb_throw(
                             },
                             item_meta_handle: attack.item_handle.clone(),
                             name: Name::new("Bomb Item"),
+                            ysort: YSort(SortLayer::Projectiles),
                         });
                     bomb_throw.thrown = !bomb_throw.thrown;
                 }
@@ -1476,23 +2325,40 @@ fn moving(
         &mut Animation,
         &mut Facing,
         &mut LinearVelocity,
+        &Stats,
         &Moving,
     )>,
+    time: GameTime,
 ) {
-    for (entity, mut animation, mut facing, mut velocity, moving) in &mut fighters {
+    for (entity, mut animation, mut facing, mut velocity, stats, moving) in &mut fighters {
+        // Use the run animation when running, if the fighter has one
+        let moving_animation =
+            if moving.running && animation.animations.contains_key(Moving::RUN_ANIMATION) {
+                Moving::RUN_ANIMATION
+            } else {
+                Moving::ANIMATION
+            };
+
         // If we aren't playing the moving animation
-        if animation.current_animation.as_deref() != Some(Moving::ANIMATION) {
+        if animation.current_animation.as_deref() != Some(moving_animation) {
             // Start the moving animation from the beginning
-            animation.play(Moving::ANIMATION, true /* repeating */);
+            animation.play(moving_animation, true /* repeating */);
         }
 
-        // Update our velocity to match our movement velocity
-        **velocity = moving.velocity;
+        // Update our velocity to match our movement velocity, speeding up toward it gradually if
+        // we have an acceleration
+        **velocity = match stats.acceleration {
+            Some(acceleration) => {
+                let max_change = acceleration * time.delta_seconds();
+                **velocity + (moving.velocity - **velocity).clamp_length_max(max_change)
+            }
+            None => moving.velocity,
+        };
 
         // Make sure we face in the direction we are moving
-        if velocity.x > 0.0 {
+        if moving.velocity.x > 0.0 {
             *facing = Facing::Right
-        } else if velocity.x < 0.0 {
+        } else if moving.velocity.x < 0.0 {
             *facing = Facing::Left
         }
 
@@ -1502,10 +2368,57 @@ fn moving(
     }
 }
 
+/// Move enemies making their entrance towards their spawn location
+fn spawning_entrance(
+    mut fighters: Query<(
+        &mut Animation,
+        &mut Facing,
+        &mut Transform,
+        &mut LinearVelocity,
+        &mut SpawningEntrance,
+    )>,
+    time: GameTime,
+) {
+    for (mut animation, mut facing, mut transform, mut velocity, mut entrance) in &mut fighters {
+        let to_target = entrance.target - transform.translation.truncate();
+
+        // Walk in from the side, or fall into place when dropping in
+        let animation_name = if to_target.x != 0.0 {
+            Moving::ANIMATION
+        } else {
+            Idling::ANIMATION
+        };
+        if animation.current_animation.as_deref() != Some(animation_name) {
+            animation.play(animation_name, true /* repeating */);
+        }
+
+        // Snap to the target once we would reach it this frame
+        if to_target.length() <= entrance.speed * time.delta_seconds() {
+            transform.translation = entrance.target.extend(transform.translation.z);
+            **velocity = Vec2::ZERO;
+            entrance.is_finished = true;
+            continue;
+        }
+
+        **velocity = to_target.normalize() * entrance.speed;
+
+        if velocity.x > 0.0 {
+            *facing = Facing::Right
+        } else if velocity.x < 0.0 {
+            *facing = Facing::Left
+        }
+    }
+}
+
 /// Update hit stunned players
+///
+/// The hit stun is held while the fighter is in a [`HitPause`].
 fn hitstun(
-    mut fighters: Query<(&mut Animation, &Facing, &mut LinearVelocity, &mut HitStun)>,
-    time: Res<Time>,
+    mut fighters: Query<
+        (&mut Animation, &Facing, &mut LinearVelocity, &mut HitStun),
+        Without<HitPause>,
+    >,
+    time: GameTime,
 ) {
     for (mut animation, facing, mut velocity, mut hitstun) in &mut fighters {
         // If this is the start of the hit stun
@@ -1531,27 +2444,226 @@ fn hitstun(
 
         // Set our figher velocity to the hit stun velocity
         **velocity = hitstun.pushback;
+        hitstun.pushback.y -= hitstun.gravity * time.delta_seconds();
+    }
+}
+
+/// Update knocked down fighters, falling to the ground if they are airborne, then sliding along it
+/// before lying still
+fn knocked_down(
+    mut commands: Commands,
+    mut fighters: Query<
+        (
+            Entity,
+            &mut Transform,
+            &mut Animation,
+            &mut LinearVelocity,
+            &mut KnockedDown,
+            &mut JuggleCount,
+            Option<&Airborne>,
+        ),
+        Without<HitPause>,
+    >,
+    time: GameTime,
+) {
+    for (
+        entity,
+        mut transform,
+        mut animation,
+        mut velocity,
+        mut knocked_down,
+        mut juggle_count,
+        airborne,
+    ) in &mut fighters
+    {
+        // If this is the start of the knockdown
+        if knocked_down.timer.elapsed_secs() == 0.0 {
+            // Falling back to lying on the last frame of the dying animation
+            if animation.animations.contains_key(KnockedDown::ANIMATION) {
+                animation.play(KnockedDown::ANIMATION, false);
+            } else {
+                animation.play(Dying::ANIMATION, false);
+            }
+        }
+
+        // Fall to the ground before the knockdown starts running down
+        if let Some(airborne) = airborne {
+            if transform.translation.y > airborne.ground_y {
+                **velocity = Vec2::new(knocked_down.pushback.x, -consts::KNOCKDOWN_FALL_SPEED);
+                continue;
+            }
+            transform.translation.y = airborne.ground_y;
+            **juggle_count = 0;
+            commands.entity(entity).remove::<Airborne>();
+        }
+
+        **velocity = if knocked_down.timer.elapsed_secs() < knocked_down.slide_duration {
+            knocked_down.pushback
+        } else {
+            Vec2::ZERO
+        };
+        knocked_down.timer.tick(time.delta());
     }
 }
 
+/// Update fighters getting up after a knockdown
+fn getting_up(
+    mut fighters: Query<(&mut Animation, &mut LinearVelocity, &mut GettingUp), Without<HitPause>>,
+    time: GameTime,
+) {
+    for (mut animation, mut velocity, mut getting_up) in &mut fighters {
+        // If this is the start of getting up
+        if getting_up.timer.elapsed_secs() == 0.0 {
+            // Falling back to recovering like from a hit
+            if animation.animations.contains_key(GettingUp::ANIMATION) {
+                animation.play(GettingUp::ANIMATION, false);
+            } else {
+                animation.play(HitStun::HITSTUN, false);
+            }
+        }
+
+        **velocity = Vec2::ZERO;
+        getting_up.timer.tick(time.delta());
+    }
+}
+
+/// Update downed players, who crawl around while their teammates revive them
+fn downed(
+    mut fighters: Query<
+        (
+            Entity,
+            &Transform,
+            &Stats,
+            &mut Health,
+            &mut Animation,
+            &mut LinearVelocity,
+            &mut Downed,
+            Option<&ActionState<PlayerAction>>,
+        ),
+        With<Player>,
+    >,
+    teammates: Query<
+        (Entity, &Transform, &Health),
+        (With<Player>, Without<Downed>, Without<Dying>),
+    >,
+    game: Res<GameMeta>,
+    time: GameTime,
+) {
+    for (
+        entity,
+        transform,
+        stats,
+        mut health,
+        mut animation,
+        mut velocity,
+        mut downed,
+        action_state,
+    ) in &mut fighters
+    {
+        // Play the downed animation, falling back to lying on the last frame of the dying animation
+        let downed_animation = if animation.animations.contains_key(Downed::ANIMATION) {
+            Downed::ANIMATION
+        } else {
+            Dying::ANIMATION
+        };
+        if animation.current_animation.as_deref() != Some(downed_animation) {
+            animation.play(downed_animation, false);
+        }
+
+        // Crawl slowly, without being able to attack
+        let direction = action_state
+            .filter(|action_state| action_state.pressed(PlayerAction::Move))
+            .and_then(|action_state| action_state.clamped_axis_pair(PlayerAction::Move))
+            .map(|axis| axis.xy())
+            .unwrap_or_default();
+        **velocity = direction * stats.movement_speed * consts::DOWNED_CRAWL_SPEED_MULTIPLIER;
+
+        // Teammates revive the player by standing near them
+        let position = transform.translation.truncate();
+        let being_revived = teammates
+            .iter()
+            .any(|(teammate, teammate_transform, health)| {
+                teammate != entity
+                    && **health > 0
+                    && teammate_transform.translation.truncate().distance(position)
+                        <= game.revive.radius
+            });
+
+        if being_revived {
+            downed.revive_progress += time.delta_seconds();
+
+            if downed.revive_progress >= game.revive.revive_duration {
+                **health = ((stats.max_health as f32 * game.revive.health) as i32).max(1);
+                downed.is_revived = true;
+            }
+        } else {
+            // Reviving has to be done in one go, and the player bleeds out while nobody is near
+            downed.revive_progress = 0.0;
+            downed.timer.tick(time.delta());
+        }
+    }
+}
+
+/// Marker for the scenery left behind by a dead enemy, which fades out over its [`Lifetime`]
+#[derive(Component)]
+pub struct Corpse;
+
 /// Update dying players
 fn dying(
     mut commands: Commands,
-    mut fighters: Query<(Entity, &mut Animation, &mut LinearVelocity), With<Dying>>,
+    mut fighters: Query<
+        (
+            Entity,
+            &mut Animation,
+            &mut LinearVelocity,
+            &Transform,
+            Option<&TextureAtlasSprite>,
+            Option<&Handle<TextureAtlas>>,
+            Option<&Enemy>,
+        ),
+        With<Dying>,
+    >,
+    game: Res<GameMeta>,
 ) {
-    for (entity, mut animation, mut velocity) in &mut fighters {
+    for (entity, mut animation, mut velocity, transform, sprite, atlas, enemy) in &mut fighters {
         // Start playing the dying animation if it isn't already
-        if animation.current_animation.as_deref() != Some(Dying::ANIMATION) {
+        if !animation.is_playing(Dying::ANIMATION) {
             **velocity = Vec2::ZERO;
             animation.play(Dying::ANIMATION, false);
 
         // When the animation is finished, despawn the fighter
         } else if animation.is_finished() {
+            // Leave a copy of the enemy's last frame behind. It has no collider and isn't an
+            // enemy, so it doesn't get in the way of collisions or the AI.
+            if let (Some(corpses), Some(sprite), Some(atlas), Some(_)) =
+                (game.enemy_corpses, sprite, atlas, enemy)
+            {
+                commands.spawn((
+                    SpriteSheetBundle {
+                        sprite: sprite.clone(),
+                        texture_atlas: atlas.clone(),
+                        transform: *transform,
+                        ..default()
+                    },
+                    Corpse,
+                    Lifetime(Timer::from_seconds(corpses.duration, TimerMode::Once)),
+                    YSort(SortLayer::GroundItems),
+                    Name::new("Corpse"),
+                ));
+            }
+
             commands.entity(entity).despawn_recursive();
         }
     }
 }
 
+/// Fade corpses out as their lifetime runs down
+fn fade_corpses(mut corpses: Query<(&Lifetime, &mut TextureAtlasSprite), With<Corpse>>) {
+    for (lifetime, mut sprite) in &mut corpses {
+        sprite.color.set_a(lifetime.percent_left());
+    }
+}
+
 /// Throw the item in the player's inventory
 fn throwing(
     mut commands: Commands,
@@ -1562,9 +2674,11 @@ fn throwing(
             &Facing,
             &mut Inventory,
             Option<&mut AvailableAttacks>,
+            Option<&ActionState<PlayerAction>>,
         ),
         With<Throwing>,
     >,
+    enemies: Query<&Transform, With<Enemy>>,
     mut being_held: Query<
         (
             Entity,
@@ -1577,13 +2691,33 @@ fn throwing(
     >,
     weapon_held: Query<(Entity, &Parent), With<MeleeWeapon>>,
     pweapon_held: Query<(Entity, &Parent), With<ProjectileWeapon>>,
-    mut items_assets: ResMut<Assets<ItemMeta>>,
-    mut active_scripts: ResMut<ActiveScripts>,
+    items_assets: Res<Assets<ItemMeta>>,
     mut script_item_throw_events: ResMut<Events<ScriptItemThrowEvent>>,
+    mut rng: ResMut<GameRng>,
 ) {
-    for (entity, fighter_transform, facing, mut inventory, available_attacks) in &mut fighters {
+    for (entity, fighter_transform, facing, mut inventory, available_attacks, action_state) in
+        &mut fighters
+    {
+        // Players aim their throws up or down with the movement axis
+        let aim_y = action_state
+            .and_then(|action_state| action_state.clamped_axis_pair(PlayerAction::Move))
+            .map(|axis| axis.y())
+            .unwrap_or_default();
+
         // If the player has an item in their inventory
         if let Some(item_meta) = inventory.take() {
+            let (throw_velocity, gravity) = item_meta.kind.throw_motion().unwrap_or_default();
+            let aim_angle = throw_aim_angle(
+                aim_y,
+                fighter_transform.translation.truncate(),
+                facing,
+                throw_velocity,
+                gravity,
+                enemies
+                    .iter()
+                    .map(|transform| transform.translation.truncate()),
+            );
+
             // Check what kind of item this is.
             //
             // TODO: We should probably create a flexible item system abstraction similar to the
@@ -1592,11 +2726,12 @@ fn throwing(
             match &item_meta.kind {
                 ItemKind::Throwable { .. } => {
                     // Throw the item!
-                    commands.spawn(Projectile::from_thrown_item(
+                    commands.spawn(ThrownItemBundle::new(
                         fighter_transform.translation + consts::THROW_ITEM_OFFSET.extend(0.0),
                         &item_meta,
                         facing,
-                        false,
+                        aim_angle,
+                        Team::Players,
                     ));
                 }
                 ItemKind::Script { script_handle, .. } => {
@@ -1609,11 +2744,12 @@ fn throwing(
                     ref item_handle, ..
                 } => {
                     commands
-                        .spawn(Projectile::from_thrown_item(
+                        .spawn(ThrownItemBundle::new(
                             fighter_transform.translation + consts::THROW_ITEM_OFFSET.extend(0.0),
                             &item_meta,
                             facing,
-                            false,
+                            aim_angle,
+                            Team::Players,
                         ))
                         .insert(Drop {
                             item: items_assets
@@ -1631,21 +2767,18 @@ fn throwing(
                     commands.entity(entity).remove::<Holding>();
                 }
                 ItemKind::MeleeWeapon { .. } => {
-                    //Drop item
-                    let ground_offset = Vec3::new(0.0, consts::GROUND_Y, consts::ITEM_LAYER);
-
-                    let item_spawn_meta = ItemSpawnMeta {
-                        location: fighter_transform.translation - ground_offset,
-                        item: String::new(),
-                        item_handle: items_assets.add(item_meta.clone()),
-                    };
-                    let item_commands = commands.spawn(ItemBundle::new(&item_spawn_meta));
-                    ItemBundle::spawn(
-                        item_commands,
-                        &item_spawn_meta,
-                        &mut items_assets,
-                        &mut active_scripts,
-                    );
+                    // Hurl the weapon, landing as an item that can be grabbed again
+                    commands
+                        .spawn(ThrownItemBundle::new(
+                            fighter_transform.translation + consts::THROW_ITEM_OFFSET.extend(0.0),
+                            &item_meta,
+                            facing,
+                            aim_angle,
+                            Team::Players,
+                        ))
+                        .insert(Drop {
+                            item: item_meta.clone(),
+                        });
 
                     if let Some(mut available_attacks) = available_attacks {
                         available_attacks.attacks.pop();
@@ -1659,21 +2792,18 @@ fn throwing(
                     }
                 }
                 ItemKind::ProjectileWeapon { .. } => {
-                    //Drop item
-                    let ground_offset = Vec3::new(0.0, consts::GROUND_Y, consts::ITEM_LAYER);
-
-                    let item_spawn_meta = ItemSpawnMeta {
-                        location: fighter_transform.translation - ground_offset,
-                        item: String::new(),
-                        item_handle: items_assets.add(item_meta.clone()),
-                    };
-                    let item_commands = commands.spawn(ItemBundle::new(&item_spawn_meta));
-                    ItemBundle::spawn(
-                        item_commands,
-                        &item_spawn_meta,
-                        &mut items_assets,
-                        &mut active_scripts,
-                    );
+                    // Hurl the weapon, landing as an item that can be grabbed again
+                    commands
+                        .spawn(ThrownItemBundle::new(
+                            fighter_transform.translation + consts::THROW_ITEM_OFFSET.extend(0.0),
+                            &item_meta,
+                            facing,
+                            aim_angle,
+                            Team::Players,
+                        ))
+                        .insert(Drop {
+                            item: item_meta.clone(),
+                        });
 
                     if let Some(mut available_attacks) = available_attacks {
                         available_attacks.attacks.pop();
@@ -1700,43 +2830,22 @@ fn throwing(
                                 explodable.attack_enemy = true;
                             }
 
-                            let direction_mul = if facing.is_left() {
-                                Vec2::new(-1.0, 1.0)
-                            } else {
-                                Vec2::ONE
-                            };
-                            let mut rng = rand::thread_rng();
                             let item = items_assets.get(item_handle).expect("Bomb item not found.");
-
-                            let (gravity, throw_velocity) = if let ItemKind::Bomb {
-                                gravity,
-                                throw_velocity,
-                                ..
-                            } = item.kind
-                            {
-                                Some((gravity, throw_velocity))
-                            } else {
-                                None
-                            }
-                            .expect("Item is not a bomb.");
+                            let (projectile, angular_velocity) =
+                                ThrownBombBundle::projectile(item, facing, aim_angle, &mut **rng);
 
                             commands.entity(head_ent).insert((
-                                LinearVelocity(
-                                    throw_velocity * direction_mul * rng.gen_range(0.8..1.2),
-                                ),
-                                Force(Vec2::new(0.0, -gravity)),
-                                AngularVelocity(
-                                    consts::THROW_ITEM_ROTATION_SPEED
-                                        * direction_mul.x
-                                        * rng.gen_range(0.8..1.2),
-                                ),
-                                CollisionGroups::new(
-                                    BodyLayers::PLAYER_ATTACK,
-                                    BodyLayers::PLAYER
-                                        | BodyLayers::ENEMY
-                                        | BodyLayers::BREAKABLE_ITEM,
+                                angular_velocity,
+                                ProjectileBundle::new(
+                                    projectile,
+                                    Collider::cuboid(
+                                        consts::ITEM_WIDTH / 2.,
+                                        consts::ITEM_HEIGHT / 2.,
+                                    ),
+                                    Team::Players,
+                                    // Thrown heads hit everyone, like the explosions they set off
+                                    true,
                                 ),
-                                Collider::cuboid(consts::ITEM_WIDTH / 2., consts::ITEM_HEIGHT / 2.),
                             ));
                         }
                     }
@@ -1752,7 +2861,7 @@ fn throwing(
 }
 
 // Trying to grab an item off the map
-fn grabbing(
+pub(crate) fn grabbing(
     mut commands: Commands,
     mut fighters: Query<
         (
@@ -1764,8 +2873,18 @@ fn grabbing(
         ),
         With<Grabbing>,
     >,
+    mut staggered_enemies: Query<
+        (Entity, &Transform, &mut Inventory, &mut AvailableAttacks),
+        (
+            With<Enemy>,
+            Or<(With<HitStun>, With<KnockedDown>)>,
+            Without<Grabbing>,
+        ),
+    >,
+    weapons: Query<(Entity, &Parent), Or<(With<MeleeWeapon>, With<ProjectileWeapon>)>>,
     items_query: Query<(Entity, &Transform, &Handle<ItemMeta>), With<Item>>,
     items_assets: Res<Assets<ItemMeta>>,
+    game: Res<GameMeta>,
     mut script_item_grab_events: ResMut<Events<ScriptItemGrabEvent>>,
 ) {
     // We need to track the picked items, otherwise, in theory, two players could pick the same item.
@@ -1776,9 +2895,11 @@ fn grabbing(
         fighter_transform,
         mut fighter_inventory,
         mut transition_intents,
-        available_attacks,
+        mut available_attacks,
     ) in &mut fighters
     {
+        let mut item_in_reach = false;
+
         // If several items are at pick distance, an arbitrary one is picked.
         for (item_ent, item_transform, item) in &items_query {
             if !picked_item_ids.contains(&item_ent) {
@@ -1790,6 +2911,8 @@ fn grabbing(
 
                 // If we are close enough
                 if fighter_item_distance <= consts::PICK_ITEM_RADIUS {
+                    item_in_reach = true;
+
                     // And our fighter isn't carrying another item
                     if fighter_inventory.is_none() {
                         match &items_assets.get(item).unwrap().kind {
@@ -1824,136 +2947,31 @@ fn grabbing(
                                 commands.entity(item_ent).insert(Transform::from_xyz(
                                     0.,
                                     consts::THROW_ITEM_OFFSET.y + image.image_size.y,
-                                    consts::PROJECTILE_Z,
+                                    consts::HELD_ITEM_Z,
                                 ));
 
                                 picked_item_ids.insert(item_ent);
                                 **fighter_inventory =
                                     Some(items_assets.get(item).expect("Item not loaded!").clone());
-                                commands.entity(item_ent).remove::<Item>().insert(BeingHeld);
+                                commands
+                                    .entity(item_ent)
+                                    .remove::<(Item, YSort)>()
+                                    .insert(BeingHeld);
                                 commands.entity(fighter_ent).add_child(item_ent);
                             }
-                            ItemKind::MeleeWeapon {
-                                ref attack,
-                                ref spritesheet,
-                                ref audio,
-                                ref sprite_offset,
-                            } => {
-                                // If its throwable, pick up the item
-                                picked_item_ids.insert(item_ent);
-                                **fighter_inventory =
-                                    Some(items_assets.get(item).expect("Item not loaded!").clone());
-                                commands.entity(item_ent).despawn_recursive();
-
-                                if let Some(mut available_attacks) = available_attacks {
-                                    available_attacks.attacks.push(attack.clone())
-                                }
-
-                                //Spawn weapon sprite on Player
-                                let mut animated_sprite = AnimatedSpriteSheetBundle {
-                                    sprite_sheet: SpriteSheetBundle {
-                                        texture_atlas: spritesheet.atlas_handle[0].clone(),
-                                        transform: Transform::from_xyz(
-                                            sprite_offset.x,
-                                            sprite_offset.y,
-                                            0.2,
-                                        ),
-                                        ..Default::default()
-                                    },
-                                    animation: Animation::new(
-                                        spritesheet.animation_fps,
-                                        spritesheet.animations.clone(),
-                                    ),
-                                };
-                                animated_sprite.animation.current_animation =
-                                    Some("idle".to_string());
-
-                                let weapon = commands
-                                    .spawn((
-                                        MeleeWeapon {
-                                            audio: audio.clone(),
-                                            attack: attack.clone(),
-                                        },
-                                        //need this because of hierarchy check in hitbox activation system,
-                                        //consider rearchitecting
-                                        AvailableAttacks {
-                                            attacks: vec![attack.clone()],
-                                        },
-                                        animated_sprite,
-                                        Attached {
-                                            position_face: true,
-                                            sync_facing: true,
-                                            sync_animation: false,
-                                        },
-                                        Facing::default(),
-                                    ))
-                                    .id();
-                                commands.entity(fighter_ent).add_child(weapon);
-                            }
-                            ItemKind::ProjectileWeapon {
-                                ref attack,
-                                ref spritesheet,
-                                ref sprite_offset,
-                                ref audio,
-                                ref bullet_velocity,
-                                ref bullet_lifetime,
-                                ref ammo,
-                                ref shoot_delay,
-                            } => {
-                                // If its throwable, pick up the item
+                            ItemKind::MeleeWeapon { .. } | ItemKind::ProjectileWeapon { .. } => {
+                                // Pick up the weapon, and hold it
                                 picked_item_ids.insert(item_ent);
-                                **fighter_inventory =
-                                    Some(items_assets.get(item).expect("Item not loaded!").clone());
+                                let item = items_assets.get(item).expect("Item not loaded!");
+                                **fighter_inventory = Some(item.clone());
                                 commands.entity(item_ent).despawn_recursive();
 
-                                if let Some(mut available_attacks) = available_attacks {
-                                    available_attacks.attacks.push(attack.clone())
-                                }
-
-                                //Spawn weapon sprite on Player
-                                let mut animated_sprite = AnimatedSpriteSheetBundle {
-                                    sprite_sheet: SpriteSheetBundle {
-                                        texture_atlas: spritesheet.atlas_handle[0].clone(),
-                                        transform: Transform::from_xyz(
-                                            sprite_offset.x,
-                                            sprite_offset.y,
-                                            0.2,
-                                        ),
-                                        ..Default::default()
-                                    },
-                                    animation: Animation::new(
-                                        spritesheet.animation_fps,
-                                        spritesheet.animations.clone(),
-                                    ),
-                                };
-                                animated_sprite.animation.current_animation =
-                                    Some("idle".to_string());
-
-                                let mut shoot_timer =
-                                    Timer::from_seconds(*shoot_delay, TimerMode::Once);
-                                shoot_timer.set_elapsed(Duration::from_secs_f32(*shoot_delay));
-
-                                let weapon = commands
-                                    .spawn((
-                                        ProjectileWeapon {
-                                            attack: attack.clone(),
-                                            animated_sprite: animated_sprite.clone(),
-                                            audio: audio.clone(),
-                                            bullet_velocity: *bullet_velocity,
-                                            bullet_lifetime: *bullet_lifetime,
-                                            ammo: *ammo,
-                                            shoot_delay: shoot_timer,
-                                        },
-                                        animated_sprite,
-                                        Attached {
-                                            position_face: true,
-                                            sync_facing: true,
-                                            sync_animation: false,
-                                        },
-                                        Facing::default(),
-                                    ))
-                                    .id();
-                                commands.entity(fighter_ent).add_child(weapon);
+                                attach_weapon(
+                                    &mut commands,
+                                    fighter_ent,
+                                    item,
+                                    available_attacks.as_deref_mut(),
+                                );
                             }
                         }
                     }
@@ -1961,34 +2979,287 @@ fn grabbing(
                 }
             }
         }
+
+        // Without an item to pick, grabbing a staggered enemy steals the weapon it is holding
+        if game.enemy_weapon_steal && !item_in_reach && fighter_inventory.is_none() {
+            for (enemy_ent, enemy_transform, mut enemy_inventory, mut enemy_attacks) in
+                &mut staggered_enemies
+            {
+                let enemy_distance = fighter_transform
+                    .translation
+                    .truncate()
+                    .distance(enemy_transform.translation.truncate());
+                let weapon_attack = match enemy_inventory.0.as_ref().map(|item| &item.kind) {
+                    Some(
+                        ItemKind::MeleeWeapon { attack, .. }
+                        | ItemKind::ProjectileWeapon { attack, .. },
+                    ) if enemy_distance <= consts::PICK_ITEM_RADIUS => attack.name.clone(),
+                    _ => continue,
+                };
+
+                // The enemy falls back on its own attacks
+                if let Some(i) = enemy_attacks
+                    .attacks
+                    .iter()
+                    .rposition(|attack| attack.name == weapon_attack)
+                {
+                    enemy_attacks.attacks.remove(i);
+                }
+                for (weapon_ent, parent) in &weapons {
+                    if parent.get() == enemy_ent {
+                        commands.entity(weapon_ent).despawn_recursive();
+                    }
+                }
+
+                let weapon = enemy_inventory.take().unwrap();
+                attach_weapon(
+                    &mut commands,
+                    fighter_ent,
+                    &weapon,
+                    available_attacks.as_deref_mut(),
+                );
+                **fighter_inventory = Some(weapon);
+                break;
+            }
+        }
+
         // Grabbing is an "instant" state, that is removed at the end of every frame. Eventually it
         // may not be and it might play a fighter animation.
         commands.entity(fighter_ent).remove::<Grabbing>();
     }
 }
 
+/// Give a fighter the attack of a weapon item, and attach the weapon's sprite to it. Items that
+/// aren't weapons are ignored.
+fn attach_weapon(
+    commands: &mut Commands,
+    fighter_ent: Entity,
+    item: &ItemMeta,
+    available_attacks: Option<&mut AvailableAttacks>,
+) {
+    match &item.kind {
+        ItemKind::MeleeWeapon {
+            ref attack,
+            ref spritesheet,
+            ref audio,
+            ref sprite_offset,
+        } => {
+            if let Some(available_attacks) = available_attacks {
+                available_attacks.attacks.push(attack.clone())
+            }
+
+            //Spawn weapon sprite on Player
+            let mut animated_sprite = AnimatedSpriteSheetBundle {
+                sprite_sheet: SpriteSheetBundle {
+                    texture_atlas: spritesheet.atlas_handle[0].clone(),
+                    transform: Transform::from_xyz(sprite_offset.x, sprite_offset.y, 0.2),
+                    ..Default::default()
+                },
+                animation: Animation::new(
+                    spritesheet.animation_fps,
+                    spritesheet.animations.clone(),
+                ),
+            };
+            animated_sprite.animation.current_animation = Some("idle".to_string());
+
+            let weapon = commands
+                .spawn((
+                    MeleeWeapon {
+                        audio: audio.clone(),
+                        attack: attack.clone(),
+                    },
+                    //need this because of hierarchy check in hitbox activation system,
+                    //consider rearchitecting
+                    AvailableAttacks {
+                        attacks: vec![attack.clone()],
+                    },
+                    animated_sprite,
+                    Attached {
+                        position_face: true,
+                        sync_facing: true,
+                        sync_animation: false,
+                    },
+                    Facing::default(),
+                ))
+                .id();
+            commands.entity(fighter_ent).add_child(weapon);
+        }
+        ItemKind::ProjectileWeapon {
+            ref attack,
+            ref spritesheet,
+            ref sprite_offset,
+            ref audio,
+            ref bullet_velocity,
+            ref bullet_lifetime,
+            ref ammo,
+            ref shoot_delay,
+            ref max_bullets,
+            ref damage_falloff,
+        } => {
+            if let Some(available_attacks) = available_attacks {
+                available_attacks.attacks.push(attack.clone())
+            }
+
+            //Spawn weapon sprite on Player
+            let mut animated_sprite = AnimatedSpriteSheetBundle {
+                sprite_sheet: SpriteSheetBundle {
+                    texture_atlas: spritesheet.atlas_handle[0].clone(),
+                    transform: Transform::from_xyz(sprite_offset.x, sprite_offset.y, 0.2),
+                    ..Default::default()
+                },
+                animation: Animation::new(
+                    spritesheet.animation_fps,
+                    spritesheet.animations.clone(),
+                ),
+            };
+            animated_sprite.animation.current_animation = Some("idle".to_string());
+
+            let mut shoot_timer = Timer::from_seconds(*shoot_delay, TimerMode::Once);
+            shoot_timer.set_elapsed(Duration::from_secs_f32(*shoot_delay));
+
+            let weapon = commands
+                .spawn((
+                    ProjectileWeapon {
+                        attack: attack.clone(),
+                        animated_sprite: animated_sprite.clone(),
+                        audio: audio.clone(),
+                        bullet_velocity: *bullet_velocity,
+                        bullet_lifetime: *bullet_lifetime,
+                        ammo: *ammo,
+                        shoot_delay: shoot_timer,
+                        max_bullets: *max_bullets,
+                        damage_falloff: *damage_falloff,
+                        bullets: default(),
+                    },
+                    animated_sprite,
+                    Attached {
+                        position_face: true,
+                        sync_facing: true,
+                        sync_animation: false,
+                    },
+                    Facing::default(),
+                ))
+                .id();
+            commands.entity(fighter_ent).add_child(weapon);
+        }
+        _ => (),
+    }
+}
+
+/// Get the offset of an attack's hitbox, mirrored to the side the attacker is facing and raised by
+/// `y_offset`
+fn attack_hitbox_offset(attack: &AttackMeta, facing: &Facing, y_offset: f32) -> Vec2 {
+    mirror_hitbox_offset(attack.hitbox.offset, facing, y_offset)
+}
+
+/// Mirror a hitbox offset to the side the attacker is facing, and raise it by `y_offset`
+fn mirror_hitbox_offset(mut offset: Vec2, facing: &Facing, y_offset: f32) -> Vec2 {
+    if facing.is_left() {
+        offset.x *= -1.0
+    }
+    offset.y += y_offset;
+    offset
+}
+
+/// Spawn the hitbox entity of an attack as a child of `parent`, on the side the attacker is facing.
+///
+/// The parent must not be mirrored itself, which holds for fighters and for attached weapons, that
+/// are only moved to the side they are facing.
+fn spawn_attack_hitbox(
+    commands: &mut Commands,
+    parent: Entity,
+    attack: &AttackMeta,
+    damage: i32,
+    facing: &Facing,
+    y_offset: f32,
+    team: Team,
+    friendly_fire: bool,
+) -> Entity {
+    let attack_entity = commands
+        .spawn(TransformBundle::from_transform(
+            Transform::from_translation(attack_hitbox_offset(attack, facing, y_offset).extend(0.0)),
+        ))
+        // Attacks that can clash also collide with the opposing attacks
+        .insert(team.attack_groups(friendly_fire, attack.clash_priority.is_some()))
+        .insert(Attack {
+            damage,
+            pushback: if facing.is_left() {
+                Vec2::NEG_X
+            } else {
+                Vec2::X
+            } * attack.velocity.unwrap_or(Vec2::ZERO),
+            hitstun_duration: attack.hitstun_duration,
+            hitbox_meta: Some(attack.hitbox),
+            clash_priority: attack.clash_priority,
+            launch: attack.launch.map(|launch| {
+                let direction_x = if facing.is_left() { -1.0 } else { 1.0 };
+                Vec2::new(launch.x * direction_x, launch.y)
+            }),
+            antiair_bonus: attack.antiair_bonus,
+            pull: attack.pull,
+            burn: attack.burn,
+            confuse: attack.confuse,
+            reaction: attack.reaction,
+        })
+        .insert(attack.frames)
+        .insert(Connected::default())
+        .id();
+    if let Some(path) = &attack.hitbox_path {
+        commands.entity(attack_entity).insert(HitboxPath(
+            path.iter()
+                .map(|&offset| mirror_hitbox_offset(offset, facing, y_offset))
+                .collect(),
+        ));
+    }
+    commands.entity(parent).push_children(&[attack_entity]);
+
+    attack_entity
+}
+
+/// Start the motion of the attacker at the start of its attack: pushing it back with the recoil of
+/// the attack, if the attack has any, and carrying the momentum of its `velocity` into the attack.
+///
+/// The attack states stop the attacker in place every frame once the attack has started, so this
+/// has to be given the velocity from before the attack, on the frame the attack starts.
+fn start_attack_motion(
+    commands: &mut Commands,
+    attacker: Entity,
+    attack: &AttackMeta,
+    facing: &Facing,
+    velocity: Vec2,
+) {
+    let momentum = velocity * attack.momentum_retention.clamp(0., 1.);
+    let recoil = match attack.self_knockback {
+        Some(self_knockback) => Recoil::new(self_knockback, facing),
+        None if momentum != Vec2::ZERO => Recoil::new(Vec2::ZERO, facing),
+        None => return,
+    };
+
+    commands.entity(attacker).insert(Recoil {
+        velocity: recoil.velocity + momentum,
+        ..recoil
+    });
+}
+
 fn melee_attacking(
     mut commands: Commands,
     mut fighters: Query<(
         Entity,
         Option<&mut MeleeAttacking>,
-        Option<&Player>,
-        Option<&Enemy>,
+        Option<&Team>,
         &AvailableAttacks,
         &mut LinearVelocity,
         &Facing,
     )>,
     mut melee_weapons: Query<(Entity, &Parent, &mut Animation, &MeleeWeapon)>,
+    game: Res<GameMeta>,
 ) {
-    for (entity, melee_attack, player, enemy, available_attacks, mut velocity, facing) in
-        &mut fighters
-    {
-        let is_player = player.is_some();
-        let is_enemy = enemy.is_some();
-        if !is_player && !is_enemy {
-            // This system only knows how to attack for players and enemies
-            continue;
-        }
+    for (entity, melee_attack, team, available_attacks, mut velocity, facing) in &mut fighters {
+        let team = match team {
+            Some(team) => *team,
+            // This system only knows how to attack for fighters on a team
+            None => continue,
+        };
 
         let mut melee_weapon = None;
         for (weapon_ent, parent, animation, weapon) in &mut melee_weapons {
@@ -2008,38 +3279,19 @@ fn melee_attacking(
 
                     let attack = available_attacks.current_attack();
 
-                    let offset = attack.hitbox.offset;
-                    let attack_frames = attack.frames;
-                    // Spawn the attack entity
-                    let attack_entity = commands
-                        .spawn(TransformBundle::from_transform(
-                            Transform::from_translation(offset.extend(0.0)),
-                        ))
-                        .insert(CollisionGroups::new(
-                            if is_player {
-                                BodyLayers::PLAYER_ATTACK
-                            } else {
-                                BodyLayers::ENEMY_ATTACK
-                            },
-                            if is_player {
-                                BodyLayers::ENEMY | BodyLayers::BREAKABLE_ITEM
-                            } else {
-                                BodyLayers::PLAYER
-                            },
-                        ))
-                        .insert(Attack {
-                            damage: attack.damage,
-                            pushback: if facing.is_left() {
-                                Vec2::NEG_X
-                            } else {
-                                Vec2::X
-                            } * attack.velocity.unwrap_or(Vec2::ZERO),
-                            hitstun_duration: attack.hitstun_duration,
-                            hitbox_meta: Some(attack.hitbox),
-                        })
-                        .insert(attack_frames)
-                        .id();
-                    commands.entity(weapon_ent).push_children(&[attack_entity]);
+                    // Spawn the attack entity on the weapon, which is only positioned by facing
+                    spawn_attack_hitbox(
+                        &mut commands,
+                        weapon_ent,
+                        attack,
+                        attack.damage,
+                        facing,
+                        0.0,
+                        team,
+                        game.friendly_fire,
+                    );
+                    // The recoil pushes back the fighter holding the weapon
+                    start_attack_motion(&mut commands, entity, attack, facing, **velocity);
 
                     // Play attack sound effect
                     if let Some(effects) = audio.effect_handles.get(MeleeAttacking::ANIMATION) {
@@ -2065,8 +3317,7 @@ fn shooting(
     mut fighters: Query<(
         Entity,
         Option<&mut Shooting>,
-        Option<&Player>,
-        Option<&Enemy>,
+        Option<&Team>,
         &AvailableAttacks,
         &mut LinearVelocity,
         &Facing,
@@ -2079,16 +3330,16 @@ fn shooting(
         &GlobalTransform,
     )>,
     shooting_particles: Query<(&Animation, Entity, &Particle), Without<ProjectileWeapon>>,
-    time: Res<Time>,
+    live_bullets: Query<(), With<Bullet>>,
+    time: GameTime,
+    game: Res<GameMeta>,
 ) {
-    for (entity, shooting, player, enemy, available_attacks, mut velocity, facing) in &mut fighters
-    {
-        let is_player = player.is_some();
-        let is_enemy = enemy.is_some();
-        if !is_player && !is_enemy {
-            // This system only knows how to attack for players and enemies
-            continue;
-        }
+    for (entity, shooting, team, available_attacks, mut velocity, facing) in &mut fighters {
+        let team = match team {
+            Some(team) => *team,
+            // This system only knows how to attack for fighters on a team
+            None => continue,
+        };
 
         let mut projectile_weapon = None;
         for (weapon_ent, parent, animation, weapon, weapon_gtransform) in &mut projectile_weapons {
@@ -2139,6 +3390,9 @@ fn shooting(
                     //Spawn bullet
                     shooting.spawned_bullet = true;
                     weapon.ammo -= 1;
+                    // The fighter has been standing still since it started shooting, so there is
+                    // no momentum to carry, only the recoil of the shot
+                    start_attack_motion(&mut commands, entity, attack, facing, Vec2::ZERO);
 
                     let direction_mul = if facing.is_left() {
                         Vec2::new(-1.0, 1.0)
@@ -2151,42 +3405,60 @@ fn shooting(
                     animated_sprite.sprite_sheet.transform = Transform::from_xyz(
                         weapon_gtransform.translation().x,
                         weapon_gtransform.translation().y,
-                        consts::PROJECTILE_Z,
+                        0.,
                     );
 
-                    let bullet_attack = commands
-                        .spawn(TransformBundle::from_transform(
-                            Transform::from_translation(
-                                (attack.hitbox.offset * direction_mul).extend(0.0),
-                            ),
-                        ))
-                        .insert(CollisionGroups::new(
-                            BodyLayers::PLAYER_ATTACK,
-                            BodyLayers::ENEMY | BodyLayers::BREAKABLE_ITEM,
-                        ))
-                        .insert(Attack {
+                    let projectile = Projectile::new(
+                        Vec2::new(weapon.bullet_velocity, 0.) * direction_mul,
+                        weapon.bullet_lifetime,
+                        Attack {
                             damage: attack.damage,
                             pushback: attack.velocity.unwrap_or(Vec2::ZERO) * direction_mul,
                             hitstun_duration: attack.hitstun_duration,
                             hitbox_meta: None,
-                        })
-                        .insert(Breakable::new(0, true))
-                        .insert(Collider::cuboid(
-                            attack.hitbox.size.x / 2.,
-                            attack.hitbox.size.y / 2.,
+                            clash_priority: None,
+                            launch: None,
+                            antiair_bonus: None,
+                            pull: None,
+                            burn: None,
+                            confuse: None,
+                            reaction: attack.reaction,
+                        },
+                    );
+                    let collider = Collider::compound(vec![(
+                        attack_hitbox_offset(attack, facing, 0.0),
+                        0.0,
+                        Collider::cuboid(attack.hitbox.size.x / 2., attack.hitbox.size.y / 2.),
+                    )]);
+
+                    let bullet = commands
+                        .spawn((
+                            animated_sprite,
+                            ProjectileBundle::new(projectile, collider, team, game.friendly_fire),
+                            Bullet {
+                                weapon: weapon_ent,
+                                shooter: entity,
+                            },
                         ))
                         .id();
+                    if let Some(meta) = weapon.damage_falloff {
+                        commands.entity(bullet).insert(DamageFalloff {
+                            meta,
+                            origin: weapon_gtransform.translation().truncate(),
+                        });
+                    }
 
-                    commands
-                        .spawn(animated_sprite)
-                        .insert(Lifetime(Timer::from_seconds(
-                            weapon.bullet_lifetime,
-                            TimerMode::Once,
-                        )))
-                        .insert(LinearVelocity(
-                            Vec2::new(weapon.bullet_velocity, 0.) * direction_mul,
-                        ))
-                        .add_child(bullet_attack);
+                    // Despawn the oldest bullets to stay within the weapon's bullet cap
+                    weapon
+                        .bullets
+                        .retain(|bullet| live_bullets.contains(*bullet));
+                    if let Some(max_bullets) = weapon.max_bullets {
+                        while weapon.bullets.len() >= max_bullets.max(1) {
+                            let oldest = weapon.bullets.pop_front().unwrap();
+                            commands.entity(oldest).despawn_recursive();
+                        }
+                    }
+                    weapon.bullets.push_back(bullet);
                 }
 
                 **velocity = Vec2::ZERO;
@@ -2222,7 +3494,1834 @@ pub struct ProjectileWeapon {
     pub bullet_velocity: f32,
     pub bullet_lifetime: f32,
     pub shoot_delay: Timer,
+    /// The maximum number of the weapon's bullets alive at once, if capped
+    pub max_bullets: Option<usize>,
+    /// How the damage of the weapon's bullets falls off as they travel, if it does
+    pub damage_falloff: Option<DamageFalloffMeta>,
+    /// The bullets fired by the weapon, from oldest to newest. Some of them may have despawned.
+    pub bullets: VecDeque<Entity>,
+}
+
+/// A bullet fired by a [`ProjectileWeapon`]
+#[derive(Component)]
+pub struct Bullet {
+    /// The weapon that fired the bullet
+    pub weapon: Entity,
+    /// The fighter that was holding the weapon, which the bullet's hits come from
+    pub shooter: Entity,
 }
 
 #[derive(Component)]
 pub struct Particle;
+
+#[cfg(test)]
+mod test {
+    use leafwing_input_manager::axislike::SingleAxis;
+
+    use bevy::{ecs::system::CommandQueue, transform::transform_propagate_system, utils::HashMap};
+    use bevy_rapier2d::{
+        prelude::{CollisionEvent, CollisionGroups},
+        rapier::geometry::CollisionEventFlags,
+    };
+
+    use crate::{
+        animation::{animation_cycling, Clip},
+        attack::{attack_damage_system, AttackFrames, Hurtbox},
+        damage::Damageable,
+        enemy::Entrance,
+        fighter::{apply_recoil, attachment_system},
+        lifetime::{lifetime_system, LifetimeExpired},
+        metadata::{
+            AnalogAttackControls, ColliderMeta, CorpseMeta, EntranceStyle, ResponseCurve,
+            StickResponseControls,
+        },
+        movement::velocity_system,
+    };
+
+    use super::*;
+
+    #[test]
+    fn test_running_is_faster_than_walking() {
+        let stats = Stats::default();
+        let direction = Vec2::new(1.0, 0.5).clamp_length_max(1.0);
+
+        let walking = Moving::new(direction, &stats, false);
+        let running = Moving::new(direction, &stats, true);
+
+        assert!(running.velocity.length() > walking.velocity.length());
+        assert_eq!(running.velocity.normalize(), walking.velocity.normalize());
+    }
+
+    #[test]
+    fn test_stick_deadzone_and_response_curve() {
+        let stats = Stats::default();
+        let linear = StickResponseControls {
+            deadzone: 0.2,
+            curve: ResponseCurve::Linear,
+        };
+        let squared = StickResponseControls {
+            curve: ResponseCurve::Squared,
+            ..linear
+        };
+        let velocity = |controls: StickResponseControls, stick: Vec2| {
+            Moving::new(controls.apply(stick), &stats, false).velocity
+        };
+
+        // Stick drift within the deadzone doesn't move the fighter
+        assert_eq!(velocity(linear, Vec2::new(0.1, -0.12)), Vec2::ZERO);
+        assert_eq!(velocity(squared, Vec2::new(0.0, 0.2)), Vec2::ZERO);
+
+        // Past the deadzone, the stick distance is rescaled and shaped by the curve
+        let half_tilt = Vec2::new(0.6, 0.0);
+        let walking_speed = stats.movement_speed;
+        assert!((velocity(linear, half_tilt).x - 0.5 * walking_speed).abs() < 0.1);
+        assert!((velocity(squared, half_tilt).x - 0.25 * walking_speed).abs() < 0.1);
+
+        // The stick direction is kept, and full tilt is full speed
+        assert_eq!(velocity(squared, -Vec2::Y), Vec2::new(0.0, -walking_speed));
+    }
+
+    #[test]
+    fn test_confused_player_movement_is_inverted() {
+        let stick_response = StickResponse(StickResponseControls {
+            deadzone: 0.,
+            curve: ResponseCurve::Linear,
+        });
+        let left = Vec2::new(-1., 0.);
+
+        assert_eq!(movement_direction(&stick_response, left, false), left);
+        // Pushing left moves a confused player right
+        assert_eq!(
+            movement_direction(&stick_response, left, true),
+            Vec2::new(1., 0.)
+        );
+    }
+
+    fn game_meta() -> GameMeta {
+        serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap()
+    }
+
+    #[test]
+    fn test_poise_absorbs_small_hits() {
+        let mut world = World::new();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ParryEvent>>();
+        world.insert_resource(game_meta());
+        let enemy = world
+            .spawn((
+                StateTransitionIntents::default(),
+                Stats {
+                    poise: 50.,
+                    ..default()
+                },
+                Stagger::default(),
+                JuggleCount::default(),
+                Handle::<FighterMeta>::default(),
+            ))
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(collect_hitstuns);
+        let mut hit = |world: &mut World, damage: i32| {
+            world.send_event(DamageEvent {
+                damageing_entity: enemy,
+                damage_velocity: Vec2::ZERO,
+                damage,
+                damaged_entity: enemy,
+                source_position: Vec2::ZERO,
+                contact_position: Vec2::ZERO,
+                hitstun_duration: 0.5,
+                pull: None,
+                reaction: HitReaction::Knockback,
+            });
+            stage.run(world);
+            let intents = &mut world.get_mut::<StateTransitionIntents>(enemy).unwrap();
+            let stunned = intents.iter().any(|intent| intent.data.is::<HitStun>());
+            intents.clear();
+            stunned
+        };
+
+        // Several small hits don't break the poise
+        assert!(!hit(&mut world, 10));
+        assert!(!hit(&mut world, 10));
+        assert!(!hit(&mut world, 10));
+
+        // But a big hit does, and resets the stagger
+        assert!(hit(&mut world, 60));
+        assert_eq!(**world.get::<Stagger>(enemy).unwrap(), 0.);
+    }
+
+    #[test]
+    fn test_air_juggle_hits_decay() {
+        let mut world = World::new();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ParryEvent>>();
+        world.insert_resource(game_meta());
+        let enemy = world
+            .spawn((
+                StateTransitionIntents::default(),
+                Stats::default(),
+                Stagger::default(),
+                JuggleCount::default(),
+                Airborne::default(),
+                Handle::<FighterMeta>::default(),
+            ))
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(collect_hitstuns);
+        let mut hit = |world: &mut World| {
+            world.send_event(DamageEvent {
+                damageing_entity: enemy,
+                damage_velocity: Vec2::new(100., 0.),
+                damage: 10,
+                damaged_entity: enemy,
+                source_position: Vec2::ZERO,
+                contact_position: Vec2::ZERO,
+                hitstun_duration: 0.5,
+                pull: None,
+                reaction: HitReaction::Knockback,
+            });
+            stage.run(world);
+            let intent = world
+                .get_mut::<StateTransitionIntents>(enemy)
+                .unwrap()
+                .pop_front()
+                .unwrap();
+            let hitstun = intent.data.downcast_ref::<HitStun>().unwrap();
+            (hitstun.timer.duration(), hitstun.pushback.x)
+        };
+
+        // Every consecutive air hit stuns for less time and knocks back less
+        let (first_duration, first_pushback) = hit(&mut world);
+        let (second_duration, second_pushback) = hit(&mut world);
+        let (third_duration, third_pushback) = hit(&mut world);
+        assert_eq!(first_duration, Duration::from_secs_f32(0.5));
+        assert!(second_duration < first_duration);
+        assert!(third_duration < second_duration);
+        assert!(third_pushback < second_pushback && second_pushback < first_pushback);
+        assert_eq!(**world.get::<JuggleCount>(enemy).unwrap(), 3);
+
+        // Landing resets the juggle
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(land_fighters);
+        stage.run(&mut world);
+        assert_eq!(**world.get::<JuggleCount>(enemy).unwrap(), 0);
+        assert!(world.get::<Airborne>(enemy).is_none());
+    }
+
+    #[test]
+    fn test_launchers_and_antiairs() {
+        let mut world = World::new();
+        world.init_resource::<Events<CollisionEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ParryEvent>>();
+        world.insert_resource(game_meta());
+
+        let spawn_target = |world: &mut World| {
+            let mut hurtbox = None;
+            let target = world
+                .spawn((
+                    Health(100),
+                    Damageable(true),
+                    StateTransitionIntents::default(),
+                    Stats::default(),
+                    Stagger::default(),
+                    JuggleCount::default(),
+                    Handle::<FighterMeta>::default(),
+                ))
+                .with_children(|parent| hurtbox = Some(parent.spawn(Hurtbox).id()))
+                .id();
+            (target, hurtbox.unwrap())
+        };
+        let (target, target_hurtbox) = spawn_target(&mut world);
+        let launcher = world
+            .spawn(Attack {
+                damage: 10,
+                pushback: Vec2::new(100., 0.),
+                hitstun_duration: 0.5,
+                launch: Some(Vec2::new(20., 300.)),
+                antiair_bonus: Some(0.5),
+                pull: None,
+                burn: None,
+                confuse: None,
+                ..default()
+            })
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(attack_damage_system)
+            .add_system(collect_hitstuns.after(attack_damage_system));
+        let mut hit = |world: &mut World| {
+            world.send_event(CollisionEvent::Started(
+                launcher,
+                target_hurtbox,
+                CollisionEventFlags::empty(),
+            ));
+            stage.run(world);
+            let intent = world
+                .get_mut::<StateTransitionIntents>(target)
+                .unwrap()
+                .pop_front()
+                .unwrap();
+            intent.data.downcast_ref::<HitStun>().unwrap().pushback
+        };
+
+        // The launcher knocks the grounded target up instead of back, making it airborne
+        let pushback = hit(&mut world);
+        assert!(pushback.y > 0.);
+        assert_eq!(**world.get::<Health>(target).unwrap(), 90);
+        assert!(world.get::<Airborne>(target).is_some());
+
+        // And it deals its anti-air bonus to the now airborne target
+        hit(&mut world);
+        assert_eq!(**world.get::<Health>(target).unwrap(), 75);
+    }
+
+    #[test]
+    fn test_parried_attack_stuns_the_attacker() {
+        let mut world = World::new();
+        world.init_resource::<Events<CollisionEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ParryEvent>>();
+        let game = game_meta();
+        let parry = game.parry.unwrap();
+        world.insert_resource(game);
+
+        let spawn_fighter = |world: &mut World, x: f32| {
+            world
+                .spawn((
+                    Health(100),
+                    Damageable(true),
+                    StateTransitionIntents::default(),
+                    Stats::default(),
+                    Stagger::default(),
+                    JuggleCount::default(),
+                    Handle::<FighterMeta>::default(),
+                    GlobalTransform::from_xyz(x, 0., 0.),
+                    Facing::Right,
+                ))
+                .id()
+        };
+        let defender = spawn_fighter(&mut world, 0.);
+        world
+            .entity_mut(defender)
+            .insert((Parrying::new(&parry), SuperMeter::new(100.)));
+        let defender_hurtbox = world.spawn(Hurtbox).id();
+        world
+            .entity_mut(defender)
+            .push_children(&[defender_hurtbox]);
+        let attacker = spawn_fighter(&mut world, 30.);
+        let attack = world
+            .spawn(Attack {
+                damage: 10,
+                hitstun_duration: 0.5,
+                ..default()
+            })
+            .id();
+        world.entity_mut(attacker).push_children(&[attack]);
+
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(attack_damage_system)
+            .add_system(collect_hitstuns.after(attack_damage_system));
+        let mut hit = |world: &mut World| {
+            world.send_event(CollisionEvent::Started(
+                attack,
+                defender_hurtbox,
+                CollisionEventFlags::empty(),
+            ));
+            stage.run(world);
+        };
+        let stunned = |world: &mut World, fighter: Entity| {
+            let mut intents = world.get_mut::<StateTransitionIntents>(fighter).unwrap();
+            let stunned = intents.iter().any(|intent| intent.data.is::<HitStun>());
+            intents.clear();
+            stunned
+        };
+
+        // An attack from the front within the parry window stuns the attacker instead
+        hit(&mut world);
+        assert_eq!(**world.get::<Health>(defender).unwrap(), 100);
+        assert!(!stunned(&mut world, defender));
+        assert!(stunned(&mut world, attacker));
+        assert_eq!(
+            world.get::<SuperMeter>(defender).unwrap().value,
+            parry.meter_gain
+        );
+
+        // Once the window has passed, the attack connects
+        world.get_mut::<Parrying>(defender).unwrap().elapsed = parry.window + 0.01;
+        hit(&mut world);
+        assert_eq!(**world.get::<Health>(defender).unwrap(), 90);
+        assert!(stunned(&mut world, defender));
+        assert!(!stunned(&mut world, attacker));
+
+        // And attacks from behind aren't parried
+        *world.get_mut::<Parrying>(defender).unwrap() = Parrying::new(&parry);
+        *world.get_mut::<Facing>(defender).unwrap() = Facing::Left;
+        hit(&mut world);
+        assert_eq!(**world.get::<Health>(defender).unwrap(), 80);
+    }
+
+    #[test]
+    fn test_parry_only_covers_its_cone() {
+        let mut world = World::new();
+        world.init_resource::<Events<CollisionEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ParryEvent>>();
+        let mut game = game_meta();
+        let mut parry = game.parry.unwrap();
+        parry.cone = 120.;
+        game.parry = Some(parry);
+        world.insert_resource(game);
+
+        let defender = world
+            .spawn((
+                Health(100),
+                Damageable(true),
+                StateTransitionIntents::default(),
+                Stats::default(),
+                Stagger::default(),
+                JuggleCount::default(),
+                Handle::<FighterMeta>::default(),
+                GlobalTransform::default(),
+                Facing::Right,
+                Parrying::new(&parry),
+            ))
+            .id();
+        let defender_hurtbox = world.spawn(Hurtbox).id();
+        world
+            .entity_mut(defender)
+            .push_children(&[defender_hurtbox]);
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(attack_damage_system);
+        // Attack the defender from the given position, returning whether the attack connected
+        let mut hit_from = |world: &mut World, position: Vec2| {
+            let attacker = world
+                .spawn(GlobalTransform::from_translation(position.extend(0.)))
+                .id();
+            let attack = world
+                .spawn(Attack {
+                    damage: 10,
+                    ..default()
+                })
+                .id();
+            world.entity_mut(attacker).push_children(&[attack]);
+            world.send_event(CollisionEvent::Started(
+                attack,
+                defender_hurtbox,
+                CollisionEventFlags::empty(),
+            ));
+
+            let health = **world.get::<Health>(defender).unwrap();
+            stage.run(world);
+            **world.get::<Health>(defender).unwrap() < health
+        };
+
+        // An attack from straight ahead is parried
+        assert!(!hit_from(&mut world, Vec2::new(30., 0.)));
+
+        // While one from a 90 degree angle, above or below, connects
+        assert!(hit_from(&mut world, Vec2::new(0., 30.)));
+        assert!(hit_from(&mut world, Vec2::new(0., -30.)));
+    }
+
+    #[test]
+    fn test_pull_attacks_knock_toward_attacker() {
+        let mut world = World::new();
+        world.init_resource::<Events<CollisionEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ParryEvent>>();
+        world.insert_resource(game_meta());
+
+        let mut target_hurtbox = None;
+        let target_position = Vec3::new(100., 20., 0.);
+        let target = world
+            .spawn((
+                Health(100),
+                Damageable(true),
+                StateTransitionIntents::default(),
+                Stats::default(),
+                Stagger::default(),
+                JuggleCount::default(),
+                Handle::<FighterMeta>::default(),
+                Transform::from_translation(target_position),
+            ))
+            .with_children(|parent| target_hurtbox = Some(parent.spawn(Hurtbox).id()))
+            .id();
+        let attacker = world.spawn(GlobalTransform::IDENTITY).id();
+        let vacuum = world
+            .spawn(Attack {
+                damage: 10,
+                pushback: Vec2::new(100., 0.),
+                hitstun_duration: 0.5,
+                pull: Some(1000.),
+                ..default()
+            })
+            .id();
+        world.entity_mut(attacker).add_child(vacuum);
+
+        world.send_event(CollisionEvent::Started(
+            vacuum,
+            target_hurtbox.unwrap(),
+            CollisionEventFlags::empty(),
+        ));
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(attack_damage_system)
+            .add_system(collect_hitstuns.after(attack_damage_system));
+        stage.run(&mut world);
+
+        let intent = world
+            .get_mut::<StateTransitionIntents>(target)
+            .unwrap()
+            .pop_front()
+            .unwrap();
+        let hitstun = intent.data.downcast_ref::<HitStun>().unwrap();
+
+        // The target is knocked toward the attacker, without being launched
+        let toward_attacker = -target_position.truncate().normalize();
+        assert!(hitstun.pushback.normalize().dot(toward_attacker) > 0.99);
+        assert_eq!(hitstun.gravity, 0.);
+        assert!(world.get::<Airborne>(target).is_none());
+
+        // And stops at the attacker instead of being pulled past it
+        let distance = hitstun.pushback.length() * hitstun.timer.duration().as_secs_f32();
+        assert!(distance <= target_position.length() + 0.01);
+    }
+
+    #[test]
+    fn test_hit_pause_holds_the_hitstun() {
+        let mut world = World::new();
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+        let fighter = world
+            .spawn((
+                Animation::new(0.1, default()),
+                Facing::default(),
+                LinearVelocity::default(),
+                HitStun {
+                    timer: Timer::from_seconds(0.3, TimerMode::Once),
+                    ..default()
+                },
+                HitPause::new(0.1),
+            ))
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(hitstun);
+        let mut step = |world: &mut World| {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(0.05));
+            stage.run(world);
+        };
+        let elapsed = |world: &World| world.get::<HitStun>(fighter).unwrap().timer.elapsed_secs();
+
+        step(&mut world);
+        assert_eq!(elapsed(&world), 0.);
+
+        // The hit stun runs once the pause is over
+        world.entity_mut(fighter).remove::<HitPause>();
+        step(&mut world);
+        assert!(elapsed(&world) > 0.);
+    }
+
+    #[test]
+    fn test_knockdown_gets_up_back_to_idle() {
+        let mut world = World::new();
+        world.init_resource::<Events<CollisionEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ParryEvent>>();
+        world.insert_resource(game_meta());
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+
+        let mut hurtbox = None;
+        let target = world
+            .spawn((
+                Idling,
+                Health(100),
+                Damageable(true),
+                StateTransitionIntents::default(),
+                Stats::default(),
+                Stagger::default(),
+                JuggleCount::default(),
+                Handle::<FighterMeta>::default(),
+                // Without knockdown animations, like the fighters that don't have them yet
+                Animation::new(
+                    0.1,
+                    [Dying::ANIMATION, HitStun::HITSTUN]
+                        .into_iter()
+                        .map(|name| {
+                            let clip = Clip {
+                                frames: 0..3,
+                                repeat: false,
+                            };
+                            (name.to_string(), clip)
+                        })
+                        .collect(),
+                ),
+                LinearVelocity::default(),
+                Transform::default(),
+            ))
+            .with_children(|parent| hurtbox = Some(parent.spawn(Hurtbox).id()))
+            .id();
+        let attack = world
+            .spawn(Attack {
+                damage: 10,
+                pushback: Vec2::new(100., 50.),
+                hitstun_duration: 0.3,
+                reaction: HitReaction::Knockdown,
+                ..default()
+            })
+            .id();
+        world.send_event(CollisionEvent::Started(
+            attack,
+            hurtbox.unwrap(),
+            CollisionEventFlags::empty(),
+        ));
+
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(attack_damage_system)
+            .add_system(collect_hitstuns.after(attack_damage_system))
+            .add_system(transition_from_idle.after(collect_hitstuns))
+            .add_system(transition_from_knocked_down.after(collect_hitstuns))
+            .add_system(transition_from_getting_up.after(collect_hitstuns))
+            .add_system(knocked_down.after(transition_from_knocked_down))
+            .add_system(getting_up.after(transition_from_getting_up));
+        let mut step = |world: &mut World| {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(0.1));
+            stage.run(world);
+        };
+
+        // The knockdown puts the target on the ground, sliding along it without being launched
+        step(&mut world);
+        assert!(world.get::<KnockedDown>(target).is_some());
+        assert!(world.get::<Idling>(target).is_none());
+        step(&mut world);
+        assert_eq!(
+            **world.get::<LinearVelocity>(target).unwrap(),
+            Vec2::new(100., 0.)
+        );
+        assert!(world.get::<Airborne>(target).is_none());
+        let current_animation = |world: &World| {
+            world
+                .get::<Animation>(target)
+                .unwrap()
+                .current_animation
+                .clone()
+        };
+        assert_eq!(current_animation(&world).as_deref(), Some(Dying::ANIMATION));
+
+        // Then it gets up, unable to be damaged while getting up
+        for _ in 0..20 {
+            if world.get::<KnockedDown>(target).is_none() {
+                break;
+            }
+            step(&mut world);
+        }
+        assert!(world.get::<GettingUp>(target).is_some());
+        assert!(world.get::<Invulnerable>(target).is_some());
+        step(&mut world);
+        assert_eq!(current_animation(&world).as_deref(), Some(HitStun::HITSTUN));
+
+        // And is back to idle once it is up
+        for _ in 0..20 {
+            if world.get::<GettingUp>(target).is_none() {
+                break;
+            }
+            step(&mut world);
+        }
+        assert!(world.get::<Idling>(target).is_some());
+        assert!(world.get::<Invulnerable>(target).is_none());
+    }
+
+    #[test]
+    fn test_airborne_knockdown_lands_before_sliding() {
+        let mut world = World::new();
+        world.init_resource::<Events<CollisionEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ParryEvent>>();
+        world.insert_resource(game_meta());
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+
+        let mut hurtbox = None;
+        let target = world
+            .spawn((
+                Idling,
+                Health(100),
+                Damageable(true),
+                StateTransitionIntents::default(),
+                Stats::default(),
+                Stagger::default(),
+                JuggleCount::default(),
+                Handle::<FighterMeta>::default(),
+                Animation::new(0.1, default()),
+                LinearVelocity::default(),
+                Transform::from_xyz(0., 100., 0.),
+                Airborne { ground_y: 0. },
+            ))
+            .with_children(|parent| hurtbox = Some(parent.spawn(Hurtbox).id()))
+            .id();
+        let attack = world
+            .spawn(Attack {
+                damage: 10,
+                pushback: Vec2::new(100., 50.),
+                hitstun_duration: 0.3,
+                reaction: HitReaction::Knockdown,
+                ..default()
+            })
+            .id();
+        world.send_event(CollisionEvent::Started(
+            attack,
+            hurtbox.unwrap(),
+            CollisionEventFlags::empty(),
+        ));
+
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(attack_damage_system)
+            .add_system(collect_hitstuns.after(attack_damage_system))
+            .add_system(transition_from_idle.after(collect_hitstuns))
+            .add_system(transition_from_knocked_down.after(collect_hitstuns))
+            .add_system(knocked_down.after(transition_from_knocked_down))
+            .add_system(velocity_system.after(knocked_down))
+            .add_system(land_fighters.after(velocity_system));
+        let mut step = |world: &mut World| {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(0.1));
+            stage.run(world);
+        };
+
+        // The knocked down target stays airborne while it falls
+        step(&mut world);
+        step(&mut world);
+        assert!(world.get::<KnockedDown>(target).is_some());
+        assert!(world.get::<Airborne>(target).is_some());
+        assert!(world.get::<LinearVelocity>(target).unwrap().y < 0.);
+
+        // Until it lands on the ground it was knocked out of the air from, and slides along it
+        for _ in 0..10 {
+            if world.get::<Airborne>(target).is_none() {
+                break;
+            }
+            step(&mut world);
+        }
+        assert!(world.get::<Airborne>(target).is_none());
+        assert_eq!(world.get::<Transform>(target).unwrap().translation.y, 0.);
+        assert_eq!(**world.get::<JuggleCount>(target).unwrap(), 0);
+        assert_eq!(
+            **world.get::<LinearVelocity>(target).unwrap(),
+            Vec2::new(100., 0.)
+        );
+        assert!(
+            world
+                .get::<KnockedDown>(target)
+                .unwrap()
+                .timer
+                .elapsed_secs()
+                > 0.
+        );
+    }
+
+    #[test]
+    fn test_left_facing_hitboxes_are_mirrored() {
+        let attack = AttackMeta {
+            name: "slash".into(),
+            damage: 10,
+            frames: AttackFrames {
+                startup: 0,
+                active: 1,
+                recovery: 2,
+            },
+            hitbox: ColliderMeta {
+                size: Vec2::splat(10.),
+                offset: Vec2::new(20., 5.),
+            },
+            ..default()
+        };
+
+        // The hitboxes spawned on fighters and the bullet colliders are mirrored
+        let right = attack_hitbox_offset(&attack, &Facing::Right, 8.);
+        let left = attack_hitbox_offset(&attack, &Facing::Left, 8.);
+        assert_eq!(right, Vec2::new(20., 13.));
+        assert_eq!(left, Vec2::new(-right.x, right.y));
+
+        // The melee weapon hitbox is mirrored too, including the weapon's own position
+        let mut world = World::new();
+        world.insert_resource(game_meta());
+        let mut spawn_fighter = |facing: Facing| {
+            world
+                .spawn((
+                    Player,
+                    Team::Players,
+                    MeleeAttacking::default(),
+                    AvailableAttacks {
+                        attacks: vec![attack.clone()],
+                    },
+                    LinearVelocity::default(),
+                    Animation::new(0.1, default()),
+                    TransformBundle::from_transform(Transform::from_xyz(100., 0., 0.)),
+                    facing,
+                ))
+                .with_children(|fighter| {
+                    fighter.spawn((
+                        MeleeWeapon {
+                            audio: AudioMeta {
+                                effects: default(),
+                                effect_handles: default(),
+                                footsteps: None,
+                                whiff: None,
+                                whiff_handle: None,
+                                voices: None,
+                                variation: None,
+                            },
+                            attack: attack.clone(),
+                        },
+                        Animation::new(0.1, default()),
+                        Attached {
+                            position_face: true,
+                            sync_facing: true,
+                            sync_animation: false,
+                        },
+                        Facing::default(),
+                        TransformBundle::from_transform(Transform::from_xyz(10., 0., 0.)),
+                    ));
+                })
+                .id()
+        };
+        let right_fighter = spawn_fighter(Facing::Right);
+        let left_fighter = spawn_fighter(Facing::Left);
+
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(attachment_system)
+            .add_system(melee_attacking.after(attachment_system))
+            .add_system(transform_propagate_system.after(melee_attacking));
+        stage.run(&mut world);
+        stage.run(&mut world);
+
+        let mut hitbox_x = |fighter: Entity| {
+            let mut hitboxes = world.query::<(&Attack, &Parent, &GlobalTransform)>();
+            let weapon = world.get::<Children>(fighter).unwrap()[0];
+            let (_, _, transform) = hitboxes
+                .iter(&world)
+                .find(|(_, parent, _)| parent.get() == weapon)
+                .unwrap();
+            transform.translation().x - 100.
+        };
+        let right = hitbox_x(right_fighter);
+        let left = hitbox_x(left_fighter);
+        assert_eq!(right, 30.);
+        assert_eq!(left, -right);
+    }
+
+    #[test]
+    fn test_analog_attack_depth() {
+        let controls = AnalogAttackControls {
+            axis: SingleAxis::symmetric(GamepadAxisType::RightZ, 0.05),
+            threshold: 0.2,
+            heavy_threshold: 0.85,
+        };
+        let attack = |name: &str| AttackMeta {
+            name: name.into(),
+            frames: AttackFrames {
+                startup: 0,
+                active: 1,
+                recovery: 2,
+            },
+            ..default()
+        };
+        let available_attacks = AvailableAttacks {
+            attacks: vec![attack("flop"), attack("punch")],
+        };
+
+        // Pull the trigger halfway and release it for a light attack
+        let mut peak_depth = 0.0;
+        assert_eq!(controls.update(&mut peak_depth, 0.1), None);
+        assert_eq!(controls.update(&mut peak_depth, 0.5), None);
+        let light = controls.update(&mut peak_depth, 0.0).unwrap();
+        assert_eq!(light, AttackStrength::Light);
+        assert!(attack_transition(&available_attacks, light)
+            .unwrap()
+            .data
+            .is::<Punching>());
+
+        // Pull the trigger all the way for a heavy attack, without a light attack on release
+        let heavy = controls.update(&mut peak_depth, 0.9).unwrap();
+        assert_eq!(heavy, AttackStrength::Heavy);
+        assert_eq!(controls.update(&mut peak_depth, 1.0), None);
+        assert_eq!(controls.update(&mut peak_depth, 0.0), None);
+        assert!(attack_transition(&available_attacks, heavy)
+            .unwrap()
+            .data
+            .is::<Flopping>());
+    }
+
+    #[test]
+    fn test_enemy_cannot_be_damaged_during_entrance() {
+        let mut world = World::new();
+        world.init_resource::<Events<CollisionEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ParryEvent>>();
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+
+        let mut hurtbox = None;
+        let enemy = world
+            .spawn((
+                Enemy,
+                Idling,
+                Health(100),
+                Damageable(true),
+                StateTransitionIntents::default(),
+                Animation::new(0.1, default()),
+                Facing::Left,
+                LinearVelocity::default(),
+                TransformBundle::from_transform(Transform::from_xyz(100., 0., 0.)),
+            ))
+            .with_children(|parent| hurtbox = Some(parent.spawn(Hurtbox).id()))
+            .id();
+        let attack = world
+            .spawn(Attack {
+                damage: 10,
+                ..default()
+            })
+            .id();
+        let hit = |world: &mut World| {
+            world.send_event(CollisionEvent::Started(
+                attack,
+                hurtbox.unwrap(),
+                CollisionEventFlags::empty(),
+            ));
+            let mut stage = SystemStage::single_threaded();
+            stage.add_system(attack_damage_system);
+            stage.run(world);
+            **world.get::<Health>(enemy).unwrap()
+        };
+
+        // The newly spawned enemy starts walking in from the side
+        let stats = Stats {
+            movement_speed: 100.,
+            ..default()
+        };
+        let mut queue = CommandQueue::default();
+        Entrance(EntranceStyle::Walk).start(
+            &mut Commands::new(&mut queue, &world),
+            enemy,
+            &Transform::from_xyz(100., 0., 0.),
+            &stats,
+            None,
+        );
+        queue.apply(&mut world);
+        assert!(world.get::<SpawningEntrance>(enemy).is_some());
+        assert!(world.get::<Idling>(enemy).is_none());
+        assert!(world.get::<Transform>(enemy).unwrap().translation.x > 100.);
+
+        // It can't be damaged while entering
+        assert_eq!(hit(&mut world), 100);
+
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(spawning_entrance)
+            .add_system(transition_from_spawning_entrance.after(spawning_entrance))
+            .add_system(velocity_system.after(spawning_entrance));
+        for _ in 0..10 {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(0.1));
+            stage.run(&mut world);
+        }
+
+        // Once it has arrived, the AI takes over and it can be damaged
+        assert!(world.get::<SpawningEntrance>(enemy).is_none());
+        assert!(world.get::<Idling>(enemy).is_some());
+        assert_eq!(world.get::<Transform>(enemy).unwrap().translation.x, 100.);
+        assert_eq!(hit(&mut world), 90);
+    }
+
+    #[test]
+    fn test_acceleration_ramps_up_movement() {
+        let mut world = World::new();
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+
+        let stats = Stats {
+            movement_speed: 150.,
+            acceleration: Some(1000.),
+            ..default()
+        };
+        let enemy = world
+            .spawn((
+                Enemy,
+                stats.clone(),
+                Animation::new(0.1, default()),
+                Facing::Left,
+                LinearVelocity::default(),
+            ))
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(moving);
+        let mut step = |world: &mut World| {
+            world
+                .entity_mut(enemy)
+                .remove::<Idling>()
+                .insert(Moving::new(Vec2::X, &stats, false));
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(0.05));
+            stage.run(world);
+            world.get::<LinearVelocity>(enemy).unwrap().x
+        };
+
+        // The enemy speeds up over several ticks instead of reaching its speed right away
+        let speeds = [step(&mut world), step(&mut world), step(&mut world)];
+        assert!(speeds[0] > 0. && speeds[0] < 150.);
+        assert!(speeds[1] > speeds[0] && speeds[1] < 150.);
+        assert_eq!(speeds[2], 150.);
+        assert!(!world.get::<Facing>(enemy).unwrap().is_left());
+
+        // Without an acceleration, it does
+        world.get_mut::<Stats>(enemy).unwrap().acceleration = None;
+        *world.get_mut::<LinearVelocity>(enemy).unwrap() = default();
+        assert_eq!(step(&mut world), 150.);
+    }
+
+    #[test]
+    fn test_downed_player_is_revived_by_nearby_teammate() {
+        let mut world = World::new();
+        let game = game_meta();
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+
+        let stats = Stats {
+            max_health: 100,
+            ..default()
+        };
+        let player = world
+            .spawn((
+                Player,
+                Health(0),
+                stats.clone(),
+                StateTransitionIntents::default(),
+                Animation::new(0.1, default()),
+                LinearVelocity::default(),
+                Handle::<FighterMeta>::default(),
+                TransformBundle::default(),
+            ))
+            .id();
+        let teammate = world
+            .spawn((
+                Player,
+                Health(100),
+                stats,
+                TransformBundle::from_transform(Transform::from_xyz(
+                    game.revive.radius * 2.,
+                    0.,
+                    0.,
+                )),
+            ))
+            .id();
+        world.insert_resource(game);
+
+        // The player is downed rather than killed, since a teammate can revive them
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(collect_fighter_eliminations);
+        stage.run(&mut world);
+        let intents = world.get::<StateTransitionIntents>(player).unwrap();
+        assert!(intents.iter().any(|intent| intent.data.is::<Downed>()));
+        assert!(!intents.iter().any(|intent| intent.data.is::<Dying>()));
+
+        let downed_duration = world.resource::<GameMeta>().revive.downed_duration;
+        world
+            .entity_mut(player)
+            .insert(Downed::new(downed_duration));
+        world
+            .get_mut::<StateTransitionIntents>(player)
+            .unwrap()
+            .clear();
+
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(downed)
+            .add_system(transition_from_downed.after(downed));
+        let mut step = |world: &mut World, seconds: f32| {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(seconds));
+            stage.run(world);
+        };
+
+        // The teammate is too far away to revive the player
+        step(&mut world, 0.1);
+        assert_eq!(world.get::<Downed>(player).unwrap().revive_progress, 0.);
+
+        // Standing next to the player for long enough revives them with part of their health
+        world.get_mut::<Transform>(teammate).unwrap().translation.x = 10.;
+        let revive_duration = world.resource::<GameMeta>().revive.revive_duration;
+        for _ in 0..((revive_duration / 0.1) as usize + 2) {
+            step(&mut world, 0.1);
+        }
+        assert!(world.get::<Downed>(player).is_none());
+        assert!(world.get::<Idling>(player).is_some());
+        assert_eq!(**world.get::<Health>(player).unwrap(), 30);
+    }
+
+    #[test]
+    fn test_combo_advances_through_its_links() {
+        let mut fighter: FighterMeta =
+            serde_yaml::from_str(include_str!("../assets/fighters/dev/dev.fighter.yaml")).unwrap();
+        let link = fighter.combo[0].clone();
+        fighter.combo = [10, 20, 30]
+            .into_iter()
+            .map(|damage| {
+                let mut link = link.clone();
+                link.attack.damage = damage;
+                link
+            })
+            .collect();
+        let animations = fighter.spritesheet.animations.clone();
+
+        let mut world = World::new();
+        world.insert_resource(game_meta());
+        world.init_resource::<Assets<FighterMeta>>();
+        let handle = world.resource_mut::<Assets<FighterMeta>>().add(fighter);
+        let entity = world
+            .spawn((
+                Player,
+                Animation::new(0.1, animations),
+                LinearVelocity::default(),
+                Facing::Right,
+                handle,
+                Chaining::default(),
+            ))
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(chaining);
+        // Run a frame, and get the damage of the attacks spawned in it
+        let mut spawned_damage = |world: &mut World| {
+            stage.run(world);
+            let mut attacks = world.query::<(Entity, &Attack)>();
+            let spawned = attacks
+                .iter(world)
+                .map(|(attack, stats)| (attack, stats.damage))
+                .collect::<Vec<_>>();
+            for (attack, _) in &spawned {
+                world.entity_mut(*attack).despawn_recursive();
+            }
+            spawned
+                .into_iter()
+                .map(|(_, damage)| damage)
+                .collect::<Vec<_>>()
+        };
+        // Press the attack again, once the current link can be canceled
+        let chain = |world: &mut World| {
+            world.get_mut::<Animation>(entity).unwrap().current_frame = link.cancel_frame();
+            world.get_mut::<Chaining>(entity).unwrap().continue_chain = true;
+        };
+
+        // The combo starts with the first link
+        assert_eq!(spawned_damage(&mut world), [10]);
+
+        // Pressing the attack before the cancel window doesn't chain
+        world.get_mut::<Chaining>(entity).unwrap().continue_chain = true;
+        assert!(spawned_damage(&mut world).is_empty());
+
+        // But it does once in the cancel window, through each link with its own damage
+        chain(&mut world);
+        assert!(spawned_damage(&mut world).is_empty());
+        assert_eq!(spawned_damage(&mut world), [20]);
+        chain(&mut world);
+        spawned_damage(&mut world);
+        assert_eq!(spawned_damage(&mut world), [30]);
+        assert_eq!(world.get::<Chaining>(entity).unwrap().link, 2);
+
+        // Chaining past the last link finishes the combo
+        chain(&mut world);
+        spawned_damage(&mut world);
+        assert!(spawned_damage(&mut world).is_empty());
+        assert!(world.get::<Chaining>(entity).unwrap().transition_to_final);
+    }
+
+    #[test]
+    fn test_attacking_in_the_air_uses_the_air_attack() {
+        let fighter: FighterMeta =
+            serde_yaml::from_str(include_str!("../assets/fighters/dev/dev.fighter.yaml")).unwrap();
+        let attacks = fighter.attacks.clone();
+        let animations = fighter.spritesheet.animations.clone();
+
+        let mut world = World::new();
+        world.insert_resource(game_meta());
+        world.insert_resource(Time::default());
+        world.init_resource::<Input<KeyCode>>();
+        world.init_resource::<Assets<FighterMeta>>();
+        let handle = world.resource_mut::<Assets<FighterMeta>>().add(fighter);
+        let mut spawn_player = |state: Option<Flopping>| {
+            let mut action_state = ActionState::<PlayerAction>::default();
+            action_state.press(PlayerAction::Attack);
+
+            let mut player = world.spawn((
+                Player,
+                action_state,
+                StateTransitionIntents::default(),
+                Inventory::default(),
+                Stats::default(),
+                AvailableAttacks {
+                    attacks: attacks.clone(),
+                },
+                AnalogAttackTrigger::default(),
+                AttackBuffer::default(),
+                StickResponse::default(),
+                SuperMeter::default(),
+                handle.clone(),
+                Animation::new(0.1, animations.clone()),
+                Transform::default(),
+                LinearVelocity::default(),
+                Facing::Right,
+                Team::Players,
+            ));
+            match state {
+                // Jumping with a flop
+                Some(flopping) => player.insert((flopping, Airborne { ground_y: 0. })),
+                None => player.insert(Idling),
+            };
+            player.id()
+        };
+        let jumping = spawn_player(Some(Flopping {
+            has_started: true,
+            ..default()
+        }));
+        let grounded = spawn_player(None);
+        let air_attacking_player = spawn_player(Some(default()));
+        world
+            .entity_mut(air_attacking_player)
+            .remove::<Flopping>()
+            .insert(AirAttacking {
+                has_started: true,
+                ..default()
+            });
+
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(collect_player_actions)
+            .add_system(transition_from_flopping.after(collect_player_actions))
+            .add_system(transition_from_air_attacking.after(collect_player_actions))
+            .add_system(transition_from_idle.after(collect_player_actions));
+        stage.run(&mut world);
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(air_attacking);
+        stage.run(&mut world);
+
+        // The jumping player cancels the flop into its air attack, using up the attack
+        assert!(world.get::<AirAttacking>(jumping).unwrap().has_started);
+        assert!(world.get::<Flopping>(jumping).is_none());
+        assert_eq!(world.get::<AttackBuffer>(jumping).unwrap().peek(), None);
+
+        // While the grounded player does its grounded attack
+        assert!(world.get::<AirAttacking>(grounded).is_none());
+        assert!(world.get::<Chaining>(grounded).is_some());
+
+        // The player already attacking in the air keeps the attack buffered for after it lands
+        assert_eq!(
+            world
+                .get::<AttackBuffer>(air_attacking_player)
+                .unwrap()
+                .peek(),
+            Some(AttackStrength::Light)
+        );
+    }
+
+    #[test]
+    fn test_rejected_super_attack_keeps_the_meter_full() {
+        let fighter: FighterMeta =
+            serde_yaml::from_str(include_str!("../assets/fighters/dev/dev.fighter.yaml")).unwrap();
+        let attacks = fighter.attacks.clone();
+        let animations = fighter.spritesheet.animations.clone();
+
+        let mut world = World::new();
+        world.insert_resource(game_meta());
+        world.insert_resource(Time::default());
+        world.init_resource::<Input<KeyCode>>();
+        world.init_resource::<Assets<FighterMeta>>();
+        let handle = world.resource_mut::<Assets<FighterMeta>>().add(fighter);
+        let spawn_player = |world: &mut World| {
+            let mut action_state = ActionState::<PlayerAction>::default();
+            action_state.press(PlayerAction::Super);
+            let mut super_meter = SuperMeter::new(100.);
+            super_meter.add(100.);
+
+            world
+                .spawn((
+                    Player,
+                    action_state,
+                    StateTransitionIntents::default(),
+                    Inventory::default(),
+                    Stats::default(),
+                    AvailableAttacks {
+                        attacks: attacks.clone(),
+                    },
+                    AnalogAttackTrigger::default(),
+                    AttackBuffer::default(),
+                    StickResponse::default(),
+                    super_meter,
+                    handle.clone(),
+                    Animation::new(0.1, animations.clone()),
+                    LinearVelocity::default(),
+                    Facing::Right,
+                ))
+                .id()
+        };
+        let stunned = spawn_player(&mut world);
+        world.entity_mut(stunned).insert(HitStun {
+            timer: Timer::from_seconds(1., TimerMode::Once),
+            ..default()
+        });
+        let idle = spawn_player(&mut world);
+        world.entity_mut(idle).insert(Idling);
+
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(collect_player_actions)
+            .add_system(transition_from_hitstun.after(collect_player_actions))
+            .add_system(transition_from_idle.after(collect_player_actions));
+        stage.run(&mut world);
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(super_attacking);
+        stage.run(&mut world);
+
+        // Hit stun rejects the super attack, which doesn't cost the meter
+        assert!(world.get::<SuperAttacking>(stunned).is_none());
+        assert!(world.get::<SuperMeter>(stunned).unwrap().is_full());
+
+        // The meter is only drained by starting the super attack
+        assert!(world.get::<SuperAttacking>(idle).unwrap().has_started);
+        assert_eq!(world.get::<SuperMeter>(idle).unwrap().value, 0.);
+    }
+
+    #[test]
+    fn test_grabbing_staggered_enemy_steals_its_weapon() {
+        let mut sword: ItemMeta =
+            serde_yaml::from_str(include_str!("../assets/items/sword/sword.item.yaml")).unwrap();
+        // The sprite of the stolen weapon needs an atlas, loaded or not
+        let (sword_attack, sword_audio) = match &mut sword.kind {
+            ItemKind::MeleeWeapon {
+                attack,
+                audio,
+                spritesheet,
+                ..
+            } => {
+                spritesheet.atlas_handle.push(default());
+                (attack.clone(), audio.clone())
+            }
+            _ => unreachable!("The sword is a melee weapon"),
+        };
+        let fighter: FighterMeta =
+            serde_yaml::from_str(include_str!("../assets/fighters/dev/dev.fighter.yaml")).unwrap();
+        let punch = fighter.attacks[0].clone();
+
+        let mut world = World::new();
+        let mut game = game_meta();
+        game.enemy_weapon_steal = true;
+        world.insert_resource(game);
+        world.init_resource::<Assets<ItemMeta>>();
+        world.init_resource::<Events<ScriptItemGrabEvent>>();
+
+        let player = world
+            .spawn((
+                Player,
+                Transform::default(),
+                Inventory::default(),
+                StateTransitionIntents::default(),
+                AvailableAttacks {
+                    attacks: vec![punch.clone()],
+                },
+            ))
+            .id();
+        let enemy = world
+            .spawn((
+                Enemy,
+                Transform::from_xyz(10., 0., 0.),
+                Inventory(Some(sword.clone())),
+                StateTransitionIntents::default(),
+                AvailableAttacks {
+                    attacks: vec![punch.clone(), sword_attack.clone()],
+                },
+            ))
+            .id();
+        let enemy_weapon = world
+            .spawn(MeleeWeapon {
+                audio: sword_audio,
+                attack: sword_attack.clone(),
+            })
+            .id();
+        world.entity_mut(enemy).push_children(&[enemy_weapon]);
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(grabbing);
+        let mut grab = |world: &mut World| {
+            world.entity_mut(player).insert(Grabbing);
+            stage.run(world);
+        };
+
+        // An enemy standing its ground keeps its weapon
+        grab(&mut world);
+        assert!(world.get::<Inventory>(player).unwrap().is_none());
+        assert!(world.get::<Inventory>(enemy).unwrap().is_some());
+
+        // But a staggered one has it stolen
+        world.entity_mut(enemy).insert(HitStun::default());
+        grab(&mut world);
+        let player_attacks = &world.get::<AvailableAttacks>(player).unwrap().attacks;
+        assert_eq!(player_attacks.last().unwrap().name, sword_attack.name);
+        assert_eq!(
+            world
+                .get::<Inventory>(player)
+                .unwrap()
+                .as_ref()
+                .unwrap()
+                .name,
+            sword.name
+        );
+        let player_weapons = world
+            .get::<Children>(player)
+            .unwrap()
+            .iter()
+            .filter(|child| world.get::<MeleeWeapon>(**child).is_some())
+            .count();
+        assert_eq!(player_weapons, 1);
+
+        // Leaving the enemy with its own attacks only
+        assert!(world.get::<Inventory>(enemy).unwrap().is_none());
+        let enemy_attacks = &world.get::<AvailableAttacks>(enemy).unwrap().attacks;
+        assert_eq!(enemy_attacks.len(), 1);
+        assert_eq!(enemy_attacks[0].name, punch.name);
+        assert!(world.get_entity(enemy_weapon).is_none());
+    }
+
+    #[test]
+    fn test_recoil_pushes_attacker_backward() {
+        let fighter: FighterMeta =
+            serde_yaml::from_str(include_str!("../assets/fighters/dev/dev.fighter.yaml")).unwrap();
+        let punch = AttackMeta {
+            self_knockback: Some(Vec2::new(100., 0.)),
+            ..fighter.attacks[0].clone()
+        };
+        let animations = fighter.spritesheet.animations.clone();
+
+        let mut world = World::new();
+        world.insert_resource(game_meta());
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+        world.init_resource::<Assets<FighterMeta>>();
+        let handle = world.resource_mut::<Assets<FighterMeta>>().add(fighter);
+        let mut spawn_attacker = |facing: Facing| {
+            world
+                .spawn((
+                    Player,
+                    Team::Players,
+                    Animation::new(0.1, animations.clone()),
+                    LinearVelocity::default(),
+                    facing,
+                    handle.clone(),
+                    AvailableAttacks {
+                        attacks: vec![punch.clone()],
+                    },
+                    Punching::default(),
+                ))
+                .id()
+        };
+        let facing_right = spawn_attacker(Facing::Right);
+        let facing_left = spawn_attacker(Facing::Left);
+
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(punching)
+            .add_system(apply_recoil.after(punching));
+        // Start the attacks, then let the recoil kick in on the next frame
+        for _ in 0..2 {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(0.05));
+            stage.run(&mut world);
+        }
+
+        // Both attackers are pushed away from the side they are facing
+        assert!(world.get::<LinearVelocity>(facing_right).unwrap().x < 0.);
+        assert!(world.get::<LinearVelocity>(facing_left).unwrap().x > 0.);
+    }
+
+    #[test]
+    fn test_attack_momentum_carries_movement_into_attack() {
+        let fighter: FighterMeta =
+            serde_yaml::from_str(include_str!("../assets/fighters/dev/dev.fighter.yaml")).unwrap();
+        let animations = fighter.spritesheet.animations.clone();
+        let punch = |momentum_retention: f32| AttackMeta {
+            self_knockback: None,
+            momentum_retention,
+            ..fighter.attacks[0].clone()
+        };
+        let attacks = [punch(0.5), punch(0.)];
+
+        let mut world = World::new();
+        world.insert_resource(game_meta());
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+        world.init_resource::<Assets<FighterMeta>>();
+        let handle = world.resource_mut::<Assets<FighterMeta>>().add(fighter);
+        // Both attackers start punching while running forward
+        let [with_momentum, without_momentum] = attacks.map(|attack| {
+            world
+                .spawn((
+                    Player,
+                    Team::Players,
+                    Animation::new(0.1, animations.clone()),
+                    LinearVelocity(Vec2::new(200., 0.)),
+                    Facing::Right,
+                    handle.clone(),
+                    AvailableAttacks {
+                        attacks: vec![attack],
+                    },
+                    Punching::default(),
+                ))
+                .id()
+        });
+
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(punching)
+            .add_system(apply_recoil.after(punching));
+        for _ in 0..2 {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(0.05));
+            stage.run(&mut world);
+        }
+
+        // Some of the movement carries into the attack, while it stops the attacker without
+        // momentum retention
+        let velocity = world.get::<LinearVelocity>(with_momentum).unwrap().x;
+        assert!(velocity > 0. && velocity < 100.);
+        assert_eq!(world.get::<LinearVelocity>(without_momentum).unwrap().x, 0.);
+        assert!(world.get::<Recoil>(without_momentum).is_none());
+    }
+
+    /// A weapon firing bullets of `attack`, with `animation` for both the weapon and its bullets
+    fn projectile_weapon(
+        attack: AttackMeta,
+        animation: Animation,
+        max_bullets: Option<usize>,
+    ) -> ProjectileWeapon {
+        ProjectileWeapon {
+            audio: AudioMeta {
+                effects: default(),
+                effect_handles: default(),
+                footsteps: None,
+                whiff: None,
+                whiff_handle: None,
+                voices: None,
+                variation: None,
+            },
+            attack,
+            animated_sprite: AnimatedSpriteSheetBundle {
+                sprite_sheet: default(),
+                animation,
+            },
+            ammo: 10,
+            bullet_velocity: 100.,
+            bullet_lifetime: 10.,
+            shoot_delay: Timer::from_seconds(0., TimerMode::Once),
+            max_bullets,
+            damage_falloff: None,
+            bullets: default(),
+        }
+    }
+
+    #[test]
+    fn test_bullets_are_capped_per_weapon() {
+        let attack = AttackMeta {
+            name: "projectile".into(),
+            damage: 10,
+            frames: AttackFrames {
+                startup: 0,
+                active: 1,
+                recovery: 2,
+            },
+            ..default()
+        };
+        let animation = Animation::new(
+            0.1,
+            [(
+                "shooting".to_string(),
+                Clip {
+                    frames: 0..3,
+                    repeat: false,
+                },
+            )]
+            .into_iter()
+            .collect(),
+        );
+
+        let mut world = World::new();
+        world.insert_resource(game_meta());
+        world.insert_resource(Time::default());
+        let fighter = world
+            .spawn((
+                Team::Players,
+                AvailableAttacks {
+                    attacks: vec![attack.clone()],
+                },
+                LinearVelocity::default(),
+                Facing::Right,
+            ))
+            .id();
+        let weapon = world
+            .spawn((
+                projectile_weapon(attack, animation.clone(), Some(2)),
+                animation,
+                GlobalTransform::default(),
+            ))
+            .id();
+        world.entity_mut(fighter).push_children(&[weapon]);
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(shooting);
+        let mut fire = |world: &mut World| {
+            world.entity_mut(fighter).insert(Shooting::default());
+            stage.run(world);
+            let mut bullets = world.query::<(Entity, &Bullet)>();
+            bullets
+                .iter(world)
+                .filter(|(_, bullet)| bullet.weapon == weapon)
+                .map(|(entity, _)| entity)
+                .collect::<Vec<_>>()
+        };
+
+        let first = fire(&mut world);
+        assert_eq!(first.len(), 1);
+        assert_eq!(fire(&mut world).len(), 2);
+
+        // Firing past the cap despawns the oldest bullet
+        let bullets = fire(&mut world);
+        assert_eq!(bullets.len(), 2);
+        assert!(!bullets.contains(&first[0]));
+        assert_eq!(world.get::<ProjectileWeapon>(weapon).unwrap().ammo, 7);
+    }
+
+    #[test]
+    fn test_bullets_dont_hit_their_shooter_with_friendly_fire() {
+        let attack = AttackMeta {
+            name: "projectile".into(),
+            damage: 10,
+            frames: AttackFrames {
+                startup: 0,
+                active: 1,
+                recovery: 2,
+            },
+            ..default()
+        };
+        let animation = Animation::new(
+            0.1,
+            [(
+                "shooting".to_string(),
+                Clip {
+                    frames: 0..3,
+                    repeat: false,
+                },
+            )]
+            .into_iter()
+            .collect(),
+        );
+
+        let mut world = World::new();
+        world.init_resource::<Events<CollisionEvent>>();
+        world.init_resource::<Events<DamageEvent>>();
+        world.init_resource::<Events<ParryEvent>>();
+        let mut game = game_meta();
+        game.friendly_fire = true;
+        world.insert_resource(game);
+        world.insert_resource(Time::default());
+
+        let spawn_player = |world: &mut World| {
+            let mut hurtbox = None;
+            let player = world
+                .spawn((
+                    Team::Players,
+                    Health(100),
+                    Damageable(true),
+                    AvailableAttacks {
+                        attacks: vec![attack.clone()],
+                    },
+                    LinearVelocity::default(),
+                    Facing::Right,
+                ))
+                .with_children(|parent| hurtbox = Some(parent.spawn(Hurtbox).id()))
+                .id();
+            (player, hurtbox.unwrap())
+        };
+        let (shooter, shooter_hurtbox) = spawn_player(&mut world);
+        let (teammate, teammate_hurtbox) = spawn_player(&mut world);
+        let weapon = world
+            .spawn((
+                projectile_weapon(attack.clone(), animation.clone(), None),
+                animation,
+                GlobalTransform::default(),
+            ))
+            .id();
+        world.entity_mut(shooter).push_children(&[weapon]);
+
+        world.entity_mut(shooter).insert(Shooting::default());
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(shooting);
+        stage.run(&mut world);
+        let bullet = world
+            .query_filtered::<Entity, With<Bullet>>()
+            .single(&world);
+
+        // With friendly fire, the bullet collides with the shooter's team
+        let groups = world.get::<CollisionGroups>(bullet).unwrap();
+        assert!(groups.filters.contains(Team::Players.body_layer()));
+
+        // It hurts the shooter's teammates, but not the shooter
+        for hurtbox in [shooter_hurtbox, teammate_hurtbox] {
+            world.send_event(CollisionEvent::Started(
+                bullet,
+                hurtbox,
+                CollisionEventFlags::empty(),
+            ));
+        }
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(attack_damage_system);
+        stage.run(&mut world);
+        assert_eq!(**world.get::<Health>(shooter).unwrap(), 100);
+        assert_eq!(**world.get::<Health>(teammate).unwrap(), 90);
+    }
+
+    #[test]
+    fn test_dead_enemy_leaves_a_fading_corpse() {
+        let mut world = World::new();
+        world.init_resource::<Events<LifetimeExpired>>();
+        let mut game = game_meta();
+        game.enemy_corpses = Some(CorpseMeta { duration: 0.5 });
+        world.insert_resource(game);
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+
+        let mut animation = Animation::new(0.1, default());
+        animation.current_animation = Some(Dying::ANIMATION.to_string());
+        animation.played_once = true;
+        let enemy = world
+            .spawn((
+                Enemy,
+                Dying,
+                animation,
+                LinearVelocity::default(),
+                SpriteSheetBundle::default(),
+            ))
+            .with_children(|parent| {
+                parent.spawn(Collider::cuboid(5., 5.));
+            })
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(dying);
+        stage.run(&mut world);
+
+        // The enemy is replaced by a corpse that doesn't collide and isn't an enemy
+        assert!(world.get_entity(enemy).is_none());
+        let corpse = world
+            .query_filtered::<Entity, With<Corpse>>()
+            .single(&world);
+        assert!(world.get::<Enemy>(corpse).is_none());
+        assert!(world.get::<Children>(corpse).is_none());
+        assert_eq!(world.query::<&Collider>().iter(&world).count(), 0);
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(fade_corpses.after(lifetime_system));
+        stage.add_system(lifetime_system);
+        let mut alpha = 1.;
+        for _ in 0..4 {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(0.1));
+            stage.run(&mut world);
+
+            // The corpse fades out as its lifetime runs down
+            let faded = world.get::<TextureAtlasSprite>(corpse).unwrap().color.a();
+            assert!(faded < alpha);
+            alpha = faded;
+        }
+
+        for _ in 0..2 {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(0.1));
+            stage.run(&mut world);
+        }
+        assert!(world.get_entity(corpse).is_none());
+    }
+
+    #[test]
+    fn test_fighter_without_dying_animation_is_despawned() {
+        let mut world = World::new();
+        world.insert_resource(game_meta());
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+
+        // The fighter only has an idle clip, which repeats, so dying falls back to it
+        let animation = Animation::new(
+            0.1,
+            HashMap::from_iter([(
+                "idle".to_string(),
+                Clip {
+                    frames: 0..3,
+                    repeat: true,
+                },
+            )]),
+        );
+        let fighter = world
+            .spawn((
+                Dying,
+                animation,
+                LinearVelocity::default(),
+                SpriteSheetBundle::default(),
+            ))
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(dying);
+        stage.add_system(animation_cycling.after(dying));
+        for _ in 0..10 {
+            if world.get_entity(fighter).is_none() {
+                break;
+            }
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(0.1));
+            stage.run(&mut world);
+        }
+
+        // The fallback plays through once instead of restarting every frame
+        assert!(world.get_entity(fighter).is_none());
+    }
+}
@@ -0,0 +1,120 @@
+//! Frame pacing from the video settings: vsync on the game window, and an optional frame rate cap.
+//!
+//! Browsers pace frames with `requestAnimationFrame`, so on the web the cap is left to them and
+//! the frame limiter never waits.
+
+use bevy::{
+    prelude::*,
+    utils::{Duration, Instant},
+    window::PresentMode,
+};
+
+use crate::{metadata::VideoSettings, platform::load_settings};
+
+pub struct FramePacingPlugin;
+
+impl Plugin for FramePacingPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FrameLimiter>()
+            .add_system(apply_video_settings.after(load_settings))
+            .add_system_to_stage(CoreStage::Last, limit_frame_rate);
+    }
+}
+
+/// Holds back the end of each frame until the frame rate cap allows the next one to start
+#[derive(Resource, Default, Debug)]
+pub struct FrameLimiter {
+    /// The shortest time a frame may take, or `None` when frames aren't capped
+    pub frame_duration: Option<Duration>,
+    last_frame_end: Option<Instant>,
+}
+
+impl FrameLimiter {
+    /// Cap the frames per second, or remove the cap with `None`
+    pub fn set_max_fps(&mut self, max_fps: Option<u32>) {
+        self.frame_duration = max_fps
+            .filter(|max_fps| *max_fps > 0)
+            .map(|max_fps| Duration::from_secs_f64(1.0 / max_fps as f64));
+    }
+}
+
+/// Sets the present mode of the window and the frame rate cap when the video settings change
+fn apply_video_settings(
+    video_settings: Option<Res<VideoSettings>>,
+    windows: Option<ResMut<Windows>>,
+    mut frame_limiter: ResMut<FrameLimiter>,
+) {
+    let video_settings = match video_settings {
+        Some(video_settings) => video_settings,
+        None => return,
+    };
+    if !video_settings.is_changed() {
+        return;
+    }
+
+    if let Some(mut windows) = windows {
+        if let Some(window) = windows.get_primary_mut() {
+            window.set_present_mode(if video_settings.vsync {
+                PresentMode::AutoVsync
+            } else {
+                PresentMode::AutoNoVsync
+            });
+        }
+    }
+
+    if cfg!(target_arch = "wasm32") {
+        frame_limiter.set_max_fps(None);
+    } else {
+        frame_limiter.set_max_fps(video_settings.max_fps);
+    }
+}
+
+/// Sleeps for whatever is left of the frame's time under the cap
+fn limit_frame_rate(mut frame_limiter: ResMut<FrameLimiter>) {
+    #[cfg(not(target_arch = "wasm32"))]
+    if let (Some(frame_duration), Some(last_frame_end)) =
+        (frame_limiter.frame_duration, frame_limiter.last_frame_end)
+    {
+        let elapsed = last_frame_end.elapsed();
+        if elapsed < frame_duration {
+            std::thread::sleep(frame_duration - elapsed);
+        }
+    }
+
+    frame_limiter.last_frame_end = Some(Instant::now());
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_changing_max_fps_updates_frame_limiter() {
+        let mut world = World::new();
+        world.init_resource::<FrameLimiter>();
+        world.insert_resource(VideoSettings {
+            vsync: true,
+            max_fps: Some(60),
+        });
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(apply_video_settings);
+
+        stage.run(&mut world);
+        assert_eq!(
+            world.resource::<FrameLimiter>().frame_duration,
+            Some(Duration::from_secs_f64(1.0 / 60.0))
+        );
+
+        world.resource_mut::<VideoSettings>().max_fps = Some(30);
+        stage.run(&mut world);
+        assert_eq!(
+            world.resource::<FrameLimiter>().frame_duration,
+            Some(Duration::from_secs_f64(1.0 / 30.0))
+        );
+
+        world.resource_mut::<VideoSettings>().max_fps = None;
+        stage.run(&mut world);
+        assert_eq!(world.resource::<FrameLimiter>().frame_duration, None);
+    }
+}
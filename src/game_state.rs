@@ -0,0 +1,62 @@
+//! Deferred [`GameState`] transitions.
+//!
+//! Systems request transitions instead of setting the next state directly, and the highest
+//! priority request of the frame is applied at the end of it, so that the outcome of several
+//! transitions requested in the same frame, like a game over on the frame the level is completed,
+//! doesn't depend on the order the systems ran in.
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::GameState;
+
+pub struct GameStatePlugin;
+
+impl Plugin for GameStatePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<GameStateRequests>()
+            .add_system_to_stage(CoreStage::Last, apply_game_state_requests);
+    }
+}
+
+/// The priority of a game state transition, from the lowest to the highest
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum TransitionPriority {
+    /// Loading and navigating the menus
+    Navigation,
+    Pause,
+    Victory,
+    GameOver,
+}
+
+/// The game state transitions requested this frame
+#[derive(Resource, Default)]
+pub struct GameStateRequests(Vec<(GameState, TransitionPriority)>);
+
+impl GameStateRequests {
+    /// Request a transition to `state`
+    pub fn request(&mut self, state: GameState, priority: TransitionPriority) {
+        self.0.push((state, priority));
+    }
+
+    /// Take the state with the highest priority out of the requests, clearing them.
+    ///
+    /// Of the requests with the same priority, the first one wins.
+    pub fn take(&mut self) -> Option<GameState> {
+        let mut highest: Option<(GameState, TransitionPriority)> = None;
+        for (state, priority) in self.0.drain(..) {
+            if highest.map_or(true, |(_, highest_priority)| priority > highest_priority) {
+                highest = Some((state, priority));
+            }
+        }
+
+        highest.map(|(state, _)| state)
+    }
+}
+
+/// Applies the highest priority transition requested this frame
+fn apply_game_state_requests(mut commands: Commands, mut requests: ResMut<GameStateRequests>) {
+    if let Some(state) = requests.take() {
+        commands.insert_resource(NextState(state));
+    }
+}
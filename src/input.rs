@@ -1,13 +1,26 @@
-use leafwing_input_manager::Actionlike;
+use bevy::prelude::*;
+use leafwing_input_manager::{
+    axislike::{AxisType, SingleAxis},
+    prelude::InputMap,
+    Actionlike,
+};
 use serde::Deserialize;
 
 #[derive(Debug, Copy, Clone, Actionlike, Deserialize, Eq, PartialEq, Hash)]
 pub enum PlayerAction {
     Move,
+    /// Modifier held while moving to run instead of walk
+    Run,
     // Attacks
     Attack,
+    /// Analog attack trigger, where the trigger depth decides the attack strength
+    AnalogAttack,
     Throw,
     Shoot,
+    /// Super attack, available when the super meter is full
+    Super,
+    /// Tapped to parry the attacks coming from the front
+    Block,
 }
 
 #[derive(Debug, Copy, Clone, Actionlike, Deserialize, Eq, PartialEq, Hash)]
@@ -21,3 +34,99 @@ pub enum MenuAction {
     Pause,
     ToggleFullscreen,
 }
+
+/// An input device that the menus can be controlled with
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MenuInputDevice {
+    Keyboard,
+    Gamepad(Gamepad),
+}
+
+/// The key that pauses the game, and unpauses it
+pub const PAUSE_KEY: KeyCode = KeyCode::Escape;
+
+/// The gamepad button that pauses the game, and unpauses it
+pub const PAUSE_BUTTON: GamepadButtonType = GamepadButtonType::Start;
+
+/// Get the menu input map, for a single device, or for all of them with `None`
+pub fn menu_input_map(device: Option<MenuInputDevice>) -> InputMap<MenuAction> {
+    let mut input_map = InputMap::default();
+
+    // Keyboard bindings
+    if !matches!(device, Some(MenuInputDevice::Gamepad(_))) {
+        input_map
+            .insert(KeyCode::Up, MenuAction::Up)
+            .insert(KeyCode::Left, MenuAction::Left)
+            .insert(KeyCode::Down, MenuAction::Down)
+            .insert(KeyCode::Right, MenuAction::Right)
+            .insert(KeyCode::Return, MenuAction::Confirm)
+            .insert(KeyCode::Escape, MenuAction::Back)
+            .insert(KeyCode::F11, MenuAction::ToggleFullscreen)
+            .insert(PAUSE_KEY, MenuAction::Pause);
+    }
+
+    // Gamepad bindings
+    if device != Some(MenuInputDevice::Keyboard) {
+        input_map
+            // Up
+            .insert(GamepadButtonType::DPadUp, MenuAction::Up)
+            .insert(
+                SingleAxis {
+                    axis_type: AxisType::Gamepad(GamepadAxisType::LeftStickY),
+                    positive_low: 0.5,
+                    negative_low: -1.0,
+                    value: None,
+                },
+                MenuAction::Up,
+            )
+            // Left
+            .insert(GamepadButtonType::DPadLeft, MenuAction::Left)
+            .insert(
+                SingleAxis {
+                    axis_type: AxisType::Gamepad(GamepadAxisType::LeftStickX),
+                    positive_low: 1.0,
+                    negative_low: -0.5,
+                    value: None,
+                },
+                MenuAction::Left,
+            )
+            // Down
+            .insert(GamepadButtonType::DPadDown, MenuAction::Down)
+            .insert(
+                SingleAxis {
+                    axis_type: AxisType::Gamepad(GamepadAxisType::LeftStickY),
+                    positive_low: 1.0,
+                    negative_low: -0.5,
+                    value: None,
+                },
+                MenuAction::Down,
+            )
+            // Right
+            .insert(GamepadButtonType::DPadRight, MenuAction::Right)
+            .insert(
+                SingleAxis {
+                    axis_type: AxisType::Gamepad(GamepadAxisType::LeftStickX),
+                    positive_low: 0.5,
+                    negative_low: -1.0,
+                    value: None,
+                },
+                MenuAction::Right,
+            )
+            // Confirm
+            .insert(GamepadButtonType::South, MenuAction::Confirm)
+            .insert(GamepadButtonType::Start, MenuAction::Confirm)
+            // Back
+            .insert(GamepadButtonType::East, MenuAction::Back)
+            // Toggle Fullscreen
+            .insert(GamepadButtonType::Mode, MenuAction::ToggleFullscreen)
+            // Pause
+            .insert(PAUSE_BUTTON, MenuAction::Pause);
+
+        // Only listen to the gamepad that controls the menus
+        if let Some(MenuInputDevice::Gamepad(gamepad)) = device {
+            input_map.set_gamepad(gamepad);
+        }
+    }
+
+    input_map
+}
@@ -0,0 +1,207 @@
+use bevy::{prelude::*, time::fixed_timestep::FixedTimesteps, transform::TransformSystem};
+
+use crate::fighter::Attached;
+
+/// Plugin smoothing the rendered position of entities with a [`PreviousTransform`] by interpolating
+/// between their previous and current simulation [`Transform`].
+///
+/// Only the [`GlobalTransform`] used for rendering is interpolated, the [`Transform`] used by
+/// gameplay is left untouched.
+pub struct InterpolationPlugin;
+
+impl Plugin for InterpolationPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InterpolationAlpha>()
+            .add_system_to_stage(CoreStage::First, store_previous_transforms)
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                update_interpolation_alpha.before(interpolate_render_transforms),
+            )
+            .add_system_to_stage(
+                CoreStage::PostUpdate,
+                interpolate_render_transforms.after(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// The label of the fixed timestep that the simulation steps on, if it runs on one
+pub const SIMULATION_TIMESTEP: &str = "simulation";
+
+/// How far, from `0.0` to `1.0`, rendering is between the previous and the current simulation step.
+///
+/// This is set every frame to the fraction of a step left over after the last step of the
+/// [`SIMULATION_TIMESTEP`]. Without that fixed timestep every frame is a full simulation step, so
+/// this stays at `1.0`.
+#[derive(Resource, Deref, DerefMut)]
+pub struct InterpolationAlpha(pub f32);
+
+impl Default for InterpolationAlpha {
+    fn default() -> Self {
+        Self(1.0)
+    }
+}
+
+/// The simulation [`Transform`] of an entity at the end of the previous step.
+///
+/// Must only be added to entities without a parent.
+#[derive(Component, Deref, DerefMut, Default, Clone, Copy)]
+pub struct PreviousTransform(pub Transform);
+
+impl PreviousTransform {
+    /// Interpolate between the previous and the `current` transform
+    pub fn interpolate(&self, current: &Transform, alpha: f32) -> Transform {
+        Transform {
+            translation: self.translation.lerp(current.translation, alpha),
+            rotation: self.rotation.slerp(current.rotation, alpha),
+            scale: self.scale.lerp(current.scale, alpha),
+        }
+    }
+}
+
+/// Set the [`InterpolationAlpha`] to the overstep of the [`SIMULATION_TIMESTEP`]
+fn update_interpolation_alpha(
+    timesteps: Option<Res<FixedTimesteps>>,
+    mut alpha: ResMut<InterpolationAlpha>,
+) {
+    let overstep = timesteps
+        .as_ref()
+        .and_then(|timesteps| timesteps.get(SIMULATION_TIMESTEP))
+        .map_or(1.0, |timestep| timestep.overstep_percentage() as f32);
+
+    **alpha = overstep.clamp(0.0, 1.0);
+}
+
+/// Store the simulation transforms before the next step, and reset the rendered transforms to them
+/// so that the interpolation of the last frame is never propagated to children.
+fn store_previous_transforms(
+    mut entities: Query<
+        (&mut PreviousTransform, &Transform, &mut GlobalTransform),
+        Without<Parent>,
+    >,
+) {
+    for (mut previous, transform, mut global_transform) in &mut entities {
+        **previous = *transform;
+        *global_transform = GlobalTransform::from(*transform);
+    }
+}
+
+/// Interpolate the rendered transforms, after they have been propagated to the children.
+///
+/// Attached sprites are moved along with their parent so they stay in sync.
+fn interpolate_render_transforms(
+    alpha: Res<InterpolationAlpha>,
+    mut entities: Query<
+        (
+            &PreviousTransform,
+            &Transform,
+            &mut GlobalTransform,
+            Option<&Children>,
+        ),
+        Without<Parent>,
+    >,
+    mut attached: Query<&mut GlobalTransform, (With<Attached>, Without<PreviousTransform>)>,
+) {
+    for (previous, transform, mut global_transform, children) in &mut entities {
+        let interpolated = previous.interpolate(transform, **alpha);
+        *global_transform = GlobalTransform::from(interpolated);
+
+        let offset =
+            GlobalTransform::from_translation(interpolated.translation - transform.translation);
+        for child in children.into_iter().flatten() {
+            if let Ok(mut child_transform) = attached.get_mut(*child) {
+                *child_transform = offset * *child_transform;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::{time::fixed_timestep::FixedTimestep, utils::Duration};
+
+    use super::*;
+
+    #[test]
+    fn test_interpolated_transform_is_between_steps() {
+        let previous = PreviousTransform(Transform::from_xyz(0., 10., 0.));
+        let current = Transform::from_xyz(100., 20., 0.);
+
+        let interpolated = previous.interpolate(&current, 0.5);
+        assert!(interpolated.translation.x > 0. && interpolated.translation.x < 100.);
+        assert!(interpolated.translation.y > 10. && interpolated.translation.y < 20.);
+        assert_eq!(interpolated.translation, Vec3::new(50., 15., 0.));
+
+        assert_eq!(
+            previous.interpolate(&current, 1.0).translation,
+            current.translation
+        );
+    }
+
+    #[test]
+    fn test_interpolation_does_not_move_simulation_transform() {
+        let mut world = World::new();
+        world.insert_resource(InterpolationAlpha(0.5));
+        let entity = world
+            .spawn((
+                PreviousTransform(Transform::from_xyz(0., 0., 0.)),
+                TransformBundle::from_transform(Transform::from_xyz(100., 0., 0.)),
+            ))
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(interpolate_render_transforms);
+        stage.run(&mut world);
+
+        assert_eq!(world.get::<Transform>(entity).unwrap().translation.x, 100.);
+        assert_eq!(
+            world
+                .get::<GlobalTransform>(entity)
+                .unwrap()
+                .translation()
+                .x,
+            50.
+        );
+    }
+
+    #[test]
+    fn test_rendered_transform_is_between_steps_of_the_simulation_timestep() {
+        let mut world = World::new();
+        world.init_resource::<InterpolationAlpha>();
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+        let entity = world
+            .spawn((
+                PreviousTransform(Transform::from_xyz(0., 0., 0.)),
+                TransformBundle::from_transform(Transform::from_xyz(100., 0., 0.)),
+            ))
+            .id();
+
+        fn simulation_step() {}
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(
+                simulation_step
+                    .with_run_criteria(FixedTimestep::step(0.04).with_label(SIMULATION_TIMESTEP)),
+            )
+            .add_system(update_interpolation_alpha.after(simulation_step))
+            .add_system(interpolate_render_transforms.after(update_interpolation_alpha));
+
+        // A frame of two and a half steps leaves half a step over
+        let mut time = world.resource_mut::<Time>();
+        let last_update = time.last_update().unwrap();
+        time.update_with_instant(last_update + Duration::from_secs_f32(0.1));
+        stage.run(&mut world);
+
+        let alpha = **world.resource::<InterpolationAlpha>();
+        assert!((alpha - 0.5).abs() < 0.01, "alpha {alpha}");
+        let x = world
+            .get::<GlobalTransform>(entity)
+            .unwrap()
+            .translation()
+            .x;
+        assert!(x > 0. && x < 100.);
+        assert!((x - 50.).abs() < 1., "x {x}");
+        assert_eq!(world.get::<Transform>(entity).unwrap().translation.x, 100.);
+    }
+}
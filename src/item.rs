@@ -1,17 +1,26 @@
 use bevy::{ecs::system::EntityCommands, prelude::*};
 use bevy_mod_js_scripting::{ActiveScripts, JsScript};
 use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
 use rand::Rng;
 
 use crate::{
     animation::{AnimatedSpriteSheetBundle, Animation, Facing},
     attack::{Attack, AttackFrames, Breakable, BrokeEvent},
-    collision::{BodyLayers, PhysicsBundle},
+    camera::{CameraShake, CameraTilt, SortLayer, YSort},
+    collision::{BodyLayers, PhysicsBundle, Team},
     consts,
     fighter::Inventory,
     lifetime::{Lifetime, LifetimeExpired},
-    metadata::{AttackMeta, ItemKind, ItemMeta, ItemSpawnMeta},
-    movement::{AngularVelocity, Force, LinearVelocity},
+    metadata::{
+        AttackMeta, GameMeta, HitReaction, ItemKind, ItemMeta, ItemSpawnMeta, LevelBoundsMeta,
+        LevelMeta,
+    },
+    movement::AngularVelocity,
+    player::{LivingPlayer, Player},
+    projectile::{Projectile, ProjectileBundle},
+    utils::GameTime,
+    GameState,
 };
 
 pub struct ItemPlugin;
@@ -19,7 +28,9 @@ pub struct ItemPlugin;
 impl Plugin for ItemPlugin {
     fn build(&self, app: &mut App) {
         app.add_system(drop_system)
+            .add_system(blink_dropped_items)
             .add_system(explodable_system)
+            .add_system(attract_loot.run_in_state(GameState::InGame))
             .add_event::<ScriptItemThrowEvent>()
             .add_event::<ScriptItemGrabEvent>();
     }
@@ -43,11 +54,44 @@ pub struct Item {
     pub spawn_sprite: bool,
 }
 
+/// Spawns the items placed in a level
+pub fn spawn_items(
+    commands: &mut Commands,
+    items: &[ItemSpawnMeta],
+    bounds: &LevelBoundsMeta,
+    items_assets: &mut ResMut<Assets<ItemMeta>>,
+    active_scripts: &mut ActiveScripts,
+) {
+    for item_spawn_meta in items {
+        let mut item_spawn_meta = item_spawn_meta.clone();
+
+        // Keep the item on the ground plane, where the fighters can reach it
+        let location = &mut item_spawn_meta.location;
+        let ground_y = location.y.clamp(bounds.bottom, bounds.top);
+        if ground_y != location.y {
+            warn!(
+                "Item `{}` placed off the ground at y = {}, moving it to the ground",
+                item_spawn_meta.item, location.y
+            );
+            location.y = ground_y;
+        }
+
+        let item_commands = commands.spawn(ItemBundle::new(&item_spawn_meta));
+        ItemBundle::spawn(
+            item_commands,
+            &item_spawn_meta,
+            items_assets,
+            active_scripts,
+        );
+    }
+}
+
 #[derive(Bundle)]
 pub struct ItemBundle {
     pub item: Item,
     pub item_meta_handle: Handle<ItemMeta>,
     pub name: Name,
+    pub ysort: YSort,
 }
 
 impl ItemBundle {
@@ -57,6 +101,7 @@ impl ItemBundle {
             item_meta_handle: item_spawn_meta.item_handle.clone(),
             // TODO: Actually include the item's name at some point
             name: Name::new("Map Item"),
+            ysort: YSort(SortLayer::GroundItems),
         }
     }
 
@@ -66,10 +111,11 @@ impl ItemBundle {
         items_assets: &mut ResMut<Assets<ItemMeta>>,
         active_scripts: &mut ActiveScripts,
     ) {
-        let ground_offset = Vec3::new(0.0, consts::GROUND_Y, consts::ITEM_LAYER);
-        let transform_bundle = TransformBundle::from_transform(Transform::from_translation(
-            item_spawn_meta.location + ground_offset,
-        ));
+        let ground_offset = Vec3::new(0.0, consts::GROUND_Y, 0.0);
+        let translation = item_spawn_meta.location + ground_offset;
+
+        let transform_bundle =
+            TransformBundle::from_transform(Transform::from_translation(translation));
 
         commands.insert(transform_bundle);
 
@@ -105,29 +151,28 @@ impl ItemBundle {
     }
 }
 
+/// An item thrown by a fighter, flying as a [`Projectile`]
 #[derive(Bundle)]
-pub struct Projectile {
+pub struct ThrownItemBundle {
     #[bundle]
     sprite_bundle: SpriteBundle,
-    velocity: LinearVelocity,
     angular_velocity: AngularVelocity,
-    force: Force,
-    collider: Collider,
-    sensor: Sensor,
-    events: ActiveEvents,
-    collision_types: ActiveCollisionTypes,
-    collision_groups: CollisionGroups,
-    attack: Attack,
-    lifetime: Lifetime,
-    breakable: Breakable,
+    #[bundle]
+    projectile_bundle: ProjectileBundle,
 }
 
-impl Projectile {
-    pub fn from_thrown_item(
+impl ThrownItemBundle {
+    /// Create a thrown item, tilting the item's throw velocity up by `aim_angle` radians, or down
+    /// for negative angles.
+    ///
+    /// Thrown weapons deal a portion of their attack damage. Add a [`Drop`] to have the thrown item
+    /// land as an item that can be grabbed again.
+    pub fn new(
         translation: Vec3,
         item_meta: &ItemMeta,
         facing: &Facing,
-        enemy: bool,
+        aim_angle: f32,
+        team: Team,
     ) -> Self {
         let direction_mul = if facing.is_left() {
             Vec2::new(-1.0, 1.0)
@@ -161,50 +206,114 @@ impl Projectile {
                 pushback,
                 hitstun_duration,
             )),
+            crate::metadata::ItemKind::MeleeWeapon { ref attack, .. }
+            | crate::metadata::ItemKind::ProjectileWeapon { ref attack, .. } => Some((
+                (attack.damage as f32 * consts::WEAPON_THROW_DAMAGE_MULTIPLIER) as i32,
+                consts::WEAPON_THROW_GRAVITY,
+                consts::WEAPON_THROW_VELOCITY,
+                consts::WEAPON_THROW_LIFETIME,
+                consts::WEAPON_THROW_PUSHBACK,
+                attack.hitstun_duration,
+            )),
             _ => None,
         }
         .expect("Non throwable item");
 
-        Self {
-            sprite_bundle: SpriteBundle {
-                texture: item_meta.image.image_handle.clone(),
-                transform: Transform::from_xyz(translation.x, translation.y, consts::PROJECTILE_Z),
-                ..default()
-            },
-            attack: Attack {
+        let projectile = Projectile::new(
+            Vec2::from_angle(aim_angle).rotate(item_vars.2) * direction_mul,
+            item_vars.3,
+            Attack {
                 damage: item_vars.0,
                 pushback: Vec2::new(item_vars.4, 0.0) * direction_mul,
                 hitstun_duration: item_vars.5,
                 hitbox_meta: None,
+                clash_priority: None,
+                launch: None,
+                antiair_bonus: None,
+                pull: None,
+                burn: None,
+                confuse: None,
+                reaction: HitReaction::Knockback,
+            },
+        )
+        .with_gravity(item_vars.1);
+
+        Self {
+            sprite_bundle: SpriteBundle {
+                texture: item_meta.image.image_handle.clone(),
+                transform: Transform::from_xyz(translation.x, translation.y, 0.),
+                ..default()
             },
-            velocity: LinearVelocity(item_vars.2 * direction_mul),
-            // Gravity
-            force: Force(Vec2::new(0.0, -item_vars.1)),
             angular_velocity: AngularVelocity(consts::THROW_ITEM_ROTATION_SPEED * direction_mul.x),
-            collider: Collider::cuboid(consts::ITEM_WIDTH / 2., consts::ITEM_HEIGHT / 2.),
-            sensor: Sensor,
-            events: ActiveEvents::COLLISION_EVENTS,
-            collision_types: ActiveCollisionTypes::default() | ActiveCollisionTypes::STATIC_STATIC,
-            //TODO: define collision layer based on the fighter shooting projectile, load for asset
-            //files of fighter which "team" they are on
-            collision_groups: CollisionGroups::new(
-                if enemy {
-                    BodyLayers::ENEMY_ATTACK
-                } else {
-                    BodyLayers::PLAYER_ATTACK
-                },
-                if enemy {
-                    BodyLayers::PLAYER
-                } else {
-                    BodyLayers::ENEMY | BodyLayers::BREAKABLE_ITEM
-                },
+            projectile_bundle: ProjectileBundle::new(
+                projectile,
+                Collider::cuboid(consts::ITEM_WIDTH / 2., consts::ITEM_HEIGHT / 2.),
+                team,
+                // Thrown items only hit the hostile teams
+                false,
             ),
-            lifetime: Lifetime(Timer::from_seconds(item_vars.3, TimerMode::Once)),
-            breakable: Breakable::new(0, false),
         }
     }
 }
 
+/// Get the angle, in radians, to tilt a throw with the given `throw_velocity` by when aiming it up
+/// or down with the movement axis.
+///
+/// Throws go straight ahead unless aimed. An aimed throw locks on to the closest enemy in front of
+/// the fighter, that is within range and in the aimed direction, and is tilted so that its arc
+/// under `gravity` passes through the enemy.
+pub fn throw_aim_angle(
+    aim_y: f32,
+    position: Vec2,
+    facing: &Facing,
+    throw_velocity: Vec2,
+    gravity: f32,
+    enemies: impl Iterator<Item = Vec2>,
+) -> f32 {
+    if aim_y.abs() < consts::THROW_AIM_DEADZONE {
+        return 0.0;
+    }
+    let aim_angle = consts::THROW_AIM_ANGLE.copysign(aim_y);
+
+    let direction_x = if facing.is_left() { -1.0 } else { 1.0 };
+    enemies
+        .filter_map(|enemy| {
+            // The offset to the enemy, with x going forward
+            let offset = (enemy - position) * Vec2::new(direction_x, 1.0);
+            let distance = offset.length();
+            let angle = offset.y.atan2(offset.x);
+
+            let in_aim = angle * aim_angle >= 0.0 && angle.abs() <= consts::THROW_AIM_ANGLE;
+            (offset.x > 0.0 && distance <= consts::THROW_LOCK_ON_RANGE && in_aim)
+                .then_some((distance, offset))
+        })
+        .min_by(|(a, _), (b, _)| a.total_cmp(b))
+        .map(|(_, offset)| {
+            launch_angle(throw_velocity.length(), gravity, offset)
+                - throw_velocity.y.atan2(throw_velocity.x)
+        })
+        .unwrap_or(aim_angle)
+}
+
+/// Get the lowest angle, in radians, to launch a projectile at `speed` so that its arc under
+/// `gravity` passes through the `target` offset ahead of it.
+///
+/// Targets out of reach get the angle that throws the farthest.
+pub fn launch_angle(speed: f32, gravity: f32, target: Vec2) -> f32 {
+    if gravity <= 0.0 {
+        return target.y.atan2(target.x);
+    }
+
+    let speed_squared = speed * speed;
+    let discriminant = speed_squared * speed_squared
+        - gravity * (gravity * target.x * target.x + 2.0 * target.y * speed_squared);
+    if discriminant < 0.0 {
+        return std::f32::consts::FRAC_PI_4;
+    }
+
+    ((speed_squared - discriminant.sqrt()) / (gravity * target.x)).atan()
+}
+
 /// A component that with Breakable, drops a item when broke.
 #[derive(Component, Clone)]
 pub struct Drop {
@@ -212,12 +321,18 @@ pub struct Drop {
     pub item: ItemMeta,
 }
 
+/// Marker component for a weapon dropped by a fighter, which disappears after the game's
+/// `dropped_item_lifetime` unless it is grabbed again
+#[derive(Component)]
+pub struct DroppedItem;
+
 fn drop_system(
     mut items_assets: ResMut<Assets<ItemMeta>>,
     mut commands: Commands,
     mut broke_event: EventReader<BrokeEvent>,
     mut lifetime_event: EventReader<LifetimeExpired>,
     mut active_scripts: ResMut<ActiveScripts>,
+    game: Option<Res<GameMeta>>,
 ) {
     let mut drops = vec![];
     for event in lifetime_event.iter() {
@@ -231,15 +346,31 @@ fn drop_system(
         }
     }
 
+    let dropped_item_lifetime = game.and_then(|game| game.dropped_item_lifetime);
     for (drop, transform) in drops {
-        let ground_offset = Vec3::new(0.0, consts::GROUND_Y, consts::ITEM_LAYER);
+        let is_weapon = matches!(
+            drop.item.kind,
+            ItemKind::MeleeWeapon { .. } | ItemKind::ProjectileWeapon { .. }
+        );
+
+        let ground_offset = Vec3::new(0.0, consts::GROUND_Y, 0.0);
 
         let item_spawn_meta = ItemSpawnMeta {
             location: transform.translation - ground_offset,
             item: String::new(),
             item_handle: items_assets.add(drop.item.clone()),
         };
-        let item_commands = commands.spawn(ItemBundle::new(&item_spawn_meta));
+        let mut item_commands = commands.spawn(ItemBundle::new(&item_spawn_meta));
+
+        // Don't let the weapons dropped in long fights clutter the level. Grabbing them despawns
+        // them, along with their lifetime.
+        if let (true, Some(seconds)) = (is_weapon, dropped_item_lifetime) {
+            item_commands.insert((
+                DroppedItem,
+                Lifetime(Timer::from_seconds(seconds, TimerMode::Once)),
+            ));
+        }
+
         ItemBundle::spawn(
             item_commands,
             &item_spawn_meta,
@@ -249,7 +380,59 @@ fn drop_system(
     }
 }
 
-/// A component that with Breakable, explodes.
+/// Makes dropped weapons blink as a warning before they disappear
+fn blink_dropped_items(mut items: Query<(&Lifetime, &mut Visibility), With<DroppedItem>>) {
+    for (lifetime, mut visibility) in &mut items {
+        let remaining = lifetime.remaining_secs();
+        visibility.is_visible = remaining > consts::DROPPED_ITEM_BLINK_DURATION
+            || (remaining / consts::DROPPED_ITEM_BLINK_INTERVAL) as u32 % 2 == 0;
+    }
+}
+
+/// Draws the items on the ground toward the nearest living player within the loot magnet's radius
+fn attract_loot(
+    mut items: Query<&mut Transform, (With<Item>, Without<Breakable>, Without<Player>)>,
+    players: Query<&Transform, LivingPlayer>,
+    game: Res<GameMeta>,
+    level_meta: Res<LevelMeta>,
+    time: GameTime,
+) {
+    let magnet = match game.loot_magnet {
+        Some(magnet) => magnet,
+        None => return,
+    };
+
+    for mut transform in &mut items {
+        let location = transform.translation.truncate();
+        let nearest_player = players
+            .iter()
+            .map(|player| player.translation.truncate())
+            .filter(|player| player.distance(location) <= magnet.radius)
+            .min_by(|a, b| a.distance(location).total_cmp(&b.distance(location)));
+
+        if let Some(player) = nearest_player {
+            // Stop at the player instead of overshooting it
+            let step = magnet.strength * time.delta_seconds();
+            let mut new_location = if player.distance(location) <= step {
+                player
+            } else {
+                location + (player - location).normalize() * step
+            };
+
+            // Keep the item on the ground and in the level
+            new_location.y = new_location
+                .y
+                .clamp(level_meta.bounds.min_y(), level_meta.bounds.max_y());
+            if let Some(right) = level_meta.bounds.right {
+                new_location.x = new_location.x.min(right);
+            }
+
+            transform.translation = new_location.extend(transform.translation.z);
+        }
+    }
+}
+
+/// A component that with Breakable, or on a thrown bomb's [`Projectile`], explodes.
 #[derive(Component, Clone)]
 pub struct Explodable {
     pub attack: AttackMeta,
@@ -260,13 +443,11 @@ pub struct Explodable {
     pub attack_enemy: bool,
 }
 
-fn explodable_system(
+pub(crate) fn explodable_system(
     mut commands: Commands,
     mut broke_event: EventReader<BrokeEvent>,
     mut explodables: Query<(
         &mut Explodable,
-        &mut Force,
-        &mut LinearVelocity,
         &mut AngularVelocity,
         &mut Transform,
         &GlobalTransform,
@@ -274,8 +455,10 @@ fn explodable_system(
         Entity,
         Option<&Parent>,
     )>,
-    time: Res<Time>,
+    time: GameTime,
     mut inventory: Query<&mut Inventory>,
+    mut camera_shake: ResMut<CameraShake>,
+    mut camera_tilt: ResMut<CameraTilt>,
 ) {
     let mut explosions = Vec::new();
 
@@ -285,32 +468,24 @@ fn explodable_system(
         }
     }
 
-    for (
-        mut explodable,
-        mut force,
-        mut velocity,
-        mut ang_vel,
-        mut transform,
-        g_transform,
-        mut animation,
-        entity,
-        parent,
-    ) in &mut explodables
+    for (mut explodable, mut ang_vel, mut transform, g_transform, mut animation, entity, parent) in
+        &mut explodables
     {
         explodable.timer.tick(time.delta());
 
         if !explodable.fusing && explodable.timer.finished() {
             // Stop bomb and start fusing
-            force.0 = Vec2::ZERO;
-            velocity.0 = Vec2::ZERO;
             ang_vel.0 = 0.;
             transform.rotation.z = 0.;
 
             animation.play("bomb_fuse", false);
             explodable.fusing = true;
 
-            //Remove explosion on contact
-            commands.entity(entity).remove::<Collider>();
+            //Remove explosion on contact, and stop the bomb where it is
+            commands
+                .entity(entity)
+                .remove::<Collider>()
+                .remove::<Projectile>();
         } else if animation.is_finished() && explodable.fusing {
             explosions.push((g_transform.compute_transform(), explodable.clone()));
 
@@ -325,12 +500,22 @@ fn explodable_system(
     }
 
     for (transform, explodable) in explosions {
+        camera_shake.add_trauma(consts::EXPLOSION_CAMERA_SHAKE);
+        camera_tilt.add_tilt(consts::EXPLOSION_CAMERA_TILT);
+
         // Spawn explosion
         let mut animated_sprite = explodable.animated_sprite.clone();
         animated_sprite.sprite_sheet.transform = transform;
         animated_sprite.sprite_sheet.transform.rotation.z = 0.;
         animated_sprite.animation.play("explosion", false);
 
+        // Explosions set off by the players hit everyone, the players included
+        let explosion_team = if explodable.attack_enemy {
+            Team::Players
+        } else {
+            Team::Enemies
+        };
+
         let attack = explodable.attack.clone();
         let seconds = animated_sprite
             .animation
@@ -345,23 +530,19 @@ fn explodable_system(
                 Sensor,
                 ActiveEvents::COLLISION_EVENTS,
                 ActiveCollisionTypes::default() | ActiveCollisionTypes::STATIC_STATIC,
-                CollisionGroups::new(
-                    if explodable.attack_enemy {
-                        BodyLayers::PLAYER_ATTACK
-                    } else {
-                        BodyLayers::ENEMY_ATTACK
-                    },
-                    if explodable.attack_enemy {
-                        BodyLayers::PLAYER | BodyLayers::ENEMY | BodyLayers::BREAKABLE_ITEM
-                    } else {
-                        BodyLayers::PLAYER
-                    },
-                ),
+                explosion_team.attack_groups(explodable.attack_enemy, false),
                 Attack {
                     damage: attack.damage,
                     pushback: attack.velocity.unwrap_or(Vec2::ZERO),
                     hitstun_duration: attack.hitstun_duration,
                     hitbox_meta: Some(explodable.attack.hitbox),
+                    clash_priority: None,
+                    launch: None,
+                    antiair_bonus: None,
+                    pull: None,
+                    burn: attack.burn,
+                    confuse: attack.confuse,
+                    reaction: attack.reaction,
                 },
                 explodable.explosion_frames,
                 transform,
@@ -376,68 +557,521 @@ fn explodable_system(
     }
 }
 
+/// A bomb thrown by a boss, flying as a [`Projectile`] until it hits something or its fuse starts
 #[derive(Bundle)]
-pub struct AnimatedProjectile {
+pub struct ThrownBombBundle {
     #[bundle]
     sprite_bundle: AnimatedSpriteSheetBundle,
-    velocity: LinearVelocity,
     angular_velocity: AngularVelocity,
-    force: Force,
-    collider: Collider,
-    sensor: Sensor,
-    events: ActiveEvents,
-    collision_types: ActiveCollisionTypes,
-    collision_groups: CollisionGroups,
-    attack: Attack,
-    breakable: Breakable,
+    #[bundle]
+    projectile_bundle: ProjectileBundle,
 }
 
-impl AnimatedProjectile {
+impl ThrownBombBundle {
+    /// Create a thrown bomb, varying its throw velocity and spin by up to 20% with `rng`.
+    ///
+    /// Add an [`Explodable`] to have the bomb explode when it hits something, or once it has
+    /// stopped and its fuse has burnt down.
     pub fn new(
         item_meta: &ItemMeta,
         facing: &Facing,
         animated_sprite: AnimatedSpriteSheetBundle,
+        team: Team,
+        rng: &mut impl Rng,
     ) -> Self {
+        let (projectile, angular_velocity) = Self::projectile(item_meta, facing, 0.0, rng);
+
+        Self {
+            sprite_bundle: animated_sprite,
+            angular_velocity,
+            projectile_bundle: ProjectileBundle::new(
+                projectile,
+                Collider::cuboid(consts::ITEM_WIDTH / 2., consts::ITEM_HEIGHT / 2.),
+                team,
+                // Bombs only hit the hostile teams
+                false,
+            ),
+        }
+    }
+
+    /// Get the projectile and the spin of a bomb thrown toward `facing`, tilting its throw velocity
+    /// up by `aim_angle` radians, or down for negative angles, and varying its speed and spin by up
+    /// to 20% with `rng`.
+    pub fn projectile(
+        item_meta: &ItemMeta,
+        facing: &Facing,
+        aim_angle: f32,
+        rng: &mut impl Rng,
+    ) -> (Projectile, AngularVelocity) {
         let direction_mul = if facing.is_left() {
             Vec2::new(-1.0, 1.0)
         } else {
             Vec2::ONE
         };
-        let mut rng = rand::thread_rng();
 
         let item_vars = match item_meta.kind {
             crate::metadata::ItemKind::Bomb {
                 damage,
                 gravity,
                 throw_velocity,
+                lifetime,
                 ..
-            } => Some((damage, gravity, throw_velocity)),
+            } => Some((damage, gravity, throw_velocity, lifetime)),
             _ => None,
         }
         .expect("Non bomb");
 
-        Self {
-            sprite_bundle: animated_sprite,
-            attack: Attack {
+        let projectile = Projectile::new(
+            Vec2::from_angle(aim_angle).rotate(item_vars.2)
+                * direction_mul
+                * rng.gen_range(0.8..1.2),
+            item_vars.3,
+            Attack {
                 damage: item_vars.0,
                 pushback: Vec2::new(consts::ITEM_ATTACK_VELOCITY, 0.0) * direction_mul,
                 hitstun_duration: consts::HITSTUN_DURATION,
                 hitbox_meta: None,
+                clash_priority: None,
+                launch: None,
+                antiair_bonus: None,
+                pull: None,
+                burn: None,
+                confuse: None,
+                reaction: HitReaction::Knockback,
+            },
+        )
+        .with_gravity(item_vars.1);
+        let angular_velocity = AngularVelocity(
+            consts::THROW_ITEM_ROTATION_SPEED * direction_mul.x * rng.gen_range(0.8..1.2),
+        );
+
+        (projectile, angular_velocity)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::{
+        fighter_state::{grabbing, Grabbing, StateTransitionIntents},
+        lifetime::LifetimePlugin,
+        metadata::LootMagnetMeta,
+        projectile::projectile_system,
+        utils::GameRng,
+    };
+
+    const BOTTLE_YAML: &str = "
+name: Bottle
+image:
+  image: bottle.png
+  image_size: [11, 31]
+kind: !Throwable
+  damage: 10
+  gravity: 1200
+  throw_velocity: [200, 300]
+  lifetime: 0.64
+  pushback: 80
+  hitstun_duration: 0.5
+";
+
+    #[test]
+    fn test_level_items_are_spawned_grabbable() {
+        let mut app = App::new();
+        app.init_resource::<Assets<ItemMeta>>()
+            .init_resource::<ActiveScripts>();
+
+        let item_handle = app
+            .world
+            .resource_mut::<Assets<ItemMeta>>()
+            .add(serde_yaml::from_str(BOTTLE_YAML).unwrap());
+        let item = |location: Vec3| ItemSpawnMeta {
+            item: "/items/bottle/bottle.item.yaml".into(),
+            item_handle: item_handle.clone(),
+            location,
+        };
+        // The second item is placed below the ground
+        let items = vec![
+            item(Vec3::new(50., -70., 0.)),
+            item(Vec3::new(100., -500., 0.)),
+        ];
+
+        app.add_startup_system(
+            move |mut commands: Commands,
+                  mut items_assets: ResMut<Assets<ItemMeta>>,
+                  mut active_scripts: ResMut<ActiveScripts>| {
+                spawn_items(
+                    &mut commands,
+                    &items,
+                    &default(),
+                    &mut items_assets,
+                    &mut active_scripts,
+                )
             },
-            velocity: LinearVelocity(item_vars.2 * direction_mul * rng.gen_range(0.8..1.2)),
-            // Gravity
-            force: Force(Vec2::new(0.0, -item_vars.1)),
-            angular_velocity: AngularVelocity(
-                consts::THROW_ITEM_ROTATION_SPEED * direction_mul.x * rng.gen_range(0.8..1.2),
+        );
+        app.update();
+
+        let items_assets = app.world.resource::<Assets<ItemMeta>>();
+        let mut spawned = app
+            .world
+            .query_filtered::<(&Transform, &Handle<ItemMeta>), With<Item>>();
+        let spawned = spawned.iter(&app.world).collect::<Vec<_>>();
+        assert_eq!(spawned.len(), 2);
+
+        for (transform, handle) in spawned {
+            // On the ground, where a fighter can stand to pick it up
+            assert!((consts::MIN_Y..=consts::MAX_Y).contains(&transform.translation.y));
+            assert!(matches!(
+                items_assets.get(handle).unwrap().kind,
+                ItemKind::Throwable { .. }
+            ));
+        }
+    }
+
+    #[test]
+    fn test_throw_aimed_up_flies_upward() {
+        let item: ItemMeta = serde_yaml::from_str(BOTTLE_YAML).unwrap();
+        let mut world = World::new();
+        let (throw_velocity, gravity) = item.kind.throw_motion().unwrap();
+        let mut throw = |aim_y: f32, enemies: Vec<Vec2>| {
+            let aim_angle = throw_aim_angle(
+                aim_y,
+                Vec2::ZERO,
+                &Facing::Right,
+                throw_velocity,
+                gravity,
+                enemies.into_iter(),
+            );
+            let thrown = world
+                .spawn(ThrownItemBundle::new(
+                    Vec3::ZERO,
+                    &item,
+                    &Facing::Right,
+                    aim_angle,
+                    Team::Players,
+                ))
+                .id();
+            world.get::<Projectile>(thrown).unwrap().velocity
+        };
+
+        // Throws go straight ahead by default
+        let straight = throw(0.0, vec![]);
+        assert_eq!(straight, Vec2::new(200., 300.));
+
+        // Holding up tilts the throw upward, and down tilts it downward
+        let up = throw(1.0, vec![]);
+        assert!(up.y > 0. && up.y > straight.y);
+        assert!((up.length() - straight.length()).abs() < 0.01);
+        assert!(throw(-1.0, vec![]).y < straight.y);
+
+        // Aimed throws lock on to an enemy in the aimed direction, landing on it
+        let enemy = Vec2::new(80., 10.);
+        let mut projectile =
+            Projectile::new(throw(1.0, vec![enemy]), 1.0, default()).with_gravity(gravity);
+        let mut transform = Transform::default();
+        while transform.translation.x < enemy.x {
+            projectile.advance(&mut transform, Duration::from_secs_f32(0.001));
+        }
+        assert!(
+            (transform.translation.y - enemy.y).abs() < 1.,
+            "landed at {}",
+            transform.translation
+        );
+
+        // But not on enemies behind the fighter
+        assert_eq!(
+            throw_aim_angle(
+                1.0,
+                Vec2::ZERO,
+                &Facing::Left,
+                throw_velocity,
+                gravity,
+                [enemy].into_iter()
             ),
-            collider: Collider::cuboid(consts::ITEM_WIDTH / 2., consts::ITEM_HEIGHT / 2.),
-            sensor: Sensor,
-            events: ActiveEvents::COLLISION_EVENTS,
-            collision_types: ActiveCollisionTypes::default() | ActiveCollisionTypes::STATIC_STATIC,
-            //TODO: define collision layer based on the fighter shooting projectile, load for asset
-            //files of fighter which "team" they are on
-            collision_groups: CollisionGroups::new(BodyLayers::ENEMY_ATTACK, BodyLayers::PLAYER),
-            breakable: Breakable::new(0, false),
+            consts::THROW_AIM_ANGLE
+        );
+    }
+
+    #[test]
+    fn test_thrown_melee_weapon_damages_and_lands_grabbable() {
+        let sword: ItemMeta =
+            serde_yaml::from_str(include_str!("../assets/items/sword/sword.item.yaml")).unwrap();
+        let mut app = App::new();
+        let mut time = Time::default();
+        time.update();
+        app.insert_resource(time)
+            .init_resource::<Assets<ItemMeta>>()
+            .init_resource::<ActiveScripts>()
+            .add_event::<CollisionEvent>()
+            .add_event::<BrokeEvent>()
+            .add_event::<LifetimeExpired>()
+            .add_system(projectile_system)
+            .add_system(drop_system.after(projectile_system));
+        let step = |app: &mut App, seconds: f32| {
+            let mut time = app.world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(seconds));
+            app.update();
+        };
+
+        let thrown = app
+            .world
+            .spawn(ThrownItemBundle::new(
+                Vec3::new(0., -100., 0.),
+                &sword,
+                &Facing::Right,
+                0.0,
+                Team::Players,
+            ))
+            .insert(Drop {
+                item: sword.clone(),
+            })
+            .id();
+
+        // The weapon flies forward, dealing part of its attack damage
+        step(&mut app, 0.2);
+        let projectile = app.world.get::<Projectile>(thrown).unwrap();
+        assert_eq!(projectile.attack.damage, 25);
+        assert!(app.world.get::<Transform>(thrown).unwrap().translation.x > 0.);
+        let mut items = app.world.query_filtered::<&Handle<ItemMeta>, With<Item>>();
+        assert_eq!(items.iter(&app.world).count(), 0);
+
+        // And lands as a weapon that can be grabbed again
+        step(&mut app, consts::WEAPON_THROW_LIFETIME);
+        assert!(app.world.get_entity(thrown).is_none());
+        let handles = items.iter(&app.world).cloned().collect::<Vec<_>>();
+        assert_eq!(handles.len(), 1);
+        assert!(matches!(
+            app.world
+                .resource::<Assets<ItemMeta>>()
+                .get(&handles[0])
+                .unwrap()
+                .kind,
+            ItemKind::MeleeWeapon { .. }
+        ));
+    }
+
+    #[test]
+    fn test_thrown_bomb_flies_then_stops_and_fuses() {
+        let bomb: ItemMeta =
+            serde_yaml::from_str(include_str!("../assets/items/bomb/bomb.item.yaml")).unwrap();
+        let (spritesheet, attack_frames, lifetime) = match &bomb.kind {
+            ItemKind::Bomb {
+                spritesheet,
+                attack_frames,
+                lifetime,
+                ..
+            } => (spritesheet, *attack_frames, *lifetime),
+            _ => unreachable!(),
+        };
+        let mut animated_sprite = AnimatedSpriteSheetBundle {
+            sprite_sheet: default(),
+            animation: Animation::new(spritesheet.animation_fps, spritesheet.animations.clone()),
+        };
+        animated_sprite.animation.play("bomb", false);
+
+        let mut app = App::new();
+        let mut time = Time::default();
+        time.update();
+        app.insert_resource(time)
+            .init_resource::<CameraShake>()
+            .init_resource::<CameraTilt>()
+            .add_event::<CollisionEvent>()
+            .add_event::<BrokeEvent>()
+            .add_event::<LifetimeExpired>()
+            .add_system(projectile_system)
+            .add_system(explodable_system);
+        let step = |app: &mut App, seconds: f32| {
+            let mut time = app.world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(seconds));
+            app.update();
+        };
+
+        let thrown = app
+            .world
+            .spawn(ThrownBombBundle::new(
+                &bomb,
+                &Facing::Right,
+                animated_sprite.clone(),
+                Team::Enemies,
+                &mut GameRng::new(0),
+            ))
+            .insert(Explodable {
+                attack: default(),
+                timer: Timer::from_seconds(lifetime, TimerMode::Once),
+                fusing: false,
+                animated_sprite,
+                explosion_frames: attack_frames,
+                attack_enemy: false,
+            })
+            .id();
+
+        // The bomb flies forward as a projectile
+        step(&mut app, 0.2);
+        assert!(app.world.get::<Projectile>(thrown).is_some());
+        assert!(app.world.get::<Transform>(thrown).unwrap().translation.x > 0.);
+
+        // Then stops and fuses where it is at the end of its flight, instead of despawning
+        step(&mut app, lifetime);
+        assert!(app.world.get::<Explodable>(thrown).unwrap().fusing);
+        assert!(app.world.get::<Projectile>(thrown).is_none());
+        assert!(app.world.get::<Collider>(thrown).is_none());
+    }
+
+    #[test]
+    fn test_loot_magnet_draws_items_toward_nearest_player() {
+        let mut game: GameMeta =
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap();
+        game.loot_magnet = Some(LootMagnetMeta {
+            radius: 100.,
+            strength: 50.,
+        });
+        let level: LevelMeta =
+            serde_yaml::from_str(include_str!("../assets/levels/1_beach/beach.level.yaml"))
+                .unwrap();
+        let ground_y = (level.bounds.min_y() + level.bounds.max_y()) / 2.;
+
+        let mut world = World::new();
+        world.insert_resource(game);
+        world.insert_resource(level);
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+
+        world.spawn((
+            Player,
+            TransformBundle::from_transform(Transform::from_xyz(0., ground_y, 0.)),
+        ));
+        world.spawn((
+            Player,
+            TransformBundle::from_transform(Transform::from_xyz(300., ground_y, 0.)),
+        ));
+        let item = |world: &mut World, x: f32| {
+            world
+                .spawn((
+                    Item { spawn_sprite: true },
+                    TransformBundle::from_transform(Transform::from_xyz(x, ground_y, 0.)),
+                ))
+                .id()
+        };
+        let nearby = item(&mut world, 80.);
+        let far = item(&mut world, 150.);
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(attract_loot);
+        let x = |world: &World, item: Entity| world.get::<Transform>(item).unwrap().translation.x;
+        let mut last_x = x(&world, nearby);
+        for _ in 0..5 {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(0.1));
+            stage.run(&mut world);
+
+            // The item in range drifts toward the nearest player, along the ground
+            let new_x = x(&world, nearby);
+            assert!(new_x < last_x);
+            assert_eq!(
+                world.get::<Transform>(nearby).unwrap().translation.y,
+                ground_y
+            );
+            last_x = new_x;
+        }
+        assert!((last_x - 55.).abs() < 0.01);
+
+        // While the item out of range of both players stays put
+        assert_eq!(x(&world, far), 150.);
+    }
+
+    #[test]
+    fn test_dropped_weapon_disappears_unless_grabbed() {
+        let mut sword: ItemMeta =
+            serde_yaml::from_str(include_str!("../assets/items/sword/sword.item.yaml")).unwrap();
+        // The sprite of the grabbed weapon needs an atlas, loaded or not
+        if let ItemKind::MeleeWeapon { spritesheet, .. } = &mut sword.kind {
+            spritesheet.atlas_handle.push(default());
         }
+        let mut game: GameMeta =
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap();
+        game.dropped_item_lifetime = Some(1.0);
+        let mut app = App::new();
+        let mut time = Time::default();
+        time.update();
+        app.insert_resource(time)
+            .insert_resource(game)
+            .init_resource::<Assets<ItemMeta>>()
+            .init_resource::<ActiveScripts>()
+            .add_event::<CollisionEvent>()
+            .add_event::<BrokeEvent>()
+            .add_event::<ScriptItemGrabEvent>()
+            .add_plugin(LifetimePlugin)
+            .add_system(projectile_system)
+            .add_system(drop_system.after(projectile_system))
+            .add_system(grabbing);
+        let step = |app: &mut App, seconds: f32| {
+            let mut time = app.world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(seconds));
+            app.update();
+        };
+
+        // Two swords are thrown in opposite directions, and land as dropped weapons
+        for facing in [Facing::Left, Facing::Right] {
+            app.world
+                .spawn(ThrownItemBundle::new(
+                    Vec3::new(0., -100., 0.),
+                    &sword,
+                    &facing,
+                    0.0,
+                    Team::Players,
+                ))
+                .insert(Drop {
+                    item: sword.clone(),
+                });
+        }
+        for _ in 0..6 {
+            step(&mut app, 0.1);
+        }
+        let mut dropped = app
+            .world
+            .query_filtered::<(Entity, &Transform), (With<Item>, With<DroppedItem>)>();
+        let landed = dropped
+            .iter(&app.world)
+            .map(|(entity, transform)| (entity, *transform))
+            .collect::<Vec<_>>();
+        assert_eq!(landed.len(), 2);
+
+        // A fighter grabs one of them back in time
+        let (grabbed, grabbed_transform) = landed[0];
+        let fighter = app
+            .world
+            .spawn((
+                Grabbing,
+                grabbed_transform,
+                Inventory(None),
+                StateTransitionIntents::default(),
+            ))
+            .id();
+        step(&mut app, 0.1);
+        assert!(app.world.get_entity(grabbed).is_none());
+        assert!(app.world.get::<Inventory>(fighter).unwrap().is_some());
+
+        // While the other one disappears once its lifetime is over
+        let (left_behind, _) = landed[1];
+        step(&mut app, 0.1);
+        assert!(app.world.get_entity(left_behind).is_some());
+        for _ in 0..10 {
+            step(&mut app, 0.1);
+        }
+        assert!(app.world.get_entity(left_behind).is_none());
+        assert!(app.world.get::<Inventory>(fighter).unwrap().is_some());
+        assert_eq!(
+            app.world
+                .query_filtered::<(), With<Item>>()
+                .iter(&app.world)
+                .count(),
+            0
+        );
     }
 }
@@ -1,6 +1,6 @@
 use bevy::prelude::*;
 
-use crate::item::Drop;
+use crate::{item::Drop, utils::GameTime};
 
 pub struct LifetimePlugin;
 
@@ -16,10 +16,10 @@ impl Plugin for LifetimePlugin {
 pub struct Lifetime(pub Timer);
 
 /// Despawn entities who's lifetime has expired
-fn lifetime_system(
+pub(crate) fn lifetime_system(
     mut commands: Commands,
     mut entities: Query<(Entity, &mut Lifetime, Option<&Drop>, Option<&Transform>)>,
-    time: Res<Time>,
+    time: GameTime,
     mut event_writer: EventWriter<LifetimeExpired>,
 ) {
     for (entity, mut lifetime, drop, transform) in &mut entities {
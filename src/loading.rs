@@ -1,24 +1,30 @@
 use bevy::prelude::*;
 use bevy_mod_js_scripting::ActiveScripts;
 use bevy_parallax::ParallaxResource;
-use iyes_loopless::{prelude::*, state::NextState};
+use iyes_loopless::prelude::*;
 
 use rand::seq::SliceRandom;
 
 use crate::{
     animation::Animation,
     assets::EguiFontDefinitions,
+    assist_ai::AssistAi,
+    camera::{BossIntro, CameraIntro},
     config::ENGINE_CONFIG,
-    enemy::{Boss, Enemy, EnemyBundle},
+    enemy::{self, Enemy, Entrance},
     fighter::ActiveFighterBundle,
-    input::MenuAction,
-    item::{Item, ItemBundle},
+    game_state::{GameStateRequests, TransitionPriority},
+    input::menu_input_map,
+    item::{self, Item},
     metadata::{
         BorderImageMeta, FighterMeta, GameHandle, GameMeta, ItemMeta, LevelHandle, LevelMeta,
         Settings,
     },
+    movement::PassedStopPoints,
     platform::Storage,
     player::{Player, PlayerBundle},
+    utils::GameRng,
+    victory::LevelTime,
     GameState, Stats,
 };
 
@@ -26,11 +32,7 @@ use bevy::{ecs::system::SystemParam, render::camera::ScalingMode};
 use bevy_egui::{egui, EguiContext};
 use bevy_fluent::Locale;
 use bevy_parallax::ParallaxCameraComponent;
-use leafwing_input_manager::{
-    axislike::{AxisType, SingleAxis},
-    prelude::InputMap,
-    InputManagerBundle,
-};
+use leafwing_input_manager::InputManagerBundle;
 
 use progress::{HasLoadProgress, LoadingResources};
 
@@ -97,6 +99,7 @@ pub struct GameLoader<'w, 's> {
     skip_next_asset_update_event: Local<'s, bool>,
     camera: Query<'w, 's, Entity, With<Camera>>,
     commands: Commands<'w, 's>,
+    state_requests: ResMut<'w, GameStateRequests>,
     game_handle: Res<'w, GameHandle>,
     assets: ResMut<'w, Assets<GameMeta>>,
     egui_ctx: ResMut<'w, EguiContext>,
@@ -121,6 +124,7 @@ impl<'w, 's> GameLoader<'w, 's> {
             mut skip_next_asset_update_event,
             camera,
             mut commands,
+            mut state_requests,
             game_handle,
             mut assets,
             mut egui_ctx,
@@ -157,7 +161,7 @@ impl<'w, 's> GameLoader<'w, 's> {
                 commands.insert_resource(EguiFontDefinitions(egui_fonts));
 
                 // Transition to the main menu when we are done
-                commands.insert_resource(NextState(GameState::MainMenu));
+                state_requests.request(GameState::MainMenu, TransitionPriority::Navigation);
             }
 
             // Set the locale resource
@@ -176,7 +180,7 @@ impl<'w, 's> GameLoader<'w, 's> {
                 camera_bundle,
                 ParallaxCameraComponent,
                 InputManagerBundle {
-                    input_map: menu_input_map(),
+                    input_map: menu_input_map(None),
                     ..default()
                 },
             ));
@@ -242,72 +246,6 @@ impl<'w, 's> GameLoader<'w, 's> {
     }
 }
 
-fn menu_input_map() -> InputMap<MenuAction> {
-    InputMap::default()
-        // Up
-        .insert(KeyCode::Up, MenuAction::Up)
-        .insert(GamepadButtonType::DPadUp, MenuAction::Up)
-        .insert(
-            SingleAxis {
-                axis_type: AxisType::Gamepad(GamepadAxisType::LeftStickY),
-                positive_low: 0.5,
-                negative_low: -1.0,
-                value: None,
-            },
-            MenuAction::Up,
-        )
-        // Left
-        .insert(KeyCode::Left, MenuAction::Left)
-        .insert(GamepadButtonType::DPadLeft, MenuAction::Left)
-        .insert(
-            SingleAxis {
-                axis_type: AxisType::Gamepad(GamepadAxisType::LeftStickX),
-                positive_low: 1.0,
-                negative_low: -0.5,
-                value: None,
-            },
-            MenuAction::Left,
-        )
-        // Down
-        .insert(KeyCode::Down, MenuAction::Down)
-        .insert(GamepadButtonType::DPadDown, MenuAction::Down)
-        .insert(
-            SingleAxis {
-                axis_type: AxisType::Gamepad(GamepadAxisType::LeftStickY),
-                positive_low: 1.0,
-                negative_low: -0.5,
-                value: None,
-            },
-            MenuAction::Down,
-        )
-        // Right
-        .insert(KeyCode::Right, MenuAction::Right)
-        .insert(GamepadButtonType::DPadRight, MenuAction::Right)
-        .insert(
-            SingleAxis {
-                axis_type: AxisType::Gamepad(GamepadAxisType::LeftStickX),
-                positive_low: 0.5,
-                negative_low: -1.0,
-                value: None,
-            },
-            MenuAction::Right,
-        )
-        // Confirm
-        .insert(KeyCode::Return, MenuAction::Confirm)
-        .insert(GamepadButtonType::South, MenuAction::Confirm)
-        .insert(GamepadButtonType::Start, MenuAction::Confirm)
-        // Back
-        .insert(KeyCode::Escape, MenuAction::Back)
-        .insert(GamepadButtonType::East, MenuAction::Back)
-        // Toggle Fullscreen
-        .insert(KeyCode::F11, MenuAction::ToggleFullscreen)
-        .insert(GamepadButtonType::Mode, MenuAction::ToggleFullscreen)
-        // Pause
-        .insert(KeyCode::Escape, MenuAction::Pause)
-        .insert(GamepadButtonType::Start, MenuAction::Pause)
-        .build()
-}
-
 /// System to run the initial game load
 fn load_game(loader: GameLoader) {
     loader.load(false);
@@ -325,6 +263,7 @@ fn hot_reload_game(loader: GameLoader) {
 fn load_level(
     level_handle: Res<LevelHandle>,
     mut commands: Commands,
+    mut state_requests: ResMut<GameStateRequests>,
     assets: Res<Assets<LevelMeta>>,
     mut items_assets: ResMut<Assets<ItemMeta>>,
     mut parallax: ResMut<ParallaxResource>,
@@ -332,7 +271,7 @@ fn load_level(
     asset_server: Res<AssetServer>,
     game: Res<GameMeta>,
     windows: Res<Windows>,
-    mut storage: ResMut<Storage>,
+    storage: Res<Storage>,
     loading_resources: LoadingResources,
     mut active_scripts: ResMut<ActiveScripts>,
 ) {
@@ -361,37 +300,48 @@ fn load_level(
         commands.insert_resource(ClearColor(level.background_color()));
 
         // Spawn the players
+        let settings = Settings::load(&storage, &game.default_settings);
         for (i, player) in level.players.iter().enumerate() {
-            commands.spawn(PlayerBundle::new(
-                player,
-                i,
-                &game,
-                storage.get(Settings::STORAGE_KEY).as_ref(),
-            ));
+            let mut player = commands.spawn(PlayerBundle::new(player, i, &game, Some(&settings)));
+
+            // The assist AI controls the second player
+            if settings.assist_ai && i == 1 {
+                player.insert(AssistAi);
+            }
         }
 
-        // Spawn the enemies
-        for enemy in &level.enemies {
-            let mut ec = commands.spawn(EnemyBundle::new(enemy));
+        // Seed the game RNG for the level
+        commands.insert_resource(GameRng::new(level.seed));
 
-            if enemy.boss {
-                ec.insert(Boss);
-            }
+        // Start the camera intro
+        commands.remove_resource::<BossIntro>();
+        if level.camera_intro.is_empty() {
+            commands.remove_resource::<CameraIntro>();
+        } else {
+            commands.insert_resource(CameraIntro::new(level.camera_intro.clone()));
         }
 
+        // Spawn the enemies
+        enemy::spawn_enemies(
+            &mut commands,
+            &level.enemies,
+            ENGINE_CONFIG.enemy_density_scale(),
+            game.enemy_engage_delay,
+        );
+        commands.insert_resource(PassedStopPoints::default());
+        commands.insert_resource(LevelTime::default());
+
         // Spawn the items
-        for item_spawn_meta in &level.items {
-            let item_commands = commands.spawn(ItemBundle::new(item_spawn_meta));
-            ItemBundle::spawn(
-                item_commands,
-                item_spawn_meta,
-                &mut items_assets,
-                &mut active_scripts,
-            )
-        }
+        item::spawn_items(
+            &mut commands,
+            &level.items,
+            &level.bounds,
+            &mut items_assets,
+            &mut active_scripts,
+        );
 
         commands.insert_resource(level.clone());
-        commands.insert_resource(NextState(GameState::InGame));
+        state_requests.request(GameState::InGame, TransitionPriority::Navigation);
     } else {
         trace!("Awaiting level load");
     }
@@ -452,6 +402,8 @@ fn load_items(
 /// Fighters are spawned as "stubs" that only contain a transform, a marker component, and a
 /// [`Handle<Fighter>`]. This system takes those stubs, populates the rest of their components once
 /// the figher asset has been loaded.
+///
+/// Enemies with an [`Entrance`] start making it once they are activated.
 fn load_fighters(
     mut commands: Commands,
     // All fighters that haven't been fully loaded yet
@@ -462,12 +414,23 @@ fn load_fighters(
             &Handle<FighterMeta>,
             Option<&Player>,
             Option<&Enemy>,
+            Option<&Entrance>,
         ),
         Without<Stats>,
     >,
+    cameras: Query<(&Transform, &OrthographicProjection), With<Camera>>,
     fighter_assets: Res<Assets<FighterMeta>>,
+    mut rng: ResMut<GameRng>,
 ) {
-    for (entity, transform, fighter_handle, player, enemy) in fighters.iter() {
+    // The horizontal extent of the camera view, that walking entrances start from
+    let view = cameras.get_single().ok().map(|(camera, projection)| {
+        (
+            camera.translation.x + projection.left * projection.scale,
+            camera.translation.x + projection.right * projection.scale,
+        )
+    });
+
+    for (entity, transform, fighter_handle, player, enemy, entrance) in fighters.iter() {
         if let Some(fighter) = fighter_assets.get(fighter_handle) {
             ActiveFighterBundle::activate_fighter_stub(
                 &mut commands,
@@ -476,7 +439,12 @@ fn load_fighters(
                 transform,
                 player,
                 enemy,
+                &mut **rng,
             );
+
+            if let Some(entrance) = entrance {
+                entrance.start(&mut commands, entity, transform, &fighter.stats, view);
+            }
         }
     }
 }
@@ -492,6 +460,7 @@ fn hot_reload_fighters(
     )>,
     mut events: EventReader<AssetEvent<FighterMeta>>,
     assets: Res<Assets<FighterMeta>>,
+    mut rng: ResMut<GameRng>,
 ) {
     for event in events.iter() {
         if let AssetEvent::Modified { handle } = event {
@@ -505,7 +474,7 @@ fn hot_reload_fighters(
                     *atlas_handle = fighter
                         .spritesheet
                         .atlas_handle
-                        .choose(&mut rand::thread_rng())
+                        .choose(&mut **rng)
                         .unwrap()
                         .clone();
                     *animation = Animation::new(
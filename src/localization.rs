@@ -4,6 +4,7 @@ use bevy::{prelude::*, utils::HashMap};
 use bevy_fluent::{BundleAsset, FluentPlugin, Locale, Localization};
 use fluent::FluentArgs;
 use fluent_content::{Content, Request};
+use unic_langid::LanguageIdentifier;
 
 /// Plugin for initializing and loading the [`Localization`] resource.
 pub struct LocalizationPlugin;
@@ -35,6 +36,81 @@ where
     }
 }
 
+/// How numbers are written in a locale
+struct NumberFormat {
+    /// The separator between groups of digits
+    group_separator: &'static str,
+    /// The separator between the integer and the fractional part
+    decimal_mark: &'static str,
+    /// The size of the group of digits closest to the decimal mark
+    primary_group: usize,
+    /// The size of the other groups of digits
+    secondary_group: usize,
+}
+
+impl NumberFormat {
+    fn new(group_separator: &'static str, decimal_mark: &'static str) -> Self {
+        Self {
+            group_separator,
+            decimal_mark,
+            primary_group: 3,
+            secondary_group: 3,
+        }
+    }
+
+    fn for_locale(locale: &LanguageIdentifier) -> Self {
+        let region = locale.region.as_ref().map(|region| region.as_str());
+        match (locale.language.as_str(), region) {
+            ("en", Some("IN")) | ("hi", _) => Self {
+                secondary_group: 2,
+                ..Self::new(",", ".")
+            },
+            ("de", Some("CH")) => Self::new("\u{2019}", "."),
+            ("fr", _) => Self::new("\u{202f}", ","),
+            ("cs" | "fi" | "nb" | "pl" | "ru" | "sv" | "uk", _) => Self::new("\u{a0}", ","),
+            ("da" | "de" | "es" | "id" | "it" | "nl" | "pt" | "tr", _) => Self::new(".", ","),
+            _ => Self::new(",", "."),
+        }
+    }
+}
+
+/// Format a number with the given amount of decimals, using the digit grouping and decimal mark of
+/// the locale.
+pub fn format_number(number: f64, decimals: usize, locale: &LanguageIdentifier) -> String {
+    let format = NumberFormat::for_locale(locale);
+    let digits = format!("{:.*}", decimals, number.abs());
+    let (integer, fraction) = match digits.split_once('.') {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (digits.as_str(), None),
+    };
+
+    // Split the integer part into groups, starting from the decimal mark
+    let mut groups = Vec::new();
+    let mut rest = integer;
+    let mut group_size = format.primary_group;
+    while rest.len() > group_size {
+        let (head, group) = rest.split_at(rest.len() - group_size);
+        groups.push(group);
+        rest = head;
+        group_size = format.secondary_group;
+    }
+    groups.push(rest);
+    groups.reverse();
+
+    let mut formatted = String::new();
+    // Don't write a sign for numbers that round to zero
+    if number < 0.0 && digits.chars().any(|c| c.is_ascii_digit() && c != '0') {
+        formatted.push('-');
+    }
+    formatted.push_str(&groups.join(format.group_separator));
+    if let Some(fraction) = fraction {
+        formatted.push_str(format.decimal_mark);
+        formatted.push_str(fraction);
+    }
+
+    formatted
+}
+
 /// Watch for locale [`BundleAsset`] load events and add any new bundles to the [`Localization`]
 /// resource.
 fn load_locales(
@@ -103,3 +179,24 @@ fn load_locales(
         *localization = new_localization;
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_format_number_for_locales() {
+        let format = |locale: &str| format_number(1234567.891, 2, &locale.parse().unwrap());
+
+        assert_eq!(format("en-US"), "1,234,567.89");
+        assert_eq!(format("fr-FR"), "1\u{202f}234\u{202f}567,89");
+        assert_eq!(format("de-DE"), "1.234.567,89");
+        assert_eq!(format("en-IN"), "12,34,567.89");
+        assert_ne!(format("en-US"), format("fr-FR"));
+
+        let en_us = "en-US".parse().unwrap();
+        assert_eq!(format_number(-999.0, 0, &en_us), "-999");
+        assert_eq!(format_number(-0.001, 1, &en_us), "0.0");
+        assert_eq!(format_number(1000.0, 0, &en_us), "1,000");
+    }
+}
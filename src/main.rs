@@ -16,18 +16,24 @@ use bevy_inspector_egui_rapier::InspectableRapierPlugin;
 
 mod animation;
 mod assets;
+mod assist_ai;
 mod attack;
+mod attract_mode;
 mod audio;
 mod camera;
 mod collision;
 mod config;
 mod consts;
+mod crt;
 mod damage;
 mod enemy;
 mod enemy_ai;
 mod fighter;
 mod fighter_state;
+mod frame_pacing;
+mod game_state;
 mod input;
+mod interpolation;
 mod item;
 mod lifetime;
 mod loading;
@@ -36,24 +42,52 @@ mod metadata;
 mod movement;
 mod platform;
 mod player;
+mod projectile;
 mod scripting;
+mod status_effect;
 mod ui;
 mod utils;
+mod victory;
 
 use animation::*;
+use assist_ai::AssistAiPlugin;
 use attack::AttackPlugin;
+use attract_mode::AttractModePlugin;
 use audio::*;
 use camera::*;
+use crt::CrtPlugin;
 use enemy_ai::WalkTarget;
 use metadata::GameMeta;
 use ui::UIPlugin;
-use utils::ResetController;
+use utils::{CosmeticRng, GameRng, ResetController};
 
 use crate::{
-    damage::DamagePlugin, fighter::FighterPlugin, fighter_state::FighterStatePlugin,
-    input::PlayerAction, item::ItemPlugin, lifetime::LifetimePlugin, loading::LoadingPlugin,
-    localization::LocalizationPlugin, metadata::GameHandle, movement::MovementPlugin,
-    platform::PlatformPlugin, scripting::ScriptingPlugin, ui::debug_tools::YSortDebugPlugin,
+    damage::DamagePlugin,
+    fighter::FighterPlugin,
+    fighter_state::FighterStatePlugin,
+    frame_pacing::FramePacingPlugin,
+    game_state::{GameStatePlugin, GameStateRequests, TransitionPriority},
+    input::PlayerAction,
+    interpolation::InterpolationPlugin,
+    item::ItemPlugin,
+    lifetime::LifetimePlugin,
+    loading::LoadingPlugin,
+    localization::LocalizationPlugin,
+    metadata::GameHandle,
+    movement::MovementPlugin,
+    platform::PlatformPlugin,
+    projectile::ProjectilePlugin,
+    scripting::ScriptingPlugin,
+    status_effect::StatusEffectPlugin,
+    ui::{
+        debug_tools::{
+            CollisionOffsetDebugPlugin, EnemyAiDebugPlugin, FrameDataDebugPlugin,
+            InputDisplayDebugPlugin, InputLatencyDebugPlugin, MovementBoundsDebugPlugin,
+            PracticeHitboxDebugPlugin, QuickRestartDebugPlugin, YSortDebugPlugin,
+        },
+        level_editor::LevelEditorPlugin,
+    },
+    victory::VictoryPlugin,
 };
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
@@ -64,6 +98,7 @@ enum GameState {
     LoadingLevel,
     InGame,
     Paused,
+    LevelComplete,
     //Editor,
 }
 
@@ -74,6 +109,29 @@ fn main() {
 
     let mut app = App::new();
 
+    // Configure asset server
+    let mut asset_plugin = AssetPlugin {
+        watch_for_changes: engine_config.hot_reload,
+        ..default()
+    };
+    if let Some(asset_folder) = &engine_config.asset_dir {
+        asset_plugin.asset_folder = asset_folder.clone();
+    }
+
+    // Layer the mod pack over the base assets, if one was specified
+    if let Some(mod_folder) = &engine_config.mod_dir {
+        let mod_io = AssetPlugin {
+            asset_folder: mod_folder.clone(),
+            watch_for_changes: false,
+        }
+        .create_platform_default_asset_io();
+        let base_io = asset_plugin.create_platform_default_asset_io();
+
+        app.insert_resource(AssetServer::with_boxed_io(Box::new(
+            assets::LayeredAssetIo::new(mod_io, base_io),
+        )));
+    }
+
     app.add_plugins({
         let mut builder = DefaultPlugins.build();
 
@@ -89,14 +147,6 @@ fn main() {
             })
             .set(ImagePlugin::default_nearest());
 
-        // Configure asset server
-        let mut asset_plugin = AssetPlugin {
-            watch_for_changes: engine_config.hot_reload,
-            ..default()
-        };
-        if let Some(asset_folder) = &engine_config.asset_dir {
-            asset_plugin.asset_folder = asset_folder.clone();
-        }
         builder = builder.set(asset_plugin);
 
         // Configure log level
@@ -117,6 +167,7 @@ fn main() {
     // Add other systems and resources
     app.insert_resource(ClearColor(Color::BLACK))
         .add_loopless_state(GameState::LoadingStorage)
+        .add_plugin(GameStatePlugin)
         .add_plugin(ScriptingPlugin)
         .add_plugin(PlatformPlugin)
         .add_plugin(LocalizationPlugin)
@@ -128,15 +179,25 @@ fn main() {
         .add_plugin(AnimationPlugin)
         .add_plugin(ParallaxPlugin)
         .add_plugin(UIPlugin)
+        .add_plugin(AttractModePlugin)
+        .add_plugin(AssistAiPlugin)
         .add_plugin(FighterStatePlugin)
         .add_plugin(MovementPlugin)
+        .add_plugin(InterpolationPlugin)
         .add_plugin(AudioPlugin)
         .add_plugin(DamagePlugin)
         .add_plugin(LifetimePlugin)
         .add_plugin(CameraPlugin)
+        .add_plugin(CrtPlugin)
+        .add_plugin(FramePacingPlugin)
         .add_plugin(ItemPlugin)
+        .add_plugin(ProjectilePlugin)
         .add_plugin(FighterPlugin)
+        .add_plugin(VictoryPlugin)
+        .add_plugin(StatusEffectPlugin)
         .insert_resource(ParallaxResource::default())
+        .init_resource::<GameRng>()
+        .init_resource::<CosmeticRng>()
         .add_system_set_to_stage(
             CoreStage::PostUpdate,
             ConditionSet::new()
@@ -163,6 +224,15 @@ fn main() {
             ..default()
         })
         .add_plugin(YSortDebugPlugin)
+        .add_plugin(CollisionOffsetDebugPlugin)
+        .add_plugin(FrameDataDebugPlugin)
+        .add_plugin(PracticeHitboxDebugPlugin)
+        .add_plugin(InputDisplayDebugPlugin)
+        .add_plugin(InputLatencyDebugPlugin)
+        .add_plugin(EnemyAiDebugPlugin)
+        .add_plugin(MovementBoundsDebugPlugin)
+        .add_plugin(QuickRestartDebugPlugin)
+        .add_plugin(LevelEditorPlugin)
         .add_plugin(InspectableRapierPlugin)
         .insert_resource(WorldInspectorParams {
             enabled: false,
@@ -192,13 +262,16 @@ fn main() {
 }
 
 /// Transition back to main menu and reset world when all players have died
+///
+/// The game over takes priority over the other transitions, like completing the level on the same
+/// frame, since the world is reset right away.
 fn game_over_on_players_death(
-    mut commands: Commands,
+    mut state_requests: ResMut<GameStateRequests>,
     query: Query<(), With<Player>>,
     reset_controller: ResetController,
 ) {
     if query.is_empty() {
-        commands.insert_resource(NextState(GameState::MainMenu));
+        state_requests.request(GameState::MainMenu, TransitionPriority::GameOver);
 
         reset_controller.reset_world();
     }
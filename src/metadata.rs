@@ -10,9 +10,12 @@ use bevy_kira_audio::AudioSource;
 use bevy_mod_js_scripting::JsScript;
 use bevy_parallax::{LayerData, ParallaxResource};
 use punchy_macros::HasLoadProgress;
-use serde::Deserialize;
+use serde::{Deserialize, Deserializer, Serialize};
 
-use crate::{animation::Clip, assets::EguiFont, attack::AttackFrames, fighter::Stats};
+use crate::{
+    animation::Clip, assets::EguiFont, attack::AttackFrames, camera::SortLayer, consts,
+    fighter::Stats,
+};
 
 pub mod settings;
 pub use settings::*;
@@ -23,6 +26,8 @@ pub mod ui;
 pub mod localization;
 pub use localization::TranslationsMeta;
 
+pub mod migration;
+
 #[derive(Resource, Deref, DerefMut)]
 pub struct GameHandle(pub Handle<GameMeta>);
 
@@ -35,8 +40,87 @@ pub struct GameMeta {
     pub start_level_handle: Handle<LevelMeta>,
     pub main_menu: MainMenuMeta,
     pub ui_theme: UIThemeMeta,
+    #[serde(default)]
+    pub input_glyphs: InputGlyphsMeta,
     pub camera_height: u32,
     pub camera_move_right_boundary: f32,
+    #[serde(default)]
+    pub stop_point: StopPointMeta,
+    #[serde(default)]
+    pub camera_zoom: CameraZoomMeta,
+    #[serde(default)]
+    pub camera_shake: CameraShakeMeta,
+    /// The camera roll on heavy impacts, on top of the shake. The camera stays level if this is not
+    /// set.
+    #[serde(default)]
+    pub camera_tilt: Option<CameraTiltMeta>,
+    #[serde(default)]
+    pub super_meter: SuperMeterMeta,
+    #[serde(default)]
+    pub enemy_alert: EnemyAlertMeta,
+    #[serde(default)]
+    pub juggle: JuggleMeta,
+    #[serde(default)]
+    pub draw_layers: DrawLayersMeta,
+    /// Fighters knocked far enough off the screen are killed. Ring-outs are disabled if this is not
+    /// set.
+    #[serde(default)]
+    pub ring_out: Option<RingOutMeta>,
+    #[serde(default)]
+    pub revive: ReviveMeta,
+    /// How long fighters knocked down by knockdown attacks stay on the ground and take to get up
+    #[serde(default)]
+    pub knockdown: KnockdownMeta,
+    /// The longest time, in seconds, that gameplay can advance by in a single frame. Longer frames
+    /// are slowed down so that entities can't skip through stop points and boundaries.
+    #[serde(default = "default_max_frame_delta")]
+    pub max_frame_delta: f32,
+    /// How long, in seconds, the attacker and the target of a hit are frozen for. The rest of the
+    /// world keeps moving.
+    #[serde(default)]
+    pub hit_pause: f32,
+    /// How long, in seconds, weapons dropped by fighters lie on the ground before disappearing.
+    /// Dropped weapons stay forever if this is not set.
+    #[serde(default)]
+    pub dropped_item_lifetime: Option<f32>,
+    /// Draws the items lying on the ground toward the nearest player. Items stay where they drop
+    /// if this is not set.
+    #[serde(default)]
+    pub loot_magnet: Option<LootMagnetMeta>,
+    /// Whether enemies outside the camera view hold their attacks, only moving, until they are
+    /// visible.
+    #[serde(default)]
+    pub offscreen_enemies_hold_attacks: bool,
+    /// The most enemies alive at once that bosses call in reinforcements up to. It only caps the
+    /// reinforcements: the enemies placed in the level always spawn, and count toward it. The
+    /// reinforcements are not capped if this is not set.
+    #[serde(default)]
+    pub max_enemies: Option<usize>,
+    /// How long, in seconds, enemies stay idle after being tripped before they engage the players.
+    /// Enemies engage right away if this is not set.
+    #[serde(default)]
+    pub enemy_engage_delay: Option<f32>,
+    /// Whether attacks hit the attacker's teammates too
+    #[serde(default)]
+    pub friendly_fire: bool,
+    /// Whether players grabbing an enemy in hit stun or knocked down steal the weapon it holds
+    #[serde(default)]
+    pub enemy_weapon_steal: bool,
+    /// The timed parry, entered by tapping block. Parries are disabled if this is not set.
+    #[serde(default)]
+    pub parry: Option<ParryMeta>,
+    /// The camera framing biased toward the boss while it starts a major attack. The camera keeps
+    /// following the players only if this is not set.
+    #[serde(default)]
+    pub boss_focus: Option<BossFocusMeta>,
+    /// The corpses dead enemies leave behind as scenery. Dead enemies vanish once their death
+    /// animation ends if this is not set.
+    #[serde(default)]
+    pub enemy_corpses: Option<CorpseMeta>,
+    /// The balancing of the enemies' targets across the players, so that they don't all go after
+    /// the same player. Enemies go after the nearest player if this is not set.
+    #[serde(default)]
+    pub aggro_balancing: Option<AggroBalancingMeta>,
 
     pub default_settings: Settings,
     pub translations: TranslationsMeta,
@@ -46,6 +130,348 @@ pub struct GameMeta {
     pub script_handles: Vec<Handle<JsScript>>,
 }
 
+fn default_max_frame_delta() -> f32 {
+    0.1
+}
+
+/// Settings for how players are held back by the level's stop points
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct StopPointMeta {
+    /// The rate, in units per second squared, at which players moving towards an active stop point
+    /// are slowed down. A value of `0` stops the player instantly at the stop point.
+    #[serde(default = "default_stop_point_deceleration")]
+    pub deceleration: f32,
+    /// Sound played when a player starts pushing against an active stop point
+    #[serde(default)]
+    pub blocked_sound: Option<String>,
+    #[serde(skip)]
+    pub blocked_sound_handle: Option<Handle<AudioSource>>,
+}
+
+impl Default for StopPointMeta {
+    fn default() -> Self {
+        Self {
+            deceleration: default_stop_point_deceleration(),
+            blocked_sound: None,
+            blocked_sound_handle: None,
+        }
+    }
+}
+
+fn default_stop_point_deceleration() -> f32 {
+    1200.
+}
+
+/// Settings for zooming the camera out as the players spread apart
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields, default)]
+#[has_load_progress(none)]
+pub struct CameraZoomMeta {
+    /// The camera scale when the players are close together
+    pub min_zoom: f32,
+    /// The maximum camera scale, when the players are spread apart
+    pub max_zoom: f32,
+    /// The horizontal distance between players from which the camera starts zooming out
+    pub min_spread: f32,
+    /// The horizontal distance between players at which the camera is zoomed out the most
+    pub max_spread: f32,
+}
+
+impl Default for CameraZoomMeta {
+    fn default() -> Self {
+        Self {
+            min_zoom: 1.0,
+            max_zoom: 1.0,
+            min_spread: 0.,
+            max_spread: 1.,
+        }
+    }
+}
+
+impl CameraZoomMeta {
+    /// Get the camera scale for the given horizontal distance between the players
+    pub fn zoom_for_spread(&self, spread: f32) -> f32 {
+        let range = (self.max_spread - self.min_spread).max(f32::EPSILON);
+        let t = ((spread - self.min_spread) / range).clamp(0.0, 1.0);
+
+        self.min_zoom + t * (self.max_zoom - self.min_zoom)
+    }
+}
+
+/// Settings for shaking the camera, such as for explosions
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields, default)]
+#[has_load_progress(none)]
+pub struct CameraShakeMeta {
+    /// The largest distance the camera is moved by a full strength shake
+    pub max_offset: Vec2,
+    /// How much of the shake strength is lost per second
+    pub decay: f32,
+    /// The distance from the right bound of the level within which the shake is dampened, so that
+    /// it fades out instead of showing past the end of the level
+    pub edge_falloff: f32,
+}
+
+impl Default for CameraShakeMeta {
+    fn default() -> Self {
+        Self {
+            max_offset: Vec2::new(12., 8.),
+            decay: 1.5,
+            edge_falloff: 50.,
+        }
+    }
+}
+
+/// Settings for the player super meter
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields, default)]
+#[has_load_progress(none)]
+pub struct SuperMeterMeta {
+    /// The meter value at which the super attack can be used
+    pub max: f32,
+    /// Meter gained per point of damage dealt
+    pub gain_per_damage_dealt: f32,
+    /// Meter gained per point of damage taken
+    pub gain_per_damage_taken: f32,
+    /// Damage multiplier of the super attack, for fighters without a `super` attack
+    pub damage_multiplier: f32,
+}
+
+impl Default for SuperMeterMeta {
+    fn default() -> Self {
+        Self {
+            max: 100.,
+            gain_per_damage_dealt: 1.0,
+            gain_per_damage_taken: 0.5,
+            damage_multiplier: 3.0,
+        }
+    }
+}
+
+/// Settings for how airborne fighters become harder to juggle with each consecutive hit
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields, default)]
+#[has_load_progress(none)]
+pub struct JuggleMeta {
+    /// The factor the hitstun duration is multiplied by for every previous air hit
+    pub hitstun_decay: f32,
+    /// The knockback resistance gained for every previous air hit
+    pub knockback_resist_per_hit: f32,
+}
+
+impl Default for JuggleMeta {
+    fn default() -> Self {
+        Self {
+            hitstun_decay: 0.7,
+            knockback_resist_per_hit: 0.15,
+        }
+    }
+}
+
+impl JuggleMeta {
+    /// Get the hitstun duration of a hit, after `juggle_count` previous air hits
+    pub fn hitstun_duration(&self, duration: f32, juggle_count: u32) -> f32 {
+        duration * self.hitstun_decay.powi(juggle_count as i32)
+    }
+
+    /// Get the knockback resistance of a fighter, after `juggle_count` previous air hits
+    pub fn knockback_resist(&self, knockback_resist: f32, juggle_count: u32) -> f32 {
+        knockback_resist + self.knockback_resist_per_hit * juggle_count as f32
+    }
+}
+
+/// The bands of draw order that y-sorted entities are drawn in, from back to front.
+///
+/// Within its band, an entity is drawn in front of the entities higher up the screen than it.
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields, default)]
+#[has_load_progress(none)]
+pub struct DrawLayersMeta {
+    /// The z position of the back of each band
+    pub ground_items: f32,
+    pub fighters: f32,
+    pub projectiles: f32,
+    /// The depth of each band. Entities never leave their band, however far up or down they are.
+    pub band_depth: f32,
+    /// How far forward, in z, an entity is drawn for every unit it is further down the screen
+    pub y_scale: f32,
+}
+
+impl Default for DrawLayersMeta {
+    fn default() -> Self {
+        Self {
+            ground_items: 100.,
+            fighters: 300.,
+            projectiles: 500.,
+            band_depth: 100.,
+            y_scale: 0.1,
+        }
+    }
+}
+
+impl DrawLayersMeta {
+    /// Get the z position of an entity in the `layer` band, at the given `y` position
+    pub fn z(&self, layer: SortLayer, y: f32) -> f32 {
+        let base = match layer {
+            SortLayer::GroundItems => self.ground_items,
+            SortLayer::Fighters => self.fighters,
+            SortLayer::Projectiles => self.projectiles,
+        };
+
+        base + (self.band_depth / 2. - y * self.y_scale).clamp(0., self.band_depth)
+    }
+}
+
+/// Settings for killing fighters that are knocked off the screen
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+#[has_load_progress(none)]
+pub struct RingOutMeta {
+    /// How far past the left or right edge of the camera view a fighter must be knocked to be
+    /// killed
+    pub margin: f32,
+}
+
+/// Settings for tilting the camera on heavy impacts
+#[derive(HasLoadProgress, Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+#[has_load_progress(none)]
+pub struct CameraTiltMeta {
+    /// The angle, in degrees, the camera is rolled by a full strength tilt
+    pub max_angle: f32,
+    /// How long, in seconds, the camera takes to ease back to level
+    pub duration: f32,
+}
+
+/// Settings for the loot magnet, which draws items toward the players
+#[derive(HasLoadProgress, Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+#[has_load_progress(none)]
+pub struct LootMagnetMeta {
+    /// How close, in pixels, an item must be to a player to be drawn toward it
+    pub radius: f32,
+    /// The speed, in pixels per second, items are drawn toward the player with
+    pub strength: f32,
+}
+
+/// Settings for the timed parry, which negates the attacks hitting a fighter from the front
+#[derive(HasLoadProgress, Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+#[has_load_progress(none)]
+pub struct ParryMeta {
+    /// How long, in seconds, attacks are parried for after tapping block
+    pub window: f32,
+    /// How long, in seconds, the fighter stays in the parry after the window, open to attacks
+    pub recovery: f32,
+    /// How long, in seconds, the attacker of a parried attack is stunned for
+    pub stun_duration: f32,
+    /// How much the super meter fills for every parried attack
+    pub meter_gain: f32,
+    /// The width, in degrees, of the cone in front of the fighter that attacks are parried from.
+    /// Attacks coming from steeper angles above or below still connect.
+    #[serde(default = "default_parry_cone")]
+    pub cone: f32,
+}
+
+fn default_parry_cone() -> f32 {
+    180.
+}
+
+/// Settings for biasing the camera toward the boss while it starts a major attack
+#[derive(HasLoadProgress, Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+#[has_load_progress(none)]
+pub struct BossFocusMeta {
+    /// How far the camera moves from the players toward the boss, from 0 to 1
+    pub weight: f32,
+    /// How long, in seconds, the camera stays biased toward the boss before panning back to the
+    /// players
+    pub duration: f32,
+    /// The distance the players are always kept away from the edges of the view
+    pub player_margin: f32,
+}
+
+/// Settings for balancing the enemies' targets across the players
+#[derive(HasLoadProgress, Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+#[has_load_progress(none)]
+pub struct AggroBalancingMeta {
+    /// How much further away a player seems to an enemy choosing its target, for each enemy
+    /// already going after that player
+    pub distance_per_enemy: f32,
+}
+
+/// Settings for the corpses dead enemies leave behind
+#[derive(HasLoadProgress, Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+#[has_load_progress(none)]
+pub struct CorpseMeta {
+    /// How long, in seconds, a corpse takes to fade out before it is cleaned up
+    pub duration: f32,
+}
+
+/// Settings for how players are downed instead of dying, and revived by their teammates
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields, default)]
+#[has_load_progress(none)]
+pub struct ReviveMeta {
+    /// How long, in seconds, a downed player can wait for a teammate before dying
+    pub downed_duration: f32,
+    /// How long, in seconds, a teammate has to stay near a downed player to revive them
+    pub revive_duration: f32,
+    /// The distance within which a teammate revives a downed player
+    pub radius: f32,
+    /// The fraction of their max health that revived players come back with
+    pub health: f32,
+}
+
+impl Default for ReviveMeta {
+    fn default() -> Self {
+        Self {
+            downed_duration: 8.,
+            revive_duration: 2.,
+            radius: 50.,
+            health: 0.3,
+        }
+    }
+}
+
+/// Settings for fighters knocked down by knockdown attacks
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields, default)]
+#[has_load_progress(none)]
+pub struct KnockdownMeta {
+    /// How long, in seconds, knocked down fighters lie on the ground, after sliding to a stop
+    pub down_duration: f32,
+    /// How long, in seconds, fighters take to get up, during which they can't be damaged
+    pub getup_duration: f32,
+}
+
+impl Default for KnockdownMeta {
+    fn default() -> Self {
+        Self {
+            down_duration: 0.8,
+            getup_duration: 0.5,
+        }
+    }
+}
+
+/// Settings for how enemies alert each other when one of them engages
+#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields, default)]
+#[has_load_progress(none)]
+pub struct EnemyAlertMeta {
+    /// The distance within which idle enemies are alerted. A value of `0` disables alerts.
+    pub radius: f32,
+}
+
+impl Default for EnemyAlertMeta {
+    fn default() -> Self {
+        Self { radius: 150. }
+    }
+}
+
 #[derive(HasLoadProgress, Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct MainMenuMeta {
@@ -60,6 +486,10 @@ pub struct MainMenuMeta {
     pub button_sounds: Vec<String>,
     #[serde(skip)]
     pub button_sound_handles: Vec<Handle<AudioSource>>,
+    /// Seconds without input on the main menu before the game starts playing itself in attract
+    /// mode. Attract mode is disabled if this is not set.
+    #[serde(default)]
+    pub attract_mode_timeout: Option<f32>,
 }
 
 #[derive(HasLoadProgress, Deserialize, Clone, Debug)]
@@ -74,10 +504,14 @@ pub struct ImageMeta {
 #[derive(Resource, Deref, DerefMut)]
 pub struct LevelHandle(pub Handle<LevelMeta>);
 
-#[derive(Resource, HasLoadProgress, TypeUuid, Deserialize, Clone, Debug)]
+#[derive(Resource, HasLoadProgress, TypeUuid, Deserialize, Serialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 #[uuid = "32111f6e-bb9a-4ea7-8988-1220b923a059"]
 pub struct LevelMeta {
+    /// The [version](migration) of the level file
+    #[serde(default = "migration::current_version")]
+    #[has_load_progress(none)]
+    pub version: u32,
     #[has_load_progress(none)]
     pub background_color: [u8; 3],
     pub parallax_background: ParallaxMeta,
@@ -89,7 +523,114 @@ pub struct LevelMeta {
     pub music: String,
     #[serde(skip)]
     pub music_handle: Handle<AudioSource>,
+    /// The position, in seconds, the music loops back to once it reaches its end. The part of the
+    /// track before it is an intro that only plays once. The whole track loops if this is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[has_load_progress(none)]
+    pub music_loop_start: Option<f64>,
+    /// Tracks played in turn with the level music, moving on when each one ends instead of
+    /// looping. The level music loops on its own if this is not set.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[has_load_progress(none)]
+    pub playlist: Option<PlaylistMeta>,
     pub stop_points: Vec<f32>,
+    /// The seed for the level's [`GameRng`][crate::utils::GameRng]. The same seed always yields
+    /// the same enemy placement.
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub seed: u64,
+    /// Waypoints the camera pans through when the level starts, before the players get control
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub camera_intro: Vec<CameraWaypointMeta>,
+    /// What the players need to do to complete the level
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub victory: VictoryCondition,
+    /// The area of the level the players can move in, and the camera can show
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub bounds: LevelBoundsMeta,
+}
+
+/// The bounds of a level. The top and bottom are relative to the ground, like spawn locations.
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct LevelBoundsMeta {
+    pub top: f32,
+    pub bottom: f32,
+    /// The x position of the end of the level, past which neither the players nor the camera go
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub right: Option<f32>,
+}
+
+impl Default for LevelBoundsMeta {
+    fn default() -> Self {
+        Self {
+            top: consts::MAX_Y - consts::GROUND_Y,
+            bottom: consts::MIN_Y - consts::GROUND_Y,
+            right: None,
+        }
+    }
+}
+
+impl LevelBoundsMeta {
+    /// The highest y position in the level
+    pub fn max_y(&self) -> f32 {
+        self.top + consts::GROUND_Y
+    }
+
+    /// The lowest y position in the level
+    pub fn min_y(&self) -> f32 {
+        self.bottom + consts::GROUND_Y
+    }
+}
+
+/// The tracks a level plays after its music
+#[derive(Deserialize, Serialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct PlaylistMeta {
+    pub tracks: Vec<String>,
+    #[serde(skip)]
+    pub track_handles: Vec<Handle<AudioSource>>,
+    #[serde(default)]
+    pub order: PlaylistOrder,
+}
+
+/// The order the tracks of a playlist are played in
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub enum PlaylistOrder {
+    /// The level music, then each track in the order they are listed
+    #[default]
+    Sequential,
+    /// All of the tracks in a random order, shuffled again every time the playlist starts over
+    Shuffle,
+}
+
+/// What the players need to do to complete a level
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, Default)]
+#[serde(deny_unknown_fields)]
+pub enum VictoryCondition {
+    /// Defeat all the enemies in the level
+    #[default]
+    DefeatAllEnemies,
+    /// Defeat the level's boss
+    DefeatBoss,
+    /// Stay alive for the given duration, in seconds
+    Survive { duration: f32 },
+    /// Get any player to the given x position
+    ReachX { x: f32 },
+}
+
+/// A camera position in a level intro
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct CameraWaypointMeta {
+    /// The camera x position
+    pub x: f32,
+    /// The time, in seconds, it takes to pan to this waypoint from the previous one
+    pub duration: f32,
 }
 
 impl LevelMeta {
@@ -103,21 +644,88 @@ impl LevelMeta {
 #[serde(deny_unknown_fields)]
 #[uuid = "d5e040c4-3de7-4b8a-b6c2-27f82f58d8f0"]
 pub struct FighterMeta {
+    /// The [version](migration) of the fighter file
+    #[serde(default = "migration::current_version")]
+    pub version: u32,
     pub name: String,
     #[serde(skip)]
     pub center_y: f32,
     #[serde(skip)]
     pub collision_offset: f32,
+    /// Overrides the collision offset, which is otherwise computed from the sprite size
+    #[serde(default)]
+    pub custom_collision_offset: Option<f32>,
     pub stats: Stats,
     pub hud: FighterHudMeta,
     pub spritesheet: FighterSpritesheetMeta,
     pub audio: AudioMeta,
     pub hurtbox: ColliderMeta,
     pub attacks: Vec<AttackMeta>,
+    /// The links of the fighter's ground combo string, chained by the `chain` attack in order
+    #[serde(default)]
+    pub combo: Vec<ComboLinkMeta>,
+    /// The attacks performed while off the ground, instead of the grounded attacks
+    #[serde(default)]
+    pub air_attacks: Vec<AirAttackMeta>,
+    /// The cinematic played when the players reach the fighter, for bosses
+    #[serde(default)]
+    pub boss_intro: Option<BossIntroMeta>,
+    /// The minions the fighter calls in while it fights, for bosses
+    #[serde(default)]
+    pub reinforcements: Option<ReinforcementsMeta>,
     pub attachment: Option<FighterSpritesheetMeta>,
 }
 
-#[derive(TypeUuid, Deserialize, Clone, Debug, Component, Reflect, FromReflect)]
+impl FighterMeta {
+    /// Get the air attack performed while holding `direction`, falling back to the neutral air
+    /// attack, and then to the first one
+    pub fn air_attack(&self, direction: AirAttackDirection) -> Option<&AirAttackMeta> {
+        let with_direction = |direction| {
+            self.air_attacks
+                .iter()
+                .find(|attack| attack.direction == direction)
+        };
+
+        with_direction(direction)
+            .or_else(|| with_direction(AirAttackDirection::Neutral))
+            .or_else(|| self.air_attacks.first())
+    }
+
+    /// Check the collision offset and collider sizes against the sprite size, returning a warning
+    /// for every value that is likely wrong.
+    pub fn validate(&self) -> Vec<String> {
+        let mut warnings = Vec::new();
+        let tile_size = self.spritesheet.tile_size.as_vec2();
+
+        if self.collision_offset.abs() > tile_size.y {
+            warnings.push(format!(
+                "collision offset {} is larger than the sprite height {}",
+                self.collision_offset, tile_size.y
+            ));
+        }
+
+        if self.hurtbox.size.x > tile_size.x || self.hurtbox.size.y > tile_size.y {
+            warnings.push(format!(
+                "hurtbox size {} is larger than the sprite size {}",
+                self.hurtbox.size, tile_size
+            ));
+        }
+
+        for attack in &self.attacks {
+            let offset = attack.hitbox.offset.abs();
+            if offset.x > tile_size.x || offset.y > tile_size.y {
+                warnings.push(format!(
+                    "`{}` attack hitbox offset {} is outside of the sprite size {}",
+                    attack.name, attack.hitbox.offset, tile_size
+                ));
+            }
+        }
+
+        warnings
+    }
+}
+
+#[derive(TypeUuid, Deserialize, Clone, Debug, Default, Component, Reflect, FromReflect)]
 #[serde(deny_unknown_fields)]
 #[uuid = "45a912f4-ea5c-4eba-9ba9-f1a726140f28"]
 pub struct AttackMeta {
@@ -130,12 +738,168 @@ pub struct AttackMeta {
     pub item: Option<String>,
     #[serde(skip)]
     pub item_handle: Handle<ItemMeta>,
+    /// Attacks with a clash priority cancel each other out when they hit an opposing attack that
+    /// also has one. The attack with the lower priority is canceled, or both if they are equal.
+    pub clash_priority: Option<u32>,
+    /// Launchers knock the target up into the air with this velocity, instead of their `velocity`
+    pub launch: Option<Vec2>,
+    /// Anti-airs deal this much more damage to airborne targets, as a fraction of their damage
+    pub antiair_bonus: Option<f32>,
+    /// Vacuum attacks pull their target toward the attacker with this speed, instead of pushing it
+    /// away with their `velocity`
+    pub pull: Option<f32>,
+    /// Fire attacks set their target on fire, dealing damage over time
+    pub burn: Option<BurnMeta>,
+    /// Confusing attacks confuse their target for this long, in seconds, turning confused enemies
+    /// against each other and inverting the movement of confused players
+    pub confuse: Option<f32>,
+    /// The hitbox offsets of the attack's active frames, one per frame, for hitboxes that sweep
+    /// with the swing. The last offset is kept for the rest of the active frames. The hitbox stays
+    /// at its static offset if this is not set.
+    #[serde(default)]
+    pub hitbox_path: Option<Vec<Vec2>>,
+    /// How the target reacts to the hit, knocked back by default
+    #[serde(default)]
+    pub reaction: HitReaction,
+    /// Recoil pushes the attacker backward with this velocity when it starts the attack, for heavy
+    /// weapons. The x velocity is always away from the direction the attacker is facing.
+    #[serde(default)]
+    pub self_knockback: Option<Vec2>,
+    /// The fraction of the attacker's movement velocity that carries into the attack when it
+    /// starts, fading out over the start of the attack like recoil. Attacks stop the attacker in
+    /// place with the default of zero. Shots never carry momentum, since the fighter stands still
+    /// until the shot goes off.
+    #[serde(default)]
+    pub momentum_retention: f32,
+}
+
+/// A link of a fighter's combo string
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ComboLinkMeta {
+    /// The animation played for the link
+    pub animation: String,
+    /// The animation frame the link starts at, to skip the wind up of chained links
+    #[serde(default)]
+    pub start_frame: usize,
+    /// The animation frame from which the next link can be chained, until the end of the link.
+    /// Defaults to the frame after the active frames.
+    #[serde(default)]
+    pub cancel_frame: Option<usize>,
+    /// The attack of the link
+    #[serde(flatten)]
+    pub attack: AttackMeta,
+}
+
+impl ComboLinkMeta {
+    /// The first animation frame the next link can be chained from
+    pub fn cancel_frame(&self) -> usize {
+        self.cancel_frame.unwrap_or(self.attack.frames.active + 1)
+    }
+}
+
+/// The cinematic of a boss showing up, framing it under its name banner
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct BossIntroMeta {
+    /// The localization key of the name banner
+    pub banner: String,
+    /// The sound played as the intro starts, such as a roar
+    #[serde(default)]
+    pub sound: Option<String>,
+    #[serde(skip)]
+    pub sound_handle: Option<Handle<AudioSource>>,
+    /// How long, in seconds, the camera frames the boss
+    pub duration: f32,
+}
+
+/// Minions a boss calls in at an interval during its fight
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct ReinforcementsMeta {
+    /// The relative asset path to the fighter of the minions
+    pub fighter: String,
+    #[serde(skip)]
+    pub fighter_handle: Handle<FighterMeta>,
+    /// How many minions are called in at a time
+    pub count: usize,
+    /// The time, in seconds, between two calls
+    pub interval: f32,
+}
+
+/// An attack performed while the fighter is off the ground, picked by the direction held
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct AirAttackMeta {
+    /// The direction held for the attack, neutral by default
+    #[serde(default)]
+    pub direction: AirAttackDirection,
+    /// The animation played for the attack
+    pub animation: String,
+    /// The velocity the fighter moves with during the attack, when facing right. This is separate
+    /// from the `velocity` the attack knocks its target back with.
+    #[serde(default)]
+    pub movement: Vec2,
+    /// The attack of the air attack
+    #[serde(flatten)]
+    pub attack: AttackMeta,
+}
+
+/// The direction held for an air attack
+#[derive(Deserialize, Default, Copy, Clone, Debug, PartialEq, Eq, Reflect, FromReflect)]
+pub enum AirAttackDirection {
+    /// No direction, or up
+    #[default]
+    Neutral,
+    /// Left or right, in the direction the fighter is going
+    Forward,
+    Down,
+}
+
+impl AirAttackDirection {
+    /// Get the direction of a movement stick
+    pub fn from_stick(stick: Vec2) -> Self {
+        if stick.y < -0.5 && stick.y.abs() >= stick.x.abs() {
+            Self::Down
+        } else if stick.x.abs() > 0.5 {
+            Self::Forward
+        } else {
+            Self::Neutral
+        }
+    }
+}
+
+/// How a fighter reacts to being hit
+#[derive(Deserialize, Default, Copy, Clone, Debug, PartialEq, Eq, Reflect, FromReflect)]
+pub enum HitReaction {
+    /// Stunned in place for a moment
+    Stagger,
+    /// Stunned and pushed away by the hit
+    #[default]
+    Knockback,
+    /// Pushed away and knocked to the ground, then getting up
+    Knockdown,
+    /// Knocked up into the air, where the following hits juggle the fighter
+    Launch,
+}
+
+/// The damage over time dealt by a fire attack
+#[derive(Deserialize, Default, Copy, Clone, Debug, Reflect, FromReflect)]
+#[serde(deny_unknown_fields)]
+pub struct BurnMeta {
+    /// The damage dealt per second
+    pub dps: f32,
+    /// How long the target burns for, in seconds
+    pub duration: f32,
 }
 
 #[derive(TypeUuid, Deserialize, Clone, Debug, Component)]
 #[serde(deny_unknown_fields)]
 #[uuid = "5e2db270-ec2e-013a-92a8-2cf05d71216b"]
 pub struct ItemMeta {
+    /// The [version](migration) of the item file
+    #[serde(default = "migration::current_version")]
+    pub version: u32,
     pub name: String,
     pub image: ImageMeta,
     pub kind: ItemKind,
@@ -180,6 +944,14 @@ pub enum ItemKind {
         bullet_lifetime: f32,
         ammo: usize,
         shoot_delay: f32,
+        /// The maximum number of the weapon's bullets alive at once. Firing past it despawns the
+        /// oldest bullet. The bullets are not capped if this is not set.
+        #[serde(default)]
+        max_bullets: Option<usize>,
+        /// How the damage of the weapon's bullets falls off as they travel. Bullets deal their full
+        /// damage over their whole range if this is not set.
+        #[serde(default)]
+        damage_falloff: Option<DamageFalloffMeta>,
     },
     Script {
         /// The relative asset path to the script for this item
@@ -197,6 +969,34 @@ pub enum ItemKind {
     },
 }
 
+impl ItemKind {
+    /// Get the throw velocity and the gravity of the item when it is thrown, or `None` for items
+    /// that aren't thrown as a projectile.
+    pub fn throw_motion(&self) -> Option<(Vec2, f32)> {
+        match self {
+            ItemKind::Throwable {
+                throw_velocity,
+                gravity,
+                ..
+            }
+            | ItemKind::BreakableBox {
+                throw_velocity,
+                gravity,
+                ..
+            }
+            | ItemKind::Bomb {
+                throw_velocity,
+                gravity,
+                ..
+            } => Some((*throw_velocity, *gravity)),
+            ItemKind::MeleeWeapon { .. } | ItemKind::ProjectileWeapon { .. } => {
+                Some((consts::WEAPON_THROW_VELOCITY, consts::WEAPON_THROW_GRAVITY))
+            }
+            ItemKind::Script { .. } => None,
+        }
+    }
+}
+
 #[derive(Deserialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct FighterHudMeta {
@@ -222,9 +1022,107 @@ pub struct AudioMeta {
     pub effects: HashMap<String, HashMap<usize, String>>,
     #[serde(skip)]
     pub effect_handles: HashMap<String, HashMap<usize, Handle<AudioSource>>>,
+    /// The footstep sounds of a fighter while it is moving
+    #[serde(default)]
+    pub footsteps: Option<FootstepsMeta>,
+    /// The sound played when an attack's active frames pass without hitting anything
+    #[serde(default)]
+    pub whiff: Option<String>,
+    #[serde(skip)]
+    pub whiff_handle: Option<Handle<AudioSource>>,
+    /// The voice clips a fighter shouts when entering some of its states
+    #[serde(default)]
+    pub voices: Option<VoicesMeta>,
+    /// How much a fighter's hit and hurt sounds vary every time they play. They always play the
+    /// same if this is not set.
+    #[serde(default)]
+    pub variation: Option<SoundVariationMeta>,
 }
 
-#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+/// A random variation of the pitch and volume of sounds, so that the same sound played over and
+/// over doesn't get repetitive
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct SoundVariationMeta {
+    /// The most the pitch goes up or down by, as a fraction of it
+    pub pitch: f64,
+    /// The most the volume goes down by, as a fraction of it
+    #[serde(default)]
+    pub volume: f64,
+}
+
+/// How the damage of a projectile falls off as it travels, from its full damage when fired
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct DamageFalloffMeta {
+    /// What the travel of the projectile is measured by
+    pub over: FalloffRange,
+    /// The fraction of its damage the projectile still deals at the end of its range
+    pub min_multiplier: f32,
+}
+
+/// The range over which the damage of a projectile falls off
+#[derive(Deserialize, Clone, Copy, Debug)]
+#[serde(deny_unknown_fields)]
+pub enum FalloffRange {
+    /// The distance from where the projectile was fired at which it deals its minimum damage
+    Distance(f32),
+    /// The lifetime of the projectile, which deals its minimum damage as it expires
+    Lifetime,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct VoicesMeta {
+    /// The clips of each state, one being randomly chosen every time the fighter enters the state
+    pub states: HashMap<VoiceState, Vec<String>>,
+    #[serde(skip)]
+    pub state_handles: HashMap<VoiceState, Vec<Handle<AudioSource>>>,
+    /// The shortest time, in seconds, between two voice clips of a fighter. Dying always gets its
+    /// voice clip.
+    pub cooldown: f32,
+}
+
+/// The fighter states that can have voice clips
+#[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum VoiceState {
+    /// Starting any attack
+    Attacking,
+    /// Getting hit, into hit stun or knocked down
+    Hurt,
+    Dying,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(deny_unknown_fields)]
+pub struct FootstepsMeta {
+    /// The sounds a footstep is randomly chosen from
+    pub sounds: Vec<String>,
+    #[serde(skip)]
+    pub sound_handles: Vec<Handle<AudioSource>>,
+    /// The time between two footsteps, in seconds, which must be positive
+    #[serde(deserialize_with = "deserialize_positive_seconds")]
+    pub cadence: f32,
+}
+
+/// Deserialize a time in seconds that repeating timers run on, rejecting times that aren't positive
+fn deserialize_positive_seconds<'de, D>(de: D) -> Result<f32, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let seconds = f32::deserialize(de)?;
+    if seconds > 0.0 && seconds.is_finite() {
+        Ok(seconds)
+    } else {
+        Err(serde::de::Error::invalid_value(
+            serde::de::Unexpected::Float(seconds.into()),
+            &"a positive number of seconds",
+        ))
+    }
+}
+
+#[derive(HasLoadProgress, Deserialize, Serialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct FighterSpawnMeta {
     pub fighter: String,
@@ -232,17 +1130,36 @@ pub struct FighterSpawnMeta {
     pub fighter_handle: Handle<FighterMeta>,
     pub location: Vec3,
     // Set only for enemies.
-    #[serde(default = "default_f32_min")]
+    #[serde(default = "default_f32_min", skip_serializing_if = "is_f32_min")]
     pub trip_point_x: f32,
     #[serde(default)]
     pub boss: bool,
+    /// How the enemy enters the level. Enemies without an entrance are simply placed at their
+    /// location.
+    #[serde(default)]
+    #[has_load_progress(none)]
+    pub entrance: Option<EntranceStyle>,
+}
+
+/// The way an enemy makes its entrance, before handing over to the AI
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum EntranceStyle {
+    /// Walk in from the nearest edge of the screen
+    Walk,
+    /// Drop down from above the screen
+    Drop,
 }
 
 fn default_f32_min() -> f32 {
     f32::MIN
 }
 
-#[derive(HasLoadProgress, TypeUuid, Deserialize, Clone, Debug)]
+fn is_f32_min(value: &f32) -> bool {
+    *value == f32::MIN
+}
+
+#[derive(HasLoadProgress, TypeUuid, Deserialize, Serialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 #[uuid = "f5092550-ec30-013a-92a9-2cf05d71216b"]
 pub struct ItemSpawnMeta {
@@ -252,7 +1169,7 @@ pub struct ItemSpawnMeta {
     pub location: Vec3,
 }
 
-#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[derive(HasLoadProgress, Deserialize, Serialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct ParallaxMeta {
     pub layers: Vec<ParallaxLayerMeta>,
@@ -264,7 +1181,7 @@ impl ParallaxMeta {
     }
 }
 
-#[derive(HasLoadProgress, Deserialize, Clone, Debug)]
+#[derive(HasLoadProgress, Deserialize, Serialize, Clone, Debug)]
 #[serde(deny_unknown_fields)]
 pub struct ParallaxLayerMeta {
     pub speed: f32,
@@ -304,3 +1221,94 @@ pub struct ColliderMeta {
     pub size: Vec2,
     pub offset: Vec2,
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const FIGHTER_YAML: &str = "
+name: Test Fighter
+hurtbox:
+  size: [36, 48]
+  offset: [0, 0]
+stats:
+  max_health: 100
+  movement_speed: 150
+hud:
+  portrait:
+    image: portrait.png
+    image_size: [35, 35]
+spritesheet:
+  image: [fighter.png]
+  tile_size: [96, 80]
+  columns: 1
+  rows: 1
+  animation_fps: 0.12
+  animations: {}
+audio:
+  effects: {}
+attacks: []
+";
+
+    #[test]
+    fn test_fighter_collision_offset_validation() {
+        let mut fighter: FighterMeta = serde_yaml::from_str(FIGHTER_YAML).unwrap();
+
+        fighter.collision_offset = 24.;
+        assert!(fighter.validate().is_empty());
+
+        fighter.collision_offset = 200.;
+        let warnings = fighter.validate();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].contains("collision offset"));
+    }
+
+    #[test]
+    fn test_footstep_cadence_must_be_positive() {
+        let footsteps = |cadence: &str| {
+            serde_yaml::from_str::<FootstepsMeta>(&format!(
+                "{{ sounds: [step.ogg], cadence: {cadence} }}"
+            ))
+        };
+
+        assert_eq!(footsteps("0.25").unwrap().cadence, 0.25);
+        assert!(footsteps("0").is_err());
+        assert!(footsteps("-0.25").is_err());
+    }
+
+    #[test]
+    fn test_air_attack_embeds_its_attack() {
+        let air_attack: AirAttackMeta = serde_yaml::from_str(
+            "
+animation: dive
+movement: [100, -250]
+name: air
+damage: 40
+frames: { startup: 0, active: 4, recovery: 5 }
+hitbox: { size: [32, 32], offset: [16, -16] }
+hitstun_duration: 0.3
+velocity: [0, 200]
+reaction: Knockdown
+",
+        )
+        .unwrap();
+        assert_eq!(air_attack.movement, Vec2::new(100., -250.));
+        assert_eq!(air_attack.attack.damage, 40);
+        assert_eq!(air_attack.attack.velocity, Some(Vec2::new(0., 200.)));
+        assert_eq!(air_attack.attack.reaction, HitReaction::Knockdown);
+
+        // Unknown fields are still rejected through the embedded attack
+        assert!(serde_yaml::from_str::<AirAttackMeta>(
+            "
+animation: dive
+name: air
+damage: 40
+frames: { startup: 0, active: 4, recovery: 5 }
+hitbox: { size: [32, 32], offset: [16, -16] }
+hitstun_duration: 0.3
+knockback: [0, 200]
+",
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,137 @@
+//! Versioning of the fighter, item, and level asset files.
+//!
+//! Asset files have a `version` field, and files without one are version 1, the format from
+//! before the files were versioned. When a schema changes in a way that breaks existing files, the
+//! version is bumped and a migration is added that upgrades the files from the previous version,
+//! so that existing content keeps working as the metadata schemas evolve.
+
+use anyhow::{anyhow, bail};
+use serde::de::DeserializeOwned;
+use serde_yaml::{Mapping, Value};
+
+/// The current version of the metadata asset files
+pub const CURRENT_VERSION: u32 = 1;
+
+/// Upgrades the contents of an asset file from one version to the next
+pub type Migration = fn(&mut Mapping);
+
+/// The migrations of fighter files, starting from version 1
+pub const FIGHTER_MIGRATIONS: &[Migration] = &[];
+
+/// The migrations of item files, starting from version 1
+pub const ITEM_MIGRATIONS: &[Migration] = &[];
+
+/// The migrations of level files, starting from version 1
+pub const LEVEL_MIGRATIONS: &[Migration] = &[];
+
+pub fn current_version() -> u32 {
+    CURRENT_VERSION
+}
+
+/// Parse an asset file, migrating it to the latest version with the given migrations
+///
+/// The latest version is the one after the last migration, which is [`CURRENT_VERSION`] for the
+/// migrations of the asset files.
+pub fn load_migrated<T: DeserializeOwned>(
+    bytes: &[u8],
+    migrations: &[Migration],
+) -> anyhow::Result<T> {
+    let latest_version = migrations.len() as u64 + 1;
+
+    let mut value: Value = serde_yaml::from_slice(bytes)?;
+    let meta = value
+        .as_mapping_mut()
+        .ok_or_else(|| anyhow!("Expected the asset file to be a map"))?;
+
+    let version = match meta.get("version") {
+        Some(version) => version
+            .as_u64()
+            .ok_or_else(|| anyhow!("Expected the asset version to be a number"))?,
+        None => 1,
+    };
+    if version == 0 || version > latest_version {
+        bail!(
+            "Unsupported asset version {}, the supported versions are 1 to {}",
+            version,
+            latest_version
+        );
+    }
+
+    for migrate in &migrations[version as usize - 1..] {
+        migrate(meta);
+    }
+    meta.insert("version".into(), latest_version.into());
+
+    Ok(serde_yaml::from_value(value)?)
+}
+
+#[cfg(test)]
+mod test {
+    use serde::Deserialize;
+
+    use super::*;
+    use crate::metadata::FighterMeta;
+
+    /// A version 2 of a made up asset, that renamed `health` to `max_health`
+    #[derive(Deserialize)]
+    #[serde(deny_unknown_fields)]
+    struct TestMeta {
+        version: u32,
+        name: String,
+        max_health: u32,
+    }
+
+    fn rename_health(meta: &mut Mapping) {
+        if let Some(health) = meta.remove("health") {
+            meta.insert("max_health".into(), health);
+        }
+    }
+
+    const TEST_MIGRATIONS: &[Migration] = &[rename_health];
+
+    #[test]
+    fn test_migration_lists_reach_the_current_version() {
+        for migrations in [FIGHTER_MIGRATIONS, ITEM_MIGRATIONS, LEVEL_MIGRATIONS] {
+            assert_eq!(migrations.len() as u32 + 1, CURRENT_VERSION);
+        }
+    }
+
+    #[test]
+    fn test_older_versions_are_migrated() {
+        // Files without a version are version 1
+        let meta: TestMeta =
+            load_migrated("name: Old\nhealth: 80".as_bytes(), TEST_MIGRATIONS).unwrap();
+        assert_eq!(meta.version, 2);
+        assert_eq!(meta.name, "Old");
+        assert_eq!(meta.max_health, 80);
+
+        // Files at the latest version are loaded as they are
+        let meta: TestMeta = load_migrated(
+            "version: 2\nname: New\nmax_health: 120".as_bytes(),
+            TEST_MIGRATIONS,
+        )
+        .unwrap();
+        assert_eq!(meta.version, 2);
+        assert_eq!(meta.max_health, 120);
+
+        // Versions from the future are reported, rather than loaded as the latest one
+        let error = load_migrated::<TestMeta>(
+            "version: 3\nname: Future\nmax_health: 1".as_bytes(),
+            TEST_MIGRATIONS,
+        )
+        .unwrap_err()
+        .to_string();
+        assert!(error.contains("Unsupported asset version 3"));
+    }
+
+    #[test]
+    fn test_current_fighter_files_load() {
+        let fighter: FighterMeta = load_migrated(
+            include_bytes!("../../assets/fighters/dev/dev.fighter.yaml"),
+            FIGHTER_MIGRATIONS,
+        )
+        .unwrap();
+
+        assert_eq!(fighter.version, CURRENT_VERSION);
+    }
+}
@@ -1,21 +1,190 @@
-use bevy::prelude::Gamepad;
-use leafwing_input_manager::{axislike::VirtualDPad, prelude::InputMap, user_input::InputKind};
+use bevy::prelude::{warn, Gamepad, Resource, Vec2};
+use leafwing_input_manager::{
+    axislike::{SingleAxis, VirtualDPad},
+    prelude::InputMap,
+    user_input::InputKind,
+};
 use punchy_macros::HasLoadProgress;
 use serde::{Deserialize, Serialize};
 
-use crate::input::PlayerAction;
+use crate::{input::PlayerAction, platform::Storage};
 
 /// Global settings, stored and accessed through [`crate::platform::Storage`]
-#[derive(HasLoadProgress, Deserialize, Serialize, Debug, Clone)]
+///
+/// The stored settings are kept loaded in a resource by [`crate::platform::load_settings`].
+#[derive(Resource, HasLoadProgress, Deserialize, Serialize, Debug, Clone)]
 #[has_load_progress(none)]
 pub struct Settings {
     // The player controller bindings
     pub player_controls: PlayerControlMethods,
+    /// Whether the second player is controlled by the assist AI, for solo play
+    #[serde(default)]
+    pub assist_ai: bool,
+    /// The CRT screen effect
+    #[serde(default)]
+    pub crt: CrtSettings,
+    /// The input device whose button glyphs are shown, or `None` for the one used last
+    #[serde(default)]
+    pub input_glyphs: Option<InputDevice>,
+    /// The music and sound effect volumes
+    #[serde(default)]
+    pub audio: AudioSettings,
+    /// Options that make the game more comfortable to play
+    #[serde(default)]
+    pub accessibility: AccessibilitySettings,
+    /// The frame pacing of the game window
+    #[serde(default)]
+    pub video: VideoSettings,
 }
 
 impl Settings {
     /// The key used to store the settings in the [`crate::platform::Storage`] resource.
     pub const STORAGE_KEY: &'static str = "settings";
+
+    /// Get the stored settings, filling in the settings missing from storage with the `defaults`.
+    ///
+    /// The defaults are used for the stored settings that can't be read, so that one bad value
+    /// doesn't reset all the other settings.
+    pub fn load(storage: &Storage, defaults: &Settings) -> Settings {
+        match storage.get::<serde_yaml::Value>(Self::STORAGE_KEY) {
+            Some(stored) => Self::merged(stored, defaults),
+            None => defaults.clone(),
+        }
+    }
+
+    /// Merge stored settings over the `defaults`, keeping the defaults for the missing or invalid
+    /// settings
+    pub fn merged(stored: serde_yaml::Value, defaults: &Settings) -> Settings {
+        let defaults_value = serde_yaml::to_value(defaults).expect("Serialize default settings");
+        let mut merged = defaults_value.clone();
+        merge_yaml(&mut merged, stored.clone());
+        if let Ok(settings) = serde_yaml::from_value(merged) {
+            return settings;
+        }
+
+        // Keep the valid top-level settings one by one
+        let mut merged = defaults_value;
+        if let serde_yaml::Value::Mapping(stored) = stored {
+            for (key, value) in stored {
+                let mut candidate = merged.clone();
+                let mut setting = serde_yaml::Mapping::new();
+                setting.insert(key.clone(), value);
+                merge_yaml(&mut candidate, serde_yaml::Value::Mapping(setting));
+
+                if serde_yaml::from_value::<Settings>(candidate.clone()).is_ok() {
+                    merged = candidate;
+                } else {
+                    warn!("Invalid stored setting {key:?}, using the default instead");
+                }
+            }
+        }
+
+        serde_yaml::from_value(merged).unwrap_or_else(|_| defaults.clone())
+    }
+}
+
+/// Recursively merge the `overlay` mappings into the `base` ones, replacing any other values
+fn merge_yaml(base: &mut serde_yaml::Value, overlay: serde_yaml::Value) {
+    match (base, overlay) {
+        (serde_yaml::Value::Mapping(base), serde_yaml::Value::Mapping(overlay)) => {
+            for (key, value) in overlay {
+                match base.get_mut(&key) {
+                    Some(base_value) => merge_yaml(base_value, value),
+                    None => {
+                        base.insert(key, value);
+                    }
+                }
+            }
+        }
+        (base, overlay) => *base = overlay,
+    }
+}
+
+/// The volumes of the audio channels, from 0 to 1
+#[derive(Resource, Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct AudioSettings {
+    pub music_volume: f64,
+    pub effects_volume: f64,
+}
+
+impl Default for AudioSettings {
+    fn default() -> Self {
+        Self {
+            music_volume: 0.5,
+            effects_volume: 0.5,
+        }
+    }
+}
+
+/// How the game window paces its frames
+#[derive(Resource, Deserialize, Serialize, Clone, Debug, PartialEq, Eq)]
+#[serde(deny_unknown_fields, default)]
+pub struct VideoSettings {
+    /// Whether frames are synced to the display's refresh rate
+    pub vsync: bool,
+    /// The most frames rendered per second, or `None` for no cap. Browsers always pace the frames
+    /// to the display, so this is ignored on the web.
+    pub max_fps: Option<u32>,
+}
+
+impl Default for VideoSettings {
+    fn default() -> Self {
+        Self {
+            vsync: true,
+            max_fps: None,
+        }
+    }
+}
+
+/// Accessibility options
+#[derive(Resource, Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct AccessibilitySettings {
+    /// How strongly the camera shakes, from 0 for no shake to 1 for the full shake
+    pub screen_shake: f32,
+    /// Turns off the purely cosmetic camera motion, such as the tilt on heavy impacts
+    pub reduced_motion: bool,
+}
+
+impl Default for AccessibilitySettings {
+    fn default() -> Self {
+        Self {
+            screen_shake: 1.0,
+            reduced_motion: false,
+        }
+    }
+}
+
+/// A kind of input device, with its own button glyphs
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq, Default)]
+pub enum InputDevice {
+    #[default]
+    Keyboard,
+    Xbox,
+    PlayStation,
+}
+
+/// Settings for the full-screen CRT effect, which curves the screen and darkens the gaps between
+/// the rows of pixels like an old TV.
+#[derive(Resource, Deserialize, Serialize, Clone, Debug, PartialEq)]
+#[serde(deny_unknown_fields, default)]
+pub struct CrtSettings {
+    pub enabled: bool,
+    /// How dark the gaps between the scanlines are, from 0 to 1
+    pub scanline_intensity: f32,
+    /// How much the screen bulges out, with 0 being flat
+    pub curvature: f32,
+}
+
+impl Default for CrtSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            scanline_intensity: 0.3,
+            curvature: 0.05,
+        }
+    }
 }
 
 #[derive(Deserialize, Serialize, Clone, Debug)]
@@ -37,28 +206,318 @@ impl PlayerControlMethods {
 
         let mut add_controls = |ctrls: &PlayerControls| {
             input_map.insert(ctrls.movement.clone(), PlayerAction::Move);
+            input_map.insert(ctrls.run, PlayerAction::Run);
             input_map.insert(ctrls.flop_attack, PlayerAction::Attack);
             input_map.insert(ctrls.shoot, PlayerAction::Shoot);
             input_map.insert(ctrls.throw, PlayerAction::Throw);
+            input_map.insert(ctrls.super_attack, PlayerAction::Super);
+            input_map.insert(ctrls.block, PlayerAction::Block);
+            if let Some(analog_attack) = &ctrls.analog_attack {
+                input_map.insert(analog_attack.axis.clone(), PlayerAction::AnalogAttack);
+            }
         };
 
         add_controls(&self.gamepad);
 
-        match player_idx {
-            0 => add_controls(&self.keyboard1),
-            1 => add_controls(&self.keyboard2),
-            _ => (),
+        if let Some(keyboard) = self.keyboard(player_idx) {
+            add_controls(keyboard);
         }
 
         input_map
     }
+
+    /// Get the keyboard controls for the given player index, if the player has any
+    pub fn keyboard(&self, player_idx: usize) -> Option<&PlayerControls> {
+        match player_idx {
+            0 => Some(&self.keyboard1),
+            1 => Some(&self.keyboard2),
+            _ => None,
+        }
+    }
+
+    /// Get the inputs bound for both keyboard players, which would control both of them at once
+    pub fn keyboard_conflicts(&self) -> Vec<InputKind> {
+        let keyboard1 = self.keyboard1.inputs();
+        self.keyboard2
+            .inputs()
+            .into_iter()
+            .filter(|input| keyboard1.contains(input))
+            .cloned()
+            .collect()
+    }
+
+    /// Resolve the conflicts created by binding an input for the keyboard player `player_idx`,
+    /// which replaced the `replaced` input.
+    ///
+    /// The other keyboard player gets the replaced input instead of the newly bound one, so that
+    /// the two players swap keys rather than sharing one.
+    pub fn resolve_keyboard_conflicts(&mut self, player_idx: usize, replaced: &InputKind) {
+        let conflicts = self.keyboard_conflicts();
+        let other = match player_idx {
+            0 => &mut self.keyboard2,
+            1 => &mut self.keyboard1,
+            _ => return,
+        };
+
+        for input in other.inputs_mut() {
+            if conflicts.contains(input) {
+                *input = replaced.clone();
+            }
+        }
+    }
 }
 
 /// Binds inputs to player actions
 #[derive(Deserialize, Serialize, Clone, Debug)]
 pub struct PlayerControls {
     pub movement: VirtualDPad,
+    pub run: InputKind,
     pub flop_attack: InputKind,
     pub throw: InputKind,
     pub shoot: InputKind,
+    pub super_attack: InputKind,
+    pub block: InputKind,
+    /// Optional analog attack trigger, in addition to the digital attack button
+    #[serde(default)]
+    pub analog_attack: Option<AnalogAttackControls>,
+    /// How attack inputs are buffered on this input device
+    #[serde(default)]
+    pub attack_buffer: AttackBufferControls,
+    /// How analog stick movement is turned into the fighter's movement speed
+    #[serde(default)]
+    pub stick_response: StickResponseControls,
+}
+
+impl PlayerControls {
+    /// The inputs bound to the player's actions, with each movement direction separately
+    fn inputs(&self) -> [&InputKind; 10] {
+        [
+            &self.movement.up,
+            &self.movement.down,
+            &self.movement.left,
+            &self.movement.right,
+            &self.run,
+            &self.flop_attack,
+            &self.throw,
+            &self.shoot,
+            &self.super_attack,
+            &self.block,
+        ]
+    }
+
+    /// The inputs bound to the player's actions, with each movement direction separately
+    fn inputs_mut(&mut self) -> [&mut InputKind; 10] {
+        [
+            &mut self.movement.up,
+            &mut self.movement.down,
+            &mut self.movement.left,
+            &mut self.movement.right,
+            &mut self.run,
+            &mut self.flop_attack,
+            &mut self.throw,
+            &mut self.shoot,
+            &mut self.super_attack,
+            &mut self.block,
+        ]
+    }
+}
+
+/// Analog movement stick settings
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct StickResponseControls {
+    /// The stick distance from the center, from `0.0` to `1.0`, under which the stick is ignored
+    pub deadzone: f32,
+    /// How the stick distance past the deadzone maps to the movement speed
+    pub curve: ResponseCurve,
+}
+
+impl Default for StickResponseControls {
+    fn default() -> Self {
+        Self {
+            deadzone: 0.15,
+            curve: ResponseCurve::Linear,
+        }
+    }
+}
+
+impl StickResponseControls {
+    /// Apply the radial deadzone and the response curve to a stick position
+    pub fn apply(&self, stick: Vec2) -> Vec2 {
+        let distance = stick.length();
+        if distance <= self.deadzone {
+            return Vec2::ZERO;
+        }
+
+        // Rescale the distance past the deadzone to the full range, so that there is no jump in speed
+        // when leaving the deadzone.
+        let scaled = ((distance - self.deadzone) / (1.0 - self.deadzone)).min(1.0);
+        let magnitude = match self.curve {
+            ResponseCurve::Linear => scaled,
+            ResponseCurve::Squared => scaled * scaled,
+        };
+
+        stick / distance * magnitude
+    }
+}
+
+/// The shape of the response of an analog input
+#[derive(Deserialize, Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ResponseCurve {
+    /// The response is proportional to the input
+    Linear,
+    /// The response is the square of the input, for finer control at small inputs
+    Squared,
+}
+
+/// Attack input buffering settings
+#[derive(Deserialize, Serialize, Clone, Copy, Debug)]
+#[serde(default)]
+pub struct AttackBufferControls {
+    /// How long, in seconds, an attack input is remembered while the fighter can't attack yet
+    pub window: f32,
+    /// Whether releasing the attack button also triggers an attack
+    pub negative_edge: bool,
+}
+
+impl Default for AttackBufferControls {
+    fn default() -> Self {
+        Self {
+            window: 0.15,
+            negative_edge: false,
+        }
+    }
+}
+
+/// Analog trigger binding, where the depth of the trigger pull decides the attack strength
+#[derive(Deserialize, Serialize, Clone, Debug)]
+pub struct AnalogAttackControls {
+    pub axis: SingleAxis,
+    /// The trigger depth past which the trigger counts as pressed
+    pub threshold: f32,
+    /// The trigger depth past which the attack is a heavy attack
+    pub heavy_threshold: f32,
+}
+
+/// The strength of an attack triggered with an analog input
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AttackStrength {
+    Light,
+    Heavy,
+}
+
+impl AnalogAttackControls {
+    /// Update the deepest trigger depth of the current pull with the current depth, and return the
+    /// strength of the attack to trigger, if any.
+    ///
+    /// A heavy attack is triggered as soon as the trigger is pulled past the heavy threshold, and a
+    /// light attack is triggered when the trigger is released without having reached it.
+    pub fn update(&self, peak_depth: &mut f32, depth: f32) -> Option<AttackStrength> {
+        let previous_peak = *peak_depth;
+        *peak_depth = if depth >= self.threshold {
+            previous_peak.max(depth)
+        } else {
+            0.0
+        };
+
+        if previous_peak < self.heavy_threshold && *peak_depth >= self.heavy_threshold {
+            Some(AttackStrength::Heavy)
+        } else if depth < self.threshold
+            && previous_peak >= self.threshold
+            && previous_peak < self.heavy_threshold
+        {
+            Some(AttackStrength::Light)
+        } else {
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy::prelude::KeyCode;
+    use leafwing_input_manager::user_input::UserInput;
+
+    use super::*;
+    use crate::metadata::GameMeta;
+
+    #[test]
+    fn test_keyboard_players_have_separate_keys() {
+        let game: GameMeta =
+            serde_yaml::from_str(include_str!("../../assets/default.game.yaml")).unwrap();
+        let mut controls = game.default_settings.player_controls;
+        assert!(controls.keyboard_conflicts().is_empty());
+
+        // Each keyboard player attacks with their own key
+        let attacks_with = |controls: &PlayerControlMethods, player_idx: usize, key: KeyCode| {
+            controls
+                .get_input_map(player_idx)
+                .get(PlayerAction::Attack)
+                .iter()
+                .any(|input| *input == UserInput::Single(InputKind::Keyboard(key)))
+        };
+        assert!(attacks_with(&controls, 0, KeyCode::Space));
+        assert!(!attacks_with(&controls, 0, KeyCode::Comma));
+        assert!(attacks_with(&controls, 1, KeyCode::Comma));
+        assert!(!attacks_with(&controls, 1, KeyCode::Space));
+
+        // Binding the other player's key swaps the keys of the two players
+        let replaced = std::mem::replace(
+            &mut controls.keyboard1.flop_attack,
+            InputKind::Keyboard(KeyCode::Comma),
+        );
+        assert_eq!(
+            controls.keyboard_conflicts(),
+            [InputKind::Keyboard(KeyCode::Comma)]
+        );
+        controls.resolve_keyboard_conflicts(0, &replaced);
+        assert!(controls.keyboard_conflicts().is_empty());
+        assert!(attacks_with(&controls, 0, KeyCode::Comma));
+        assert!(attacks_with(&controls, 1, KeyCode::Space));
+    }
+
+    #[test]
+    fn test_settings_round_trip() {
+        let game: GameMeta =
+            serde_yaml::from_str(include_str!("../../assets/default.game.yaml")).unwrap();
+        let mut settings = game.default_settings;
+        settings.assist_ai = true;
+        settings.audio.music_volume = 0.25;
+        settings.accessibility.screen_shake = 0.0;
+        settings.player_controls.keyboard1.block = InputKind::Keyboard(KeyCode::Z);
+
+        let serialized = serde_yaml::to_string(&settings).unwrap();
+        let deserialized: Settings = serde_yaml::from_str(&serialized).unwrap();
+        assert_eq!(
+            serde_yaml::to_value(&deserialized).unwrap(),
+            serde_yaml::to_value(&settings).unwrap()
+        );
+        assert_eq!(
+            deserialized.player_controls.keyboard1.block,
+            InputKind::Keyboard(KeyCode::Z)
+        );
+    }
+
+    #[test]
+    fn test_partial_and_invalid_settings_use_defaults() {
+        let game: GameMeta =
+            serde_yaml::from_str(include_str!("../../assets/default.game.yaml")).unwrap();
+        let defaults = game.default_settings;
+
+        // Missing settings, like the controls here, are filled in from the defaults, and invalid
+        // ones are replaced by them
+        let stored = serde_yaml::from_str(
+            "assist_ai: true\naudio:\n  music_volume: 0.2\ncrt: not a crt setting\n",
+        )
+        .unwrap();
+        let settings = Settings::merged(stored, &defaults);
+        assert!(settings.assist_ai);
+        assert_eq!(settings.audio.music_volume, 0.2);
+        assert_eq!(settings.audio.effects_volume, defaults.audio.effects_volume);
+        assert_eq!(settings.crt, defaults.crt);
+        assert_eq!(
+            serde_yaml::to_value(&settings.player_controls).unwrap(),
+            serde_yaml::to_value(&defaults.player_controls).unwrap()
+        );
+    }
 }
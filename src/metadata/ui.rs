@@ -1,5 +1,7 @@
 use std::sync::Arc;
 
+use bevy::input::gamepad::GamepadButtonType;
+
 use super::*;
 
 #[derive(HasLoadProgress, Deserialize, Clone, Debug)]
@@ -15,6 +17,30 @@ pub struct UIThemeMeta {
     pub button_styles: HashMap<ButtonStyle, ButtonThemeMeta>,
 }
 
+/// The glyphs shown for the buttons of each kind of gamepad, such as `South: A` for Xbox
+/// controllers. Buttons without a glyph, and keyboard keys, are shown by name.
+#[derive(HasLoadProgress, Deserialize, Clone, Debug, Default)]
+#[serde(deny_unknown_fields, default)]
+#[has_load_progress(none)]
+pub struct InputGlyphsMeta {
+    pub xbox: HashMap<GamepadButtonType, String>,
+    pub playstation: HashMap<GamepadButtonType, String>,
+}
+
+impl InputGlyphsMeta {
+    /// Get the button glyphs of an input device, or `None` for the keyboard
+    pub fn gamepad_buttons(
+        &self,
+        device: InputDevice,
+    ) -> Option<&HashMap<GamepadButtonType, String>> {
+        match device {
+            InputDevice::Keyboard => None,
+            InputDevice::Xbox => Some(&self.xbox),
+            InputDevice::PlayStation => Some(&self.playstation),
+        }
+    }
+}
+
 #[derive(Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash)]
 #[serde(deny_unknown_fields)]
 #[serde(try_from = "String")]
@@ -106,6 +132,9 @@ pub struct HudThemeMeta {
     pub portrait_frame: BorderImageMeta,
     pub font: FontMeta,
     pub lifebar: ProgressBarMeta,
+    /// The color of each player's name in their HUD panel, by player index
+    #[serde(default)]
+    pub player_colors: Vec<ColorMeta>,
 }
 
 #[derive(HasLoadProgress, Deserialize, Clone, Debug)]
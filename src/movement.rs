@@ -1,15 +1,22 @@
+use std::time::Duration;
+
 use bevy::{
     math::{Quat, Vec2},
     prelude::*,
-    time::Time,
+    utils::HashSet,
 };
 use iyes_loopless::prelude::*;
 
 use crate::{
+    attack::FlashingTimer,
+    camera::max_player_x,
     consts::{self, LEFT_BOUNDARY_MAX_DISTANCE},
+    damage::HitPause,
     enemy::SpawnLocationX,
     metadata::{GameMeta, LevelMeta},
-    GameState, Player,
+    player::LivingPlayer,
+    utils::GameTime,
+    GameState,
 };
 
 /// Plugin handling movement and rotation through velocities and torques.
@@ -31,6 +38,9 @@ impl Plugin for MovementPlugin {
             .register_type::<Torque>()
             // Init resources
             .init_resource::<LeftMovementBoundary>()
+            .init_resource::<PassedStopPoints>()
+            .add_event::<StopPointBlockedEvent>()
+            .add_system(stop_point_blocked_flash.run_in_state(GameState::InGame))
             // Add systems that modify velocity based on forces
             .add_system_set_to_stage(
                 CoreStage::PostUpdate,
@@ -71,7 +81,12 @@ impl Plugin for MovementPlugin {
 pub struct LinearVelocity(pub Vec2);
 
 /// System that updates translations based on entity velocities.
-pub fn velocity_system(mut query: Query<(&mut Transform, &LinearVelocity)>, time: Res<Time>) {
+///
+/// Entities in a [`HitPause`] don't move.
+pub fn velocity_system(
+    mut query: Query<(&mut Transform, &LinearVelocity), Without<HitPause>>,
+    time: GameTime,
+) {
     for (mut transform, dir) in &mut query.iter_mut() {
         transform.translation += dir.0.extend(0.) * time.delta_seconds();
     }
@@ -94,7 +109,7 @@ impl AngularVelocity {
 /// System that applies rotations based on entity torques.
 pub fn angular_velocity_system(
     mut query: Query<(&mut Transform, &AngularVelocity)>,
-    time: Res<Time>,
+    time: GameTime,
 ) {
     for (mut transform, torque) in &mut query.iter_mut() {
         transform.rotation *= Quat::from_rotation_z(**torque * time.delta_seconds());
@@ -107,7 +122,7 @@ pub fn angular_velocity_system(
 pub struct Force(pub Vec2);
 
 // Applies forces to linear velocities
-pub fn force_system(mut query: Query<(&mut LinearVelocity, &Force)>, time: Res<Time>) {
+pub fn force_system(mut query: Query<(&mut LinearVelocity, &Force)>, time: GameTime) {
     for (mut velocity, force) in &mut query.iter_mut() {
         **velocity += **force * time.delta_seconds();
     }
@@ -119,7 +134,7 @@ pub fn force_system(mut query: Query<(&mut LinearVelocity, &Force)>, time: Res<T
 pub struct Torque(pub f32);
 
 // Applies torques to angular velocities
-pub fn torque_system(mut query: Query<(&mut AngularVelocity, &Torque)>, time: Res<Time>) {
+pub fn torque_system(mut query: Query<(&mut AngularVelocity, &Torque)>, time: GameTime) {
     for (mut velocity, torque) in &mut query.iter_mut() {
         **velocity += **torque * time.delta_seconds();
     }
@@ -127,7 +142,7 @@ pub fn torque_system(mut query: Query<(&mut AngularVelocity, &Torque)>, time: Re
 
 // (Moving) bondary before which, the players can't go back.
 #[derive(Resource)]
-pub struct LeftMovementBoundary(f32);
+pub struct LeftMovementBoundary(pub f32);
 
 impl Default for LeftMovementBoundary {
     fn default() -> Self {
@@ -137,64 +152,132 @@ impl Default for LeftMovementBoundary {
 
 /// Updates player left movement boundary
 pub fn update_left_movement_boundary(
-    query: Query<&Transform, With<Player>>,
+    query: Query<&Transform, LivingPlayer>,
     mut boundary: ResMut<LeftMovementBoundary>,
     game_meta: Res<GameMeta>,
 ) {
-    let max_player_x = query
-        .iter()
-        .map(|transform| transform.translation.x)
-        .max_by(|ax, bx| ax.total_cmp(bx));
-
-    if let Some(max_player_x) = max_player_x {
+    if let Some(max_player_x) = max_player_x(query.iter()) {
         boundary.0 = boundary
             .0
             .max(max_player_x - game_meta.camera_move_right_boundary - LEFT_BOUNDARY_MAX_DISTANCE);
     }
 }
 
+/// The indices of the level stop points that the players have passed.
+///
+/// Passed stop points never block the players again, even if enemies are later spawned behind
+/// them.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct PassedStopPoints(pub HashSet<usize>);
+
+/// Event sent when a player starts pushing against an active stop point
+pub struct StopPointBlockedEvent {
+    pub player: Entity,
+}
+
+/// Limits a player's x velocity when approaching an active stop point.
+///
+/// With a non-zero `deceleration` the player is slowed down so that it comes to a rest at the stop
+/// point, instead of hitting an invisible wall. In any case the player is never moved past the
+/// stop point.
+fn stop_point_velocity_x(
+    location_x: f32,
+    velocity_x: f32,
+    stop_point_x: f32,
+    deceleration: f32,
+    dt: f32,
+) -> f32 {
+    let mut velocity_x = velocity_x;
+
+    if deceleration > 0. {
+        // The highest speed from which we can still come to a rest before the stop point
+        let distance = (stop_point_x - location_x).max(0.);
+        velocity_x = velocity_x.min((2. * deceleration * distance).sqrt());
+    }
+
+    // Can be simplified, but it's harder to understand.
+    if location_x + velocity_x * dt > stop_point_x {
+        velocity_x = 0.;
+    }
+
+    velocity_x
+}
+
+/// Marker component for players that ignore the movement constraints, toggled by the debug console
+#[derive(Component)]
+pub struct NoClip;
+
 /// Constrains player movement based on multiple factors
 fn constrain_player_movement(
     enemy_spawn_locations_query: Query<&'static SpawnLocationX>,
     level_meta: Res<LevelMeta>,
     game_meta: Res<GameMeta>,
     left_movement_boundary: Res<LeftMovementBoundary>,
-    mut players: Query<(&Transform, &mut LinearVelocity), With<Player>>,
-    time: Res<Time>,
+    mut players: Query<(Entity, &Transform, &mut LinearVelocity), (LivingPlayer, Without<NoClip>)>,
+    mut blocked_players: Local<HashSet<Entity>>,
+    mut blocked_events: EventWriter<StopPointBlockedEvent>,
+    mut passed_stop_points: ResMut<PassedStopPoints>,
+    time: GameTime,
 ) {
     let dt = time.delta_seconds();
 
     // Collect player positions and velocities
     let mut player_velocities = players
         .iter_mut()
-        .map(|(transform, vel)| (transform.translation, vel))
+        .map(|(entity, transform, vel)| (entity, transform.translation, vel))
         .collect::<Vec<_>>();
 
-    // Identify the current stop point
-    let current_stop_point = level_meta.stop_points.iter().find(|point_x| {
-        player_velocities
-            .iter()
-            .any(|(location, dir)| location.x < **point_x && **point_x <= location.x + dir.x)
-    });
+    // Identify the current stop point, ignoring the ones already passed
+    let current_stop_point = level_meta
+        .stop_points
+        .iter()
+        .enumerate()
+        .find(|&(i, &point_x)| {
+            !passed_stop_points.contains(&i)
+                && player_velocities
+                    .iter()
+                    .any(|(_, location, dir)| location.x < point_x && point_x <= location.x + dir.x)
+        });
+
+    let mut still_blocked_players = HashSet::new();
 
     // If there is a current stop point
-    if let Some(current_stop_point) = current_stop_point {
+    if let Some((stop_point_i, &current_stop_point)) = current_stop_point {
         let any_enemy_behind_stop_point = enemy_spawn_locations_query
             .iter()
-            .any(|SpawnLocationX(spawn_x)| spawn_x <= current_stop_point);
+            .any(|SpawnLocationX(spawn_x)| *spawn_x <= current_stop_point);
 
         // Prevent movement beyond the stop point if there are enemies not yet defeated behind the
         // stop point.
         if any_enemy_behind_stop_point {
-            for (location, velocity) in player_velocities.iter_mut() {
-                // Can be simplified, but it's harder to understand.
-                if location.x + velocity.x * dt > *current_stop_point {
-                    velocity.x = 0.;
+            for (entity, location, velocity) in player_velocities.iter_mut() {
+                let new_velocity_x = stop_point_velocity_x(
+                    location.x,
+                    velocity.x,
+                    current_stop_point,
+                    game_meta.stop_point.deceleration,
+                    dt,
+                );
+
+                // The player is pushing against the stop point
+                if new_velocity_x < velocity.x {
+                    still_blocked_players.insert(*entity);
+
+                    if !blocked_players.contains(entity) {
+                        blocked_events.send(StopPointBlockedEvent { player: *entity });
+                    }
                 }
+
+                velocity.x = new_velocity_x;
             }
+        } else {
+            // The stop point is cleared, so it won't block the players again
+            passed_stop_points.insert(stop_point_i);
         }
     }
 
+    *blocked_players = still_blocked_players;
+
     // Then, we perform the absolute clamping (screen top/left/bottom), and we collect the data
     // required for the relative clamping.
 
@@ -203,17 +286,19 @@ fn constrain_player_movement(
     #[allow(clippy::needless_collect)] // False alarm
     let velocities = player_velocities
         .into_iter()
-        .map(|(location, mut velocity)| {
+        .map(|(_, location, mut velocity)| {
             let new_x = location.x + velocity.x * dt;
 
-            if new_x < left_movement_boundary.0 {
+            if new_x < left_movement_boundary.0
+                || matches!(level_meta.bounds.right, Some(right) if new_x > right)
+            {
                 velocity.x = 0.;
             }
 
             //Restrict player to the ground
             let new_y = location.y + velocity.y * dt + consts::GROUND_OFFSET;
 
-            if new_y >= consts::MAX_Y || new_y <= consts::MIN_Y {
+            if new_y >= level_meta.bounds.max_y() || new_y <= level_meta.bounds.min_y() {
                 velocity.y = 0.;
             }
 
@@ -236,3 +321,203 @@ fn constrain_player_movement(
             }
         });
 }
+
+/// Briefly tints players that started pushing against an active stop point
+fn stop_point_blocked_flash(
+    mut commands: Commands,
+    mut events: EventReader<StopPointBlockedEvent>,
+) {
+    for event in events.iter() {
+        if let Some(mut player) = commands.get_entity(event.player) {
+            player.insert(FlashingTimer {
+                timer: Timer::new(Duration::from_millis(150), TimerMode::Once),
+                color: Color::rgb(1.0, 0.6, 0.6),
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::player::Player;
+
+    #[test]
+    fn test_stop_point_deceleration() {
+        let dt = 1. / 60.;
+        let stop_point_x = 100.;
+        let input_velocity_x = 150.;
+
+        let mut location_x = stop_point_x - 10.;
+        let mut velocities = Vec::new();
+        for _ in 0..60 {
+            let velocity_x =
+                stop_point_velocity_x(location_x, input_velocity_x, stop_point_x, 1200., dt);
+            location_x += velocity_x * dt;
+            velocities.push(velocity_x);
+        }
+
+        // The player is slowed down over multiple ticks instead of being stopped in one
+        assert!(velocities[0] > 0.);
+        assert!(velocities[1] > 0.);
+        assert!(velocities[1] < velocities[0]);
+        assert!(velocities.windows(2).all(|w| w[1] <= w[0]));
+
+        // And it never goes past the stop point
+        assert!(location_x <= stop_point_x);
+    }
+
+    #[test]
+    fn test_passed_stop_point_is_not_blocked_again() {
+        let mut world = World::new();
+        let game: GameMeta =
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap();
+        let mut level: LevelMeta =
+            serde_yaml::from_str(include_str!("../assets/levels/1_beach/beach.level.yaml"))
+                .unwrap();
+        level.stop_points = vec![100.];
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(game);
+        world.insert_resource(level);
+        world.insert_resource(time);
+        world.init_resource::<LeftMovementBoundary>();
+        world.init_resource::<PassedStopPoints>();
+        world.init_resource::<Events<StopPointBlockedEvent>>();
+
+        let player = world
+            .spawn((
+                Player,
+                Transform::from_xyz(98., 0., 0.),
+                LinearVelocity::default(),
+            ))
+            .id();
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(constrain_player_movement);
+        let mut move_player = |world: &mut World| {
+            **world.get_mut::<LinearVelocity>(player).unwrap() = Vec2::new(50., 0.);
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs(1));
+            stage.run(world);
+            world.get::<LinearVelocity>(player).unwrap().x
+        };
+
+        // An enemy behind the stop point blocks the player
+        let enemy = world.spawn(SpawnLocationX(50.)).id();
+        assert_eq!(move_player(&mut world), 0.);
+        assert!(world.resource::<PassedStopPoints>().is_empty());
+
+        // Once the enemy is defeated, the player passes the stop point
+        world.despawn(enemy);
+        assert_eq!(move_player(&mut world), 50.);
+        assert!(world.resource::<PassedStopPoints>().contains(&0));
+
+        // And an enemy spawned behind it afterwards doesn't block the player again
+        world.spawn(SpawnLocationX(50.));
+        assert_eq!(move_player(&mut world), 50.);
+    }
+
+    #[test]
+    fn test_level_bounds_clamp_player_movement() {
+        let mut world = World::new();
+        let game: GameMeta =
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap();
+        let level_yaml = format!(
+            "{}\nbounds:\n  top: 20\n  bottom: -20\n  right: 200\n",
+            include_str!("../assets/levels/1_beach/beach.level.yaml")
+        );
+        let mut level: LevelMeta = serde_yaml::from_str(&level_yaml).unwrap();
+        level.stop_points.clear();
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(game);
+        world.insert_resource(level);
+        world.insert_resource(time);
+        world.init_resource::<LeftMovementBoundary>();
+        world.init_resource::<PassedStopPoints>();
+        world.init_resource::<Events<StopPointBlockedEvent>>();
+
+        let player = world
+            .spawn((Player, Transform::default(), LinearVelocity::default()))
+            .id();
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(constrain_player_movement);
+        let mut move_player = |world: &mut World, from: Vec2, velocity: Vec2| {
+            world.get_mut::<Transform>(player).unwrap().translation = from.extend(0.);
+            **world.get_mut::<LinearVelocity>(player).unwrap() = velocity;
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs(1));
+            stage.run(world);
+            **world.get::<LinearVelocity>(player).unwrap()
+        };
+
+        // The player is stopped at the level's top, below the default top
+        let top = consts::GROUND_Y + 20.;
+        assert!(top + 5. < consts::MAX_Y);
+        let up = Vec2::new(0., 10.);
+        assert_eq!(move_player(&mut world, Vec2::new(0., top - 15.), up), up);
+        assert_eq!(
+            move_player(&mut world, Vec2::new(0., top - 5.), up),
+            Vec2::ZERO
+        );
+
+        // And at the level's bottom, above the default bottom
+        let down = Vec2::new(0., -10.);
+        let bottom = consts::GROUND_Y - 20.;
+        assert_eq!(
+            move_player(&mut world, Vec2::new(0., bottom + 5.), down),
+            Vec2::ZERO
+        );
+
+        // The player can't go past the end of the level
+        let right = Vec2::new(10., 0.);
+        let ground = consts::GROUND_Y;
+        assert_eq!(
+            move_player(&mut world, Vec2::new(185., ground), right),
+            right
+        );
+        assert_eq!(
+            move_player(&mut world, Vec2::new(195., ground), right),
+            Vec2::ZERO
+        );
+    }
+
+    #[test]
+    fn test_long_frame_is_clamped() {
+        let mut world = World::new();
+        let mut game: GameMeta =
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap();
+        game.max_frame_delta = 0.1;
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(game);
+        world.insert_resource(time);
+
+        let entity = world
+            .spawn((Transform::default(), LinearVelocity(Vec2::new(100., 0.))))
+            .id();
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(velocity_system);
+
+        // A 2 second hitch only advances movement by the max frame delta
+        let mut time = world.resource_mut::<Time>();
+        let last_update = time.last_update().unwrap();
+        time.update_with_instant(last_update + Duration::from_secs(2));
+        stage.run(&mut world);
+
+        let x = world.get::<Transform>(entity).unwrap().translation.x;
+        assert!(x > 0. && x <= 10. + f32::EPSILON);
+    }
+
+    #[test]
+    fn test_stop_point_without_deceleration() {
+        let dt = 1. / 60.;
+
+        // Without deceleration, the player moves freely until the next step would pass the stop
+        // point.
+        assert_eq!(stop_point_velocity_x(50., 150., 100., 0., dt), 150.);
+        assert_eq!(stop_point_velocity_x(99., 150., 100., 0., dt), 0.);
+    }
+}
@@ -13,7 +13,13 @@ use native as backend;
 #[cfg(target_arch = "wasm32")]
 use wasm as backend;
 
-use crate::GameState;
+use crate::{
+    game_state::{GameStateRequests, TransitionPriority},
+    metadata::{
+        AccessibilitySettings, AudioSettings, CrtSettings, GameMeta, Settings, VideoSettings,
+    },
+    GameState,
+};
 
 pub struct PlatformPlugin;
 
@@ -23,7 +29,8 @@ impl Plugin for PlatformPlugin {
         app.add_system(wasm::update_canvas_size);
 
         app.init_resource::<Storage>()
-            .add_system(load_storage.run_in_state(GameState::LoadingStorage));
+            .add_system(load_storage.run_in_state(GameState::LoadingStorage))
+            .add_system(load_settings.run_not_in_state(GameState::LoadingStorage));
     }
 }
 
@@ -33,7 +40,7 @@ impl Plugin for PlatformPlugin {
 /// Will transition to [`GameState::LoadingGame`] when finished.
 pub fn load_storage(
     mut started: Local<bool>,
-    mut commands: Commands,
+    mut state_requests: ResMut<GameStateRequests>,
     mut storage: ResMut<Storage>,
 ) {
     // If we haven't started loading
@@ -47,8 +54,47 @@ pub fn load_storage(
     } else if storage.is_loaded() {
         debug!("Platform storage loaded");
         // Load game
-        commands.insert_resource(NextState(GameState::LoadingGame));
+        state_requests.request(GameState::LoadingGame, TransitionPriority::Navigation);
+    }
+}
+
+/// Keeps the [`Settings`] resource, and the resources of its parts, up to date with the stored
+/// settings.
+///
+/// The stored settings are only read again when the [`Storage`] or the [`GameMeta`], that has the
+/// default settings, changed. Only the settings that differ from their resource are inserted, so
+/// that the systems applying them only run on actual changes.
+pub fn load_settings(
+    mut commands: Commands,
+    storage: Res<Storage>,
+    game: Option<Res<GameMeta>>,
+    crt_settings: Option<Res<CrtSettings>>,
+    audio_settings: Option<Res<AudioSettings>>,
+    accessibility_settings: Option<Res<AccessibilitySettings>>,
+    video_settings: Option<Res<VideoSettings>>,
+) {
+    let game = match game {
+        Some(game) => game,
+        None => return,
+    };
+    if !storage.is_changed() && !game.is_changed() {
+        return;
+    }
+
+    let settings = Settings::load(&storage, &game.default_settings);
+    if crt_settings.as_deref() != Some(&settings.crt) {
+        commands.insert_resource(settings.crt.clone());
+    }
+    if audio_settings.as_deref() != Some(&settings.audio) {
+        commands.insert_resource(settings.audio.clone());
+    }
+    if accessibility_settings.as_deref() != Some(&settings.accessibility) {
+        commands.insert_resource(settings.accessibility.clone());
     }
+    if video_settings.as_deref() != Some(&settings.video) {
+        commands.insert_resource(settings.video.clone());
+    }
+    commands.insert_resource(settings);
 }
 
 /// The type of the inner data in [`Storage`]
@@ -140,12 +186,14 @@ impl Storage {
     }
 
     /// Try to get a value from the in-memory storage cache.
-    pub fn try_get<T>(&mut self, key: &str) -> Result<Option<T>, StorageError>
+    ///
+    /// This only needs shared access, so that reading a value doesn't mark the [`Storage`]
+    /// resource as changed. Storage has to be done loading, as checked by
+    /// [`is_loaded()`][Self::is_loaded], for the values to be available.
+    pub fn try_get<T>(&self, key: &str) -> Result<Option<T>, StorageError>
     where
         T: Serialize + DeserializeOwned,
     {
-        self.check_pending_data_load();
-
         if let Some(data) = &self.data {
             let value = data.get(key).cloned();
 
@@ -167,7 +215,7 @@ impl Storage {
     ///
     /// This will panic if storage has not been loaded yet or if there is a deserialization error.
     #[track_caller]
-    pub fn get<T>(&mut self, key: &str) -> Option<T>
+    pub fn get<T>(&self, key: &str) -> Option<T>
     where
         T: Serialize + DeserializeOwned,
     {
@@ -443,3 +491,40 @@ mod wasm {
         sender
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_settings_are_only_loaded_again_when_storage_changes() {
+        let mut world = World::new();
+        world.insert_resource::<GameMeta>(
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap(),
+        );
+        world.insert_resource(Storage {
+            data: Some(default()),
+            data_receiver: None,
+            backend_sender: async_channel::unbounded().0,
+        });
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(load_settings);
+
+        stage.run(&mut world);
+        assert!(!world.resource::<CrtSettings>().enabled);
+
+        // Reading the settings must not count as a storage change
+        world.clear_trackers();
+        stage.run(&mut world);
+        assert!(!world.is_resource_changed::<Storage>());
+        assert!(!world.is_resource_changed::<CrtSettings>());
+
+        world.resource_mut::<Storage>().set(
+            Settings::STORAGE_KEY,
+            &serde_yaml::from_str::<serde_yaml::Value>("crt: { enabled: true }").unwrap(),
+        );
+        stage.run(&mut world);
+        assert!(world.resource::<CrtSettings>().enabled);
+    }
+}
@@ -1,12 +1,16 @@
 use bevy::prelude::*;
-use leafwing_input_manager::InputManagerBundle;
+use leafwing_input_manager::{user_input::InputKind, InputManagerBundle};
 
 use crate::{
     animation::Facing,
     consts,
-    fighter::Inventory,
+    fighter::{Inventory, SuperMeter},
+    fighter_state::Dying,
     input::PlayerAction,
-    metadata::{FighterMeta, FighterSpawnMeta, GameMeta, Settings},
+    metadata::{
+        AnalogAttackControls, AttackBufferControls, AttackStrength, FighterMeta, FighterSpawnMeta,
+        GameMeta, Settings, StickResponseControls,
+    },
 };
 
 #[derive(Component)]
@@ -15,6 +19,91 @@ pub struct Player;
 #[derive(Component)]
 pub struct PlayerIndex(pub usize);
 
+/// Query filter matching players that are still alive.
+///
+/// Dying players are excluded from the camera and movement boundary calculations, so that the
+/// camera keeps following the remaining players.
+pub type LivingPlayer = (With<Player>, Without<Dying>);
+
+/// The analog attack trigger bindings of a player, and the state of the current trigger pull
+#[derive(Component, Default)]
+pub struct AnalogAttackTrigger {
+    pub controls: Option<AnalogAttackControls>,
+    pub peak_depth: f32,
+}
+
+impl AnalogAttackTrigger {
+    /// Update the trigger with its current depth, returning the strength of the attack to trigger
+    pub fn update(&mut self, depth: f32) -> Option<AttackStrength> {
+        let controls = self.controls.as_ref()?;
+        controls.update(&mut self.peak_depth, depth)
+    }
+}
+
+/// The deadzone and response curve applied to a player's analog movement
+#[derive(Component, Default, Deref)]
+pub struct StickResponse(pub StickResponseControls);
+
+/// Buffers a player's attack inputs, so that attacks pressed shortly before the fighter is able to
+/// attack are not dropped.
+#[derive(Component, Default)]
+pub struct AttackBuffer {
+    /// The buffer settings used for the gamepad
+    pub gamepad: AttackBufferControls,
+    /// The keyboard attack binding and buffer settings, for players with keyboard controls
+    pub keyboard: Option<(InputKind, AttackBufferControls)>,
+    /// The buffered attack, and the time left before it is dropped
+    buffered: Option<(AttackStrength, f32)>,
+}
+
+impl AttackBuffer {
+    /// Get the buffer settings of the device the attack binding is being used on
+    pub fn controls(&self, keyboard_input: &Input<KeyCode>) -> AttackBufferControls {
+        match self.keyboard {
+            Some((InputKind::Keyboard(key), controls))
+                if keyboard_input.pressed(key) || keyboard_input.just_released(key) =>
+            {
+                controls
+            }
+            _ => self.gamepad,
+        }
+    }
+
+    /// Advance the buffer by `delta` seconds and buffer the attack that was pressed, if any.
+    ///
+    /// With negative edge enabled, releasing the attack button also buffers a light attack.
+    pub fn update(
+        &mut self,
+        controls: AttackBufferControls,
+        pressed: Option<AttackStrength>,
+        released: bool,
+        delta: f32,
+    ) {
+        if let Some((_, time_left)) = &mut self.buffered {
+            *time_left -= delta;
+            if *time_left < 0.0 {
+                self.buffered = None;
+            }
+        }
+
+        let attack = pressed
+            .or_else(|| (controls.negative_edge && released).then_some(AttackStrength::Light));
+        if let Some(strength) = attack {
+            self.buffered = Some((strength, controls.window));
+        }
+    }
+
+    /// Get the buffered attack, if there is one, leaving it buffered
+    pub fn peek(&self) -> Option<AttackStrength> {
+        self.buffered.map(|(strength, _)| strength)
+    }
+
+    /// Take the buffered attack, if there is one
+    pub fn take(&mut self) -> Option<AttackStrength> {
+        self.buffered.take().map(|(strength, _)| strength)
+    }
+}
+
 #[derive(Bundle)]
 pub struct PlayerBundle {
     player: Player,
@@ -26,6 +115,10 @@ pub struct PlayerBundle {
     fighter_handle: Handle<FighterMeta>,
     #[bundle]
     input_manager_bundle: InputManagerBundle<PlayerAction>,
+    analog_attack_trigger: AnalogAttackTrigger,
+    attack_buffer: AttackBuffer,
+    stick_response: StickResponse,
+    super_meter: SuperMeter,
 }
 
 impl PlayerBundle {
@@ -43,11 +136,25 @@ impl PlayerBundle {
 
         let fighter_handle = player_meta.fighter_handle.clone();
 
+        let player_controls = &settings
+            .unwrap_or(&game_meta.default_settings)
+            .player_controls;
+
         let input_manager_bundle = InputManagerBundle {
-            input_map: settings
-                .unwrap_or(&game_meta.default_settings)
-                .player_controls
-                .get_input_map(player_i),
+            input_map: player_controls.get_input_map(player_i),
+            ..default()
+        };
+
+        let analog_attack_trigger = AnalogAttackTrigger {
+            controls: player_controls.gamepad.analog_attack.clone(),
+            ..default()
+        };
+
+        let attack_buffer = AttackBuffer {
+            gamepad: player_controls.gamepad.attack_buffer,
+            keyboard: player_controls
+                .keyboard(player_i)
+                .map(|keyboard| (keyboard.flop_attack, keyboard.attack_buffer)),
             ..default()
         };
 
@@ -58,7 +165,48 @@ impl PlayerBundle {
             transform_bundle,
             fighter_handle,
             input_manager_bundle,
+            analog_attack_trigger,
+            attack_buffer,
+            stick_response: StickResponse(player_controls.gamepad.stick_response),
+            super_meter: SuperMeter::new(game_meta.super_meter.max),
             inventory: Inventory(None),
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_attack_buffer() {
+        let controls = AttackBufferControls {
+            window: 0.1,
+            negative_edge: false,
+        };
+        let mut buffer = AttackBuffer::default();
+
+        // An attack pressed within the window is still buffered
+        buffer.update(controls, Some(AttackStrength::Light), false, 0.016);
+        buffer.update(controls, None, false, 0.05);
+        assert_eq!(buffer.peek(), Some(AttackStrength::Light));
+        assert_eq!(buffer.take(), Some(AttackStrength::Light));
+        assert_eq!(buffer.take(), None);
+
+        // An attack pressed longer ago than the window is dropped
+        buffer.update(controls, Some(AttackStrength::Light), false, 0.016);
+        buffer.update(controls, None, false, 0.05);
+        buffer.update(controls, None, false, 0.06);
+        assert_eq!(buffer.take(), None);
+
+        // Releasing the attack button only buffers an attack with negative edge enabled
+        buffer.update(controls, None, true, 0.016);
+        assert_eq!(buffer.take(), None);
+        let negative_edge = AttackBufferControls {
+            negative_edge: true,
+            ..controls
+        };
+        buffer.update(negative_edge, None, true, 0.016);
+        assert_eq!(buffer.take(), Some(AttackStrength::Light));
+    }
+}
@@ -0,0 +1,320 @@
+use std::time::Duration;
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    attack::{Attack, BrokeEvent},
+    camera::{SortLayer, YSort},
+    collision::Team,
+    item::{Drop, Explodable},
+    lifetime::LifetimeExpired,
+    utils::GameTime,
+    GameState,
+};
+
+pub struct ProjectilePlugin;
+
+impl Plugin for ProjectilePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(projectile_system.run_in_state(GameState::InGame));
+    }
+}
+
+/// A component for entities that fly through the level carrying an [`Attack`], such as bullets and
+/// thrown items.
+///
+/// Projectiles are moved, and despawned when they run out of hits or lifetime, by the
+/// [`projectile_system`].
+#[derive(Component, Clone, Debug)]
+pub struct Projectile {
+    /// The current velocity of the projectile
+    pub velocity: Vec2,
+    /// The downward acceleration applied to the projectile's velocity
+    pub gravity: f32,
+    /// The projectile despawns when this timer finishes
+    pub lifetime: Timer,
+    /// The number of things the projectile can pass through before despawning on contact
+    pub pierce: u32,
+    /// The number of things the projectile has hit
+    pub hit_count: u32,
+    /// The attack dealt by the projectile on contact
+    pub attack: Attack,
+}
+
+impl Projectile {
+    pub fn new(velocity: Vec2, lifetime: f32, attack: Attack) -> Self {
+        Self {
+            velocity,
+            gravity: 0.0,
+            lifetime: Timer::from_seconds(lifetime, TimerMode::Once),
+            pierce: 0,
+            hit_count: 0,
+            attack,
+        }
+    }
+
+    pub fn with_gravity(mut self, gravity: f32) -> Self {
+        self.gravity = gravity;
+        self
+    }
+
+    pub fn with_pierce(mut self, pierce: u32) -> Self {
+        self.pierce = pierce;
+        self
+    }
+
+    /// Move the projectile forward in time, returning whether its lifetime has ended
+    pub fn advance(&mut self, transform: &mut Transform, delta: Duration) -> bool {
+        let delta_seconds = delta.as_secs_f32();
+
+        self.velocity.y -= self.gravity * delta_seconds;
+        transform.translation += (self.velocity * delta_seconds).extend(0.0);
+
+        self.lifetime.tick(delta);
+        self.lifetime.finished()
+    }
+
+    /// Register a hit, returning whether the projectile is spent and should despawn
+    pub fn hit(&mut self) -> bool {
+        self.hit_count += 1;
+        self.is_spent()
+    }
+
+    /// Whether the projectile has hit more things than it can pierce
+    pub fn is_spent(&self) -> bool {
+        self.hit_count > self.pierce
+    }
+}
+
+#[derive(Bundle)]
+pub struct ProjectileBundle {
+    projectile: Projectile,
+    attack: Attack,
+    collider: Collider,
+    sensor: Sensor,
+    events: ActiveEvents,
+    collision_types: ActiveCollisionTypes,
+    collision_groups: CollisionGroups,
+    ysort: YSort,
+}
+
+impl ProjectileBundle {
+    /// Create a projectile shot by a fighter of `team`, hitting the teams hostile to it
+    pub fn new(
+        projectile: Projectile,
+        collider: Collider,
+        team: Team,
+        friendly_fire: bool,
+    ) -> Self {
+        Self {
+            attack: projectile.attack,
+            projectile,
+            collider,
+            sensor: Sensor,
+            events: ActiveEvents::COLLISION_EVENTS,
+            collision_types: ActiveCollisionTypes::default() | ActiveCollisionTypes::STATIC_STATIC,
+            collision_groups: team.attack_groups(friendly_fire, false),
+            ysort: YSort(SortLayer::Projectiles),
+        }
+    }
+}
+
+/// Moves projectiles and despawns them once they are spent or their lifetime has ended
+pub fn projectile_system(
+    mut commands: Commands,
+    mut projectiles: Query<(
+        Entity,
+        &mut Projectile,
+        &mut Transform,
+        Option<&Drop>,
+        Option<&Explodable>,
+    )>,
+    mut collision_events: EventReader<CollisionEvent>,
+    mut broke_events: EventWriter<BrokeEvent>,
+    mut lifetime_events: EventWriter<LifetimeExpired>,
+    time: GameTime,
+) {
+    for event in collision_events.iter() {
+        if let CollisionEvent::Started(e1, e2, _flags) = event {
+            for entity in [e1, e2] {
+                if let Ok((entity, mut projectile, transform, drop, explodable)) =
+                    projectiles.get_mut(*entity)
+                {
+                    if projectile.is_spent() {
+                        continue;
+                    }
+
+                    if projectile.hit() {
+                        broke_events.send(BrokeEvent {
+                            drop: drop.cloned(),
+                            transform: Some(*transform),
+                            explodable: explodable.cloned(),
+                        });
+                        commands.entity(entity).despawn_recursive();
+                    }
+                }
+            }
+        }
+    }
+
+    for (entity, mut projectile, mut transform, drop, explodable) in &mut projectiles {
+        if projectile.is_spent() {
+            continue;
+        }
+
+        // Explodable projectiles, like bombs, stop and fuse at the end of their lifetime instead of
+        // despawning, see [`explodable_system`](crate::item::explodable_system)
+        if projectile.advance(&mut transform, time.delta()) && explodable.is_none() {
+            lifetime_events.send(LifetimeExpired {
+                drop: drop.cloned(),
+                transform: Some(*transform),
+            });
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use bevy_rapier2d::rapier::geometry::CollisionEventFlags;
+
+    use super::*;
+    use crate::{
+        animation::{AnimatedSpriteSheetBundle, Animation},
+        attack::{attack_damage_system, AttackFrames, Hurtbox},
+        damage::{DamageEvent, Damageable, Health, ParryEvent},
+        metadata::FighterMeta,
+    };
+
+    fn step(app: &mut App, seconds: f32) {
+        let mut time = app.world.resource_mut::<Time>();
+        let last_update = time.last_update().unwrap();
+        time.update_with_instant(last_update + Duration::from_secs_f32(seconds));
+        app.update();
+    }
+
+    #[test]
+    fn test_projectile_travels_damages_and_expires() {
+        let mut app = App::new();
+        let mut time = Time::default();
+        time.update();
+        app.insert_resource(time)
+            .add_event::<CollisionEvent>()
+            .add_event::<DamageEvent>()
+            .add_event::<ParryEvent>()
+            .add_event::<BrokeEvent>()
+            .add_event::<LifetimeExpired>()
+            .add_system(projectile_system)
+            .add_system(attack_damage_system);
+
+        let attack = Attack {
+            damage: 10,
+            ..default()
+        };
+        let spawn_projectile = |app: &mut App| {
+            app.world
+                .spawn((
+                    TransformBundle::default(),
+                    ProjectileBundle::new(
+                        Projectile::new(Vec2::new(100., 0.), 1.0, attack),
+                        Collider::cuboid(1., 1.),
+                        Team::Players,
+                        false,
+                    ),
+                ))
+                .id()
+        };
+
+        // The projectile travels along its velocity
+        let projectile = spawn_projectile(&mut app);
+        step(&mut app, 0.5);
+        let x = app
+            .world
+            .get::<Transform>(projectile)
+            .unwrap()
+            .translation
+            .x;
+        assert!((x - 50.).abs() < 0.01);
+
+        // The projectile damages what it hits and despawns
+        let mut hurtbox = None;
+        let enemy = app
+            .world
+            .spawn((Health(100), Damageable(true)))
+            .with_children(|parent| hurtbox = Some(parent.spawn(Hurtbox).id()))
+            .id();
+        app.world.send_event(CollisionEvent::Started(
+            projectile,
+            hurtbox.unwrap(),
+            CollisionEventFlags::empty(),
+        ));
+        step(&mut app, 0.1);
+        assert_eq!(**app.world.get::<Health>(enemy).unwrap(), 90);
+        assert!(app.world.get_entity(projectile).is_none());
+
+        // A projectile that hits nothing despawns at the end of its lifetime
+        let projectile = spawn_projectile(&mut app);
+        step(&mut app, 0.6);
+        assert!(app.world.get_entity(projectile).is_some());
+        step(&mut app, 0.6);
+        assert!(app.world.get_entity(projectile).is_none());
+    }
+
+    #[test]
+    fn test_explodable_projectile_breaks_into_its_explosion() {
+        let mut app = App::new();
+        let mut time = Time::default();
+        time.update();
+        app.insert_resource(time)
+            .add_event::<CollisionEvent>()
+            .add_event::<BrokeEvent>()
+            .add_event::<LifetimeExpired>()
+            .add_system(projectile_system);
+
+        let fighter: FighterMeta =
+            serde_yaml::from_str(include_str!("../assets/fighters/dev/dev.fighter.yaml")).unwrap();
+        let explodable = Explodable {
+            attack: fighter.attacks[0].clone(),
+            timer: Timer::from_seconds(1.0, TimerMode::Once),
+            fusing: false,
+            animated_sprite: AnimatedSpriteSheetBundle {
+                sprite_sheet: default(),
+                animation: Animation::new(0.1, default()),
+            },
+            explosion_frames: AttackFrames {
+                startup: 0,
+                active: 1,
+                recovery: 2,
+            },
+            attack_enemy: false,
+        };
+        let projectile = app
+            .world
+            .spawn((
+                TransformBundle::default(),
+                ProjectileBundle::new(
+                    Projectile::new(Vec2::new(100., 0.), 1.0, Attack::default()),
+                    Collider::cuboid(1., 1.),
+                    Team::Enemies,
+                    false,
+                ),
+                explodable,
+            ))
+            .id();
+        let target = app.world.spawn_empty().id();
+        app.world.send_event(CollisionEvent::Started(
+            projectile,
+            target,
+            CollisionEventFlags::empty(),
+        ));
+        step(&mut app, 0.1);
+
+        let events = app.world.resource::<Events<BrokeEvent>>();
+        let broke = events.get_reader().iter(events).next().unwrap();
+        let explodable = broke.explodable.as_ref().unwrap();
+        assert_eq!(explodable.attack.name, fighter.attacks[0].name);
+        assert!(!explodable.attack_enemy);
+    }
+}
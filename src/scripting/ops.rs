@@ -2,8 +2,9 @@ use bevy::{ecs::event::ManualEventReader, prelude::*, utils::HashMap};
 use bevy_mod_js_scripting::{
     serde_json, JsRuntimeOp, JsScript, JsValueRef, JsValueRefs, OpContext, OpMap,
 };
+use serde::{Deserialize, Serialize};
 
-use crate::item::ScriptItemGrabEvent;
+use crate::{damage::Health, fighter::Stats, item::ScriptItemGrabEvent};
 
 /// Returns the list of custom scripting ops we use for Punchy
 pub fn get_ops() -> OpMap {
@@ -16,6 +17,8 @@ pub fn get_ops() -> OpMap {
     // Here `punchyGetItemGrabEvents` is the op name, which means it can be run from JavaScript by
     // calling `bevyModJsScriptingOpSync("punchyGetItemGrabEvents", argument1, anotherArgument)`;
     ops.insert("punchyGetItemGrabEvents", Box::new(ItemGetGrabEvents));
+    ops.insert("punchyGetFighterStats", Box::new(GetFighterStats));
+    ops.insert("punchySetFighterStats", Box::new(SetFighterStats));
 
     ops
 }
@@ -125,3 +128,214 @@ impl JsRuntimeOp for ItemGetGrabEvents {
         )
     }
 }
+
+/// An entity passed to an op from JavaScript, either as an entity object returned by the scripting
+/// API, or as the number of its `bits`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ScriptEntity {
+    Bits(u64),
+    Entity { bits: u64 },
+}
+
+impl From<ScriptEntity> for Entity {
+    fn from(entity: ScriptEntity) -> Self {
+        match entity {
+            ScriptEntity::Bits(bits) | ScriptEntity::Entity { bits } => Entity::from_bits(bits),
+        }
+    }
+}
+
+/// The stats of a fighter, as seen by scripts
+#[derive(Serialize, Deserialize, Debug, PartialEq)]
+pub struct FighterStats {
+    pub health: i32,
+    pub max_health: i32,
+    pub movement_speed: f32,
+    pub run_speed: Option<f32>,
+    pub knockback_resist: f32,
+}
+
+/// A change to the stats of a fighter, requested by a script. Stats that are left out are unchanged.
+#[derive(Deserialize, Default, Debug)]
+#[serde(deny_unknown_fields, default)]
+pub struct FighterStatsChange {
+    pub health: Option<i32>,
+    pub max_health: Option<i32>,
+    pub movement_speed: Option<f32>,
+    pub run_speed: Option<f32>,
+    pub knockback_resist: Option<f32>,
+}
+
+/// Get the stats of a fighter, or `None` if the entity is not a fighter
+pub fn get_fighter_stats(world: &World, entity: Entity) -> Option<FighterStats> {
+    let entity = world.get_entity(entity)?;
+    let stats = entity.get::<Stats>()?;
+    let health = entity.get::<Health>()?;
+
+    Some(FighterStats {
+        health: **health,
+        max_health: stats.max_health,
+        movement_speed: stats.movement_speed,
+        run_speed: stats.run_speed,
+        knockback_resist: stats.knockback_resist,
+    })
+}
+
+/// Change the stats of a fighter, returning whether the entity is a fighter.
+///
+/// The health is kept within the maximum health.
+pub fn set_fighter_stats(world: &mut World, entity: Entity, change: &FighterStatsChange) -> bool {
+    let mut entity = match world.get_entity_mut(entity) {
+        Some(entity) if entity.contains::<Stats>() && entity.contains::<Health>() => entity,
+        _ => return false,
+    };
+
+    let max_health = {
+        let mut stats = entity.get_mut::<Stats>().unwrap();
+        if let Some(max_health) = change.max_health {
+            stats.max_health = max_health.max(1);
+        }
+        if let Some(movement_speed) = change.movement_speed {
+            stats.movement_speed = movement_speed.max(0.);
+        }
+        if let Some(run_speed) = change.run_speed {
+            stats.run_speed = Some(run_speed.max(0.));
+        }
+        if let Some(knockback_resist) = change.knockback_resist {
+            stats.knockback_resist = knockback_resist.clamp(0., 1.);
+        }
+        stats.max_health
+    };
+
+    let mut health = entity.get_mut::<Health>().unwrap();
+    let new_health = change.health.unwrap_or(**health).min(max_health);
+    if new_health != **health {
+        **health = new_health;
+    }
+
+    true
+}
+
+/// JavaScript for the fighter stats ops
+const FIGHTER_STATS_JS: &str = r#"
+    if (!globalThis.punchy) {
+        globalThis.punchy = {}
+    }
+
+    // Get the stats and health of a fighter, or `null` if the entity is not a fighter
+    globalThis.punchy.getFighterStats = (entity) => {
+        return bevyModJsScriptingOpSync('punchyGetFighterStats', entity);
+    }
+
+    // Change the stats or health of a fighter, such as `{ movement_speed: 200 }`, returning
+    // whether the entity is a fighter
+    globalThis.punchy.setFighterStats = (entity, change) => {
+        return bevyModJsScriptingOpSync('punchySetFighterStats', entity, change);
+    }
+"#;
+
+struct GetFighterStats;
+impl JsRuntimeOp for GetFighterStats {
+    fn js(&self) -> Option<&'static str> {
+        Some(FIGHTER_STATS_JS)
+    }
+
+    fn run(
+        &self,
+        _ctx: OpContext,
+        world: &mut bevy::prelude::World,
+        args: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        let (entity,): (ScriptEntity,) = serde_json::from_value(args)?;
+
+        Ok(serde_json::to_value(get_fighter_stats(
+            world,
+            entity.into(),
+        ))?)
+    }
+}
+
+struct SetFighterStats;
+impl JsRuntimeOp for SetFighterStats {
+    /// The JavaScript for this op is added by [`GetFighterStats`]
+    fn js(&self) -> Option<&'static str> {
+        None
+    }
+
+    fn run(
+        &self,
+        _ctx: OpContext,
+        world: &mut bevy::prelude::World,
+        args: serde_json::Value,
+    ) -> anyhow::Result<serde_json::Value> {
+        run_set_fighter_stats(world, args)
+    }
+}
+
+/// Parse the arguments of a `punchySetFighterStats` op call, and apply the change.
+///
+/// Scripts are warned about entities that are not fighters, instead of failing.
+fn run_set_fighter_stats(
+    world: &mut World,
+    args: serde_json::Value,
+) -> anyhow::Result<serde_json::Value> {
+    let (entity, change): (ScriptEntity, FighterStatsChange) = serde_json::from_value(args)?;
+    let entity = entity.into();
+
+    let is_fighter = set_fighter_stats(world, entity, &change);
+    if !is_fighter {
+        warn!(
+            "Script tried to set the stats of {:?}, which is not a fighter",
+            entity
+        );
+    }
+
+    Ok(serde_json::Value::Bool(is_fighter))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_script_stat_change_is_applied() {
+        let mut world = World::new();
+        let fighter = world
+            .spawn((
+                Stats {
+                    max_health: 100,
+                    movement_speed: 150.,
+                    ..default()
+                },
+                Health(50),
+            ))
+            .id();
+
+        // The arguments as sent by `punchy.setFighterStats(fighter, { ... })`
+        let args = serde_json::json!([
+            { "bits": fighter.to_bits() },
+            { "movement_speed": 200.0, "health": 500 }
+        ]);
+        let result = run_set_fighter_stats(&mut world, args).unwrap();
+        assert_eq!(result, serde_json::Value::Bool(true));
+
+        let stats = world.get::<Stats>(fighter).unwrap();
+        assert_eq!(stats.movement_speed, 200.);
+        assert_eq!(stats.max_health, 100);
+        // Health can't go over the maximum
+        assert_eq!(get_fighter_stats(&world, fighter).unwrap().health, 100);
+
+        // Entities that are gone, or aren't fighters, are reported instead of failing
+        let not_fighter = world.spawn(Health(10)).id();
+        let despawned = world.spawn_empty().id();
+        world.despawn(despawned);
+        for entity in [not_fighter, despawned] {
+            let args = serde_json::json!([entity.to_bits(), { "max_health": 1 }]);
+            let result = run_set_fighter_stats(&mut world, args).unwrap();
+            assert_eq!(result, serde_json::Value::Bool(false));
+            assert!(get_fighter_stats(&world, entity).is_none());
+        }
+        assert_eq!(**world.get::<Health>(not_fighter).unwrap(), 10);
+    }
+}
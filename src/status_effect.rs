@@ -0,0 +1,277 @@
+//! Status effects applied to fighters by attacks, lasting for a while after the hit.
+
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::CollisionGroups;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    attack::{Attack, AttackOwners},
+    collision::Team,
+    consts,
+    damage::{self, DamageEvent, Damageable, Health, Invulnerable},
+    metadata::{BurnMeta, HitReaction},
+    utils::GameTime,
+    GameState,
+};
+
+pub struct StatusEffectPlugin;
+
+impl Plugin for StatusEffectPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system_set(
+            ConditionSet::new()
+                .run_in_state(GameState::InGame)
+                .with_system(burn)
+                .with_system(spawn_burning_flames)
+                .with_system(flicker_burning_flames)
+                .with_system(confusion)
+                .with_system(confused_attacks_hit_allies)
+                .into(),
+        );
+    }
+}
+
+/// A fighter set on fire, taking damage periodically until the burn is over
+#[derive(Component, Clone, Debug)]
+pub struct Burning {
+    /// The damage dealt per second
+    pub dps: f32,
+    pub timer: Timer,
+    tick_timer: Timer,
+    /// The damage that is yet to be dealt, for damage rates that don't add up to whole ticks
+    pending_damage: f32,
+}
+
+impl Burning {
+    pub fn new(burn: BurnMeta) -> Self {
+        Self {
+            dps: burn.dps,
+            timer: Timer::from_seconds(burn.duration, TimerMode::Once),
+            tick_timer: Timer::from_seconds(consts::BURN_TICK_INTERVAL, TimerMode::Repeating),
+            pending_damage: 0.0,
+        }
+    }
+
+    /// Set the fighter on fire again, restarting the burn instead of stacking a second one
+    pub fn refresh(&mut self, burn: BurnMeta) {
+        self.dps = self.dps.max(burn.dps);
+        self.timer = Timer::from_seconds(
+            burn.duration.max(self.timer.remaining_secs()),
+            TimerMode::Once,
+        );
+    }
+}
+
+/// A confused fighter, turning against its own team until the confusion wears off.
+///
+/// Confused enemies target the other enemies, and confused players move the opposite way of their
+/// movement input.
+#[derive(Component, Clone, Debug)]
+pub struct Confused {
+    pub timer: Timer,
+}
+
+impl Confused {
+    pub fn new(duration: f32) -> Self {
+        Self {
+            timer: Timer::from_seconds(duration, TimerMode::Once),
+        }
+    }
+
+    /// Confuse the fighter again, extending the confusion instead of stacking a second one
+    pub fn refresh(&mut self, duration: f32) {
+        self.timer =
+            Timer::from_seconds(duration.max(self.timer.remaining_secs()), TimerMode::Once);
+    }
+}
+
+/// Marker component for the flame shown on burning fighters
+#[derive(Component)]
+pub struct BurningFlame;
+
+/// Deals the burn damage of burning fighters, until the burn is over or they are defeated.
+///
+/// Fighters that can't take damage still burn, but don't take the burn damage in the meantime.
+pub(crate) fn burn(
+    mut commands: Commands,
+    mut fighters: Query<(
+        Entity,
+        &mut Burning,
+        &mut Health,
+        Option<&Transform>,
+        Option<&Damageable>,
+        Option<&Invulnerable>,
+    )>,
+    mut damage_events: EventWriter<DamageEvent>,
+    time: GameTime,
+) {
+    for (entity, mut burning, mut health, transform, damageable, invulnerable) in &mut fighters {
+        let delta = time.delta();
+        burning.timer.tick(delta);
+        burning.tick_timer.tick(delta);
+
+        if damage::can_take_damage(damageable, invulnerable) {
+            let ticks = burning.tick_timer.times_finished_this_tick();
+            burning.pending_damage += ticks as f32 * burning.dps * consts::BURN_TICK_INTERVAL;
+        }
+        let damage = burning.pending_damage.floor();
+        if damage >= 1.0 {
+            burning.pending_damage -= damage;
+            **health -= damage as i32;
+
+            // Damage over time is dealt by the burning fighter to itself
+            let position = transform
+                .map(|transform| transform.translation.truncate())
+                .unwrap_or_default();
+            damage_events.send(DamageEvent {
+                damage_velocity: Vec2::ZERO,
+                damageing_entity: entity,
+                damaged_entity: entity,
+                source_position: position,
+                contact_position: position,
+                damage: damage as i32,
+                hitstun_duration: 0.0,
+                pull: None,
+                reaction: HitReaction::Knockback,
+            });
+        }
+
+        if burning.timer.finished() || **health <= 0 {
+            commands.entity(entity).remove::<Burning>();
+        }
+    }
+}
+
+/// Shows a flame on fighters that have been set on fire
+fn spawn_burning_flames(mut commands: Commands, fighters: Query<Entity, Added<Burning>>) {
+    for entity in &fighters {
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((
+                BurningFlame,
+                SpriteBundle {
+                    sprite: Sprite {
+                        color: Color::ORANGE_RED,
+                        custom_size: Some(consts::BURNING_FLAME_SIZE),
+                        ..default()
+                    },
+                    transform: Transform::from_translation(consts::BURNING_FLAME_OFFSET),
+                    ..default()
+                },
+            ));
+        });
+    }
+}
+
+/// Makes the flames of burning fighters flicker, and puts them out once the burn is over
+fn flicker_burning_flames(
+    mut commands: Commands,
+    mut flames: Query<(Entity, &Parent, &mut Transform, &mut Sprite), With<BurningFlame>>,
+    burning: Query<(), With<Burning>>,
+    time: Res<Time>,
+) {
+    for (entity, parent, mut transform, mut sprite) in &mut flames {
+        if !burning.contains(parent.get()) {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let flicker = (time.elapsed_seconds() * 20.0 + entity.index() as f32).sin();
+        transform.scale = Vec3::new(1.0 - flicker * 0.1, 1.0 + flicker * 0.15, 1.0);
+        sprite.color = if flicker > 0.0 {
+            Color::ORANGE_RED
+        } else {
+            Color::ORANGE
+        };
+    }
+}
+
+/// Wears off the confusion of confused fighters
+fn confusion(mut commands: Commands, mut fighters: Query<(Entity, &mut Confused)>, time: GameTime) {
+    for (entity, mut confused) in &mut fighters {
+        confused.timer.tick(time.delta());
+        if confused.timer.finished() {
+            commands.entity(entity).remove::<Confused>();
+        }
+    }
+}
+
+/// Lets the attacks of confused fighters hit their own team
+fn confused_attacks_hit_allies(
+    mut attacks: Query<(Entity, &mut CollisionGroups), Added<Attack>>,
+    owners: AttackOwners,
+    confused: Query<&Team, With<Confused>>,
+) {
+    for (attack, mut groups) in &mut attacks {
+        if let Ok(team) = confused.get(owners.get(attack)) {
+            groups.filters |= team.body_layer();
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+
+    #[test]
+    fn test_burning_deals_periodic_damage_until_over() {
+        let mut world = World::new();
+        world.init_resource::<Events<DamageEvent>>();
+        let mut time = Time::default();
+        time.update();
+        world.insert_resource(time);
+
+        let fighter = world
+            .spawn((
+                Health(100),
+                Burning::new(BurnMeta {
+                    dps: 10.,
+                    duration: 2.,
+                }),
+            ))
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(burn);
+        let mut step = |world: &mut World| {
+            let mut time = world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(0.25));
+            stage.run(world);
+            **world.get::<Health>(fighter).unwrap()
+        };
+
+        // The fighter takes damage every tick, rather than every frame
+        assert_eq!(step(&mut world), 100);
+        assert_eq!(step(&mut world), 95);
+        assert_eq!(step(&mut world), 95);
+        assert_eq!(step(&mut world), 90);
+
+        // Until the burn is over
+        for _ in 0..4 {
+            step(&mut world);
+        }
+        assert_eq!(**world.get::<Health>(fighter).unwrap(), 80);
+        assert!(world.get::<Burning>(fighter).is_none());
+        assert_eq!(step(&mut world), 80);
+
+        // Burning again refreshes the burn instead of stacking it
+        let burn = BurnMeta {
+            dps: 10.,
+            duration: 1.,
+        };
+        let mut burning = Burning::new(burn);
+        burning.refresh(burn);
+        assert_eq!(burning.dps, 10.);
+        assert_eq!(burning.timer.duration(), Duration::from_secs(1));
+
+        // And it can defeat the fighter
+        world
+            .entity_mut(fighter)
+            .insert((Health(5), Burning::new(burn)));
+        step(&mut world);
+        assert_eq!(step(&mut world), 0);
+        assert!(world.get::<Burning>(fighter).is_none());
+    }
+}
@@ -1,13 +1,15 @@
 use bevy::{prelude::*, utils::HashMap, window::WindowId};
 use bevy_egui::{egui, EguiContext, EguiPlugin, EguiRenderInputContainer, EguiSettings};
 use iyes_loopless::prelude::*;
-use leafwing_input_manager::prelude::ActionState;
+use leafwing_input_manager::prelude::{ActionState, InputMap};
 
 use crate::{
     assets::{EguiFont, EguiFontDefinitions},
+    attract_mode::AttractMode,
     audio,
     config::ENGINE_CONFIG,
-    input::MenuAction,
+    game_state::{GameStateRequests, TransitionPriority},
+    input::{menu_input_map, MenuAction, MenuInputDevice, PAUSE_BUTTON, PAUSE_KEY},
     metadata::GameMeta,
     GameState,
 };
@@ -15,7 +17,11 @@ use crate::{
 pub mod hud;
 pub mod widgets;
 
+pub mod console;
 pub mod debug_tools;
+pub mod input_glyphs;
+pub mod level_complete_menu;
+pub mod level_editor;
 pub mod main_menu;
 pub mod pause_menu;
 
@@ -27,17 +33,28 @@ pub struct UIPlugin;
 impl Plugin for UIPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<WidgetAdjacencies>()
+            .init_resource::<input_glyphs::ActiveInputDevice>()
+            .init_resource::<PausingDevice>()
             .add_plugin(EguiPlugin)
+            .add_system(input_glyphs::detect_input_device)
             .add_system(handle_menu_input.run_if_resource_exists::<GameMeta>())
             .add_enter_system(GameState::MainMenu, main_menu::spawn_main_menu_background)
             .add_enter_system(GameState::MainMenu, audio::play_menu_music)
             .add_exit_system(GameState::MainMenu, main_menu::despawn_main_menu_background)
             .add_exit_system(GameState::MainMenu, audio::stop_menu_music)
             .add_system(unpause.run_in_state(GameState::Paused))
+            .add_enter_system(GameState::Paused, restrict_menu_input)
+            .add_exit_system(GameState::Paused, release_menu_input)
             .add_system_set(
                 ConditionSet::new()
                     .run_in_state(GameState::InGame)
                     .with_system(hud::render_hud)
+                    .with_system(hud::render_boss_hud)
+                    .with_system(hud::render_boss_intro_banner)
+                    .with_system(hud::render_survival_countdown)
+                    .with_system(hud::collect_hurt_indicators)
+                    .with_system(hud::render_hurt_indicators)
+                    .with_system(hud::render_downed_indicators)
                     .with_system(pause)
                     .into(),
             )
@@ -49,6 +66,12 @@ impl Plugin for UIPlugin {
                     .with_system(pause_menu::pause_menu)
                     .into(),
             )
+            .add_system_set(
+                ConditionSet::new()
+                    .run_in_state(GameState::LevelComplete)
+                    .with_system(level_complete_menu::level_complete_menu)
+                    .into(),
+            )
             .add_system_set(
                 ConditionSet::new()
                     .run_in_state(GameState::MainMenu)
@@ -57,25 +80,83 @@ impl Plugin for UIPlugin {
             );
 
         if ENGINE_CONFIG.debug_tools {
-            app.add_system(debug_tools::debug_tools_window)
+            app.add_plugin(console::ConsolePlugin)
+                .add_system(debug_tools::debug_tools_window)
                 .add_system_to_stage(CoreStage::Last, debug_tools::rapier_debug_render);
         }
     }
 }
 
+/// The device that last requested a pause, which controls the pause menu if the pause goes through
+#[derive(Resource, Default)]
+struct PausingDevice(Option<MenuInputDevice>);
+
 /// Transition game to pause state
-fn pause(mut commands: Commands, input: Query<&ActionState<MenuAction>>) {
+///
+/// The pause menu is then only controlled by the device that paused, so that several players
+/// don't fight over the menu.
+///
+/// The game can't be paused in attract mode, where any input goes back to the main menu instead.
+fn pause(
+    mut state_requests: ResMut<GameStateRequests>,
+    mut device: ResMut<PausingDevice>,
+    input: Query<&ActionState<MenuAction>>,
+    attract_mode: Option<Res<AttractMode>>,
+    keys: Res<Input<KeyCode>>,
+    gamepad_buttons: Res<Input<GamepadButton>>,
+) {
     let input = input.single();
-    if input.just_pressed(MenuAction::Pause) {
-        commands.insert_resource(NextState(GameState::Paused));
+    if input.just_pressed(MenuAction::Pause) && attract_mode.is_none() {
+        state_requests.request(GameState::Paused, TransitionPriority::Pause);
+        device.0 = pausing_device(&keys, &gamepad_buttons);
     }
 }
 
+/// Let only the device that paused control the menus, once the pause has won over any other
+/// transition requested with it
+fn restrict_menu_input(
+    mut device: ResMut<PausingDevice>,
+    mut input_map: Query<&mut InputMap<MenuAction>>,
+) {
+    if let Some(device) = device.0.take() {
+        for mut input_map in &mut input_map {
+            *input_map = menu_input_map(Some(device));
+        }
+    }
+}
+
+/// Get the device that just pressed pause
+fn pausing_device(
+    keys: &Input<KeyCode>,
+    gamepad_buttons: &Input<GamepadButton>,
+) -> Option<MenuInputDevice> {
+    if keys.just_pressed(PAUSE_KEY) {
+        return Some(MenuInputDevice::Keyboard);
+    }
+
+    gamepad_buttons
+        .get_just_pressed()
+        .find(|button| button.button_type == PAUSE_BUTTON)
+        .map(|button| MenuInputDevice::Gamepad(button.gamepad))
+}
+
+/// Let every device control the menus again after leaving the pause menu, and clear its focus so
+/// that it starts from its default button the next time
+fn release_menu_input(
+    mut input_map: Query<&mut InputMap<MenuAction>>,
+    mut egui_ctx: ResMut<EguiContext>,
+) {
+    for mut input_map in &mut input_map {
+        *input_map = menu_input_map(None);
+    }
+    egui_ctx.ctx_mut().clear_focus();
+}
+
 // Transition game out of paused state
-fn unpause(mut commands: Commands, input: Query<&ActionState<MenuAction>>) {
+fn unpause(mut state_requests: ResMut<GameStateRequests>, input: Query<&ActionState<MenuAction>>) {
     let input = input.single();
     if input.just_pressed(MenuAction::Pause) {
-        commands.insert_resource(NextState(GameState::InGame));
+        state_requests.request(GameState::InGame, TransitionPriority::Pause);
     }
 }
 
@@ -312,3 +393,71 @@ fn update_ui_scale(
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_pausing_gamepad_controls_pause_menu() {
+        let player_2_gamepad = Gamepad::new(1);
+        for device in [
+            MenuInputDevice::Gamepad(player_2_gamepad),
+            MenuInputDevice::Keyboard,
+        ] {
+            let mut world = World::new();
+            world.init_resource::<GameStateRequests>();
+            world.init_resource::<PausingDevice>();
+            let mut keys = Input::<KeyCode>::default();
+            let mut gamepad_buttons = Input::<GamepadButton>::default();
+            match device {
+                MenuInputDevice::Gamepad(gamepad) => {
+                    gamepad_buttons.press(GamepadButton::new(gamepad, PAUSE_BUTTON))
+                }
+                MenuInputDevice::Keyboard => keys.press(PAUSE_KEY),
+            }
+            world.insert_resource(keys);
+            world.insert_resource(gamepad_buttons);
+
+            let mut action_state = ActionState::<MenuAction>::default();
+            action_state.press(MenuAction::Pause);
+            let menu_input = world.spawn((action_state, menu_input_map(None))).id();
+
+            let mut stage = SystemStage::single_threaded();
+            stage.add_system(pause);
+            stage.run(&mut world);
+
+            // The menu still listens to every device until the game is actually paused, in case a
+            // higher priority transition wins over the pause
+            assert_eq!(
+                world.resource_mut::<GameStateRequests>().take(),
+                Some(GameState::Paused)
+            );
+            let confirm = world
+                .get::<InputMap<MenuAction>>(menu_input)
+                .unwrap()
+                .get(MenuAction::Confirm);
+            assert!(confirm.contains(&KeyCode::Return.into()));
+            assert!(confirm.contains(&GamepadButtonType::South.into()));
+
+            // Then the device that paused is the only one the menu listens to
+            let mut stage = SystemStage::single_threaded();
+            stage.add_system(restrict_menu_input);
+            stage.run(&mut world);
+            let input_map = world.get::<InputMap<MenuAction>>(menu_input).unwrap();
+            let confirm = input_map.get(MenuAction::Confirm);
+            let keyboard_confirm = confirm.contains(&KeyCode::Return.into());
+            let gamepad_confirm = confirm.contains(&GamepadButtonType::South.into());
+            match device {
+                MenuInputDevice::Gamepad(gamepad) => {
+                    assert_eq!(input_map.gamepad(), Some(gamepad));
+                    assert!(gamepad_confirm && !keyboard_confirm);
+                }
+                MenuInputDevice::Keyboard => {
+                    assert_eq!(input_map.gamepad(), None);
+                    assert!(keyboard_confirm && !gamepad_confirm);
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use bevy::{ecs::system::SystemState, prelude::*, utils::HashMap};
+use bevy_egui::*;
+use bevy_mod_js_scripting::ActiveScripts;
+
+use crate::{
+    consts,
+    damage::Health,
+    enemy::EnemyBundle,
+    item::ItemBundle,
+    metadata::{FighterSpawnMeta, ItemMeta, ItemSpawnMeta},
+    movement::NoClip,
+    player::{LivingPlayer, Player},
+};
+
+/// The maximum amount of lines kept in the console scrollback
+const SCROLLBACK_LEN: usize = 200;
+
+/// Plugin for the in-game debug console, which can be toggled by pressing the backtick key
+pub struct ConsolePlugin;
+
+impl Plugin for ConsolePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Console>()
+            .init_resource::<ConsoleCommands>()
+            .add_system(console_window)
+            .add_system(run_console_commands.at_end());
+    }
+}
+
+/// The console state and scrollback
+#[derive(Resource, Default)]
+pub struct Console {
+    visible: bool,
+    input: String,
+    scrollback: Vec<String>,
+    /// Commands submitted this frame, waiting to be run against the world
+    pending: Vec<String>,
+}
+
+impl Console {
+    fn print(&mut self, line: impl Into<String>) {
+        self.scrollback.push(line.into());
+        if self.scrollback.len() > SCROLLBACK_LEN {
+            self.scrollback.remove(0);
+        }
+    }
+}
+
+/// The handler for a console command, which is passed the command arguments and returns the
+/// line to print in the console.
+pub type ConsoleCommandFn = fn(&mut World, &[&str]) -> Result<String, String>;
+
+/// Registry of console commands, by name
+#[derive(Resource)]
+pub struct ConsoleCommands(HashMap<String, ConsoleCommandFn>);
+
+impl Default for ConsoleCommands {
+    fn default() -> Self {
+        let mut commands = Self(default());
+
+        commands.register("spawn", spawn_command);
+        commands.register("give", give_command);
+        commands.register("sethealth", set_health_command);
+        commands.register("goto", goto_command);
+        commands.register("noclip", noclip_command);
+
+        commands
+    }
+}
+
+impl ConsoleCommands {
+    /// Register a console command, replacing any command with the same name
+    pub fn register(&mut self, name: &str, handler: ConsoleCommandFn) {
+        self.0.insert(name.into(), handler);
+    }
+
+    /// Parse and run a command line against the world
+    pub fn run(&self, world: &mut World, line: &str) -> Result<String, String> {
+        let mut words = line.split_whitespace();
+        let name = words.next().ok_or_else(|| "Empty command".to_string())?;
+        let args = words.collect::<Vec<_>>();
+
+        let handler = self
+            .0
+            .get(name)
+            .ok_or_else(|| format!("Unknown command: {}", name))?;
+
+        handler(world, &args)
+    }
+}
+
+/// Renders the console and collects the submitted commands
+fn console_window(
+    mut console: ResMut<Console>,
+    mut egui_context: ResMut<EguiContext>,
+    input: Res<Input<KeyCode>>,
+) {
+    if input.just_pressed(KeyCode::Grave) {
+        console.visible = !console.visible;
+    }
+
+    if !console.visible {
+        return;
+    }
+
+    egui::TopBottomPanel::top("console").show(egui_context.ctx_mut(), |ui| {
+        egui::ScrollArea::vertical()
+            .max_height(200.0)
+            .stick_to_bottom(true)
+            .show(ui, |ui| {
+                for line in &console.scrollback {
+                    ui.monospace(line);
+                }
+            });
+
+        // Don't type the backtick used to toggle the console
+        console.input.retain(|c| c != '`');
+
+        let response = ui.add(
+            egui::TextEdit::singleline(&mut console.input)
+                .desired_width(f32::INFINITY)
+                .font(egui::TextStyle::Monospace),
+        );
+
+        if response.lost_focus() && ui.input().key_pressed(egui::Key::Enter) {
+            let line = std::mem::take(&mut console.input).trim().to_string();
+
+            if !line.is_empty() {
+                console.print(format!("> {}", line));
+                console.pending.push(line);
+            }
+        }
+
+        response.request_focus();
+    });
+}
+
+/// Runs the commands submitted to the console
+fn run_console_commands(world: &mut World) {
+    let pending = std::mem::take(&mut world.resource_mut::<Console>().pending);
+    if pending.is_empty() {
+        return;
+    }
+
+    world.resource_scope(|world, commands: Mut<ConsoleCommands>| {
+        for line in pending {
+            let output = match commands.run(world, &line) {
+                Ok(output) => output,
+                Err(e) => format!("Error: {}", e),
+            };
+
+            if !output.is_empty() {
+                world.resource_mut::<Console>().print(output);
+            }
+        }
+    });
+}
+
+/// Get the x position of the front-most living player
+fn player_x(world: &mut World) -> Result<f32, String> {
+    world
+        .query_filtered::<&Transform, LivingPlayer>()
+        .iter(world)
+        .map(|transform| transform.translation.x)
+        .reduce(f32::max)
+        .ok_or_else(|| "No players".to_string())
+}
+
+/// `spawn enemy <fighter>`: spawns an enemy in front of the players
+fn spawn_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let fighter = match args {
+        ["enemy", fighter] => fighter,
+        _ => return Err("Usage: spawn enemy <fighter>".into()),
+    };
+
+    let path = format!("fighters/{0}/{0}.fighter.yaml", fighter);
+    let location = Vec3::new(player_x(world)? + 150., 0., 0.);
+    let fighter_handle = world.resource::<AssetServer>().load(&path);
+
+    world.spawn(EnemyBundle::new(&FighterSpawnMeta {
+        fighter: path,
+        fighter_handle,
+        location,
+        trip_point_x: f32::MIN,
+        boss: false,
+        entrance: None,
+    }));
+
+    Ok(format!("Spawned {}", fighter))
+}
+
+/// `give <item>`: drops an item at the feet of the first player
+fn give_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let item = match args {
+        [item] => item,
+        _ => return Err("Usage: give <item>".into()),
+    };
+
+    let path = format!("items/{0}/{0}.item.yaml", item);
+    let item_handle = world.resource::<AssetServer>().get_handle(&path);
+    if world
+        .resource::<Assets<ItemMeta>>()
+        .get(&item_handle)
+        .is_none()
+    {
+        return Err(format!("Item not loaded: {}", path));
+    }
+
+    let player_translation = world
+        .query_filtered::<&Transform, LivingPlayer>()
+        .iter(world)
+        .next()
+        .ok_or_else(|| "No players".to_string())?
+        .translation;
+
+    let item_spawn_meta = ItemSpawnMeta {
+        item: path,
+        item_handle,
+        location: Vec3::new(
+            player_translation.x,
+            player_translation.y - consts::GROUND_Y,
+            0.,
+        ),
+    };
+
+    let mut state: SystemState<(Commands, ResMut<Assets<ItemMeta>>, ResMut<ActiveScripts>)> =
+        SystemState::new(world);
+    let (mut commands, mut items_assets, mut active_scripts) = state.get_mut(world);
+    let item_commands = commands.spawn(ItemBundle::new(&item_spawn_meta));
+    ItemBundle::spawn(
+        item_commands,
+        &item_spawn_meta,
+        &mut items_assets,
+        &mut active_scripts,
+    );
+    state.apply(world);
+
+    Ok(format!("Gave {}", item))
+}
+
+/// `sethealth <n>`: sets the health of all players
+fn set_health_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let health = match args {
+        [health] => health
+            .parse::<i32>()
+            .map_err(|e| format!("Invalid health: {}", e))?,
+        _ => return Err("Usage: sethealth <n>".into()),
+    };
+
+    for mut player_health in world
+        .query_filtered::<&mut Health, With<Player>>()
+        .iter_mut(world)
+    {
+        **player_health = health;
+    }
+
+    Ok(format!("Set player health to {}", health))
+}
+
+/// `goto <x>`: moves all players to the given x position
+fn goto_command(world: &mut World, args: &[&str]) -> Result<String, String> {
+    let x = match args {
+        [x] => x.parse::<f32>().map_err(|e| format!("Invalid x: {}", e))?,
+        _ => return Err("Usage: goto <x>".into()),
+    };
+
+    for mut transform in world
+        .query_filtered::<&mut Transform, LivingPlayer>()
+        .iter_mut(world)
+    {
+        transform.translation.x = x;
+    }
+
+    Ok(format!("Moved players to {}", x))
+}
+
+/// `noclip`: toggles the movement constraints for all players
+fn noclip_command(world: &mut World, _args: &[&str]) -> Result<String, String> {
+    let players = world
+        .query_filtered::<(Entity, Option<&NoClip>), With<Player>>()
+        .iter(world)
+        .map(|(entity, noclip)| (entity, noclip.is_some()))
+        .collect::<Vec<_>>();
+
+    let enable = !players.iter().any(|(_, noclip)| *noclip);
+    for (entity, _) in players {
+        if enable {
+            world.entity_mut(entity).insert(NoClip);
+        } else {
+            world.entity_mut(entity).remove::<NoClip>();
+        }
+    }
+
+    Ok(format!("Noclip {}", if enable { "on" } else { "off" }))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_set_health_command() {
+        let mut world = World::new();
+        let player = world.spawn((Player, Health(100))).id();
+
+        let commands = ConsoleCommands::default();
+        assert!(commands.run(&mut world, "sethealth 1").is_ok());
+        assert_eq!(**world.get::<Health>(player).unwrap(), 1);
+
+        assert!(commands.run(&mut world, "sethealth lots").is_err());
+        assert!(commands.run(&mut world, "unknown").is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use bevy::prelude::*;
+use bevy::{prelude::*, utils::HashMap};
 use bevy_egui::*;
 use bevy_fluent::Localization;
 use bevy_inspector_egui::{
@@ -13,8 +13,27 @@ use bevy_rapier2d::{
         prelude::{DebugRenderBackend, DebugRenderObject},
     },
 };
+use iyes_loopless::prelude::*;
+use leafwing_input_manager::prelude::*;
 
-use crate::{camera::YSort, localization::LocalizationExt, metadata::FighterMeta};
+use crate::{
+    animation::Animation,
+    attack::{Attack, AttackFrames},
+    camera::YSort,
+    consts::LEFT_BOUNDARY_MAX_DISTANCE,
+    enemy::Enemy,
+    enemy_ai::WalkTarget,
+    fighter::AvailableAttacks,
+    game_state::{GameStateRequests, TransitionPriority},
+    input::PlayerAction,
+    localization::LocalizationExt,
+    metadata::{FighterMeta, GameMeta, LevelMeta},
+    movement::{LeftMovementBoundary, PassedStopPoints},
+    player::{LivingPlayer, Player, PlayerIndex},
+    ui::level_editor::LevelEditor,
+    utils::ResetController,
+    GameState, Stats,
+};
 
 /// System that renders the debug tools window which can be toggled by pressing F12
 pub fn debug_tools_window(
@@ -25,6 +44,14 @@ pub fn debug_tools_window(
     mut rapier_debug: ResMut<DebugRenderContext>,
     mut inspector: ResMut<WorldInspectorParams>,
     mut ysort_debug: ResMut<YSortDebug>,
+    mut collision_offset_debug: ResMut<CollisionOffsetDebug>,
+    mut frame_data_debug: ResMut<FrameDataDebug>,
+    mut level_editor: ResMut<LevelEditor>,
+    mut input_display_debug: ResMut<InputDisplayDebug>,
+    mut enemy_ai_debug: ResMut<EnemyAiDebug>,
+    mut practice_hitbox_debug: ResMut<PracticeHitboxDebug>,
+    mut movement_bounds_debug: ResMut<MovementBoundsDebug>,
+    mut input_latency_debug: ResMut<InputLatencyDebug>,
 ) {
     let ctx = egui_context.ctx_mut();
 
@@ -47,6 +74,36 @@ pub fn debug_tools_window(
         ysort_debug.enabled = !ysort_debug.enabled;
     }
 
+    // Shortcut to toggle the fighter collision offsets without having to use the menu
+    if input.just_pressed(KeyCode::F7) {
+        collision_offset_debug.enabled = !collision_offset_debug.enabled;
+    }
+
+    // Shortcut to toggle the frame data window without having to use the menu
+    if input.just_pressed(KeyCode::F6) {
+        frame_data_debug.enabled = !frame_data_debug.enabled;
+    }
+
+    // Shortcut to toggle the level editor without having to use the menu
+    if input.just_pressed(KeyCode::F5) {
+        level_editor.enabled = !level_editor.enabled;
+    }
+
+    // Shortcut to toggle the input display without having to use the menu
+    if input.just_pressed(KeyCode::F4) {
+        input_display_debug.enabled = !input_display_debug.enabled;
+    }
+
+    // Shortcut to toggle the enemy AI targets without having to use the menu
+    if input.just_pressed(KeyCode::F2) {
+        enemy_ai_debug.enabled = !enemy_ai_debug.enabled;
+    }
+
+    // Shortcut to toggle the practice hitboxes without having to use the menu
+    if input.just_pressed(KeyCode::F1) {
+        practice_hitbox_debug.enabled = !practice_hitbox_debug.enabled;
+    }
+
     // Display debug tool window
     egui::Window::new(localization.get("debug-tools"))
         // ID is needed because title comes from localizaition which can change
@@ -70,6 +127,54 @@ pub fn debug_tools_window(
                 &mut ysort_debug.enabled,
                 format!("{} ( F8 )", localization.get("show-ysort-lines")),
             );
+
+            // Show fighter collision offsets
+            ui.checkbox(
+                &mut collision_offset_debug.enabled,
+                format!("{} ( F7 )", localization.get("show-collision-offsets")),
+            );
+
+            // Show the frame data of the current attack
+            ui.checkbox(
+                &mut frame_data_debug.enabled,
+                format!("{} ( F6 )", localization.get("show-frame-data")),
+            );
+
+            // Place entities in the level and export them to a level file
+            ui.checkbox(
+                &mut level_editor.enabled,
+                format!("{} ( F5 )", localization.get("show-level-editor")),
+            );
+
+            // Show the inputs of each player
+            ui.checkbox(
+                &mut input_display_debug.enabled,
+                format!("{} ( F4 )", localization.get("show-input-display")),
+            );
+
+            // Show what the enemy AI is going for
+            ui.checkbox(
+                &mut enemy_ai_debug.enabled,
+                format!("{} ( F2 )", localization.get("show-enemy-ai")),
+            );
+
+            // Color attack hitboxes by the phase of their attack
+            ui.checkbox(
+                &mut practice_hitbox_debug.enabled,
+                format!("{} ( F1 )", localization.get("show-practice-hitboxes")),
+            );
+
+            // Show the invisible constraints on player movement
+            ui.checkbox(
+                &mut movement_bounds_debug.enabled,
+                localization.get("show-movement-bounds"),
+            );
+
+            // Measure the frames between presses and the fighter reacting
+            ui.checkbox(
+                &mut input_latency_debug.enabled,
+                localization.get("show-input-latency"),
+            );
         });
 }
 
@@ -135,9 +240,12 @@ impl<'world, 'state, 'a, 'b, 'c> RapierEguiRenderBackend<'world, 'state, 'a, 'b,
             _ => None,
         };
 
-        let color = color.map(|co| co.as_hsla_f32()).unwrap_or(default);
+        // Rapier's colors are HSLA, like the custom ones are converted to
+        let [hue, saturation, lightness, alpha] =
+            color.map(|co| co.as_hsla_f32()).unwrap_or(default);
+        let [r, g, b, a] = Color::hsla(hue, saturation, lightness, alpha).as_rgba_f32();
 
-        egui::Rgba::from_rgba_premultiplied(color[0], color[1], color[2], color[3]).into()
+        egui::Rgba::from_rgba_premultiplied(r, g, b, a).into()
     }
 }
 
@@ -202,7 +310,7 @@ pub struct YSortDebug {
 fn draw_ysort_lines(
     ysort_debug: Res<YSortDebug>,
     mut egui_context: ResMut<EguiContext>,
-    query: Query<(&YSort, &Handle<FighterMeta>, &Transform)>,
+    query: Query<(&Handle<FighterMeta>, &Transform), With<YSort>>,
     camera_query: Query<(&Camera, &GlobalTransform)>,
     fighter_assets: Res<Assets<FighterMeta>>,
 ) {
@@ -211,7 +319,7 @@ fn draw_ysort_lines(
     }
 
     if let Ok((camera, camera_transform)) = camera_query.get_single() {
-        for (ysort, fighter_meta, transform) in query.iter() {
+        for (fighter_meta, transform) in query.iter() {
             //If the fighter meta is not loaded default to 16.0
             let half_width = if let Some(meta) = fighter_assets.get(fighter_meta) {
                 meta.spritesheet.tile_size.x as f32 / 2.
@@ -224,12 +332,10 @@ fn draw_ysort_lines(
                 .show(egui_context.ctx_mut(), |ui| {
                     let mut a = transform.translation;
                     a.x += half_width;
-                    a.y -= ysort.0;
                     a.z = 0.;
 
                     let mut b = transform.translation;
                     b.x -= half_width;
-                    b.y -= ysort.0;
                     b.z = 0.;
 
                     let a = camera.world_to_ndc(camera_transform, a);
@@ -252,3 +358,1124 @@ fn draw_ysort_lines(
         }
     }
 }
+
+/// A plugin that draws each fighter's body collider and collision offset
+pub struct CollisionOffsetDebugPlugin;
+
+impl Plugin for CollisionOffsetDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(CollisionOffsetDebug {
+            enabled: false,
+            body_stroke: Stroke::new(1.0, Color32::LIGHT_BLUE),
+            offset_stroke: Stroke::new(1.0, Color32::YELLOW),
+        })
+        .add_system(draw_collision_offsets);
+    }
+}
+
+#[derive(Resource)]
+pub struct CollisionOffsetDebug {
+    enabled: bool,
+    body_stroke: egui::Stroke,
+    offset_stroke: egui::Stroke,
+}
+
+/// Renders the fighter body colliders and a line at their collision offset
+fn draw_collision_offsets(
+    collision_offset_debug: Res<CollisionOffsetDebug>,
+    mut egui_context: ResMut<EguiContext>,
+    query: Query<(&Handle<FighterMeta>, &Transform)>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+) {
+    if !collision_offset_debug.enabled {
+        return;
+    }
+
+    let (camera, camera_transform) = if let Ok(camera) = camera_query.get_single() {
+        camera
+    } else {
+        return;
+    };
+
+    egui::CentralPanel::default()
+        .frame(egui::Frame::none())
+        .show(egui_context.ctx_mut(), |ui| {
+            let half_size = ui.available_size() / 2.0;
+
+            // Map world coordinates to egui points
+            let to_egui = |position: Vec3| {
+                camera
+                    .world_to_ndc(camera_transform, position)
+                    .map(|ndc| (egui::Vec2::new(ndc.x, -ndc.y) * half_size + half_size).to_pos2())
+            };
+
+            for (fighter_handle, transform) in &query {
+                let fighter = if let Some(fighter) = fighter_assets.get(fighter_handle) {
+                    fighter
+                } else {
+                    continue;
+                };
+
+                let mut center = transform.translation;
+                center.y += fighter.collision_offset;
+                center.z = 0.;
+                let half_body = (fighter.hurtbox.size / 2.0).extend(0.);
+                let half_width = Vec3::new(fighter.spritesheet.tile_size.x as f32 / 2., 0., 0.);
+
+                // Draw the body collider
+                if let (Some(min), Some(max)) =
+                    (to_egui(center - half_body), to_egui(center + half_body))
+                {
+                    ui.painter().rect_stroke(
+                        egui::Rect::from_two_pos(min, max),
+                        0.0,
+                        collision_offset_debug.body_stroke,
+                    );
+                }
+
+                // Draw the collision offset across the sprite
+                if let (Some(a), Some(b)) =
+                    (to_egui(center - half_width), to_egui(center + half_width))
+                {
+                    ui.painter()
+                        .line_segment([a, b], collision_offset_debug.offset_stroke);
+                }
+            }
+        });
+}
+
+/// A plugin that shows the frame data of the selected fighter's current attack
+pub struct FrameDataDebugPlugin;
+
+impl Plugin for FrameDataDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<FrameDataDebug>()
+            .add_system(frame_data_window);
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct FrameDataDebug {
+    enabled: bool,
+    /// The fighter to show the frame data of
+    fighter: Option<Entity>,
+}
+
+/// The phase an attack is in at an animation frame
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum AttackPhase {
+    Startup,
+    Active,
+    Recovery,
+}
+
+impl AttackPhase {
+    /// Get the phase of an attack at an animation frame, matching when its hitbox is active
+    fn at_frame(frames: &AttackFrames, current_frame: usize) -> Self {
+        if current_frame < frames.startup {
+            AttackPhase::Startup
+        } else if current_frame <= frames.active {
+            AttackPhase::Active
+        } else {
+            AttackPhase::Recovery
+        }
+    }
+
+    /// The color of hitboxes in this phase on the practice hitbox overlay
+    fn hitbox_color(self) -> Color {
+        match self {
+            AttackPhase::Startup => Color::YELLOW,
+            AttackPhase::Active => Color::RED,
+            AttackPhase::Recovery => Color::BLUE,
+        }
+    }
+}
+
+/// Renders the frame data window, highlighting the phase of the attack in progress
+fn frame_data_window(
+    mut frame_data_debug: ResMut<FrameDataDebug>,
+    mut egui_context: ResMut<EguiContext>,
+    localization: Res<Localization>,
+    fighters: Query<
+        (
+            Entity,
+            &Name,
+            &AvailableAttacks,
+            &Animation,
+            Option<&Children>,
+        ),
+        With<Handle<FighterMeta>>,
+    >,
+    attacks: Query<&AttackFrames, With<Attack>>,
+) {
+    if !frame_data_debug.enabled {
+        return;
+    }
+
+    // Select the first fighter if there is no selected fighter anymore
+    if frame_data_debug
+        .fighter
+        .map_or(true, |fighter| !fighters.contains(fighter))
+    {
+        frame_data_debug.fighter = fighters.iter().next().map(|(entity, ..)| entity);
+    }
+
+    let FrameDataDebug { enabled, fighter } = &mut *frame_data_debug;
+    let fighter_label = |entity: Entity| {
+        fighters
+            .get(entity)
+            .map(|(_, name, ..)| format!("{} ( {} )", name, entity.index()))
+            .unwrap_or_default()
+    };
+
+    egui::Window::new(localization.get("frame-data"))
+        // ID is needed because title comes from localizaition which can change
+        .id(egui::Id::new("frame_data"))
+        .open(enabled)
+        .show(egui_context.ctx_mut(), |ui| {
+            egui::ComboBox::from_label(localization.get("fighter"))
+                .selected_text(fighter.map(fighter_label).unwrap_or_default())
+                .show_ui(ui, |ui| {
+                    for (entity, ..) in &fighters {
+                        ui.selectable_value(fighter, Some(entity), fighter_label(entity));
+                    }
+                });
+
+            let (available_attacks, animation, children) =
+                if let Some(Ok((_, _, available_attacks, animation, children))) =
+                    fighter.map(|fighter| fighters.get(fighter))
+                {
+                    (available_attacks, animation, children)
+                } else {
+                    return;
+                };
+            let attack = available_attacks.current_attack();
+
+            // The attack is in progress while its hitbox entity exists
+            let spawned_frames = children
+                .into_iter()
+                .flatten()
+                .find_map(|child| attacks.get(*child).ok());
+            let frames = spawned_frames.unwrap_or(&attack.frames);
+            let phase =
+                spawned_frames.map(|frames| AttackPhase::at_frame(frames, animation.current_frame));
+
+            ui.label(format!("{}: {}", localization.get("attack"), attack.name));
+            ui.horizontal(|ui| {
+                for (label, label_phase, frame_count) in [
+                    ("startup", AttackPhase::Startup, frames.startup),
+                    (
+                        "active",
+                        AttackPhase::Active,
+                        (frames.active + 1).saturating_sub(frames.startup),
+                    ),
+                    (
+                        "recovery",
+                        AttackPhase::Recovery,
+                        frames.recovery.saturating_sub(frames.active + 1),
+                    ),
+                ] {
+                    let text = format!("{}: {}", localization.get(label), frame_count);
+                    if phase == Some(label_phase) {
+                        ui.colored_label(Color32::YELLOW, text);
+                    } else {
+                        ui.label(text);
+                    }
+                }
+            });
+            ui.label(format!(
+                "{}: {}",
+                localization.get("current-frame"),
+                if phase.is_some() {
+                    animation.current_frame.to_string()
+                } else {
+                    "-".into()
+                }
+            ));
+            ui.label(format!("{}: {}", localization.get("damage"), attack.damage));
+            ui.label(format!(
+                "{}: {}s",
+                localization.get("hitstun"),
+                attack.hitstun_duration
+            ));
+            let pushback = attack.velocity.unwrap_or(Vec2::ZERO);
+            ui.label(format!(
+                "{}: {}, {}",
+                localization.get("pushback"),
+                pushback.x,
+                pushback.y
+            ));
+        });
+}
+
+/// A plugin that colors the collision shapes of attack hitboxes by the phase of their attack, to
+/// practice the timing of attacks
+pub struct PracticeHitboxDebugPlugin;
+
+impl Plugin for PracticeHitboxDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PracticeHitboxDebug>()
+            .add_system(color_practice_hitboxes);
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct PracticeHitboxDebug {
+    enabled: bool,
+}
+
+/// Colors attack hitboxes yellow during startup, red while active, and blue during recovery
+fn color_practice_hitboxes(
+    mut commands: Commands,
+    practice_hitbox_debug: Res<PracticeHitboxDebug>,
+    mut rapier_debug: ResMut<DebugRenderContext>,
+    hitboxes: Query<(Entity, &AttackFrames, &Parent), With<Attack>>,
+    animations: Query<&Animation>,
+) {
+    if practice_hitbox_debug.is_changed() {
+        if practice_hitbox_debug.enabled {
+            // The colors are drawn on the collision shapes
+            rapier_debug.enabled = true;
+        } else {
+            for (entity, ..) in &hitboxes {
+                commands.entity(entity).remove::<ColliderDebugColor>();
+            }
+        }
+    }
+    if !practice_hitbox_debug.enabled {
+        return;
+    }
+
+    for (entity, frames, parent) in &hitboxes {
+        if let Ok(animation) = animations.get(parent.get()) {
+            let phase = AttackPhase::at_frame(frames, animation.current_frame);
+            commands
+                .entity(entity)
+                .insert(ColliderDebugColor(phase.hitbox_color()));
+        }
+    }
+}
+
+/// A plugin that shows the actions each player is pressing, like the input display of fighting
+/// game training modes
+pub struct InputDisplayDebugPlugin;
+
+impl Plugin for InputDisplayDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputDisplayDebug>()
+            .add_system(input_display);
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct InputDisplayDebug {
+    enabled: bool,
+}
+
+/// The state of a pressed action in the input display
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputDisplayState {
+    /// Pressed this frame
+    Pressed,
+    Held,
+    /// Released this frame
+    Released,
+}
+
+/// The inputs of a player, as shown in the input display
+#[derive(Debug, Clone, PartialEq)]
+struct PlayerInputDisplay {
+    player: usize,
+    /// The direction of the movement stick
+    stick: Vec2,
+    /// The actions that are pressed, or were just released
+    actions: Vec<(PlayerAction, InputDisplayState)>,
+}
+
+impl PlayerInputDisplay {
+    fn new(player: usize, action_state: &ActionState<PlayerAction>) -> Self {
+        let stick = action_state
+            .clamped_axis_pair(PlayerAction::Move)
+            .map(|axis| Vec2::new(axis.x(), axis.y()))
+            .unwrap_or_default();
+        let actions = PlayerAction::variants()
+            // The movement is shown by the stick
+            .filter(|action| *action != PlayerAction::Move)
+            .filter_map(|action| {
+                let state = if action_state.just_pressed(action) {
+                    InputDisplayState::Pressed
+                } else if action_state.pressed(action) {
+                    InputDisplayState::Held
+                } else if action_state.just_released(action) {
+                    InputDisplayState::Released
+                } else {
+                    return None;
+                };
+                Some((action, state))
+            })
+            .collect();
+
+        Self {
+            player,
+            stick,
+            actions,
+        }
+    }
+
+    /// An arrow pointing in the direction of the stick, out of eight directions
+    fn stick_arrow(&self) -> &'static str {
+        if self.stick.length() < 0.1 {
+            return "·";
+        }
+
+        const ARROWS: [&str; 8] = ["→", "↗", "↑", "↖", "←", "↙", "↓", "↘"];
+        let angle = self.stick.y.atan2(self.stick.x);
+        let octant = (angle / std::f32::consts::FRAC_PI_4).round() as i32;
+        ARROWS[octant.rem_euclid(8) as usize]
+    }
+}
+
+/// Renders the input display in the bottom left corner of the screen
+fn input_display(
+    input_display_debug: Res<InputDisplayDebug>,
+    mut egui_context: ResMut<EguiContext>,
+    players: Query<(&PlayerIndex, &ActionState<PlayerAction>), With<Player>>,
+) {
+    if !input_display_debug.enabled {
+        return;
+    }
+
+    let mut displays = players
+        .iter()
+        .map(|(index, action_state)| PlayerInputDisplay::new(index.0, action_state))
+        .collect::<Vec<_>>();
+    displays.sort_by_key(|display| display.player);
+
+    egui::Area::new("input_display")
+        .anchor(egui::Align2::LEFT_BOTTOM, egui::vec2(8.0, -8.0))
+        .show(egui_context.ctx_mut(), |ui| {
+            for display in &displays {
+                ui.horizontal(|ui| {
+                    ui.monospace(format!("P{} {}", display.player + 1, display.stick_arrow()));
+                    for (action, state) in &display.actions {
+                        let color = match state {
+                            InputDisplayState::Pressed => Color32::YELLOW,
+                            InputDisplayState::Held => Color32::WHITE,
+                            InputDisplayState::Released => Color32::DARK_GRAY,
+                        };
+                        ui.colored_label(color, format!("{action:?}"));
+                    }
+                });
+            }
+        });
+}
+
+/// A plugin that measures how many frames a player's fighter takes to react to a press, to evaluate
+/// the responsiveness of the controls
+pub struct InputLatencyDebugPlugin;
+
+impl Plugin for InputLatencyDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<InputLatencyDebug>()
+            // Measure after the fighter states have reacted to the inputs of this frame
+            .add_system_to_stage(CoreStage::PostUpdate, measure_input_latency)
+            .add_system(input_latency_window);
+    }
+}
+
+/// Presses that the fighter doesn't react to within this many frames aren't measured
+const MAX_INPUT_LATENCY_FRAMES: u32 = 60;
+
+#[derive(Resource, Default)]
+pub struct InputLatencyDebug {
+    enabled: bool,
+    /// The press waiting for the fighter to react
+    pending: Option<PendingPress>,
+    /// The animation each player was playing on the last frame
+    animations: HashMap<Entity, Option<String>>,
+    /// The measured latencies, in frames
+    samples: Vec<u32>,
+}
+
+impl InputLatencyDebug {
+    /// The average of the measured latencies, in frames
+    fn average(&self) -> Option<f32> {
+        if self.samples.is_empty() {
+            return None;
+        }
+        Some(self.samples.iter().sum::<u32>() as f32 / self.samples.len() as f32)
+    }
+}
+
+/// A press that the fighter hasn't visibly reacted to yet
+struct PendingPress {
+    player: Entity,
+    /// The animation the fighter was playing when the action was pressed
+    animation: Option<String>,
+    /// The frames elapsed since the press
+    frames: u32,
+}
+
+/// Counts the frames between a press and the fighter's animation changing
+fn measure_input_latency(
+    mut input_latency_debug: ResMut<InputLatencyDebug>,
+    players: Query<(Entity, &ActionState<PlayerAction>, &Animation), With<Player>>,
+) {
+    if !input_latency_debug.enabled {
+        input_latency_debug.pending = None;
+        input_latency_debug.animations.clear();
+        return;
+    }
+
+    let InputLatencyDebug {
+        pending,
+        animations,
+        samples,
+        ..
+    } = &mut *input_latency_debug;
+    for (entity, action_state, animation) in &players {
+        let current = &animation.current_animation;
+        let previous = animations
+            .insert(entity, current.clone())
+            .unwrap_or_else(|| current.clone());
+
+        if pending.is_none()
+            && PlayerAction::variants().any(|action| action_state.just_pressed(action))
+        {
+            *pending = Some(PendingPress {
+                player: entity,
+                animation: previous,
+                frames: 0,
+            });
+        }
+
+        if let Some(press) = pending.as_mut().filter(|press| press.player == entity) {
+            if *current != press.animation {
+                samples.push(press.frames);
+                *pending = None;
+            } else if press.frames >= MAX_INPUT_LATENCY_FRAMES {
+                *pending = None;
+            } else {
+                press.frames += 1;
+            }
+        }
+    }
+}
+
+/// Renders the measured input latencies
+fn input_latency_window(
+    mut input_latency_debug: ResMut<InputLatencyDebug>,
+    mut egui_context: ResMut<EguiContext>,
+    localization: Res<Localization>,
+) {
+    if !input_latency_debug.enabled {
+        return;
+    }
+
+    let average = input_latency_debug.average();
+    let InputLatencyDebug {
+        enabled, samples, ..
+    } = &mut *input_latency_debug;
+    egui::Window::new(localization.get("input-latency"))
+        // ID is needed because title comes from localizaition which can change
+        .id(egui::Id::new("input_latency"))
+        .open(enabled)
+        .show(egui_context.ctx_mut(), |ui| {
+            let frames = |frames: Option<f32>| {
+                frames.map_or("-".to_string(), |frames| format!("{frames:.1}"))
+            };
+            ui.label(format!(
+                "{}: {}",
+                localization.get("last-latency"),
+                frames(samples.last().map(|&last| last as f32))
+            ));
+            ui.label(format!(
+                "{}: {}",
+                localization.get("average-latency"),
+                frames(average)
+            ));
+            ui.label(format!(
+                "{}: {}",
+                localization.get("samples"),
+                samples.len()
+            ));
+            if ui.button(localization.get("reset")).clicked() {
+                samples.clear();
+            }
+        });
+}
+
+/// A plugin that draws what the enemy AI is going for: the spot each enemy walks to, a line to the
+/// player it is after, and the ranges it attacks and alerts other enemies within
+pub struct EnemyAiDebugPlugin;
+
+impl Plugin for EnemyAiDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<EnemyAiDebug>()
+            .add_system(collect_enemy_ai_markers)
+            .add_system(draw_enemy_ai_markers.after(collect_enemy_ai_markers));
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct EnemyAiDebug {
+    enabled: bool,
+    /// The markers of the enemies going for a target this frame
+    markers: Vec<EnemyAiMarker>,
+}
+
+/// What an enemy is going for, in world coordinates
+#[derive(Debug, Clone, PartialEq)]
+struct EnemyAiMarker {
+    position: Vec2,
+    /// The spot the enemy walks to
+    target: Vec2,
+    /// The position of the player the enemy is after
+    player: Vec2,
+    /// The distance to its target within which the enemy attacks
+    attack_distance: f32,
+    /// The distance ranged enemies keep from the player
+    preferred_range: Option<f32>,
+}
+
+/// Collects the markers of the enemies with a [`WalkTarget`]
+fn collect_enemy_ai_markers(
+    mut enemy_ai_debug: ResMut<EnemyAiDebug>,
+    enemies: Query<(&Transform, &WalkTarget, Option<&Stats>), With<Enemy>>,
+) {
+    enemy_ai_debug.markers.clear();
+    if !enemy_ai_debug.enabled {
+        return;
+    }
+
+    enemy_ai_debug.markers = enemies
+        .iter()
+        .map(|(transform, target, stats)| EnemyAiMarker {
+            position: transform.translation.truncate(),
+            target: target.position,
+            player: target.player_pos,
+            attack_distance: target.attack_distance,
+            preferred_range: stats.and_then(|stats| stats.preferred_range),
+        })
+        .collect();
+}
+
+/// Renders the enemy AI markers
+fn draw_enemy_ai_markers(
+    enemy_ai_debug: Res<EnemyAiDebug>,
+    mut egui_context: ResMut<EguiContext>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+    game: Option<Res<GameMeta>>,
+) {
+    if !enemy_ai_debug.enabled {
+        return;
+    }
+
+    let (camera, camera_transform) = if let Ok(camera) = camera_query.get_single() {
+        camera
+    } else {
+        return;
+    };
+    let alert_radius = game.map_or(0.0, |game| game.enemy_alert.radius);
+
+    egui::CentralPanel::default()
+        .frame(egui::Frame::none())
+        .show(egui_context.ctx_mut(), |ui| {
+            let half_size = ui.available_size() / 2.0;
+
+            // Map world coordinates to egui points
+            let to_egui = |position: Vec2| {
+                camera
+                    .world_to_ndc(camera_transform, position.extend(0.))
+                    .map(|ndc| (egui::Vec2::new(ndc.x, -ndc.y) * half_size + half_size).to_pos2())
+            };
+            // Draw a circle with a radius in world units
+            let circle = |center: Vec2, radius: f32, stroke: Stroke| {
+                if let (Some(a), Some(b)) = (to_egui(center), to_egui(center + Vec2::X * radius)) {
+                    ui.painter().circle_stroke(a, a.distance(b), stroke);
+                }
+            };
+
+            for marker in &enemy_ai_debug.markers {
+                // The player the enemy is after
+                if let (Some(a), Some(b)) = (to_egui(marker.position), to_egui(marker.player)) {
+                    ui.painter()
+                        .line_segment([a, b], Stroke::new(1.0, Color32::LIGHT_RED));
+                }
+
+                // The spot it walks to, and the range it attacks from there
+                if let Some(target) = to_egui(marker.target) {
+                    ui.painter().circle_filled(target, 3.0, Color32::YELLOW);
+                }
+                circle(
+                    marker.target,
+                    marker.attack_distance,
+                    Stroke::new(1.0, Color32::YELLOW),
+                );
+
+                // The range ranged enemies keep from the player
+                if let Some(range) = marker.preferred_range {
+                    circle(marker.player, range, Stroke::new(1.0, Color32::LIGHT_BLUE));
+                }
+
+                // The range it alerts the other enemies within
+                if alert_radius > 0.0 {
+                    circle(
+                        marker.position,
+                        alert_radius,
+                        Stroke::new(1.0, Color32::GRAY),
+                    );
+                }
+            }
+        });
+}
+
+/// A plugin that draws the invisible constraints on player movement: the edges of the camera view,
+/// the line the players push the camera past, the left movement boundary, the stop points, the
+/// ground plane and how far ahead of the hindmost player the others may go
+pub struct MovementBoundsDebugPlugin;
+
+impl Plugin for MovementBoundsDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MovementBoundsDebug>()
+            .add_system(
+                collect_movement_bounds
+                    .run_if_resource_exists::<LevelMeta>()
+                    .run_if_resource_exists::<LeftMovementBoundary>(),
+            )
+            .add_system(draw_movement_bounds.after(collect_movement_bounds));
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct MovementBoundsDebug {
+    enabled: bool,
+    /// The lines to draw this frame
+    lines: Vec<BoundsLine>,
+}
+
+/// A movement constraint, as a line in world coordinates
+#[derive(Debug, Clone, PartialEq)]
+struct BoundsLine {
+    start: Vec2,
+    end: Vec2,
+    kind: BoundsLineKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum BoundsLineKind {
+    /// An edge of the camera view, or the right bound of the level
+    Camera,
+    /// The line past which the players push the camera forward
+    CameraPush,
+    /// The boundary the players can't go back past
+    LeftBoundary,
+    /// A stop point, which doesn't block the players anymore once it has been passed
+    StopPoint { passed: bool },
+    /// The top or the bottom of the ground the players walk on
+    Ground,
+    /// How far ahead of the hindmost player the other players can go
+    RelativeClamp,
+}
+
+impl BoundsLineKind {
+    fn stroke(self) -> Stroke {
+        let color = match self {
+            BoundsLineKind::Camera => Color32::WHITE,
+            BoundsLineKind::CameraPush => Color32::LIGHT_BLUE,
+            BoundsLineKind::LeftBoundary => Color32::RED,
+            BoundsLineKind::StopPoint { passed: false } => Color32::YELLOW,
+            BoundsLineKind::StopPoint { passed: true } => Color32::GRAY,
+            BoundsLineKind::Ground => Color32::GREEN,
+            BoundsLineKind::RelativeClamp => Color32::from_rgb(255, 128, 0),
+        };
+        Stroke::new(1.0, color)
+    }
+}
+
+/// Collects the lines of the constraints applied by the movement systems
+fn collect_movement_bounds(
+    mut movement_bounds_debug: ResMut<MovementBoundsDebug>,
+    level_meta: Res<LevelMeta>,
+    game_meta: Res<GameMeta>,
+    left_movement_boundary: Res<LeftMovementBoundary>,
+    passed_stop_points: Res<PassedStopPoints>,
+    players: Query<&Transform, LivingPlayer>,
+    camera_query: Query<(&Transform, &OrthographicProjection), With<Camera>>,
+) {
+    movement_bounds_debug.lines.clear();
+    if !movement_bounds_debug.enabled {
+        return;
+    }
+
+    let bounds = &level_meta.bounds;
+    let (min_y, max_y) = (bounds.min_y(), bounds.max_y());
+    let mut lines = Vec::new();
+    let mut vertical = |x: f32, kind: BoundsLineKind| {
+        lines.push(BoundsLine {
+            start: Vec2::new(x, min_y),
+            end: Vec2::new(x, max_y),
+            kind,
+        });
+    };
+
+    vertical(left_movement_boundary.0, BoundsLineKind::LeftBoundary);
+    for (i, &stop_point) in level_meta.stop_points.iter().enumerate() {
+        vertical(
+            stop_point,
+            BoundsLineKind::StopPoint {
+                passed: passed_stop_points.contains(&i),
+            },
+        );
+    }
+    if let Some(right) = bounds.right {
+        vertical(right, BoundsLineKind::Camera);
+    }
+
+    // The other players stop once they get this far ahead of the hindmost one
+    let min_player_x = players
+        .iter()
+        .map(|transform| transform.translation.x)
+        .reduce(f32::min);
+    if let Some(min_player_x) = min_player_x {
+        vertical(
+            min_player_x + LEFT_BOUNDARY_MAX_DISTANCE + game_meta.camera_move_right_boundary,
+            BoundsLineKind::RelativeClamp,
+        );
+    }
+
+    // The edges of the view, and the line past which the players move the camera
+    let mut left = left_movement_boundary.0;
+    let mut right = left + LEFT_BOUNDARY_MAX_DISTANCE;
+    if let Ok((camera, projection)) = camera_query.get_single() {
+        let x = camera.translation.x;
+        left = x + projection.left * projection.scale;
+        right = x + projection.right * projection.scale;
+        vertical(left, BoundsLineKind::Camera);
+        vertical(right, BoundsLineKind::Camera);
+        vertical(
+            x + game_meta.camera_move_right_boundary * projection.scale,
+            BoundsLineKind::CameraPush,
+        );
+    }
+
+    // The ground plane, across the view
+    for y in [min_y, max_y] {
+        lines.push(BoundsLine {
+            start: Vec2::new(left, y),
+            end: Vec2::new(right, y),
+            kind: BoundsLineKind::Ground,
+        });
+    }
+
+    movement_bounds_debug.lines = lines;
+}
+
+/// Renders the movement bounds
+fn draw_movement_bounds(
+    movement_bounds_debug: Res<MovementBoundsDebug>,
+    mut egui_context: ResMut<EguiContext>,
+    camera_query: Query<(&Camera, &GlobalTransform)>,
+) {
+    if !movement_bounds_debug.enabled {
+        return;
+    }
+
+    let (camera, camera_transform) = if let Ok(camera) = camera_query.get_single() {
+        camera
+    } else {
+        return;
+    };
+
+    egui::CentralPanel::default()
+        .frame(egui::Frame::none())
+        .show(egui_context.ctx_mut(), |ui| {
+            let half_size = ui.available_size() / 2.0;
+
+            // Map world coordinates to egui points
+            let to_egui = |position: Vec2| {
+                camera
+                    .world_to_ndc(camera_transform, position.extend(0.))
+                    .map(|ndc| (egui::Vec2::new(ndc.x, -ndc.y) * half_size + half_size).to_pos2())
+            };
+
+            for line in &movement_bounds_debug.lines {
+                if let (Some(a), Some(b)) = (to_egui(line.start), to_egui(line.end)) {
+                    ui.painter().line_segment([a, b], line.kind.stroke());
+                }
+            }
+        });
+}
+
+pub struct QuickRestartDebugPlugin;
+
+impl Plugin for QuickRestartDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_system(quick_restart.run_in_state(GameState::InGame));
+    }
+}
+
+/// Restarts the current level when F3 is pressed, without going through the menus
+///
+/// The world is reset and the level is loaded again, which restarts the level music when the game
+/// re-enters [`GameState::InGame`].
+fn quick_restart(
+    input: Res<Input<KeyCode>>,
+    mut state_requests: ResMut<GameStateRequests>,
+    reset_controller: ResetController,
+) {
+    if input.just_pressed(KeyCode::F3) {
+        reset_controller.reset_world();
+        state_requests.request(GameState::LoadingLevel, TransitionPriority::Navigation);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::item::Item;
+
+    #[test]
+    fn test_practice_hitbox_color_follows_attack_phase() {
+        let mut world = World::new();
+        world.init_resource::<DebugRenderContext>();
+        world.insert_resource(PracticeHitboxDebug { enabled: true });
+
+        let fighter = world.spawn(Animation::new(0.05, default())).id();
+        let hitbox = world
+            .spawn((
+                Attack::default(),
+                AttackFrames {
+                    startup: 2,
+                    active: 4,
+                    recovery: 7,
+                },
+            ))
+            .id();
+        world.entity_mut(fighter).add_child(hitbox);
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(color_practice_hitboxes);
+        let mut color_on_frame = |world: &mut World, frame: usize| {
+            world.get_mut::<Animation>(fighter).unwrap().current_frame = frame;
+            stage.run(world);
+            world.get::<ColliderDebugColor>(hitbox).map(|color| color.0)
+        };
+
+        assert_eq!(color_on_frame(&mut world, 0), Some(Color::YELLOW));
+        assert_eq!(color_on_frame(&mut world, 3), Some(Color::RED));
+        assert_eq!(color_on_frame(&mut world, 6), Some(Color::BLUE));
+        assert!(world.resource::<DebugRenderContext>().enabled);
+
+        // Turning the overlay off gives the hitbox its usual color back
+        world.resource_mut::<PracticeHitboxDebug>().enabled = false;
+        assert_eq!(color_on_frame(&mut world, 3), None);
+    }
+
+    #[test]
+    fn test_attack_phase_at_frame() {
+        let frames = AttackFrames {
+            startup: 2,
+            active: 4,
+            recovery: 7,
+        };
+        let phases = (0..7)
+            .map(|frame| AttackPhase::at_frame(&frames, frame))
+            .collect::<Vec<_>>();
+
+        assert_eq!(
+            phases,
+            [
+                AttackPhase::Startup,
+                AttackPhase::Startup,
+                AttackPhase::Active,
+                AttackPhase::Active,
+                AttackPhase::Active,
+                AttackPhase::Recovery,
+                AttackPhase::Recovery,
+            ]
+        );
+    }
+
+    #[test]
+    fn test_input_display_shows_pressed_actions() {
+        let mut action_state = ActionState::<PlayerAction>::default();
+        let display = PlayerInputDisplay::new(0, &action_state);
+        assert!(display.actions.is_empty());
+        assert_eq!(display.stick_arrow(), "·");
+
+        action_state.press(PlayerAction::Attack);
+        let display = PlayerInputDisplay::new(1, &action_state);
+        assert_eq!(display.player, 1);
+        assert_eq!(
+            display.actions,
+            [(PlayerAction::Attack, InputDisplayState::Pressed)]
+        );
+
+        action_state.release(PlayerAction::Attack);
+        let display = PlayerInputDisplay::new(1, &action_state);
+        assert_eq!(
+            display.actions,
+            [(PlayerAction::Attack, InputDisplayState::Released)]
+        );
+    }
+
+    #[test]
+    fn test_quick_restart_clears_the_level() {
+        let mut world = World::new();
+        world.init_resource::<GameStateRequests>();
+        world.init_resource::<Input<KeyCode>>();
+        let camera = world.spawn((Camera::default(), Transform::default())).id();
+        for x in [100., 200.] {
+            world.spawn((Enemy, Transform::from_xyz(x, 0., 0.)));
+        }
+        world.spawn((
+            Item {
+                spawn_sprite: false,
+            },
+            Transform::default(),
+        ));
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(quick_restart);
+
+        // Nothing happens until the hotkey is pressed
+        stage.run(&mut world);
+        assert_eq!(world.query::<&Enemy>().iter(&world).count(), 2);
+
+        world.resource_mut::<Input<KeyCode>>().press(KeyCode::F3);
+        stage.run(&mut world);
+        assert_eq!(world.query::<&Enemy>().iter(&world).count(), 0);
+        assert_eq!(world.query::<&Item>().iter(&world).count(), 0);
+        assert!(world.get_entity(camera).is_some());
+
+        // The level is loaded again, which then re-enters the game
+        let next_state = world.resource_mut::<GameStateRequests>().take();
+        assert_eq!(next_state, Some(GameState::LoadingLevel));
+    }
+
+    #[test]
+    fn test_enemy_ai_overlay_marks_each_walk_target() {
+        let mut world = World::new();
+        world.init_resource::<EnemyAiDebug>();
+        let walk_target = |x: f32| WalkTarget {
+            position: Vec2::new(x, 0.),
+            attack_distance: 20.,
+            player_pos: Vec2::ZERO,
+        };
+        world.spawn((Enemy, Transform::from_xyz(100., 0., 0.), walk_target(30.)));
+        world.spawn((Enemy, Transform::from_xyz(200., 0., 0.), walk_target(-30.)));
+        // Enemies without a target have nothing to show
+        world.spawn((Enemy, Transform::from_xyz(300., 0., 0.)));
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(collect_enemy_ai_markers);
+
+        // Nothing is collected while the overlay is disabled
+        stage.run(&mut world);
+        assert!(world.resource::<EnemyAiDebug>().markers.is_empty());
+
+        world.resource_mut::<EnemyAiDebug>().enabled = true;
+        stage.run(&mut world);
+        let mut targets = world
+            .resource::<EnemyAiDebug>()
+            .markers
+            .iter()
+            .map(|marker| marker.target.x)
+            .collect::<Vec<_>>();
+        targets.sort_by(f32::total_cmp);
+        assert_eq!(targets, [-30., 30.]);
+    }
+
+    #[test]
+    fn test_movement_bounds_overlay_draws_boundary_and_stop_points() {
+        let mut world = World::new();
+        world.init_resource::<MovementBoundsDebug>();
+        let game: GameMeta =
+            serde_yaml::from_str(include_str!("../../assets/default.game.yaml")).unwrap();
+        let mut level: LevelMeta =
+            serde_yaml::from_str(include_str!("../../assets/levels/1_beach/beach.level.yaml"))
+                .unwrap();
+        level.stop_points = vec![300., 600.];
+        world.insert_resource(game);
+        world.insert_resource(level);
+        world.insert_resource(LeftMovementBoundary(-120.));
+        world.insert_resource(PassedStopPoints([0].into_iter().collect()));
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(collect_movement_bounds);
+
+        // Nothing is collected while the overlay is disabled
+        stage.run(&mut world);
+        assert!(world.resource::<MovementBoundsDebug>().lines.is_empty());
+
+        world.resource_mut::<MovementBoundsDebug>().enabled = true;
+        stage.run(&mut world);
+        let lines = &world.resource::<MovementBoundsDebug>().lines;
+        let line_x = |kind: BoundsLineKind| {
+            lines
+                .iter()
+                .filter(|line| line.kind == kind)
+                .map(|line| {
+                    assert_eq!(line.start.x, line.end.x);
+                    line.start.x
+                })
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(line_x(BoundsLineKind::LeftBoundary), [-120.]);
+        assert_eq!(line_x(BoundsLineKind::StopPoint { passed: true }), [300.]);
+        assert_eq!(line_x(BoundsLineKind::StopPoint { passed: false }), [600.]);
+
+        // The line follows the boundary as it moves forward
+        world.resource_mut::<LeftMovementBoundary>().0 = 40.;
+        stage.run(&mut world);
+        let lines = &world.resource::<MovementBoundsDebug>().lines;
+        assert!(lines
+            .iter()
+            .any(|line| line.kind == BoundsLineKind::LeftBoundary && line.start.x == 40.));
+    }
+
+    #[test]
+    fn test_input_latency_counts_frames_until_the_animation_changes() {
+        let mut world = World::new();
+        world.insert_resource(InputLatencyDebug {
+            enabled: true,
+            ..default()
+        });
+        let mut animation = Animation::new(0.1, default());
+        animation.current_animation = Some("idle".to_string());
+        let player = world
+            .spawn((Player, ActionState::<PlayerAction>::default(), animation))
+            .id();
+
+        let mut stage = SystemStage::single_threaded();
+        stage.add_system(measure_input_latency);
+        stage.run(&mut world);
+
+        // The attack is pressed, and the fighter starts attacking three frames later
+        world
+            .get_mut::<ActionState<PlayerAction>>(player)
+            .unwrap()
+            .press(PlayerAction::Attack);
+        stage.run(&mut world);
+        world
+            .get_mut::<ActionState<PlayerAction>>(player)
+            .unwrap()
+            .release(PlayerAction::Attack);
+        stage.run(&mut world);
+        stage.run(&mut world);
+        assert!(world.resource::<InputLatencyDebug>().samples.is_empty());
+
+        world
+            .get_mut::<Animation>(player)
+            .unwrap()
+            .current_animation = Some("attacking".to_string());
+        stage.run(&mut world);
+
+        let input_latency_debug = world.resource::<InputLatencyDebug>();
+        assert_eq!(input_latency_debug.samples, [3]);
+        assert_eq!(input_latency_debug.average(), Some(3.));
+        assert!(input_latency_debug.pending.is_none());
+    }
+}
@@ -2,16 +2,48 @@
 
 use bevy::prelude::*;
 use bevy_egui::{egui, EguiContext};
+use bevy_fluent::{Locale, Localization};
 
 use crate::{
-    damage::Health,
-    fighter::Inventory,
-    metadata::{FighterMeta, GameMeta},
+    camera::BossIntro,
+    consts,
+    damage::{DamageEvent, Health},
+    enemy::{Boss, BossCooldowns},
+    fighter::{Inventory, SuperMeter},
+    fighter_state::{Downed, Dying},
+    localization::{format_number, LocalizationExt},
+    metadata::{FighterMeta, GameMeta, LevelMeta, VictoryCondition},
     player::PlayerIndex,
     ui::widgets::{bordered_frame::BorderedFrame, progress_bar::ProgressBar, EguiUIExt},
+    victory::LevelTime,
     Player, Stats,
 };
 
+/// Get the anchor of each player's HUD panel, in order, for the given number of players.
+///
+/// Panels fill the top corners first and then the bottom corners, with less space around them when
+/// there are more than two players, to leave more room for the game.
+pub fn player_hud_anchors(player_count: usize) -> Vec<(egui::Align2, egui::Vec2)> {
+    let margin = if player_count > 2 {
+        consts::PLAYER_HUD_MARGIN / 2.0
+    } else {
+        consts::PLAYER_HUD_MARGIN
+    };
+
+    [
+        (egui::Align2::LEFT_TOP, egui::Vec2::new(margin, margin)),
+        (egui::Align2::RIGHT_TOP, egui::Vec2::new(-margin, margin)),
+        (egui::Align2::LEFT_BOTTOM, egui::Vec2::new(margin, -margin)),
+        (
+            egui::Align2::RIGHT_BOTTOM,
+            egui::Vec2::new(-margin, -margin),
+        ),
+    ]
+    .into_iter()
+    .take(player_count)
+    .collect()
+}
+
 pub fn render_hud(
     mut egui_context: ResMut<EguiContext>,
     players: Query<
@@ -21,6 +53,7 @@ pub fn render_hud(
             &Health,
             &Handle<FighterMeta>,
             &Inventory,
+            &SuperMeter,
         ),
         With<Player>,
     >,
@@ -31,8 +64,10 @@ pub fn render_hud(
 
     // Helper struct for holding player hud info
     struct PlayerInfo {
+        index: usize,
         name: String,
         life: f32,
+        super_meter: f32,
         portrait_texture_id: egui::TextureId,
         portrait_size: egui::Vec2,
         item: Option<ItemInfo>,
@@ -45,30 +80,34 @@ pub fn render_hud(
 
     // Collect player info
     let mut players = players.iter().collect::<Vec<_>>();
-    players.sort_by_key(|(player_i, _, _, _, _)| player_i.0);
+    players.sort_by_key(|(player_i, _, _, _, _, _)| player_i.0);
 
     let player_infos = players
         .into_iter()
-        .filter_map(|(_, stats, health, fighter_handle, inventory)| {
-            fighter_assets.get(fighter_handle).map(|fighter| {
-                let portrait_size = fighter.hud.portrait.image_size;
-                PlayerInfo {
-                    name: fighter.name.clone(),
-                    life: **health as f32 / stats.max_health as f32,
-                    portrait_texture_id: egui_context
-                        .add_image(fighter.hud.portrait.image_handle.clone_weak()),
-                    portrait_size: egui::Vec2::new(portrait_size.x, portrait_size.y),
-                    item: inventory.as_ref().map(|item_meta| ItemInfo {
-                        texture_id: egui_context
-                            .add_image(item_meta.image.image_handle.clone_weak()),
-                        size: egui::Vec2::new(
-                            item_meta.image.image_size.x,
-                            item_meta.image.image_size.y,
-                        ),
-                    }),
-                }
-            })
-        })
+        .filter_map(
+            |(player_i, stats, health, fighter_handle, inventory, super_meter)| {
+                fighter_assets.get(fighter_handle).map(|fighter| {
+                    let portrait_size = fighter.hud.portrait.image_size;
+                    PlayerInfo {
+                        index: player_i.0,
+                        name: fighter.name.clone(),
+                        life: **health as f32 / stats.max_health as f32,
+                        super_meter: super_meter.value / super_meter.max,
+                        portrait_texture_id: egui_context
+                            .add_image(fighter.hud.portrait.image_handle.clone_weak()),
+                        portrait_size: egui::Vec2::new(portrait_size.x, portrait_size.y),
+                        item: inventory.as_ref().map(|item_meta| ItemInfo {
+                            texture_id: egui_context
+                                .add_image(item_meta.image.image_handle.clone_weak()),
+                            size: egui::Vec2::new(
+                                item_meta.image.image_size.x,
+                                item_meta.image.image_size.y,
+                            ),
+                        }),
+                    }
+                })
+            },
+        )
         .collect::<Vec<_>>();
 
     let border = ui_theme.hud.portrait_frame.border_size;
@@ -80,42 +119,372 @@ pub fn render_hud(
         bottom: border.bottom * scale,
     };
 
-    egui::CentralPanel::default()
-        .frame(egui::Frame::none())
-        .show(egui_context.ctx_mut(), |ui| {
-            ui.add_space(10.0);
-            ui.horizontal(|ui| {
-                for player in player_infos {
-                    ui.add_space(20.0);
-
-                    ui.vertical(|ui| {
-                        ui.allocate_ui(egui::Vec2::new(ui_theme.hud.player_hud_width, 50.), |ui| {
-                            ui.themed_label(&ui_theme.hud.font, &player.name);
-
-                            ui.horizontal(|ui| {
-                                BorderedFrame::new(&ui_theme.hud.portrait_frame)
-                                    .padding(portrait_frame_padding)
-                                    .show(ui, |ui| {
-                                        ui.image(player.portrait_texture_id, player.portrait_size);
-                                    });
+    // Players are laid out in the order of their index, so the panels follow players joining and
+    // leaving.
+    let anchors = player_hud_anchors(player_infos.len());
+    for (player, (align, offset)) in player_infos.into_iter().zip(anchors) {
+        let font = match ui_theme.hud.player_colors.get(player.index) {
+            Some(color) => ui_theme.hud.font.colored(*color),
+            None => ui_theme.hud.font.clone(),
+        };
+
+        egui::Area::new(("player_hud", player.index))
+            .anchor(align, offset)
+            .show(egui_context.ctx_mut(), |ui| {
+                ui.vertical(|ui| {
+                    ui.allocate_ui(egui::Vec2::new(ui_theme.hud.player_hud_width, 50.), |ui| {
+                        ui.themed_label(&font, &player.name);
+
+                        ui.horizontal(|ui| {
+                            BorderedFrame::new(&ui_theme.hud.portrait_frame)
+                                .padding(portrait_frame_padding)
+                                .show(ui, |ui| {
+                                    ui.image(player.portrait_texture_id, player.portrait_size);
+                                });
+
+                            ui.vertical(|ui| {
+                                ui.add_space(5.0);
+                                ProgressBar::new(&ui_theme.hud.lifebar, player.life)
+                                    .min_width(ui.available_width())
+                                    .show(ui);
+
+                                ui.add_space(3.0);
+                                ProgressBar::new(&ui_theme.hud.lifebar, player.super_meter)
+                                    .min_width(ui.available_width())
+                                    .show(ui);
 
                                 ui.vertical(|ui| {
-                                    ui.add_space(5.0);
-                                    ProgressBar::new(&ui_theme.hud.lifebar, player.life)
-                                        .min_width(ui.available_width())
-                                        .show(ui);
-
-                                    ui.vertical(|ui| {
-                                        if let Some(item) = player.item {
-                                            ui.add_space(5.0);
-                                            ui.image(item.texture_id, item.size);
-                                        }
-                                    });
+                                    if let Some(item) = player.item {
+                                        ui.add_space(5.0);
+                                        ui.image(item.texture_id, item.size);
+                                    }
                                 });
                             });
                         });
                     });
-                }
+                });
             });
+    }
+}
+
+/// Shows the health of the living bosses at the bottom of the screen, with the cooldowns of their
+/// special attacks below it, so that players can see when the next one is coming.
+pub fn render_boss_hud(
+    mut egui_context: ResMut<EguiContext>,
+    bosses: Query<
+        (
+            Entity,
+            &Stats,
+            &Health,
+            &Handle<FighterMeta>,
+            &BossCooldowns,
+        ),
+        (With<Boss>, Without<Dying>),
+    >,
+    game: Res<GameMeta>,
+    fighter_assets: Res<Assets<FighterMeta>>,
+) {
+    let ui_theme = &game.ui_theme;
+    let width = ui_theme.hud.player_hud_width * 2.;
+    let mut offset = -consts::PLAYER_HUD_MARGIN;
+
+    for (entity, stats, health, fighter_handle, cooldowns) in &bosses {
+        let fighter = match fighter_assets.get(fighter_handle) {
+            Some(fighter) => fighter,
+            None => continue,
+        };
+        let life = **health as f32 / stats.max_health as f32;
+
+        let response = egui::Area::new(("boss_hud", entity))
+            .anchor(egui::Align2::CENTER_BOTTOM, egui::Vec2::new(0., offset))
+            .show(egui_context.ctx_mut(), |ui| {
+                ui.set_width(width);
+                ui.vertical_centered(|ui| {
+                    ui.themed_label(&ui_theme.hud.font, &fighter.name);
+                    ProgressBar::new(&ui_theme.hud.lifebar, life)
+                        .min_width(width)
+                        .show(ui);
+
+                    // A row of segments for each attack, filling up as it comes off cooldown
+                    for cooldown in &cooldowns.0 {
+                        ui.add_space(3.);
+                        let filled = cooldown_segments(cooldowns.progress(cooldown.attack));
+                        let (rect, _) = ui
+                            .allocate_exact_size(egui::Vec2::new(width, 6.), egui::Sense::hover());
+                        let segment_width = rect.width() / consts::BOSS_COOLDOWN_SEGMENTS as f32;
+
+                        for i in 0..consts::BOSS_COOLDOWN_SEGMENTS {
+                            let segment = egui::Rect::from_min_size(
+                                rect.min + egui::Vec2::new(i as f32 * segment_width, 0.),
+                                egui::Vec2::new(segment_width - 2., rect.height()),
+                            );
+                            let color = if i >= filled {
+                                egui::Color32::from_black_alpha(160)
+                            } else if filled == consts::BOSS_COOLDOWN_SEGMENTS {
+                                // Ready to fire
+                                egui::Color32::from_rgb(230, 60, 40)
+                            } else {
+                                egui::Color32::from_rgb(230, 170, 40)
+                            };
+                            ui.painter().rect_filled(segment, 0., color);
+                        }
+                    }
+                });
+            })
+            .response;
+
+        // Stack the bars of multiple bosses
+        offset -= response.rect.height() + consts::PLAYER_HUD_MARGIN / 2.;
+    }
+}
+
+/// Shows the name banner of the boss during its intro, until the camera goes back to the players
+pub fn render_boss_intro_banner(
+    mut egui_context: ResMut<EguiContext>,
+    intro: Option<Res<BossIntro>>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+) {
+    let intro = match intro {
+        Some(intro) if !intro.is_returning() => intro,
+        _ => return,
+    };
+
+    egui::Area::new("boss_intro_banner")
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .show(egui_context.ctx_mut(), |ui| {
+            BorderedFrame::new(&game.ui_theme.panel.border)
+                .padding(game.ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.themed_label(
+                        &game.main_menu.title_font,
+                        &localization.get(intro.banner.as_str()),
+                    );
+                });
+        });
+}
+
+/// Get the number of filled segments of a boss attack cooldown indicator, from its progress
+pub fn cooldown_segments(progress: f32) -> usize {
+    ((progress.clamp(0., 1.) * consts::BOSS_COOLDOWN_SEGMENTS as f32) as usize)
+        .min(consts::BOSS_COOLDOWN_SEGMENTS)
+}
+
+/// Shows the seconds left to survive, in levels won by surviving
+pub fn render_survival_countdown(
+    mut egui_context: ResMut<EguiContext>,
+    level: Res<LevelMeta>,
+    level_time: Res<LevelTime>,
+    game: Res<GameMeta>,
+    locale: Res<Locale>,
+) {
+    let duration = match level.victory {
+        VictoryCondition::Survive { duration } => duration,
+        _ => return,
+    };
+    let seconds_left = (duration - **level_time).max(0.0).ceil();
+
+    egui::Area::new("survival_countdown")
+        .anchor(
+            egui::Align2::CENTER_TOP,
+            egui::Vec2::new(0.0, consts::PLAYER_HUD_MARGIN),
+        )
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.themed_label(
+                &game.ui_theme.hud.font,
+                &format_number(seconds_left as f64, 0, &locale.requested),
+            );
         });
 }
+
+/// Component added to a player that has been hit, pointing toward the source of the hit
+#[derive(Component)]
+pub struct HurtIndicator {
+    /// The angle, in radians, from the player to the source of the hit
+    pub angle: f32,
+    pub timer: Timer,
+}
+
+/// Get the angle, in radians, from the player to the source of a hit
+pub fn hurt_indicator_angle(player_position: Vec2, source_position: Vec2) -> f32 {
+    let direction = source_position - player_position;
+    direction.y.atan2(direction.x)
+}
+
+/// Adds hurt indicators to players that have been damaged
+pub fn collect_hurt_indicators(
+    mut commands: Commands,
+    mut damage_events: EventReader<DamageEvent>,
+    players: Query<&Transform, With<Player>>,
+) {
+    // Damage over time doesn't come from any direction
+    for event in damage_events
+        .iter()
+        .filter(|event| !event.is_damage_over_time())
+    {
+        if let Ok(transform) = players.get(event.damaged_entity) {
+            commands.entity(event.damaged_entity).insert(HurtIndicator {
+                angle: hurt_indicator_angle(
+                    transform.translation.truncate(),
+                    event.source_position,
+                ),
+                timer: Timer::from_seconds(consts::HURT_INDICATOR_DURATION, TimerMode::Once),
+            });
+        }
+    }
+}
+
+/// Renders an arrow around damaged players, pointing toward the source of the hit and fading out
+pub fn render_hurt_indicators(
+    mut commands: Commands,
+    mut egui_context: ResMut<EguiContext>,
+    mut players: Query<(Entity, &GlobalTransform, &mut HurtIndicator)>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    time: Res<Time>,
+) {
+    let (camera, camera_transform) = if let Ok(camera) = camera.get_single() {
+        camera
+    } else {
+        return;
+    };
+
+    let ctx = egui_context.ctx_mut();
+    let screen_rect = ctx.screen_rect();
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("hurt_indicators"),
+    ));
+
+    for (entity, transform, mut indicator) in &mut players {
+        indicator.timer.tick(time.delta());
+        if indicator.timer.finished() {
+            commands.entity(entity).remove::<HurtIndicator>();
+            continue;
+        }
+
+        let ndc = match camera.world_to_ndc(camera_transform, transform.translation()) {
+            Some(ndc) => ndc,
+            None => continue,
+        };
+
+        // Map NDC coordinates to egui points, inverting y
+        let center = egui::Pos2::new(
+            screen_rect.left() + (ndc.x + 1.0) / 2.0 * screen_rect.width(),
+            screen_rect.top() + (1.0 - ndc.y) / 2.0 * screen_rect.height(),
+        );
+        let direction = egui::Vec2::new(indicator.angle.cos(), -indicator.angle.sin());
+        let normal = egui::Vec2::new(-direction.y, direction.x);
+
+        let alpha = 1.0 - indicator.timer.percent();
+        let color = egui::Color32::from_rgba_unmultiplied(255, 40, 40, (alpha * 255.0) as u8);
+
+        painter.add(egui::Shape::convex_polygon(
+            vec![
+                center + direction * 48.0,
+                center + direction * 36.0 + normal * 8.0,
+                center + direction * 36.0 - normal * 8.0,
+            ],
+            color,
+            egui::Stroke::none(),
+        ));
+    }
+}
+
+/// Renders a bar above downed players, showing the time left before they bleed out and the progress
+/// of their revive
+pub fn render_downed_indicators(
+    mut egui_context: ResMut<EguiContext>,
+    players: Query<(&GlobalTransform, &Downed), With<Player>>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    game: Res<GameMeta>,
+) {
+    let (camera, camera_transform) = if let Ok(camera) = camera.get_single() {
+        camera
+    } else {
+        return;
+    };
+
+    let ctx = egui_context.ctx_mut();
+    let screen_rect = ctx.screen_rect();
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("downed_indicators"),
+    ));
+
+    for (transform, downed) in &players {
+        let ndc = match camera.world_to_ndc(camera_transform, transform.translation()) {
+            Some(ndc) => ndc,
+            None => continue,
+        };
+
+        // Map NDC coordinates to egui points, inverting y
+        let center = egui::Pos2::new(
+            screen_rect.left() + (ndc.x + 1.0) / 2.0 * screen_rect.width(),
+            screen_rect.top() + (1.0 - ndc.y) / 2.0 * screen_rect.height(),
+        ) - egui::Vec2::new(0.0, 64.0);
+        let bar = egui::Rect::from_center_size(center, egui::Vec2::new(48.0, 6.0));
+
+        // The bleed out time left, in red, with the revive progress drawn over it in green
+        let bleed_out = 1.0 - downed.timer.percent();
+        let revive = (downed.revive_progress / game.revive.revive_duration).clamp(0.0, 1.0);
+        let filled = |fraction: f32| {
+            egui::Rect::from_min_size(
+                bar.min,
+                egui::Vec2::new(bar.width() * fraction, bar.height()),
+            )
+        };
+
+        painter.rect_filled(bar, 0.0, egui::Color32::from_black_alpha(160));
+        painter.rect_filled(filled(bleed_out), 0.0, egui::Color32::from_rgb(200, 40, 40));
+        painter.rect_filled(filled(revive), 0.0, egui::Color32::from_rgb(40, 200, 60));
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::f32::consts::PI;
+
+    use super::*;
+
+    #[test]
+    fn test_hurt_indicator_angle_from_left() {
+        let angle = hurt_indicator_angle(Vec2::new(100., 0.), Vec2::new(20., 0.));
+        assert!((angle.abs() - PI).abs() < f32::EPSILON);
+
+        let angle = hurt_indicator_angle(Vec2::new(100., 0.), Vec2::new(180., 0.));
+        assert!(angle.abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn test_three_players_get_distinct_corners() {
+        let anchors = player_hud_anchors(3);
+        assert_eq!(anchors.len(), 3);
+
+        for (i, (align, _)) in anchors.iter().enumerate() {
+            // Each panel is in a corner that no other panel is in
+            assert_ne!(align.x(), egui::Align::Center);
+            assert_ne!(align.y(), egui::Align::Center);
+            assert!(anchors[i + 1..].iter().all(|(other, _)| other != align));
+        }
+
+        // There are only four corners
+        assert_eq!(player_hud_anchors(6).len(), 4);
+    }
+
+    #[test]
+    fn test_boss_cooldown_indicator_fills_toward_ready() {
+        let mut cooldowns = BossCooldowns::default();
+        let attack = cooldowns.0[0].attack;
+        let segments = |cooldowns: &BossCooldowns| cooldown_segments(cooldowns.progress(attack));
+        assert_eq!(segments(&cooldowns), consts::BOSS_COOLDOWN_SEGMENTS);
+
+        cooldowns.trigger(attack);
+        assert_eq!(segments(&cooldowns), 0);
+
+        let duration = cooldowns.0[0].timer.duration();
+        cooldowns.tick(duration / 2);
+        assert_eq!(segments(&cooldowns), consts::BOSS_COOLDOWN_SEGMENTS / 2);
+        cooldowns.tick(duration);
+        assert_eq!(segments(&cooldowns), consts::BOSS_COOLDOWN_SEGMENTS);
+    }
+}
@@ -0,0 +1,145 @@
+//! Button glyphs for the kind of input device the player is using, so that menus show the buttons
+//! the way they are labeled on the player's device.
+
+use bevy::{input::gamepad::GamepadEventType, prelude::*, utils::HashMap};
+use leafwing_input_manager::user_input::InputKind;
+
+use crate::{
+    input::PlayerAction,
+    metadata::{InputDevice, InputGlyphsMeta, Settings},
+};
+
+/// The kind of input device that was used last
+#[derive(Resource, Default)]
+pub struct ActiveInputDevice(pub InputDevice);
+
+impl ActiveInputDevice {
+    /// Get the device whose glyphs are shown, which can be overridden in the settings
+    pub fn glyph_device(&self, settings: &Settings) -> InputDevice {
+        settings.input_glyphs.unwrap_or(self.0)
+    }
+}
+
+/// Guess the kind of a gamepad from its name
+pub fn gamepad_kind(name: Option<&str>) -> InputDevice {
+    let name = name.unwrap_or_default().to_lowercase();
+    let is_playstation = [
+        "playstation",
+        "sony",
+        "dualshock",
+        "dualsense",
+        "ps3",
+        "ps4",
+        "ps5",
+    ]
+    .iter()
+    .any(|pattern| name.contains(pattern));
+
+    if is_playstation {
+        InputDevice::PlayStation
+    } else {
+        InputDevice::Xbox
+    }
+}
+
+/// Keeps track of the kind of input device that was used last
+pub fn detect_input_device(
+    mut active_device: ResMut<ActiveInputDevice>,
+    keys: Res<Input<KeyCode>>,
+    mut gamepad_events: EventReader<GamepadEvent>,
+    gamepads: Res<Gamepads>,
+) {
+    let mut device = None;
+    if keys.get_just_pressed().next().is_some() {
+        device = Some(InputDevice::Keyboard);
+    }
+    for event in gamepad_events.iter() {
+        if let GamepadEventType::ButtonChanged(_, value) = event.event_type {
+            if value > 0.5 {
+                device = Some(gamepad_kind(gamepads.name(event.gamepad)));
+            }
+        }
+    }
+
+    // Only change the resource when the device changes, so that the menus can react to it
+    if let Some(device) = device {
+        if active_device.0 != device {
+            active_device.0 = device;
+        }
+    }
+}
+
+/// Get the glyph of an input, using the button glyphs given for gamepad buttons
+pub fn input_glyph(
+    input: &InputKind,
+    gamepad_buttons: Option<&HashMap<GamepadButtonType, String>>,
+) -> String {
+    match (input, gamepad_buttons) {
+        (InputKind::GamepadButton(button), Some(glyphs)) => glyphs
+            .get(button)
+            .cloned()
+            .unwrap_or_else(|| format!("{button:?}")),
+        (input, _) => input.to_string(),
+    }
+}
+
+/// Get the glyph of the input the first player has bound to an action, on the given device.
+///
+/// Actions that aren't bound to a single input, like moving, don't have a glyph.
+pub fn action_glyph(
+    action: PlayerAction,
+    device: InputDevice,
+    settings: &Settings,
+    glyphs: &InputGlyphsMeta,
+) -> Option<String> {
+    let controls = &settings.player_controls;
+    let controls = match device {
+        InputDevice::Keyboard => &controls.keyboard1,
+        InputDevice::Xbox | InputDevice::PlayStation => &controls.gamepad,
+    };
+    let input = match action {
+        PlayerAction::Run => &controls.run,
+        PlayerAction::Attack => &controls.flop_attack,
+        PlayerAction::Throw => &controls.throw,
+        PlayerAction::Shoot => &controls.shoot,
+        PlayerAction::Super => &controls.super_attack,
+        PlayerAction::Block => &controls.block,
+        PlayerAction::Move | PlayerAction::AnalogAttack => return None,
+    };
+
+    Some(input_glyph(input, glyphs.gamepad_buttons(device)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::metadata::GameMeta;
+
+    #[test]
+    fn test_detected_device_changes_attack_glyph() {
+        let game: GameMeta =
+            serde_yaml::from_str(include_str!("../../assets/default.game.yaml")).unwrap();
+        let mut settings = game.default_settings.clone();
+        let mut active_device = ActiveInputDevice::default();
+
+        let attack_glyph = |active_device: &ActiveInputDevice, settings: &Settings| {
+            let device = active_device.glyph_device(settings);
+            action_glyph(PlayerAction::Attack, device, settings, &game.input_glyphs).unwrap()
+        };
+
+        active_device.0 = gamepad_kind(Some("Xbox Wireless Controller"));
+        assert_eq!(attack_glyph(&active_device, &settings), "A");
+
+        active_device.0 = gamepad_kind(Some("Sony DualSense Wireless Controller"));
+        assert_eq!(attack_glyph(&active_device, &settings), "Cross");
+
+        active_device.0 = InputDevice::Keyboard;
+        let keyboard_glyph = attack_glyph(&active_device, &settings);
+        assert_ne!(keyboard_glyph, "A");
+        assert_ne!(keyboard_glyph, "Cross");
+
+        // The glyphs of a device can be chosen in the settings, whatever the device used
+        settings.input_glyphs = Some(InputDevice::Xbox);
+        assert_eq!(attack_glyph(&active_device, &settings), "A");
+    }
+}
@@ -0,0 +1,73 @@
+use bevy::prelude::*;
+use bevy_egui::*;
+use bevy_fluent::Localization;
+
+use crate::{
+    game_state::{GameStateRequests, TransitionPriority},
+    localization::LocalizationExt,
+    metadata::{ButtonStyle, FontStyle, GameMeta},
+    utils::ResetController,
+    GameState,
+};
+
+use super::widgets::{bordered_button::BorderedButton, bordered_frame::BorderedFrame, EguiUIExt};
+
+pub fn level_complete_menu(
+    mut state_requests: ResMut<GameStateRequests>,
+    mut egui_context: ResMut<EguiContext>,
+    game: Res<GameMeta>,
+    localization: Res<Localization>,
+    reset_controller: ResetController,
+) {
+    let ui_theme = &game.ui_theme;
+
+    egui::CentralPanel::default()
+        .frame(egui::Frame::none())
+        .show(egui_context.ctx_mut(), |ui| {
+            let screen_rect = ui.max_rect();
+
+            let menu_width = 300.0;
+            let x_margin = (screen_rect.width() - menu_width) / 2.0;
+            let outer_margin = egui::style::Margin::symmetric(x_margin, screen_rect.height() * 0.2);
+
+            BorderedFrame::new(&ui_theme.panel.border)
+                .margin(outer_margin)
+                .padding(ui_theme.panel.padding.into())
+                .show(ui, |ui| {
+                    ui.set_min_width(ui.available_width());
+
+                    let heading_font = ui_theme
+                        .font_styles
+                        .get(&FontStyle::Heading)
+                        .expect("Missing 'heading' font style")
+                        .colored(ui_theme.panel.font_color);
+
+                    ui.vertical_centered(|ui| {
+                        ui.themed_label(&heading_font, &localization.get("level-complete"));
+
+                        ui.add_space(10.0);
+
+                        let main_menu_button = BorderedButton::themed(
+                            ui_theme,
+                            &ButtonStyle::Normal,
+                            &localization.get("main-menu"),
+                        )
+                        .min_size(egui::vec2(ui.available_width(), 0.0))
+                        .show(ui);
+
+                        // Focus the main menu button by default
+                        if ui.memory().focus().is_none() {
+                            main_menu_button.request_focus();
+                        }
+
+                        if main_menu_button.clicked() {
+                            reset_controller.reset_world();
+
+                            state_requests
+                                .request(GameState::MainMenu, TransitionPriority::Navigation);
+                            ui.ctx().clear_focus();
+                        }
+                    });
+                })
+        });
+}
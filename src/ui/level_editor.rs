@@ -0,0 +1,346 @@
+//! Debug level editor, for placing enemies, items, and stop points by clicking in the level, and
+//! exporting the arrangement as a level file.
+
+use bevy::prelude::*;
+use bevy_egui::*;
+use bevy_fluent::Localization;
+
+use crate::{
+    consts,
+    localization::LocalizationExt,
+    metadata::{FighterSpawnMeta, ItemSpawnMeta, LevelHandle, LevelMeta},
+};
+
+/// The level file that placements are exported to and imported from by default
+const DEFAULT_LEVEL_PATH: &str = "assets/levels/exported.level.yaml";
+
+pub struct LevelEditorPlugin;
+
+impl Plugin for LevelEditorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelEditor>()
+            .add_system(level_editor_window)
+            .add_system(place_on_click.after(level_editor_window))
+            .add_system_to_stage(CoreStage::Last, draw_placements);
+    }
+}
+
+#[derive(Resource)]
+pub struct LevelEditor {
+    pub enabled: bool,
+    /// What is placed when clicking in the level
+    pub tool: PlacementTool,
+    /// The name of the fighter or item to place
+    pub name: String,
+    /// The level file to export to and import from
+    pub path: String,
+    pub placements: LevelPlacements,
+    /// The result of the last export or import
+    status: String,
+}
+
+impl Default for LevelEditor {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            tool: PlacementTool::Enemy,
+            name: "bandit".into(),
+            path: DEFAULT_LEVEL_PATH.into(),
+            placements: default(),
+            status: String::new(),
+        }
+    }
+}
+
+/// The kind of thing placed by the level editor
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlacementTool {
+    Enemy,
+    Item,
+    StopPoint,
+}
+
+/// The enemies, items, and stop points placed in the level editor, in the [`LevelMeta`] schema.
+///
+/// Locations are relative to the ground, like in level files.
+#[derive(Default, Clone, Debug)]
+pub struct LevelPlacements {
+    pub enemies: Vec<FighterSpawnMeta>,
+    pub items: Vec<ItemSpawnMeta>,
+    pub stop_points: Vec<f32>,
+}
+
+impl LevelPlacements {
+    /// Get the placements of a level, to keep editing them
+    pub fn from_level(level: &LevelMeta) -> Self {
+        Self {
+            enemies: level.enemies.clone(),
+            items: level.items.clone(),
+            stop_points: level.stop_points.clone(),
+        }
+    }
+
+    /// Place the fighter or item `name` at a world position, or a stop point at its x position
+    pub fn place(&mut self, tool: PlacementTool, name: &str, position: Vec2) {
+        let location = Vec3::new(position.x, position.y - consts::GROUND_Y, 0.);
+
+        match tool {
+            PlacementTool::Enemy => self.enemies.push(FighterSpawnMeta {
+                fighter: format!("/fighters/{0}/{0}.fighter.yaml", name),
+                fighter_handle: default(),
+                location,
+                trip_point_x: f32::MIN,
+                boss: false,
+                entrance: None,
+            }),
+            PlacementTool::Item => self.items.push(ItemSpawnMeta {
+                item: format!("/items/{0}/{0}.item.yaml", name),
+                item_handle: default(),
+                location,
+            }),
+            PlacementTool::StopPoint => {
+                self.stop_points.push(position.x);
+                self.stop_points.sort_by(f32::total_cmp);
+            }
+        }
+    }
+
+    /// Get a copy of `level` with its enemies, items, and stop points replaced by the placements
+    pub fn to_level(&self, level: &LevelMeta) -> LevelMeta {
+        LevelMeta {
+            enemies: self.enemies.clone(),
+            items: self.items.clone(),
+            stop_points: self.stop_points.clone(),
+            ..level.clone()
+        }
+    }
+}
+
+/// Renders the level editor window
+fn level_editor_window(
+    mut level_editor: ResMut<LevelEditor>,
+    mut egui_context: ResMut<EguiContext>,
+    localization: Res<Localization>,
+    level_handle: Option<Res<LevelHandle>>,
+    level_assets: Res<Assets<LevelMeta>>,
+) {
+    if !level_editor.enabled {
+        return;
+    }
+
+    let level = level_handle.and_then(|handle| level_assets.get(&handle));
+    let editor = &mut *level_editor;
+
+    egui::Window::new(localization.get("level-editor"))
+        // ID is needed because title comes from localizaition which can change
+        .id(egui::Id::new("level_editor"))
+        .open(&mut editor.enabled)
+        .show(egui_context.ctx_mut(), |ui| {
+            ui.horizontal(|ui| {
+                for (tool, label) in [
+                    (PlacementTool::Enemy, "enemy"),
+                    (PlacementTool::Item, "item"),
+                    (PlacementTool::StopPoint, "stop-point"),
+                ] {
+                    ui.selectable_value(&mut editor.tool, tool, localization.get(label));
+                }
+            });
+            if editor.tool != PlacementTool::StopPoint {
+                ui.text_edit_singleline(&mut editor.name);
+            }
+
+            ui.label(format!(
+                "{}: {}, {}: {}, {}: {}",
+                localization.get("enemies"),
+                editor.placements.enemies.len(),
+                localization.get("items"),
+                editor.placements.items.len(),
+                localization.get("stop-points"),
+                editor.placements.stop_points.len(),
+            ));
+
+            ui.separator();
+            ui.text_edit_singleline(&mut editor.path);
+            ui.horizontal(|ui| {
+                if ui.button(localization.get("export-level")).clicked() {
+                    editor.status = match level {
+                        Some(level) => {
+                            export_level(&editor.placements.to_level(level), &editor.path)
+                        }
+                        None => Err("No level loaded".into()),
+                    }
+                    .map_or_else(|e| e, |_| format!("Exported {}", editor.path));
+                }
+
+                if ui.button(localization.get("import-level")).clicked() {
+                    editor.status = match import_level(&editor.path) {
+                        Ok(level) => {
+                            editor.placements = LevelPlacements::from_level(&level);
+                            format!("Imported {}", editor.path)
+                        }
+                        Err(e) => e,
+                    };
+                }
+
+                if ui.button(localization.get("clear")).clicked() {
+                    editor.placements = default();
+                }
+            });
+            ui.label(editor.status.as_str());
+        });
+}
+
+/// Write a level to a level file
+fn export_level(level: &LevelMeta, path: &str) -> Result<(), String> {
+    let yaml = serde_yaml::to_string(level).map_err(|e| e.to_string())?;
+    std::fs::write(path, yaml).map_err(|e| e.to_string())
+}
+
+/// Read a level from a level file
+fn import_level(path: &str) -> Result<LevelMeta, String> {
+    let yaml = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+    serde_yaml::from_str(&yaml).map_err(|e| e.to_string())
+}
+
+/// Places the current tool where the level is clicked, while the level editor is open
+fn place_on_click(
+    mut level_editor: ResMut<LevelEditor>,
+    mut egui_context: ResMut<EguiContext>,
+    mouse_input: Res<Input<MouseButton>>,
+    windows: Res<Windows>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+) {
+    // Don't place anything when clicking on the debug windows
+    if !level_editor.enabled
+        || !mouse_input.just_pressed(MouseButton::Left)
+        || egui_context.ctx_mut().wants_pointer_input()
+    {
+        return;
+    }
+
+    let cursor = windows
+        .get_primary()
+        .and_then(|window| window.cursor_position());
+    let (camera, camera_transform) = match camera.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+
+    if let Some(ray) = cursor.and_then(|cursor| camera.viewport_to_world(camera_transform, cursor))
+    {
+        let LevelEditor {
+            tool,
+            name,
+            placements,
+            ..
+        } = &mut *level_editor;
+        placements.place(*tool, name, ray.origin.truncate());
+    }
+}
+
+/// Marks the placements in the level while the level editor is open
+fn draw_placements(
+    level_editor: Res<LevelEditor>,
+    mut egui_context: ResMut<EguiContext>,
+    camera: Query<(&Camera, &GlobalTransform)>,
+    level: Option<Res<LevelMeta>>,
+) {
+    if !level_editor.enabled {
+        return;
+    }
+    let (camera, camera_transform) = match camera.get_single() {
+        Ok(camera) => camera,
+        Err(_) => return,
+    };
+
+    let ctx = egui_context.ctx_mut();
+    let screen_rect = ctx.screen_rect();
+    let painter = ctx.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("level_editor_placements"),
+    ));
+
+    // Map world positions to egui points, inverting y
+    let to_screen = |position: Vec3| {
+        camera.world_to_ndc(camera_transform, position).map(|ndc| {
+            egui::Pos2::new(
+                screen_rect.left() + (ndc.x + 1.0) / 2.0 * screen_rect.width(),
+                screen_rect.top() + (1.0 - ndc.y) / 2.0 * screen_rect.height(),
+            )
+        })
+    };
+    let ground_offset = Vec3::new(0., consts::GROUND_Y, 0.);
+
+    let placements = &level_editor.placements;
+    for (locations, color) in [
+        (
+            placements
+                .enemies
+                .iter()
+                .map(|enemy| enemy.location)
+                .collect::<Vec<_>>(),
+            egui::Color32::RED,
+        ),
+        (
+            placements.items.iter().map(|item| item.location).collect(),
+            egui::Color32::YELLOW,
+        ),
+    ] {
+        for location in locations {
+            if let Some(point) = to_screen(location + ground_offset) {
+                painter.circle_filled(point, 6.0, color);
+            }
+        }
+    }
+
+    // Stop points span the walkable area of the level
+    let bounds = match &level {
+        Some(level) => &level.bounds,
+        None => return,
+    };
+    for x in &placements.stop_points {
+        let top = to_screen(Vec3::new(*x, bounds.max_y(), 0.));
+        let bottom = to_screen(Vec3::new(*x, bounds.min_y(), 0.));
+        if let (Some(top), Some(bottom)) = (top, bottom) {
+            painter.line_segment([top, bottom], (2.0, egui::Color32::LIGHT_BLUE));
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_exported_enemies_round_trip() {
+        let level: LevelMeta =
+            serde_yaml::from_str(include_str!("../../assets/levels/1_beach/beach.level.yaml"))
+                .unwrap();
+
+        let mut placements = LevelPlacements::default();
+        placements.place(PlacementTool::Enemy, "bandit", Vec2::new(100., -150.));
+        placements.place(PlacementTool::Enemy, "slinger", Vec2::new(300., -100.));
+
+        let yaml = serde_yaml::to_string(&placements.to_level(&level)).unwrap();
+        let exported: LevelMeta = serde_yaml::from_str(&yaml).unwrap();
+        let imported = LevelPlacements::from_level(&exported);
+
+        let spawns = |placements: &LevelPlacements| {
+            placements
+                .enemies
+                .iter()
+                .map(|enemy| (enemy.fighter.clone(), enemy.location))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(spawns(&imported), spawns(&placements));
+        assert_eq!(
+            imported.enemies[0].location,
+            Vec3::new(100., -150. - consts::GROUND_Y, 0.)
+        );
+
+        // The rest of the level is kept as it was
+        assert!(imported.items.is_empty());
+        assert_eq!(exported.players.len(), level.players.len());
+        assert_eq!(exported.music, level.music);
+    }
+}
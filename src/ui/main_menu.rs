@@ -1,22 +1,28 @@
-use bevy::{app::AppExit, ecs::system::SystemParam, prelude::*};
+use bevy::{app::AppExit, ecs::system::SystemParam, prelude::*, utils::HashMap};
 use bevy_egui::{egui::style::Margin, *};
 use bevy_fluent::Localization;
 use egui_extras::Column;
-use iyes_loopless::state::NextState;
 use leafwing_input_manager::{
-    axislike::SingleAxis, prelude::ActionState, user_input::InputKind, Actionlike,
+    axislike::SingleAxis,
+    prelude::{ActionState, InputMap},
+    user_input::{InputKind, UserInput},
+    Actionlike,
 };
 
 use crate::{
     config::ENGINE_CONFIG,
-    input::MenuAction,
+    game_state::{GameStateRequests, TransitionPriority},
+    input::{MenuAction, PlayerAction},
     localization::LocalizationExt,
-    metadata::{ButtonStyle, FontStyle, GameMeta, LevelHandle, Settings},
+    metadata::{
+        ButtonStyle, FontStyle, GameMeta, InputDevice, InputGlyphsMeta, LevelHandle, Settings,
+    },
     platform::Storage,
     GameState,
 };
 
 use super::{
+    input_glyphs::{input_glyph, ActiveInputDevice},
     widgets::{bordered_button::BorderedButton, bordered_frame::BorderedFrame, EguiUIExt},
     EguiContextExt, EguiResponseExt, WidgetAdjacencies,
 };
@@ -62,14 +68,20 @@ pub fn despawn_main_menu_background(
 #[derive(Clone, Copy)]
 pub enum MenuPage {
     Main,
-    Settings { tab: SettingsTab },
+    /// The controls of the first player, with the glyphs of the given device
+    HowToPlay {
+        device: InputDevice,
+    },
+    Settings {
+        tab: SettingsTab,
+    },
 }
 
 /// Which settings tab we are on
 #[derive(Clone, Copy, PartialEq, Eq)]
 pub enum SettingsTab {
     Controls,
-    #[allow(unused)] // Just for now until we get sound settings setup
+    Video,
     Sound,
 }
 
@@ -88,8 +100,8 @@ impl Default for SettingsTab {
 impl SettingsTab {
     const TABS: &'static [(Self, &'static str)] = &[
         (Self::Controls, "controls"),
-        // For now, hide the sound tab because we don't have it working yet.
-        // (Self::Sound, "sound")
+        (Self::Video, "video"),
+        (Self::Sound, "sound"),
     ];
 }
 
@@ -100,13 +112,16 @@ pub struct MenuSystemParams<'w, 's> {
     modified_settings: Local<'s, Option<Settings>>,
     currently_binding_input_idx: Local<'s, Option<usize>>,
     commands: Commands<'w, 's>,
+    state_requests: ResMut<'w, GameStateRequests>,
     game: Res<'w, GameMeta>,
     localization: Res<'w, Localization>,
     menu_input: Query<'w, 's, &'static mut ActionState<MenuAction>>,
     app_exit: EventWriter<'w, 's, AppExit>,
     storage: ResMut<'w, Storage>,
+    settings: Option<Res<'w, Settings>>,
     adjacencies: ResMut<'w, WidgetAdjacencies>,
     control_inputs: ControlInputBindingEvents<'w, 's>,
+    active_input_device: Res<'w, ActiveInputDevice>,
 }
 
 /// Render the main menu UI
@@ -115,7 +130,7 @@ pub fn main_menu_system(mut params: MenuSystemParams, mut egui_context: ResMut<E
 
     // Go to previous menu if back button is pressed
     if menu_input.pressed(MenuAction::Back) {
-        if let MenuPage::Settings { .. } = *params.menu_page {
+        if let MenuPage::Settings { .. } | MenuPage::HowToPlay { .. } = *params.menu_page {
             *params.menu_page = MenuPage::Main;
             egui_context.ctx_mut().clear_focus();
         }
@@ -149,6 +164,7 @@ pub fn main_menu_system(mut params: MenuSystemParams, mut egui_context: ResMut<E
                     // Render the menu based on the current menu selection
                     match *params.menu_page {
                         MenuPage::Main => main_menu_ui(&mut params, ui),
+                        MenuPage::HowToPlay { device } => how_to_play_ui(&mut params, ui, device),
                         MenuPage::Settings { tab } => settings_menu_ui(&mut params, ui, tab),
                     }
                 });
@@ -161,10 +177,12 @@ fn main_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui) {
         menu_page,
         modified_settings,
         commands,
+        state_requests,
         game,
         localization,
         app_exit,
         storage,
+        active_input_device,
         ..
     } = params;
 
@@ -189,7 +207,39 @@ fn main_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui) {
 
         if start_button.clicked() || ENGINE_CONFIG.auto_start {
             commands.insert_resource(LevelHandle(game.start_level_handle.clone()));
-            commands.insert_resource(NextState(GameState::LoadingLevel));
+            state_requests.request(GameState::LoadingLevel, TransitionPriority::Navigation);
+        }
+
+        // Assist AI toggle, for an AI ally to control the second player
+        let mut settings = Settings::load(storage, &game.default_settings);
+        let assist_ai_label = format!(
+            "{}: {}",
+            localization.get("assist-ai"),
+            localization.get(if settings.assist_ai { "on" } else { "off" })
+        );
+        if BorderedButton::themed(ui_theme, &ButtonStyle::Normal, &assist_ai_label)
+            .min_size(min_button_size)
+            .show(ui)
+            .clicked()
+        {
+            settings.assist_ai = !settings.assist_ai;
+            storage.set(Settings::STORAGE_KEY, &settings);
+            storage.save();
+        }
+
+        // How to play button, showing the controls for the device in use
+        if BorderedButton::themed(
+            ui_theme,
+            &ButtonStyle::Normal,
+            &localization.get("how-to-play"),
+        )
+        .min_size(min_button_size)
+        .show(ui)
+        .clicked()
+        {
+            **menu_page = MenuPage::HowToPlay {
+                device: active_input_device.glyph_device(&settings),
+            };
         }
 
         // Settings button
@@ -203,11 +253,7 @@ fn main_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui) {
         .clicked()
         {
             **menu_page = MenuPage::Settings { tab: default() };
-            **modified_settings = Some(
-                storage
-                    .get(Settings::STORAGE_KEY)
-                    .unwrap_or_else(|| game.default_settings.clone()),
-            );
+            **modified_settings = Some(Settings::load(storage, &game.default_settings));
         }
 
         // Quit button
@@ -226,6 +272,200 @@ fn main_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui) {
     });
 }
 
+/// Render the how to play screen, listing the actions of the first player and their bindings
+fn how_to_play_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui, device: InputDevice) {
+    let ui_theme = &params.game.ui_theme;
+
+    let bigger_font = ui_theme
+        .font_styles
+        .get(&FontStyle::Bigger)
+        .unwrap()
+        .colored(ui_theme.panel.font_color);
+    let label_font = ui_theme
+        .font_styles
+        .get(&FontStyle::Normal)
+        .unwrap()
+        .colored(ui_theme.panel.font_color);
+
+    // Read the bindings from the loaded settings, so that they reflect any rebinds
+    let settings = params
+        .settings
+        .as_deref()
+        .unwrap_or(&params.game.default_settings);
+    let rows = how_to_play_rows(
+        &settings.player_controls.get_input_map(0),
+        device,
+        &params.game.input_glyphs,
+    );
+
+    ui.vertical_centered(|ui| {
+        ui.themed_label(
+            ui_theme.font_styles.get(&FontStyle::Heading).unwrap(),
+            &params.localization.get("how-to-play"),
+        );
+
+        // Add buttons to the bottom
+        ui.with_layout(egui::Layout::bottom_up(egui::Align::Center), |ui| {
+            let min_button_size = egui::vec2(ui.available_width() / 2.0, 0.0);
+
+            let back_button = BorderedButton::themed(
+                ui_theme,
+                &ButtonStyle::Normal,
+                &params.localization.get("back"),
+            )
+            .min_size(min_button_size)
+            .show(ui)
+            .focus_by_default(ui);
+
+            if back_button.clicked() {
+                *params.menu_page = MenuPage::Main;
+                ui.ctx().clear_focus();
+            }
+
+            // Switch between the keyboard and gamepad controls
+            let device_label = format!(
+                "{}: {}",
+                params.localization.get("button-prompts"),
+                params.localization.get(match device {
+                    InputDevice::Keyboard => "keyboard",
+                    InputDevice::Xbox => "xbox",
+                    InputDevice::PlayStation => "playstation",
+                })
+            );
+            let device_button =
+                BorderedButton::themed(ui_theme, &ButtonStyle::Normal, &device_label)
+                    .min_size(min_button_size)
+                    .show(ui);
+
+            if device_button.clicked() {
+                *params.menu_page = MenuPage::HowToPlay {
+                    device: match device {
+                        InputDevice::Keyboard => InputDevice::Xbox,
+                        InputDevice::Xbox => InputDevice::PlayStation,
+                        InputDevice::PlayStation => InputDevice::Keyboard,
+                    },
+                };
+            }
+
+            params
+                .adjacencies
+                .widget(&device_button)
+                .above(&back_button)
+                .to_left_of(&back_button);
+            params
+                .adjacencies
+                .widget(&back_button)
+                .to_left_of(&device_button);
+
+            ui.vertical(|ui| {
+                egui_extras::TableBuilder::new(ui)
+                    .cell_layout(egui::Layout::centered_and_justified(
+                        egui::Direction::LeftToRight,
+                    ))
+                    .column(Column::exact(label_font.size * 7.0))
+                    .column(Column::remainder())
+                    .header(bigger_font.size * 1.5, |mut row| {
+                        row.col(|ui| {
+                            ui.themed_label(&bigger_font, &params.localization.get("action"));
+                        });
+                        row.col(|ui| {
+                            ui.themed_label(&bigger_font, &params.localization.get("controls"));
+                        });
+                    })
+                    .body(|mut body| {
+                        for row in &rows {
+                            body.row(label_font.size * 1.5, |mut table_row| {
+                                table_row.col(|ui| {
+                                    ui.themed_label(
+                                        &label_font,
+                                        &params.localization.get(row.name()),
+                                    );
+                                });
+                                table_row.col(|ui| {
+                                    ui.themed_label(&label_font, &row.inputs.join(", "));
+                                });
+                            });
+                        }
+                    });
+            });
+        });
+    });
+}
+
+/// An action on the how to play screen, with the inputs bound to it
+#[derive(Debug)]
+struct HowToPlayRow {
+    action: PlayerAction,
+    /// The formatted inputs bound to the action on the device shown
+    inputs: Vec<String>,
+}
+
+impl HowToPlayRow {
+    /// The localization key of the action's name
+    fn name(&self) -> &'static str {
+        match self.action {
+            PlayerAction::Move => "move",
+            PlayerAction::Run => "run",
+            PlayerAction::Attack => "flop-attack",
+            PlayerAction::AnalogAttack => "analog-attack",
+            PlayerAction::Throw => "throwgrab",
+            PlayerAction::Shoot => "shoot",
+            PlayerAction::Super => "super-attack",
+            PlayerAction::Block => "block",
+        }
+    }
+}
+
+/// List every player action with the inputs bound to it in `input_map` on the given device.
+///
+/// Inputs of the other kind of device are left out, so the keyboard shows no gamepad bindings
+/// and the other way around.
+fn how_to_play_rows(
+    input_map: &InputMap<PlayerAction>,
+    device: InputDevice,
+    glyphs: &InputGlyphsMeta,
+) -> Vec<HowToPlayRow> {
+    let gamepad_glyphs = glyphs.gamepad_buttons(device);
+    let on_gamepad = device != InputDevice::Keyboard;
+    let is_gamepad_input = |input: &InputKind| {
+        matches!(
+            input,
+            InputKind::GamepadButton(_) | InputKind::SingleAxis(_) | InputKind::DualAxis(_)
+        )
+    };
+
+    PlayerAction::variants()
+        .map(|action| {
+            let inputs = input_map
+                .get(action)
+                .iter()
+                .filter_map(|input| {
+                    let (kinds, separator) = match input {
+                        UserInput::Single(kind) => (vec![kind], ""),
+                        UserInput::Chord(kinds) => (kinds.iter().collect(), "+"),
+                        UserInput::VirtualDPad(dpad) => {
+                            (vec![&dpad.up, &dpad.down, &dpad.left, &dpad.right], "/")
+                        }
+                    };
+
+                    kinds
+                        .iter()
+                        .all(|kind| is_gamepad_input(kind) == on_gamepad)
+                        .then(|| {
+                            kinds
+                                .iter()
+                                .map(|kind| format_input(kind, gamepad_glyphs))
+                                .collect::<Vec<_>>()
+                                .join(separator)
+                        })
+                })
+                .collect();
+
+            HowToPlayRow { action, inputs }
+        })
+        .collect()
+}
+
 /// Render the settings menu
 fn settings_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui, current_tab: SettingsTab) {
     // Disable all the buttons if we are currently binding an input
@@ -362,7 +602,22 @@ fn settings_menu_ui(params: &mut MenuSystemParams, ui: &mut egui::Ui, current_ta
                             &bottom_buttons,
                         )
                     }
-                    SettingsTab::Sound => sound_settings_ui(ui, &params.game),
+                    SettingsTab::Video => video_settings_ui(
+                        params,
+                        ui,
+                        // Reset button clicked
+                        bottom_buttons[1].clicked(),
+                        &tabs,
+                        &bottom_buttons,
+                    ),
+                    SettingsTab::Sound => sound_settings_ui(
+                        params,
+                        ui,
+                        // Reset button clicked
+                        bottom_buttons[1].clicked(),
+                        &tabs,
+                        &bottom_buttons,
+                    ),
                 }
             });
         });
@@ -405,6 +660,18 @@ fn controls_settings_ui(
         + small_button_style.padding.top
         + small_button_style.padding.bottom;
 
+    // Show the gamepad bindings with the glyphs of the gamepad in use, or of an Xbox controller
+    let glyph_device = params
+        .active_input_device
+        .glyph_device(params.modified_settings.as_ref().unwrap());
+    let gamepad_glyphs = params
+        .game
+        .input_glyphs
+        .gamepad_buttons(match glyph_device {
+            InputDevice::Keyboard => InputDevice::Xbox,
+            device => device,
+        });
+
     // Mutably borrow the player controlls settings
     let controls = &mut params.modified_settings.as_mut().unwrap().player_controls;
 
@@ -442,6 +709,14 @@ fn controls_settings_ui(
                 &mut controls.gamepad.movement.right,
             ],
         ),
+        (
+            &params.localization.get("run"),
+            [
+                &mut controls.keyboard1.run,
+                &mut controls.keyboard2.run,
+                &mut controls.gamepad.run,
+            ],
+        ),
         (
             &params.localization.get("flop-attack"),
             [
@@ -466,11 +741,32 @@ fn controls_settings_ui(
                 &mut controls.gamepad.throw,
             ],
         ),
+        (
+            &params.localization.get("super-attack"),
+            [
+                &mut controls.keyboard1.super_attack,
+                &mut controls.keyboard2.super_attack,
+                &mut controls.gamepad.super_attack,
+            ],
+        ),
+        (
+            &params.localization.get("block"),
+            [
+                &mut controls.keyboard1.block,
+                &mut controls.keyboard2.block,
+                &mut controls.gamepad.block,
+            ],
+        ),
     ];
 
+    let row_count = input_rows.len();
+
     // Collect input button responses for building adjacency graph
     let mut input_buttons = Vec::new();
 
+    // The keyboard player whose input was bound, and the input it replaced
+    let mut rebound_keyboard = None;
+
     // Create input table
     egui_extras::TableBuilder::new(ui)
         .cell_layout(egui::Layout::centered_and_justified(
@@ -520,7 +816,7 @@ fn controls_settings_ui(
                             let button = BorderedButton::themed(
                                 ui_theme,
                                 &ButtonStyle::Small,
-                                format_input(input),
+                                format_input(input, gamepad_glyphs),
                             )
                             .show(ui);
 
@@ -579,7 +875,13 @@ fn controls_settings_ui(
 
                                                     // Set the input for this button to the pressed
                                                     // input
-                                                    **input = input_kind;
+                                                    let replaced =
+                                                        std::mem::replace(*input, input_kind);
+                                                    if matches!(binding_kind, BindingKind::Keyboard)
+                                                    {
+                                                        rebound_keyboard =
+                                                            Some((button_idx, replaced));
+                                                    }
 
                                                 // If the user cancelled the input binding
                                                 } else if get_input.is_err() {
@@ -611,8 +913,13 @@ fn controls_settings_ui(
             }
         });
 
+    // Don't let a single key control both keyboard players
+    if let Some((player_idx, replaced)) = rebound_keyboard {
+        controls.resolve_keyboard_conflicts(player_idx, &replaced);
+    }
+
     // Set adjacency for all of the gamepad input buttons
-    for row_idx in 0..input_rows.len() {
+    for row_idx in 0..row_count {
         if row_idx == 0 {
             // Reverse button order here so that the first input button gets priority when
             // navigating down from the tabs.
@@ -634,7 +941,7 @@ fn controls_settings_ui(
             }
 
         // If this is the last row, the input buttons are above the bottom buttons
-        } else if row_idx == input_rows.len() - 1 {
+        } else if row_idx == row_count - 1 {
             for i in 0..3 {
                 let button_above = &input_buttons[(row_idx - 1) * 3 + i];
                 let button = &input_buttons[row_idx * 3 + i];
@@ -666,14 +973,255 @@ fn controls_settings_ui(
     }
 }
 
+/// Render the video settings UI
+fn video_settings_ui(
+    params: &mut MenuSystemParams,
+    ui: &mut egui::Ui,
+    should_reset: bool,
+    settings_tabs: &[egui::Response],
+    bottom_buttons: &[egui::Response],
+) {
+    let ui_theme = &params.game.ui_theme;
+
+    // Reset the settings when reset button is clicked
+    if should_reset {
+        let settings = params.modified_settings.as_mut().unwrap();
+        settings.crt = params.game.default_settings.crt.clone();
+        settings.input_glyphs = params.game.default_settings.input_glyphs;
+        settings.accessibility = params.game.default_settings.accessibility.clone();
+        settings.video = params.game.default_settings.video.clone();
+    }
+
+    let label_font = ui_theme
+        .font_styles
+        .get(&FontStyle::Normal)
+        .unwrap()
+        .colored(ui_theme.panel.font_color);
+
+    let settings = params.modified_settings.as_mut().unwrap();
+    let crt = &mut settings.crt;
+
+    ui.add_space(label_font.size);
+    ui.vertical_centered(|ui| {
+        // CRT effect toggle
+        let crt_label = format!(
+            "{}: {}",
+            params.localization.get("crt-effect"),
+            params
+                .localization
+                .get(if crt.enabled { "on" } else { "off" })
+        );
+        let crt_button = BorderedButton::themed(ui_theme, &ButtonStyle::Normal, &crt_label)
+            .min_size(egui::vec2(ui.available_width() / 2.0, 0.0))
+            .show(ui);
+        if crt_button.clicked() {
+            crt.enabled = !crt.enabled;
+        }
+
+        // The strength of the effect can only be adjusted while it is enabled
+        let sliders = ui
+            .add_enabled_ui(crt.enabled, |ui| {
+                ui.add_space(label_font.size * 0.5);
+                ui.themed_label(&label_font, &params.localization.get("scanline-intensity"));
+                let intensity = ui.add(egui::Slider::new(&mut crt.scanline_intensity, 0.0..=1.0));
+
+                ui.themed_label(&label_font, &params.localization.get("screen-curvature"));
+                let curvature = ui.add(egui::Slider::new(&mut crt.curvature, 0.0..=0.25));
+
+                [intensity, curvature]
+            })
+            .inner;
+
+        // The glyphs of the button prompts, which follow the device in use when set to auto
+        ui.add_space(label_font.size * 0.5);
+        let prompts_label = format!(
+            "{}: {}",
+            params.localization.get("button-prompts"),
+            params.localization.get(match settings.input_glyphs {
+                None => "auto",
+                Some(InputDevice::Keyboard) => "keyboard",
+                Some(InputDevice::Xbox) => "xbox",
+                Some(InputDevice::PlayStation) => "playstation",
+            })
+        );
+        let prompts_button = BorderedButton::themed(ui_theme, &ButtonStyle::Normal, &prompts_label)
+            .min_size(egui::vec2(ui.available_width() / 2.0, 0.0))
+            .show(ui);
+        if prompts_button.clicked() {
+            settings.input_glyphs = match settings.input_glyphs {
+                None => Some(InputDevice::Keyboard),
+                Some(InputDevice::Keyboard) => Some(InputDevice::Xbox),
+                Some(InputDevice::Xbox) => Some(InputDevice::PlayStation),
+                Some(InputDevice::PlayStation) => None,
+            };
+        }
+
+        // How strongly the camera shakes, for players sensitive to it
+        ui.add_space(label_font.size * 0.5);
+        ui.themed_label(&label_font, &params.localization.get("screen-shake"));
+        let screen_shake = ui.add(egui::Slider::new(
+            &mut settings.accessibility.screen_shake,
+            0.0..=1.0,
+        ));
+
+        let reduced_motion_label = format!(
+            "{}: {}",
+            params.localization.get("reduced-motion"),
+            params
+                .localization
+                .get(if settings.accessibility.reduced_motion {
+                    "on"
+                } else {
+                    "off"
+                })
+        );
+        let reduced_motion_button =
+            BorderedButton::themed(ui_theme, &ButtonStyle::Normal, &reduced_motion_label)
+                .min_size(egui::vec2(ui.available_width() / 2.0, 0.0))
+                .show(ui);
+        if reduced_motion_button.clicked() {
+            settings.accessibility.reduced_motion = !settings.accessibility.reduced_motion;
+        }
+
+        // The frame pacing
+        ui.add_space(label_font.size * 0.5);
+        let vsync_label = format!(
+            "{}: {}",
+            params.localization.get("vsync"),
+            params
+                .localization
+                .get(if settings.video.vsync { "on" } else { "off" })
+        );
+        let vsync_button = BorderedButton::themed(ui_theme, &ButtonStyle::Normal, &vsync_label)
+            .min_size(egui::vec2(ui.available_width() / 2.0, 0.0))
+            .show(ui);
+        if vsync_button.clicked() {
+            settings.video.vsync = !settings.video.vsync;
+        }
+
+        let max_fps_label = format!(
+            "{}: {}",
+            params.localization.get("frame-rate-cap"),
+            match settings.video.max_fps {
+                Some(max_fps) => max_fps.to_string(),
+                None => params.localization.get("unlimited"),
+            }
+        );
+        let max_fps_button = BorderedButton::themed(ui_theme, &ButtonStyle::Normal, &max_fps_label)
+            .min_size(egui::vec2(ui.available_width() / 2.0, 0.0))
+            .show(ui);
+        if max_fps_button.clicked() {
+            settings.video.max_fps = next_max_fps(settings.video.max_fps);
+        }
+
+        // Set adjacency from the tabs, through the toggles and sliders, to the bottom buttons
+        for tab in settings_tabs {
+            params.adjacencies.widget(tab).above(&crt_button);
+        }
+        params
+            .adjacencies
+            .widget(&crt_button)
+            .to_right_of(&settings_tabs[settings_tabs.len() - 1])
+            .above(&sliders[0]);
+        params
+            .adjacencies
+            .widget(&sliders[1])
+            .below(&sliders[0])
+            .above(&prompts_button);
+        params
+            .adjacencies
+            .widget(&prompts_button)
+            .above(&screen_shake);
+        params
+            .adjacencies
+            .widget(&screen_shake)
+            .above(&reduced_motion_button);
+        params
+            .adjacencies
+            .widget(&reduced_motion_button)
+            .above(&vsync_button);
+        params
+            .adjacencies
+            .widget(&vsync_button)
+            .above(&max_fps_button);
+        params
+            .adjacencies
+            .widget(&max_fps_button)
+            .to_left_of(&bottom_buttons[0]);
+        for button in bottom_buttons {
+            params.adjacencies.widget(&max_fps_button).above(button);
+        }
+    });
+}
+
+/// Get the frame rate cap that follows `max_fps` in the video settings, going back to no cap after
+/// the highest one
+fn next_max_fps(max_fps: Option<u32>) -> Option<u32> {
+    const CAPS: [u32; 4] = [30, 60, 120, 144];
+
+    match max_fps {
+        None => Some(CAPS[0]),
+        Some(max_fps) => CAPS.iter().copied().find(|cap| *cap > max_fps),
+    }
+}
+
 /// Render the sound settings UI
-fn sound_settings_ui(_ui: &mut egui::Ui, _game: &GameMeta) {
-    // This is un-reachable right now
-    todo!("Implement sound settings UI");
+fn sound_settings_ui(
+    params: &mut MenuSystemParams,
+    ui: &mut egui::Ui,
+    should_reset: bool,
+    settings_tabs: &[egui::Response],
+    bottom_buttons: &[egui::Response],
+) {
+    let ui_theme = &params.game.ui_theme;
+
+    // Reset the settings when reset button is clicked
+    if should_reset {
+        params.modified_settings.as_mut().unwrap().audio =
+            params.game.default_settings.audio.clone();
+    }
+
+    let label_font = ui_theme
+        .font_styles
+        .get(&FontStyle::Normal)
+        .unwrap()
+        .colored(ui_theme.panel.font_color);
+
+    let audio = &mut params.modified_settings.as_mut().unwrap().audio;
+
+    ui.add_space(label_font.size);
+    ui.vertical_centered(|ui| {
+        ui.themed_label(&label_font, &params.localization.get("music-volume"));
+        let music = ui.add(egui::Slider::new(&mut audio.music_volume, 0.0..=1.0));
+
+        ui.add_space(label_font.size * 0.5);
+        ui.themed_label(&label_font, &params.localization.get("effects-volume"));
+        let effects = ui.add(egui::Slider::new(&mut audio.effects_volume, 0.0..=1.0));
+
+        // Set adjacency from the tabs, through the sliders, to the bottom buttons
+        for tab in settings_tabs {
+            params.adjacencies.widget(tab).above(&music);
+        }
+        params
+            .adjacencies
+            .widget(&music)
+            .to_right_of(&settings_tabs[settings_tabs.len() - 1])
+            .above(&effects);
+        params
+            .adjacencies
+            .widget(&effects)
+            .to_left_of(&bottom_buttons[0]);
+        for button in bottom_buttons {
+            params.adjacencies.widget(&effects).above(button);
+        }
+    });
 }
 
-/// Format an InputKind as a user-facing string
-fn format_input(input: &InputKind) -> String {
+/// Format an InputKind as a user-facing string, using the given glyphs for gamepad buttons
+fn format_input(
+    input: &InputKind,
+    gamepad_glyphs: Option<&HashMap<GamepadButtonType, String>>,
+) -> String {
     match input {
         InputKind::SingleAxis(axis) => {
             // If we set the positive low to 1.0, then that means we don't trigger on positive
@@ -687,7 +1235,7 @@ fn format_input(input: &InputKind) -> String {
 
             format!("{stick} {direction}")
         }
-        other => other.to_string(),
+        other => input_glyph(other, gamepad_glyphs),
     }
 }
 
@@ -700,6 +1248,7 @@ pub struct ControlInputBindingEvents<'w, 's> {
 }
 
 /// The kind of input binding to listen for.
+#[derive(Clone, Copy)]
 enum BindingKind {
     Keyboard,
     Gamepad,
@@ -766,3 +1315,47 @@ impl<'w, 's> ControlInputBindingEvents<'w, 's> {
         })
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_how_to_play_lists_each_action_binding() {
+        let game: GameMeta =
+            serde_yaml::from_str(include_str!("../../assets/default.game.yaml")).unwrap();
+        let mut settings = game.default_settings.clone();
+        settings.player_controls.keyboard1.block = KeyCode::Z.into();
+        let input_map = settings.player_controls.get_input_map(0);
+
+        let inputs = |device| {
+            how_to_play_rows(&input_map, device, &game.input_glyphs)
+                .into_iter()
+                .map(|row| (row.action, row.inputs))
+                .collect::<HashMap<_, _>>()
+        };
+
+        // Every action is listed, with the keyboard bindings including the rebound one
+        let keyboard = inputs(InputDevice::Keyboard);
+        assert_eq!(keyboard.len(), PlayerAction::variants().count());
+        let key = |key: KeyCode| format_input(&key.into(), None);
+        assert_eq!(
+            keyboard[&PlayerAction::Move],
+            [[KeyCode::W, KeyCode::S, KeyCode::A, KeyCode::D]
+                .map(key)
+                .join("/")]
+        );
+        assert_eq!(keyboard[&PlayerAction::Attack], [key(KeyCode::Space)]);
+        assert_eq!(keyboard[&PlayerAction::Block], [key(KeyCode::Z)]);
+        assert!(keyboard[&PlayerAction::AnalogAttack].is_empty());
+
+        // Gamepad bindings use the glyphs of the device
+        let xbox = inputs(InputDevice::Xbox);
+        assert_eq!(xbox[&PlayerAction::Attack], ["A"]);
+        assert_eq!(xbox[&PlayerAction::Block], ["LB"]);
+        assert_eq!(
+            inputs(InputDevice::PlayStation)[&PlayerAction::Attack],
+            ["Cross"]
+        );
+    }
+}
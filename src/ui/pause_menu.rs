@@ -1,9 +1,9 @@
 use bevy::prelude::*;
 use bevy_egui::*;
 use bevy_fluent::Localization;
-use iyes_loopless::state::NextState;
 
 use crate::{
+    game_state::{GameStateRequests, TransitionPriority},
     localization::LocalizationExt,
     metadata::{ButtonStyle, FontStyle, GameMeta},
     utils::ResetController,
@@ -16,7 +16,7 @@ use super::{
 };
 
 pub fn pause_menu(
-    mut commands: Commands,
+    mut state_requests: ResMut<GameStateRequests>,
     mut egui_context: ResMut<EguiContext>,
     game: Res<GameMeta>,
     localization: Res<Localization>,
@@ -66,7 +66,7 @@ pub fn pause_menu(
                         }
 
                         if continue_button.clicked() {
-                            commands.insert_resource(NextState(GameState::InGame));
+                            state_requests.request(GameState::InGame, TransitionPriority::Pause);
                         }
 
                         if BorderedButton::themed(
@@ -81,7 +81,8 @@ pub fn pause_menu(
                             reset_controller.reset_world();
 
                             // Show the main menu
-                            commands.insert_resource(NextState(GameState::MainMenu));
+                            state_requests
+                                .request(GameState::MainMenu, TransitionPriority::Navigation);
                             ui.ctx().clear_focus();
                         }
                     });
@@ -1,8 +1,91 @@
+use std::{marker::PhantomData, time::Duration};
+
 use bevy::{
     ecs::system::SystemParam,
     hierarchy::DespawnRecursiveExt,
-    prelude::{Camera, Commands, Entity, Query, Transform, With, Without},
+    prelude::{
+        Camera, Commands, Deref, DerefMut, Entity, Query, Res, Resource, Transform, With, Without,
+    },
+    time::Time,
 };
+use rand::{rngs::StdRng, SeedableRng};
+
+use crate::metadata::GameMeta;
+
+/// Seeded random number generator used for the level layout and spawn-time randomness.
+///
+/// It is re-seeded with the level seed when a level is loaded, so that the same seed yields the
+/// same enemy placement and randomized enemy attributes on every machine running the same build.
+#[derive(Resource, Deref, DerefMut)]
+pub struct GameRng(pub StdRng);
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for GameRng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// Seeded random number generator used for purely cosmetic randomness, like the variations of
+/// sounds.
+///
+/// It is kept apart from the [`GameRng`] so that cosmetic effects never change the draws that the
+/// gameplay depends on.
+#[derive(Resource, Deref, DerefMut)]
+pub struct CosmeticRng(pub StdRng);
+
+impl CosmeticRng {
+    pub fn new(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+impl Default for CosmeticRng {
+    fn default() -> Self {
+        Self::new(0)
+    }
+}
+
+/// System parameter giving the time elapsed since the last frame to gameplay systems.
+///
+/// The delta is clamped to the game's [`max_frame_delta`](GameMeta::max_frame_delta), so that a
+/// long frame, like a hitch or a stall while the browser tab is in the background, can't move
+/// entities through stop points or boundaries in a single step.
+#[derive(SystemParam)]
+pub struct GameTime<'w, 's> {
+    time: Res<'w, Time>,
+    // The game meta isn't available until it has been loaded
+    game: Option<Res<'w, GameMeta>>,
+    #[system_param(ignore)]
+    _phantom: PhantomData<&'s ()>,
+}
+
+impl<'w, 's> GameTime<'w, 's> {
+    /// The clamped time elapsed since the last frame.
+    ///
+    /// The delta isn't clamped if the max frame delta is negative or not a number.
+    pub fn delta(&self) -> Duration {
+        let delta = self.time.delta();
+        let max_delta = self
+            .game
+            .as_ref()
+            .and_then(|game| Duration::try_from_secs_f32(game.max_frame_delta).ok());
+        match max_delta {
+            Some(max_delta) => delta.min(max_delta),
+            None => delta,
+        }
+    }
+
+    /// The clamped time elapsed since the last frame, in seconds
+    pub fn delta_seconds(&self) -> f32 {
+        self.delta().as_secs_f32()
+    }
+}
 
 /// System parameter that can be used to reset the game world.
 ///
@@ -32,3 +115,36 @@ impl<'w, 's> ResetController<'w, 's> {
         }
     }
 }
+
+#[cfg(test)]
+mod test {
+    use bevy::{ecs::system::SystemState, prelude::World};
+
+    use super::*;
+
+    #[test]
+    fn test_game_time_clamps_long_frames() {
+        let mut game: GameMeta =
+            serde_yaml::from_str(include_str!("../assets/default.game.yaml")).unwrap();
+        game.max_frame_delta = 0.1;
+        let mut time = Time::default();
+        time.update();
+        let last_update = time.last_update().unwrap();
+        time.update_with_instant(last_update + Duration::from_secs_f32(0.5));
+
+        let mut world = World::new();
+        world.insert_resource(time);
+        world.insert_resource(game);
+        let mut delta = |world: &mut World| {
+            let mut state = SystemState::<GameTime>::new(world);
+            state.get_mut(world).delta_seconds()
+        };
+        assert!((delta(&mut world) - 0.1).abs() < 1e-4);
+
+        // Invalid max frame deltas leave the frame unclamped instead of panicking
+        for max_frame_delta in [-1., f32::NAN] {
+            world.resource_mut::<GameMeta>().max_frame_delta = max_frame_delta;
+            assert!((delta(&mut world) - 0.5).abs() < 1e-4);
+        }
+    }
+}
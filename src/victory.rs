@@ -0,0 +1,217 @@
+//! Level victory conditions
+
+use bevy::prelude::*;
+use iyes_loopless::prelude::*;
+
+use crate::{
+    attract_mode::AttractMode,
+    camera::{BossIntro, CameraIntro},
+    enemy::{Boss, Enemy},
+    fighter_state::Dying,
+    game_state::{GameStateRequests, TransitionPriority},
+    metadata::{LevelMeta, VictoryCondition},
+    player::LivingPlayer,
+    utils::GameTime,
+    GameState,
+};
+
+pub struct VictoryPlugin;
+
+impl Plugin for VictoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<LevelTime>()
+            .add_system(
+                tick_level_time
+                    .run_in_state(GameState::InGame)
+                    .run_unless_resource_exists::<AttractMode>()
+                    .run_unless_resource_exists::<CameraIntro>()
+                    .run_unless_resource_exists::<BossIntro>()
+                    .before(victory_check),
+            )
+            .add_system(
+                victory_check
+                    .run_in_state(GameState::InGame)
+                    .run_unless_resource_exists::<AttractMode>(),
+            );
+    }
+}
+
+/// The time, in seconds, that the current level has been played for.
+///
+/// The camera and boss intros don't count, since the players have no control during them.
+#[derive(Resource, Default, Deref, DerefMut)]
+pub struct LevelTime(pub f32);
+
+fn tick_level_time(mut level_time: ResMut<LevelTime>, time: GameTime) {
+    **level_time += time.delta_seconds();
+}
+
+/// Completes the level once the players meet its [`VictoryCondition`]
+fn victory_check(
+    mut state_requests: ResMut<GameStateRequests>,
+    level: Res<LevelMeta>,
+    level_time: Res<LevelTime>,
+    enemies: Query<Option<&Boss>, (With<Enemy>, Without<Dying>)>,
+    players: Query<&Transform, LivingPlayer>,
+) {
+    let victory = match level.victory {
+        VictoryCondition::DefeatAllEnemies => enemies.is_empty(),
+        VictoryCondition::DefeatBoss => !enemies.iter().any(|boss| boss.is_some()),
+        VictoryCondition::Survive { duration } => **level_time >= duration,
+        VictoryCondition::ReachX { x } => {
+            players.iter().any(|transform| transform.translation.x >= x)
+        }
+    };
+
+    if victory {
+        state_requests.request(GameState::LevelComplete, TransitionPriority::Victory);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
+
+    use super::*;
+    use crate::player::Player;
+
+    fn level_world(victory: VictoryCondition) -> World {
+        let mut level: LevelMeta =
+            serde_yaml::from_str(include_str!("../assets/levels/1_beach/beach.level.yaml"))
+                .unwrap();
+        level.victory = victory;
+        let mut time = Time::default();
+        time.update();
+
+        let mut world = World::new();
+        world.insert_resource(level);
+        world.insert_resource(time);
+        world.init_resource::<LevelTime>();
+        world.init_resource::<GameStateRequests>();
+        world
+    }
+
+    /// Run the victory check after `seconds`, returning whether the level was completed
+    fn check(world: &mut World, seconds: f32) -> bool {
+        let mut time = world.resource_mut::<Time>();
+        let last_update = time.last_update().unwrap();
+        time.update_with_instant(last_update + Duration::from_secs_f32(seconds));
+
+        let mut stage = SystemStage::single_threaded();
+        stage
+            .add_system(tick_level_time.before(victory_check))
+            .add_system(victory_check);
+        stage.run(world);
+
+        world.resource_mut::<GameStateRequests>().take() == Some(GameState::LevelComplete)
+    }
+
+    #[test]
+    fn test_defeat_all_enemies() {
+        let mut world = level_world(VictoryCondition::DefeatAllEnemies);
+        let enemies = [world.spawn(Enemy).id(), world.spawn(Enemy).id()];
+        assert!(!check(&mut world, 0.1));
+
+        world.entity_mut(enemies[0]).insert(Dying);
+        assert!(!check(&mut world, 0.1));
+
+        world.entity_mut(enemies[1]).insert(Dying);
+        assert!(check(&mut world, 0.1));
+    }
+
+    #[test]
+    fn test_defeat_boss() {
+        let mut world = level_world(VictoryCondition::DefeatBoss);
+        world.spawn(Enemy);
+        let boss = world.spawn((Enemy, Boss)).id();
+        assert!(!check(&mut world, 0.1));
+
+        // The other enemies don't need to be defeated
+        world.entity_mut(boss).insert(Dying);
+        assert!(check(&mut world, 0.1));
+    }
+
+    #[test]
+    fn test_survive() {
+        let mut world = level_world(VictoryCondition::Survive { duration: 10. });
+        assert!(!check(&mut world, 5.));
+        assert!(!check(&mut world, 4.));
+        assert!(check(&mut world, 2.));
+    }
+
+    #[test]
+    fn test_level_time_pauses_during_intros() {
+        let level: LevelMeta =
+            serde_yaml::from_str(include_str!("../assets/levels/1_beach/beach.level.yaml"))
+                .unwrap();
+        let mut time = Time::default();
+        time.update();
+
+        let mut app = App::new();
+        app.insert_resource(level)
+            .insert_resource(time)
+            .insert_resource(CurrentState(GameState::InGame))
+            .init_resource::<GameStateRequests>()
+            .add_plugin(VictoryPlugin);
+        let mut step = |app: &mut App, seconds: f32| {
+            let mut time = app.world.resource_mut::<Time>();
+            let last_update = time.last_update().unwrap();
+            time.update_with_instant(last_update + Duration::from_secs_f32(seconds));
+            app.update();
+            **app.world.resource::<LevelTime>()
+        };
+
+        // The level intro doesn't count
+        app.insert_resource(CameraIntro::new(Vec::new()));
+        assert_eq!(step(&mut app, 0.1), 0.);
+        app.world.remove_resource::<CameraIntro>();
+        assert!((step(&mut app, 0.1) - 0.1).abs() < 1e-4);
+
+        // Neither does the intro of a boss
+        app.insert_resource(BossIntro {
+            boss: Entity::from_raw(0),
+            banner: String::new(),
+            duration: 1.,
+            start_x: None,
+            elapsed: 0.,
+        });
+        assert!((step(&mut app, 0.1) - 0.1).abs() < 1e-4);
+        app.world.remove_resource::<BossIntro>();
+        assert!((step(&mut app, 0.1) - 0.2).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_game_over_wins_over_simultaneous_victory() {
+        for victory_first in [true, false] {
+            // The last enemy and the last player are defeated on the same frame
+            let mut world = level_world(VictoryCondition::DefeatAllEnemies);
+            world.spawn((Enemy, Dying));
+
+            let mut stage = SystemStage::single_threaded();
+            if victory_first {
+                stage.add_system(victory_check.before(crate::game_over_on_players_death));
+            } else {
+                stage.add_system(victory_check.after(crate::game_over_on_players_death));
+            }
+            stage.add_system(crate::game_over_on_players_death);
+            stage.run(&mut world);
+
+            assert_eq!(
+                world.resource_mut::<GameStateRequests>().take(),
+                Some(GameState::MainMenu)
+            );
+        }
+    }
+
+    #[test]
+    fn test_reach_x() {
+        let mut world = level_world(VictoryCondition::ReachX { x: 500. });
+        let player = world
+            .spawn((Player, Transform::from_xyz(100., 0., 0.)))
+            .id();
+        assert!(!check(&mut world, 0.1));
+
+        world.get_mut::<Transform>(player).unwrap().translation.x = 600.;
+        assert!(check(&mut world, 0.1));
+    }
+}